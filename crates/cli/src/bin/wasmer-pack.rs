@@ -8,6 +8,11 @@ fn main() -> Result<(), Error> {
     match cmd {
         Cmd::JavaScript(js) => js.run(Language::JavaScript),
         Cmd::Python(py) => py.run(Language::Python),
+        Cmd::Rust(rs) => rs.run(Language::Rust),
+        Cmd::Go(go) => go.run(Language::Go),
+        Cmd::C(c) => c.run(Language::C),
+        Cmd::Ruby(rb) => rb.run(Language::Ruby),
+        Cmd::Php(php) => php.run(Language::Php),
         Cmd::Show(show) => show.run(),
     }
 }
@@ -21,6 +26,19 @@ enum Cmd {
     /// Generate Python bindings.
     #[clap(alias = "py")]
     Python(Codegen),
+    /// Generate a Rust crate which runs the package using `wasmer`.
+    #[clap(alias = "rs")]
+    Rust(Codegen),
+    /// Generate a Go module which runs the package using `wasmer-go`.
+    Go(Codegen),
+    /// Generate a C library and CMake project which runs the package using
+    /// the Wasmer C API.
+    C(Codegen),
+    /// Generate a Ruby gem which runs the package using `wasmer-ruby`.
+    #[clap(alias = "rb")]
+    Ruby(Codegen),
+    /// Generate a Composer package which runs the package using `wasmer-php`.
+    Php(Codegen),
     /// Show metadata for the bindings that would be generated from a Pirita
     /// file.
     Show(Show),
@@ -12,7 +12,18 @@ pub struct Codegen {
     /// Where to save the generated bindings.
     #[clap(short, long)]
     pub out_dir: Option<PathBuf>,
-    /// The Pirita file to read.
+    /// Fail if a library exports functions which aren't in its interface.
+    #[clap(long)]
+    pub deny_extra_exports: bool,
+    /// A directory of WEBC files to look for the package's dependencies in.
+    #[clap(long)]
+    pub dependencies: Option<PathBuf>,
+    /// The local Wasmer cache or registry mirror to look in when the input
+    /// is a package name (defaults to `~/.wasmer/cache`).
+    #[clap(long, env = "WASMER_PACK_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// The Pirita file, package directory, or JSON manifest to read, or a
+    /// package name (e.g. `wasmer/python@3.12`) to look up in the cache.
     pub input: PathBuf,
 }
 
@@ -21,13 +32,26 @@ impl Codegen {
         let Codegen {
             name,
             out_dir,
+            deny_extra_exports,
+            dependencies,
+            cache_dir,
             input,
         } = self;
-        let pkg = crate::utils::load(&input)?;
-        let options = wasmer_pack::BindingsOptions { name };
+        let (pkg, warnings) =
+            crate::utils::load(&input, dependencies.as_deref(), cache_dir.as_deref())?;
+        crate::utils::print_warnings(&warnings);
+        let options = wasmer_pack::BindingsOptions {
+            name,
+            deny_extra_exports,
+        };
         let files = match language {
             Language::JavaScript => wasmer_pack::generate_javascript(&pkg, &options)?,
             Language::Python => wasmer_pack::generate_python(&pkg, &options)?,
+            Language::Rust => wasmer_pack::generate_rust(&pkg, &options)?,
+            Language::Go => wasmer_pack::generate_go(&pkg, &options)?,
+            Language::C => wasmer_pack::generate_c(&pkg, &options)?,
+            Language::Ruby => wasmer_pack::generate_ruby(&pkg, &options)?,
+            Language::Php => wasmer_pack::generate_php(&pkg, &options)?,
         };
 
         let metadata = pkg.metadata();
@@ -53,6 +77,11 @@ impl Codegen {
 pub enum Language {
     JavaScript,
     Python,
+    Rust,
+    Go,
+    C,
+    Ruby,
+    Php,
 }
 
 impl Language {
@@ -60,6 +89,11 @@ impl Language {
         match self {
             Language::JavaScript => "JavaScript",
             Language::Python => "Python",
+            Language::Rust => "Rust",
+            Language::Go => "Go",
+            Language::C => "C",
+            Language::Ruby => "Ruby",
+            Language::Php => "PHP",
         }
     }
 }
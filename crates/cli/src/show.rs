@@ -9,15 +9,30 @@ pub struct Show {
     /// The format to use when emitting metadata.
     #[clap(short, long, default_value_t = Format::Text)]
     format: Format,
-    /// The Pirita file to read.
+    /// A directory of WEBC files to look for the package's dependencies in.
+    #[clap(long)]
+    dependencies: Option<PathBuf>,
+    /// The local Wasmer cache or registry mirror to look in when the input
+    /// is a package name (defaults to `~/.wasmer/cache`).
+    #[clap(long, env = "WASMER_PACK_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+    /// The Pirita file, package directory, or JSON manifest to read, or a
+    /// package name (e.g. `wasmer/python@3.12`) to look up in the cache.
     input: PathBuf,
 }
 
 impl Show {
     pub fn run(self) -> Result<(), Error> {
-        let Show { format, input } = self;
+        let Show {
+            format,
+            dependencies,
+            cache_dir,
+            input,
+        } = self;
 
-        let pkg = crate::utils::load(&input)?;
+        let (pkg, warnings) =
+            crate::utils::load(&input, dependencies.as_deref(), cache_dir.as_deref())?;
+        crate::utils::print_warnings(&warnings);
 
         let summary: Summary = summarize(&pkg);
 
@@ -64,7 +79,7 @@ fn summarize(pkg: &Package) -> Summary {
     Summary {
         description: description.clone(),
         name: package_name.to_string(),
-        version: version.clone(),
+        version: version.to_string(),
         bindings,
         commands,
     }
@@ -1,13 +1,118 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Error};
-use wasmer_pack::Package;
+use wasmer_pack::{Dependency, Package, Resolver, Warning, WebcDirectory};
 use webc::Container;
 
-pub(crate) fn load(path: &Path) -> Result<Package, Error> {
-    load_container(path)
-        .and_then(|webc| Package::from_webc(&webc))
-        .with_context(|| format!("Unable to load the package from \"{}\"", path.display()))
+/// Load a [`Package`], along with any [`Warning`]s about content that was
+/// skipped.
+///
+/// If provided, the `dependencies` directory is searched for WEBC files
+/// containing atoms used by the package's commands.
+///
+/// When `path` doesn't exist but looks like a package name (e.g.
+/// `wasmer/python@3.12`), the package is looked up in the `cache_dir` (or the
+/// local Wasmer cache) instead.
+pub(crate) fn load(
+    path: &Path,
+    dependencies: Option<&Path>,
+    cache_dir: Option<&Path>,
+) -> Result<(Package, Vec<Warning>), Error> {
+    if !path.exists() {
+        if let Some(package) = package_specifier(path) {
+            // It could just as easily be a mistyped path, so mention both
+            return load_from_cache(&package, dependencies, cache_dir).with_context(|| {
+                format!(
+                    "\"{}\" doesn't exist, and it couldn't be loaded as a package name either",
+                    path.display()
+                )
+            });
+        }
+    }
+
+    let pkg = if is_json_manifest(path) {
+        load_manifest(path).map(|pkg| (pkg, Vec::new()))
+    } else {
+        load_container(path).and_then(|webc| {
+            match dependencies {
+                Some(dir) => Package::from_webc_with_resolver(&webc, &WebcDirectory::new(dir)),
+                None => Package::from_webc_with_warnings(&webc),
+            }
+            .map_err(Error::from)
+        })
+    };
+
+    pkg.with_context(|| format!("Unable to load the package from \"{}\"", path.display()))
+}
+
+pub(crate) fn print_warnings(warnings: &[Warning]) {
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
+}
+
+/// Check whether the user passed in a package name instead of a path.
+fn package_specifier(path: &Path) -> Option<Dependency> {
+    let raw = path.to_str()?;
+    if !raw.contains('/') {
+        return None;
+    }
+
+    raw.parse().ok()
+}
+
+fn load_from_cache(
+    package: &Dependency,
+    dependencies: Option<&Path>,
+    cache_dir: Option<&Path>,
+) -> Result<(Package, Vec<Warning>), Error> {
+    let cache_dir = match cache_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => default_cache_dir().context(
+            "Unable to determine where the Wasmer cache is. Please specify it with --cache-dir",
+        )?,
+    };
+    let cache = WebcDirectory::new(&cache_dir);
+    let specifier = format!("{}@{}", package.package_name, package.version);
+
+    let webc = cache.resolve(package)?.with_context(|| {
+        format!(
+            "Unable to find a copy of {specifier} in \"{}\"",
+            cache_dir.display()
+        )
+    })?;
+
+    // The package's dependencies are probably in the cache too
+    let dependencies = dependencies.map_or(cache, WebcDirectory::new);
+
+    Package::from_webc_with_resolver(&webc, &dependencies)
+        .with_context(|| format!("Unable to load \"{specifier}\" from the cache"))
+}
+
+/// Where the Wasmer CLI caches packages (`$WASMER_DIR/cache`, defaulting to
+/// `~/.wasmer/cache`).
+fn default_cache_dir() -> Option<PathBuf> {
+    let wasmer_dir = match std::env::var_os("WASMER_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            PathBuf::from(home).join(".wasmer")
+        }
+    };
+
+    Some(wasmer_dir.join("cache"))
+}
+
+fn is_json_manifest(path: &Path) -> bool {
+    path.is_file() && path.extension().map_or(false, |ext| ext == "json")
+}
+
+fn load_manifest(path: &Path) -> Result<Package, Error> {
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    Package::from_json_manifest(&json, base_dir).map_err(Error::from)
 }
 
 fn load_container(path: &Path) -> Result<Container, Error> {
@@ -1,4 +1,4 @@
-use assert_cmd::Command;
+use assert_cmd::{assert::Assert, Command};
 use flate2::read::GzDecoder;
 use std::{
     collections::BTreeSet,
@@ -95,15 +95,165 @@ fn load_a_package_from_a_tarball() {
 #[test]
 fn load_a_package_from_a_directory() {
     let temp = TempDir::new().unwrap();
-    let mut archive = Archive::new(GzDecoder::new(WIT_PACK_TARBALL));
-    archive.unpack(temp.path()).unwrap();
+    let pkg_dir = unpack_wit_pack(&temp);
 
-    let meta = metadata(temp.path());
+    let meta = metadata(&pkg_dir);
 
     insta::assert_display_snapshot!(format!("{meta:#}"));
     assert_contains_libraries_and_commands(&meta, &["wit-pack"], &[]);
 }
 
+#[test]
+fn generate_a_rust_crate() {
+    let temp = TempDir::new().unwrap();
+    let pkg_dir = unpack_wit_pack(&temp);
+    let out_dir = temp.path().join("out");
+
+    generate("rust", &pkg_dir, &out_dir, &[]).success();
+
+    for file in ["Cargo.toml", "src/lib.rs", "src/bindings.rs"] {
+        assert!(out_dir.join(file).exists(), "{file} wasn't generated");
+    }
+    let lib_rs = std::fs::read_to_string(out_dir.join("src/lib.rs")).unwrap();
+    assert!(lib_rs.contains("pub fn bindings() -> bindings::Bindings"));
+}
+
+#[test]
+fn generate_a_ruby_gem() {
+    let temp = TempDir::new().unwrap();
+    let pkg_dir = unpack_wit_pack(&temp);
+    let out_dir = temp.path().join("out");
+
+    generate("ruby", &pkg_dir, &out_dir, &["--name=wit-pack"]).success();
+
+    for file in [
+        "wit_pack.gemspec",
+        "lib/wit_pack.rb",
+        "lib/wit_pack/abi.rb",
+        "lib/wit_pack/bindings.rb",
+    ] {
+        assert!(out_dir.join(file).exists(), "{file} wasn't generated");
+    }
+    // Exported resources are supported, unlike the Go and C bindings
+    let bindings = std::fs::read_dir(out_dir.join("lib/wit_pack/bindings"))
+        .unwrap()
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect::<String>();
+    assert!(bindings.contains("class Metadata < "));
+}
+
+#[test]
+fn generate_a_php_package() {
+    let temp = TempDir::new().unwrap();
+    let pkg_dir = unpack_wit_pack(&temp);
+    let out_dir = temp.path().join("out");
+
+    generate("php", &pkg_dir, &out_dir, &[]).success();
+
+    for file in [
+        "composer.json",
+        "src/Bindings.php",
+        "src/Internal/Abi.php",
+        "src/Internal/Resource.php",
+        "src/Internal/Slab.php",
+        "src/Bindings/WitPack/WitPack.php",
+    ] {
+        assert!(out_dir.join(file).exists(), "{file} wasn't generated");
+    }
+    let metadata =
+        std::fs::read_to_string(out_dir.join("src/Bindings/WitPack/Metadata.php")).unwrap();
+    assert!(metadata.contains("final class Metadata extends Resource"));
+}
+
+#[test]
+fn bindings_which_dont_support_resources_reject_them() {
+    let temp = TempDir::new().unwrap();
+    let pkg_dir = unpack_wit_pack(&temp);
+
+    for (language, name) in [("go", "Go"), ("c", "C")] {
+        let out_dir = temp.path().join(language);
+
+        let output = generate(language, &pkg_dir, &out_dir, &[]).failure();
+
+        let stderr = String::from_utf8_lossy(&output.get_output().stderr);
+        let expected = format!("uses resources, which isn't supported by the {name} bindings");
+        assert!(stderr.contains(&expected), "{language}: {stderr}");
+
+        assert!(!out_dir.exists(), "{language}");
+    }
+}
+
+#[test]
+fn load_a_package_from_a_json_manifest() {
+    let temp = TempDir::new().unwrap();
+    let pkg_dir = unpack_wit_pack(&temp);
+    let manifest = serde_json::json!({
+        "manifest_version": 1,
+        "metadata": {
+            "name": "wasmer/wit-pack",
+            "version": "0.3.0",
+            "description": "The WebAssembly interface to wit-pack.",
+        },
+        "libraries": [
+            {
+                "module": { "path": "wit_pack_wasm.wasm" },
+                "abi": "none",
+                "exports": { "path": "wit-pack.exports.wit" },
+            }
+        ],
+    });
+    let manifest_path = pkg_dir.join("manifest.json");
+    std::fs::write(&manifest_path, manifest.to_string()).unwrap();
+
+    let meta = metadata(&manifest_path);
+
+    insta::assert_display_snapshot!(format!("{meta:#}"));
+    assert_contains_libraries_and_commands(&meta, &["wit-pack"], &[]);
+
+    generate_bindings(&manifest_path, &temp.path().join("out"));
+}
+
+#[test]
+fn load_a_package_from_a_cache_directory() {
+    let cache = TempDir::new().unwrap();
+    let pkg = webc::wasmer_package::Package::from_tarball(Cursor::new(WIT_PACK_TARBALL)).unwrap();
+    let checkouts = cache.path().join("checkouts");
+    std::fs::create_dir_all(&checkouts).unwrap();
+    std::fs::write(checkouts.join("wit-pack.bin"), pkg.serialize().unwrap()).unwrap();
+
+    let output = Command::cargo_bin("wasmer-pack")
+        .unwrap()
+        .arg("show")
+        .arg("--format=json")
+        .arg("--cache-dir")
+        .arg(cache.path())
+        .arg("wasmer/wit-pack@0.3")
+        .assert()
+        .success();
+    let meta: serde_json::Value = serde_json::from_slice(&output.get_output().stdout).unwrap();
+
+    assert_eq!(meta["name"], "wasmer/wit-pack");
+    assert_contains_libraries_and_commands(&meta, &["wit-pack"], &[]);
+
+    let output = Command::cargo_bin("wasmer-pack")
+        .unwrap()
+        .arg("show")
+        .arg("--cache-dir")
+        .arg(cache.path())
+        .arg("wasmer/wit-pack@2")
+        .assert()
+        .failure();
+    let stderr = String::from_utf8_lossy(&output.get_output().stderr);
+    assert!(
+        stderr.contains("\"wasmer/wit-pack@2\" doesn't exist"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("Unable to find a copy of wasmer/wit-pack@^2"),
+        "{stderr}"
+    );
+}
+
 #[track_caller]
 fn assert_contains_libraries_and_commands(
     meta: &serde_json::Value,
@@ -144,14 +294,29 @@ fn metadata(webc_file: &Path) -> serde_json::Value {
 }
 
 fn generate_bindings(webc_file: &Path, out_dir: &Path) {
+    generate("js", webc_file, out_dir, &[]).success();
+}
+
+/// Run `wasmer-pack <language> <package> --out-dir <out_dir>` with any extra
+/// arguments, leaving it up to the caller to check whether it succeeded.
+fn generate(language: &str, package: &Path, out_dir: &Path, args: &[&str]) -> Assert {
     Command::cargo_bin("wasmer-pack")
         .unwrap()
-        .arg("js")
-        .arg(webc_file)
+        .arg(language)
+        .arg(package)
+        .args(args)
         .arg("--out-dir")
         .arg(out_dir)
         .assert()
-        .success();
+}
+
+/// Unpack the wit-pack tarball into a `pkg/` directory.
+fn unpack_wit_pack(temp: &TempDir) -> PathBuf {
+    let pkg_dir = temp.path().join("pkg");
+    Archive::new(GzDecoder::new(WIT_PACK_TARBALL))
+        .unpack(&pkg_dir)
+        .unwrap();
+    pkg_dir
 }
 
 fn cached_url(url: &str) -> PathBuf {
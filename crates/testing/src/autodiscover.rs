@@ -2,8 +2,8 @@ use std::{
     collections::{BTreeSet, HashSet},
     env,
     fs::{self, File},
-    io::BufReader,
-    path::Path,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
     process::{Command, Stdio},
     time::Instant,
 };
@@ -46,14 +46,41 @@ pub fn autodiscover(crate_dir: impl AsRef<Path>) -> Result<(), Error> {
         );
         crate::generate_bindings(&bindings, &wapm_package, language, None)?;
 
-        match language {
-            Language::JavaScript => {
-                setup_javascript(crate_dir, &bindings)?;
-                run_jest(crate_dir)?;
-            }
-            Language::Python => {
-                setup_python(crate_dir, &bindings)?;
-                run_pytest(crate_dir)?;
+        if let Some(reason) = missing_requirement(crate_dir, language) {
+            tracing::warn!(
+                language = language.name(),
+                "Skipping the {} tests because {reason}",
+                language.name(),
+            );
+        } else {
+            match language {
+                Language::JavaScript => {
+                    setup_javascript(crate_dir, &bindings)?;
+                    run_jest(crate_dir)?;
+                }
+                Language::Python => {
+                    setup_python(crate_dir, &bindings)?;
+                    run_pytest(crate_dir)?;
+                }
+                Language::Go => {
+                    setup_go(crate_dir, &bindings)?;
+                    run_go_test(crate_dir)?;
+                }
+                Language::C => {
+                    setup_c(crate_dir, &bindings)?;
+                    run_ctest(crate_dir)?;
+                }
+                Language::Ruby => {
+                    setup_ruby(crate_dir)?;
+                    run_rspec(crate_dir, &bindings)?;
+                }
+                Language::Php => {
+                    setup_php(crate_dir)?;
+                    run_phpunit(crate_dir)?;
+                }
+                // We never detect Rust tests because every crate contains *.rs
+                // files
+                Language::Rust => {}
             }
         }
 
@@ -76,6 +103,18 @@ fn detected_languages(crate_dir: &Path) -> HashSet<Language> {
             Some("mjs") | Some("js") | Some("ts") => {
                 languages.insert(Language::JavaScript);
             }
+            Some("go") => {
+                languages.insert(Language::Go);
+            }
+            Some("c") | Some("cpp") => {
+                languages.insert(Language::C);
+            }
+            Some("rb") => {
+                languages.insert(Language::Ruby);
+            }
+            Some("php") => {
+                languages.insert(Language::Php);
+            }
             _ => {}
         }
     }
@@ -109,6 +148,10 @@ fn snapshot_generated_bindings(
     settings.add_filter(r#""\d+\.\d+\.\d+""#, r#""x.y.z""#);
     // Also ignore the generator version comments
     settings.add_filter(r"wasmer-pack v\d+\.\d+\.\d+", "wasmer-pack vX.Y.Z");
+    // WebAssembly modules are named after a hash of their contents, which
+    // changes every time the example is recompiled
+    settings.add_filter(r"[0-9a-f]{64}\.wasm", "[hash].wasm");
+    settings.add_filter(r"wasm_[0-9a-f]{64}", "wasm_[hash]");
 
     let _guard = settings.bind_to_scope();
 
@@ -155,6 +198,22 @@ fn language_specific_matches(package_dir: &Path, language: Language) -> Result<W
             .add("*.in")?
             .add("py.typed")?
             .build()?,
+        Language::Go => builder.add("*.go")?.add("go.mod")?.build()?,
+        Language::C => builder
+            .add("*.c")?
+            .add("*.h")?
+            // The embedded WebAssembly changes every time the example is
+            // recompiled
+            .add("!*_bundled.h")?
+            .add("CMakeLists.txt")?
+            .build()?,
+        Language::Ruby => builder
+            .add("*.rb")?
+            .add("*.gemspec")?
+            .add("Gemfile")?
+            .build()?,
+        Language::Php => builder.add("*.php")?.add("composer.json")?.build()?,
+        Language::Rust => builder.add("*.rs")?.add("*.toml")?.build()?,
     };
 
     let walk = WalkBuilder::new(package_dir)
@@ -250,6 +309,196 @@ fn run_pytest(crate_dir: &Path) -> Result<(), Error> {
     Ok(())
 }
 
+fn setup_go(crate_dir: &Path, generated_bindings: &Path) -> Result<(), Error> {
+    let generated_go_mod = std::fs::read_to_string(generated_bindings.join("go.mod"))
+        .context("Unable to read the generated go.mod")?;
+    let module = generated_go_mod
+        .lines()
+        .find_map(|line| line.strip_prefix("module "))
+        .context("The generated go.mod doesn't declare a module")?
+        .trim();
+
+    if !crate_dir.join("go.mod").exists() {
+        run_tool(
+            crate_dir,
+            "go",
+            &["mod", "init", "tests"],
+            "Initializing the Go module",
+        )?;
+    }
+
+    // Point the tests at the freshly generated bindings
+    let replace = format!(
+        "-replace={module}=./{}",
+        generated_bindings.strip_prefix(crate_dir)?.display()
+    );
+    let require = format!("-require={module}@v0.0.0");
+    run_tool(
+        crate_dir,
+        "go",
+        &["mod", "edit", &replace, &require],
+        "Adding the generated bindings as a dependency",
+    )?;
+    run_tool(crate_dir, "go", &["mod", "tidy"], "Installing dependencies")?;
+
+    Ok(())
+}
+
+fn run_go_test(crate_dir: &Path) -> Result<(), Error> {
+    run_tool(crate_dir, "go", &["test", "-v", "./..."], "Running go test")
+}
+
+fn setup_c(crate_dir: &Path, generated_bindings: &Path) -> Result<(), Error> {
+    // The tests' CMakeLists.txt is expected to add_subdirectory() the
+    // generated bindings using this variable
+    let bindings = format!("-DWASMER_PACK_BINDINGS={}", generated_bindings.display());
+    let build_dir = crate_dir.join("build");
+    let build_dir = build_dir.to_str().context("Invalid build directory")?;
+
+    run_tool(
+        crate_dir,
+        "cmake",
+        &["-S", ".", "-B", build_dir, &bindings],
+        "Configuring the CMake project",
+    )?;
+    run_tool(
+        crate_dir,
+        "cmake",
+        &["--build", build_dir],
+        "Compiling the tests",
+    )?;
+
+    Ok(())
+}
+
+fn run_ctest(crate_dir: &Path) -> Result<(), Error> {
+    run_tool(
+        &crate_dir.join("build"),
+        "ctest",
+        &["--output-on-failure"],
+        "Running ctest",
+    )
+}
+
+fn setup_ruby(crate_dir: &Path) -> Result<(), Error> {
+    if crate_dir.join("Gemfile").exists() {
+        run_tool(crate_dir, "bundle", &["install"], "Installing dependencies")?;
+    }
+
+    Ok(())
+}
+
+fn run_rspec(crate_dir: &Path, generated_bindings: &Path) -> Result<(), Error> {
+    // The generated gem is put on the load path instead of being installed,
+    // so the tests can just require it
+    let lib = generated_bindings.join("lib");
+    let lib = lib.to_str().context("Invalid bindings directory")?;
+
+    if crate_dir.join("Gemfile").exists() {
+        run_tool(
+            crate_dir,
+            "bundle",
+            &["exec", "rspec", "-I", lib],
+            "Running rspec",
+        )
+    } else {
+        run_tool(crate_dir, "rspec", &["-I", lib], "Running rspec")
+    }
+}
+
+fn setup_php(crate_dir: &Path) -> Result<(), Error> {
+    // The tests are expected to pull in the generated package using a "path"
+    // repository in their composer.json
+    if crate_dir.join("composer.json").exists() {
+        run_tool(
+            crate_dir,
+            "composer",
+            &["install", "--no-interaction"],
+            "Installing dependencies",
+        )?;
+    }
+
+    Ok(())
+}
+
+fn run_phpunit(crate_dir: &Path) -> Result<(), Error> {
+    let local_phpunit = crate_dir.join("vendor").join("bin").join("phpunit");
+
+    if local_phpunit.exists() {
+        let program = local_phpunit.to_str().context("Invalid crate directory")?;
+        run_tool(crate_dir, program, &[], "Running phpunit")
+    } else {
+        run_tool(crate_dir, "phpunit", &[], "Running phpunit")
+    }
+}
+
+/// Check whether the toolchain needed to run a language's tests is available.
+///
+/// Contributors aren't expected to install every language's toolchain, so
+/// the Go, C, Ruby, and PHP tests are skipped when something is missing
+/// instead of failing. JavaScript and Python are always required.
+fn missing_requirement(crate_dir: &Path, language: Language) -> Option<String> {
+    let programs: &[&str] = match language {
+        Language::Go => &["go"],
+        Language::C => &["cmake", "ctest"],
+        Language::Ruby if crate_dir.join("Gemfile").exists() => &["bundle"],
+        Language::Ruby => &["rspec"],
+        Language::Php if crate_dir.join("composer.json").exists() => &["php", "composer"],
+        Language::Php => &["phpunit"],
+        Language::JavaScript | Language::Python | Language::Rust => &[],
+    };
+
+    if let Some(program) = programs.iter().find(|program| !is_installed(program)) {
+        return Some(format!("{program} isn't installed"));
+    }
+
+    if language == Language::C && !wasmer_dir().join("include").join("wasmer.h").exists() {
+        return Some(format!(
+            "the Wasmer C API isn't installed in \"{}\" (set $WASMER_DIR to use a different directory)",
+            wasmer_dir().display()
+        ));
+    }
+
+    None
+}
+
+fn is_installed(program: &str) -> bool {
+    let result = Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    !matches!(result, Err(e) if e.kind() == io::ErrorKind::NotFound)
+}
+
+/// Where the Wasmer C API is installed, using the same lookup as the
+/// generated `CMakeLists.txt`.
+fn wasmer_dir() -> PathBuf {
+    match env::var_os("WASMER_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".wasmer"),
+    }
+}
+
+/// Run a program, failing if it doesn't exit successfully.
+fn run_tool(dir: &Path, program: &str, args: &[&str], description: &str) -> Result<(), Error> {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    tracing::info!(?cmd, "{description}");
+    let status = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .current_dir(dir)
+        .status()
+        .with_context(|| format!("Unable to run {program}. Is it installed?"))?;
+    anyhow::ensure!(status.success(), "\"{program} {}\" failed", args.join(" "));
+
+    Ok(())
+}
+
 fn shell() -> Command {
     if cfg!(target_os = "windows") {
         let mut cmd = Command::new("cmd");
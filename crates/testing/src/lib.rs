@@ -71,7 +71,37 @@
 //! - Run `yarn install` to install any missing dependencies
 //! - Execute tests using `yarn test` (the default is to use [jest])
 //!
+//! # Go
+//!
+//! When `*.go` files are detected, the generated module is added to the
+//! crate's `go.mod` (created with `go mod init` if necessary) using a
+//! `replace` directive, then the tests are executed with `go test`.
+//!
+//! # C
+//!
+//! When `*.c` or `*.cpp` files are detected, the crate's `CMakeLists.txt` is
+//! configured with `-DWASMER_PACK_BINDINGS=...` pointing at the generated
+//! bindings (which it should `add_subdirectory()`), compiled, and the tests
+//! are executed with `ctest`.
+//!
+//! # Ruby
+//!
+//! When `*.rb` files are detected, `bundle install` is run if there is a
+//! `Gemfile`, then the tests are executed with [rspec], with the generated
+//! gem's `lib/` directory on the load path.
+//!
+//! # PHP
+//!
+//! When `*.php` files are detected, `composer install` is run if there is a
+//! `composer.json` (which should pull in the generated bindings with a
+//! `path` repository), then the tests are executed with `phpunit`.
+//!
+//! The Go, C, Ruby, and PHP tests are skipped with a warning if their
+//! toolchain (or, for C, the Wasmer C API under `$WASMER_DIR`) isn't
+//! installed.
+//!
 //! [pytest]: https://docs.pytest.org/
+//! [rspec]: https://rspec.info/
 //! [jest]: https://jestjs.io/
 
 mod autodiscover;
@@ -41,6 +41,9 @@ pub fn generate_bindings(
     let codegen = Codegen {
         name,
         out_dir: Some(dest.to_path_buf()),
+        deny_extra_exports: false,
+        dependencies: None,
+        cache_dir: None,
         input: wapm_dir.to_path_buf(),
     };
     codegen.run(lang)?;
@@ -3,6 +3,7 @@ import {
     Result,
     Error as WasmerPackError,
     File,
+    Metadata,
     Package,
     BindingsOptions,
 } from "@wasmer/wasmer-pack/src/bindings/wasmer-pack/wasmer-pack.js";
@@ -26,7 +27,12 @@ describe("wasmer-pack bindings", () => {
         const pkg = unwrap(Package.fromWebc(wasmerPack, webc));
         const files: File[] = unwrap(pkg.generateJavascript());
 
-        const generatedFiles = files.map((f) => f.filename).sort();
+        // WebAssembly modules are named after a hash of their contents
+        const isWasm = (f: string) => /^package\/src\/wasm\/[0-9a-f]{64}\.wasm$/.test(f);
+        const filenames = files.map((f) => f.filename);
+        expect(filenames.filter(isWasm).length).toEqual(1);
+
+        const generatedFiles = filenames.filter(f => !isWasm(f)).sort();
         expect(generatedFiles).toEqual([
             "package/package.json",
             "package/src/bindings/index.d.ts",
@@ -34,7 +40,6 @@ describe("wasmer-pack bindings", () => {
             "package/src/bindings/wasmer-pack/intrinsics.js",
             "package/src/bindings/wasmer-pack/wasmer-pack.d.ts",
             "package/src/bindings/wasmer-pack/wasmer-pack.js",
-            "package/src/bindings/wasmer-pack/wasmer-pack.wasm",
             "package/src/index.d.ts",
             "package/src/index.js",
         ]);
@@ -52,6 +57,18 @@ describe("wasmer-pack bindings", () => {
             })
         );
     });
+
+    it("reports invalid version numbers as parse errors", async () => {
+        const wasmerPack = await bindings.wasmer_pack();
+
+        const result = Metadata.new(wasmerPack, "wasmer/example", "not-a-version");
+
+        expect(result.tag).toEqual("err");
+        if (result.tag == "err") {
+            expect(result.val.code).toEqual("parse");
+            expect(result.val.message).toContain("\"not-a-version\" is not a valid semver version number");
+        }
+    });
 });
 
 function unwrap<T>(result: Result<T, WasmerPackError>): T {
@@ -20,17 +20,20 @@ impl crate::wasmer_pack::Package for Package {
         metadata: Handle<Metadata>,
         libraries: Vec<wasmer_pack::Library>,
         commands: Vec<wasmer_pack::Command>,
-    ) -> Handle<crate::Package> {
+    ) -> Result<Handle<crate::Package>, wasmer_pack::Error> {
         let metadata = metadata.0.borrow().clone();
         let libraries = libraries.into_iter().map(|lib| lib.into()).collect();
         let commands = commands.into_iter().map(|cmd| cmd.into()).collect();
-        let pkg = original::Package::new(metadata, libraries, commands);
+        let pkg = original::Package::try_new(metadata, libraries, commands)
+            .map_err(original::Error::from)?;
 
-        Handle::new(Package(pkg))
+        Ok(Handle::new(Package(pkg)))
     }
 
     fn from_webc(bytes: Vec<u8>) -> Result<Handle<crate::Package>, wasmer_pack::Error> {
-        let webc = webc::Container::from_bytes(bytes).context("Invalid webc")?;
+        let webc = webc::Container::from_bytes(bytes)
+            .context("Invalid webc")
+            .map_err(original::Error::Load)?;
         let pkg = original::Package::from_webc(&webc)?;
         Ok(Handle::new(Package(pkg)))
     }
@@ -39,7 +42,10 @@ impl crate::wasmer_pack::Package for Package {
         &self,
         options: wasmer_pack::BindingsOptions,
     ) -> Result<Vec<wasmer_pack::File>, wasmer_pack::Error> {
-        let original_options = original::BindingsOptions { name: options.name };
+        let original_options = original::BindingsOptions {
+            name: options.name,
+            ..Default::default()
+        };
         let files = original::generate_javascript(&self.0, &original_options)?;
         Ok(unwrap_files(files))
     }
@@ -48,7 +54,10 @@ impl crate::wasmer_pack::Package for Package {
         &self,
         options: wasmer_pack::BindingsOptions,
     ) -> Result<Vec<wasmer_pack::File>, wasmer_pack::Error> {
-        let original_options = original::BindingsOptions { name: options.name };
+        let original_options = original::BindingsOptions {
+            name: options.name,
+            ..Default::default()
+        };
         let files = original::generate_python(&self.0, &original_options)?;
         Ok(unwrap_files(files))
     }
@@ -72,6 +81,14 @@ impl crate::wasmer_pack::Interface for Interface {
         Ok(Handle::new(Interface(exports)))
     }
 
+    fn from_component_wit(
+        name: String,
+        contents: String,
+    ) -> Result<Handle<Interface>, wasmer_pack::Error> {
+        let exports = original::Interface::from_component_wit(&name, &contents)?;
+        Ok(Handle::new(Interface(exports)))
+    }
+
     fn from_path(path: String) -> Result<Handle<Interface>, wasmer_pack::Error> {
         let exports = original::Interface::from_path(path)?;
         Ok(Handle::new(Interface(exports)))
@@ -85,7 +102,12 @@ impl crate::wasmer_pack::Metadata for Metadata {
         package_name: String,
         version: String,
     ) -> Result<Handle<crate::Metadata>, wasmer_pack::Error> {
-        let meta = original::Metadata::new(package_name.parse()?, version);
+        let package_name = package_name.parse().map_err(original::Error::Parse)?;
+        let version = version
+            .parse()
+            .with_context(|| format!("\"{version}\" is not a valid semver version number"))
+            .map_err(original::Error::Parse)?;
+        let meta = original::Metadata::new(package_name, version);
 
         Ok(Handle::new(Metadata(RefCell::new(meta))))
     }
@@ -93,6 +115,26 @@ impl crate::wasmer_pack::Metadata for Metadata {
     fn set_description(&self, description: String) {
         self.0.borrow_mut().description = Some(description);
     }
+
+    fn set_license(&self, license: String) {
+        self.0.borrow_mut().license = Some(license);
+    }
+
+    fn set_homepage(&self, homepage: String) {
+        self.0.borrow_mut().homepage = Some(homepage);
+    }
+
+    fn set_repository(&self, repository: String) {
+        self.0.borrow_mut().repository = Some(repository);
+    }
+
+    fn set_readme(&self, readme: String) {
+        self.0.borrow_mut().readme = Some(readme);
+    }
+
+    fn set_authors(&self, authors: Vec<String>) {
+        self.0.borrow_mut().authors = authors;
+    }
 }
 
 impl From<crate::wasmer_pack::Abi> for original::Abi {
@@ -106,7 +148,13 @@ impl From<crate::wasmer_pack::Abi> for original::Abi {
 
 impl From<Error> for crate::wasmer_pack::Error {
     fn from(e: Error) -> Self {
+        let code = match e.downcast_ref::<original::Error>() {
+            Some(original) => error_code(original),
+            None => wasmer_pack::ErrorCode::Other,
+        };
+
         crate::wasmer_pack::Error {
+            code,
             message: e.to_string(),
             verbose: format!("{e:?}"),
             causes: e.chain().map(|e| e.to_string()).collect(),
@@ -114,6 +162,22 @@ impl From<Error> for crate::wasmer_pack::Error {
     }
 }
 
+impl From<original::Error> for crate::wasmer_pack::Error {
+    fn from(e: original::Error) -> Self {
+        Error::from(e).into()
+    }
+}
+
+fn error_code(e: &original::Error) -> wasmer_pack::ErrorCode {
+    match e {
+        original::Error::Load(_) => wasmer_pack::ErrorCode::Load,
+        original::Error::Parse(_) => wasmer_pack::ErrorCode::Parse,
+        original::Error::Validation(_) => wasmer_pack::ErrorCode::Validation,
+        original::Error::Generate(_) => wasmer_pack::ErrorCode::Generate,
+        _ => wasmer_pack::ErrorCode::Other,
+    }
+}
+
 impl From<wasmer_pack::Library> for original::Library {
     fn from(lib: wasmer_pack::Library) -> Self {
         let wasmer_pack::Library {
@@ -137,7 +201,7 @@ impl From<wasmer_pack::Library> for original::Library {
 impl From<wasmer_pack::Command> for original::Command {
     fn from(cmd: wasmer_pack::Command) -> Self {
         let wasmer_pack::Command { name, wasm } = cmd;
-        original::Command { name, wasm }
+        original::Command::new(name, wasm)
     }
 }
 
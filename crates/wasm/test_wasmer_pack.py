@@ -8,6 +8,8 @@ from wasmer_pack.bindings.wasmer_pack import (
     BindingsOptions,
     Err,
     Error,
+    ErrorCode,
+    Metadata,
     Ok,
     Package,
     T,
@@ -44,14 +46,27 @@ def test_generate_bindings_for_wasmer_pack():
             'wasmer_pack/bindings/__init__.py',
             'wasmer_pack/bindings/wasmer_pack/__init__.py',
             'wasmer_pack/bindings/wasmer_pack/bindings.py',
-            'wasmer_pack/bindings/wasmer_pack/wasmer-pack-wasm.wasm',
             'wasmer_pack/py.typed',
         }
         filenames = {f.filename for f in files}
+        # WebAssembly modules are named after a hash of their contents
+        wasm_files = {f for f in filenames if f.startswith('wasmer_pack/wasm/')}
         print("Expected", expected)
         print("Actual", filenames)
 
-        assert filenames == expected
+        assert filenames - wasm_files == expected
+        assert len(wasm_files) == 1
+        assert all(f.endswith('.wasm') for f in wasm_files)
 
     finally:
         pkg.drop()
+
+
+def test_invalid_version_numbers_are_parse_errors():
+    wasmer_pack = bindings.wasmer_pack()
+
+    result = Metadata.new(wasmer_pack, "wasmer/example", "not-a-version")
+
+    assert isinstance(result, Err)
+    assert result.value.code == ErrorCode.PARSE
+    assert '"not-a-version" is not a valid semver version number' in result.value.message
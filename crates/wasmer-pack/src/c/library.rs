@@ -0,0 +1,1733 @@
+//! Generate the C declarations and glue code for a single library.
+//!
+//! Like the Go generator, there is no `wai-bindgen` backend for the Wasmer C
+//! API, so this module walks each function's canonical ABI instructions
+//! itself.
+
+use std::collections::BTreeSet;
+
+use heck::{ToShoutySnakeCase, ToSnakeCase};
+use wai_parser::{
+    abi::{AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType},
+    Docs, Function, Interface, SizeAlign, Type, TypeDefKind, TypeId,
+};
+
+use crate::{
+    codegen::{
+        self, parenthesize, replace_ident, uses_ident, FunctionCode, Layout, Lifted, ITER_BASE,
+        ITER_ELEM,
+    },
+    Library,
+};
+
+/// Reserved words in C and C++, which can't be used as identifiers in a
+/// header that both languages include.
+const KEYWORDS: &[&str] = &[
+    "alignas",
+    "alignof",
+    "and",
+    "asm",
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "constexpr",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "explicit",
+    "export",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "friend",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "mutable",
+    "namespace",
+    "new",
+    "noexcept",
+    "not",
+    "nullptr",
+    "operator",
+    "or",
+    "private",
+    "protected",
+    "public",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typedef",
+    "typename",
+    "union",
+    "unsigned",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+    "xor",
+];
+
+/// Variables declared by the generated functions, which a parameter mustn't
+/// shadow.
+const GLUE_NAMES: &[&str] = &["args", "data", "env", "lib", "results", "ret"];
+
+/// The C code generated for a library.
+pub(crate) struct LibraryCode {
+    /// Declarations which go in the package's header.
+    pub(crate) header: String,
+    /// Definitions which go in the package's source file.
+    pub(crate) source: String,
+}
+
+/// Generate the C code for a library, where `pkg` is the prefix used by
+/// package-wide items like strings.
+pub(crate) fn generate(lib: &Library, pkg: &str, uses_volumes: bool) -> LibraryCode {
+    let prefix = ident(&lib.interface_name().to_snake_case());
+    let names = Names { pkg, lib: &prefix };
+    let mut gen = LibraryGenerator {
+        names,
+        header: String::new(),
+        source: String::new(),
+        defined: BTreeSet::new(),
+    };
+
+    let imports: Vec<&Interface> = lib.imports.iter().map(|i| &i.parsed).collect();
+
+    gen.type_definitions(&lib.exports.parsed);
+    for iface in &imports {
+        gen.type_definitions(iface);
+    }
+    for iface in &imports {
+        gen.host_struct(iface);
+    }
+
+    gen.instance_declarations(&lib.exports.parsed, &imports, lib.interface_name());
+    gen.instance_struct(&lib.exports.parsed, &imports);
+    for iface in &imports {
+        gen.import_callbacks(iface);
+    }
+    gen.import_resolver(&imports);
+    gen.constructor(lib, &imports, uses_volumes);
+    gen.destructor();
+    gen.exported_functions(&lib.exports.parsed);
+
+    LibraryCode {
+        header: indent(&gen.header),
+        source: indent(&gen.source),
+    }
+}
+
+/// The prefixes used when naming things in the generated code.
+#[derive(Debug, Copy, Clone)]
+struct Names<'a> {
+    /// The prefix for package-wide items (i.e. `wasmer_pack`).
+    pkg: &'a str,
+    /// The prefix for everything belonging to the library (i.e. `calculator`).
+    lib: &'a str,
+}
+
+impl Names<'_> {
+    /// The C type used to represent a WAI type.
+    fn c_type(&self, iface: &Interface, ty: &Type) -> String {
+        match ty {
+            Type::Unit | Type::U8 => "uint8_t".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::U16 => "uint16_t".to_string(),
+            Type::U32 | Type::Char => "uint32_t".to_string(),
+            Type::U64 => "uint64_t".to_string(),
+            Type::S8 => "int8_t".to_string(),
+            Type::S16 => "int16_t".to_string(),
+            Type::S32 => "int32_t".to_string(),
+            Type::S64 => "int64_t".to_string(),
+            Type::Float32 => "float".to_string(),
+            Type::Float64 => "double".to_string(),
+            Type::String => format!("{}_string_t", self.pkg),
+            Type::Handle(_) => unreachable!("resources are rejected during validation"),
+            Type::Id(id) => format!("{}_{}_t", self.lib, type_ident(iface, *id)),
+        }
+    }
+
+    /// The function which releases any memory owned by a value of this type.
+    fn free_fn(&self, iface: &Interface, ty: &Type) -> Option<String> {
+        match ty {
+            Type::String => Some(format!("{}_string_free", self.pkg)),
+            Type::Id(id) if owns_memory(iface, ty) => {
+                Some(format!("{}_{}_free", self.lib, type_ident(iface, *id)))
+            }
+            _ => None,
+        }
+    }
+
+    /// A parameter declaration, where anything bigger than a number is
+    /// passed by pointer.
+    fn param(&self, iface: &Interface, name: &str, ty: &Type) -> String {
+        if is_scalar(iface, ty) {
+            format!("{} {name}", self.c_type(iface, ty))
+        } else {
+            format!("const {} *{name}", self.c_type(iface, ty))
+        }
+    }
+
+    fn params(&self, iface: &Interface, func: &Function) -> String {
+        func.params
+            .iter()
+            .map(|(name, ty)| format!(", {}", self.param(iface, &param_name(name), ty)))
+            .collect()
+    }
+
+    /// The trailing out-parameter used to return a function's result.
+    fn ret(&self, iface: &Interface, func: &Function) -> String {
+        match func.result {
+            Type::Unit => String::new(),
+            ref ty => format!(", {} *ret", self.c_type(iface, ty)),
+        }
+    }
+}
+
+struct LibraryGenerator<'a> {
+    names: Names<'a>,
+    header: String,
+    source: String,
+    /// The types that have already been defined, so types used by both the
+    /// exports and imports are only defined once.
+    defined: BTreeSet<String>,
+}
+
+impl LibraryGenerator<'_> {
+    fn push_header(&mut self, code: &str) {
+        self.header.push('\n');
+        self.header.push_str(code);
+    }
+
+    fn push_source(&mut self, code: &str) {
+        self.source.push('\n');
+        self.source.push_str(code);
+    }
+
+    fn type_definitions(&mut self, iface: &Interface) {
+        for (id, _) in iface.types.iter() {
+            self.define(iface, id);
+        }
+    }
+
+    /// Define a type, making sure the types it contains are defined first.
+    fn define(&mut self, iface: &Interface, id: TypeId) {
+        let type_ident = type_ident(iface, id);
+        if !self.defined.insert(type_ident.clone()) {
+            return;
+        }
+
+        let def = &iface.types[id];
+        for dep in dependencies(&def.kind) {
+            if let Type::Id(dep) = dep {
+                self.define(iface, *dep);
+            }
+        }
+
+        let names = self.names;
+        let ty = Type::Id(id);
+        let tag = format!("{}_{type_ident}", names.lib);
+        let name = names.c_type(iface, &ty);
+        let consts = tag.to_shouty_snake_case();
+        let field = |ty: &Type, field: &str| format!("{} {field};\n", names.c_type(iface, ty));
+
+        let mut code = String::new();
+        push_docs(&mut code, &def.docs);
+
+        match &def.kind {
+            TypeDefKind::Record(record) => {
+                code.push_str(&format!("typedef struct {tag} {{\n"));
+                for f in &record.fields {
+                    push_docs(&mut code, &f.docs);
+                    code.push_str(&field(&f.ty, &ident(&f.name.to_snake_case())));
+                }
+                if record.fields.is_empty() {
+                    code.push_str("uint8_t unused;\n");
+                }
+                code.push_str(&format!("}} {name};\n"));
+            }
+            TypeDefKind::Tuple(tuple) => {
+                code.push_str(&format!("typedef struct {tag} {{\n"));
+                for (i, ty) in tuple.types.iter().enumerate() {
+                    code.push_str(&field(ty, &format!("f{i}")));
+                }
+                if tuple.types.is_empty() {
+                    code.push_str("uint8_t unused;\n");
+                }
+                code.push_str(&format!("}} {name};\n"));
+            }
+            TypeDefKind::Flags(flags) => {
+                let repr = uint_repr(flags.flags.len().saturating_sub(1));
+                code.push_str(&format!("typedef {repr} {name};\n"));
+                for (i, flag) in flags.flags.iter().enumerate() {
+                    push_docs(&mut code, &flag.docs);
+                    code.push_str(&format!(
+                        "#define {consts}_{} (({name}) 1 << {i})\n",
+                        flag.name.to_shouty_snake_case()
+                    ));
+                }
+            }
+            TypeDefKind::Enum(e) => {
+                code.push_str(&format!("typedef {} {name};\n", uint_repr(e.cases.len())));
+                for (i, case) in e.cases.iter().enumerate() {
+                    push_docs(&mut code, &case.docs);
+                    code.push_str(&format!(
+                        "#define {consts}_{} {i}\n",
+                        case.name.to_shouty_snake_case()
+                    ));
+                }
+            }
+            TypeDefKind::Variant(variant) => {
+                let members = variant
+                    .cases
+                    .iter()
+                    .map(|c| (ident(&c.name.to_snake_case()), &c.ty));
+                push_tagged_union(&mut code, iface, names, &tag, variant.cases.len(), members);
+                code.push_str(&format!("}} {name};\n"));
+                for (i, case) in variant.cases.iter().enumerate() {
+                    push_docs(&mut code, &case.docs);
+                    code.push_str(&format!(
+                        "#define {consts}_{} {i}\n",
+                        case.name.to_shouty_snake_case()
+                    ));
+                }
+            }
+            TypeDefKind::Union(union) => {
+                let members = union
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (format!("f{i}"), &c.ty));
+                push_tagged_union(&mut code, iface, names, &tag, union.cases.len(), members);
+                code.push_str(&format!("}} {name};\n"));
+            }
+            TypeDefKind::Option(payload) => {
+                code.push_str(&format!("typedef struct {tag} {{\nbool is_some;\n"));
+                if *payload != Type::Unit {
+                    code.push_str(&field(payload, "val"));
+                }
+                code.push_str(&format!("}} {name};\n"));
+            }
+            TypeDefKind::Expected(e) => {
+                code.push_str(&format!("typedef struct {tag} {{\nbool is_err;\n"));
+                if e.ok != Type::Unit || e.err != Type::Unit {
+                    code.push_str("union {\n");
+                    if e.ok != Type::Unit {
+                        code.push_str(&field(&e.ok, "ok"));
+                    }
+                    if e.err != Type::Unit {
+                        code.push_str(&field(&e.err, "err"));
+                    }
+                    code.push_str("} val;\n");
+                }
+                code.push_str(&format!("}} {name};\n"));
+            }
+            TypeDefKind::List(element) => {
+                code.push_str(&format!(
+                    "typedef struct {tag} {{\n{} *ptr;\nsize_t len;\n}} {name};\n",
+                    names.c_type(iface, element)
+                ));
+            }
+            TypeDefKind::Type(aliased) => {
+                code.push_str(&format!(
+                    "typedef {} {name};\n",
+                    names.c_type(iface, aliased)
+                ));
+            }
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                unreachable!("futures and streams are rejected during validation")
+            }
+        }
+
+        self.push_header(&code);
+
+        if let Some(free) = names.free_fn(iface, &ty) {
+            self.push_header(&format!(
+                "// Release any memory owned by a {name}.\nvoid {free}({name} *ptr);\n"
+            ));
+            self.free_definition(iface, &def.kind, &free, &name);
+        }
+    }
+
+    fn free_definition(&mut self, iface: &Interface, kind: &TypeDefKind, free: &str, name: &str) {
+        let names = self.names;
+        let release = |ty: &Type, place: &str| {
+            names
+                .free_fn(iface, ty)
+                .map(|f| format!("{f}(&{place});\n"))
+                .unwrap_or_default()
+        };
+
+        let mut body = String::new();
+        match kind {
+            TypeDefKind::Record(record) => {
+                for f in &record.fields {
+                    let place = format!("ptr->{}", ident(&f.name.to_snake_case()));
+                    body.push_str(&release(&f.ty, &place));
+                }
+            }
+            TypeDefKind::Tuple(tuple) => {
+                for (i, ty) in tuple.types.iter().enumerate() {
+                    body.push_str(&release(ty, &format!("ptr->f{i}")));
+                }
+            }
+            TypeDefKind::Variant(variant) => {
+                let cases = variant
+                    .cases
+                    .iter()
+                    .map(|c| (ident(&c.name.to_snake_case()), &c.ty));
+                push_case_frees(&mut body, &release, cases);
+            }
+            TypeDefKind::Union(union) => {
+                let cases = union
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (format!("f{i}"), &c.ty));
+                push_case_frees(&mut body, &release, cases);
+            }
+            TypeDefKind::Option(payload) => {
+                body.push_str(&format!(
+                    "if (ptr->is_some) {{\n{}}}\n",
+                    release(payload, "ptr->val")
+                ));
+            }
+            TypeDefKind::Expected(e) => {
+                let ok = release(&e.ok, "ptr->val.ok");
+                let err = release(&e.err, "ptr->val.err");
+                match (ok.is_empty(), err.is_empty()) {
+                    (false, false) => {
+                        body.push_str(&format!("if (ptr->is_err) {{\n{err}}} else {{\n{ok}}}\n"))
+                    }
+                    (true, false) => body.push_str(&format!("if (ptr->is_err) {{\n{err}}}\n")),
+                    (false, true) => body.push_str(&format!("if (!ptr->is_err) {{\n{ok}}}\n")),
+                    (true, true) => {}
+                }
+            }
+            TypeDefKind::List(element) => {
+                let elem = release(element, "ptr->ptr[i]");
+                if !elem.is_empty() {
+                    body.push_str(&format!(
+                        "for (size_t i = 0; i < ptr->len; i++) {{\n{elem}}}\n"
+                    ));
+                }
+                body.push_str("free(ptr->ptr);\nptr->ptr = NULL;\nptr->len = 0;\n");
+            }
+            TypeDefKind::Type(aliased) => body.push_str(&release(aliased, "*ptr")),
+            TypeDefKind::Flags(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Future(_)
+            | TypeDefKind::Stream(_) => {
+                unreachable!("{kind:?} values never own memory")
+            }
+        }
+
+        self.push_source(&format!("void {free}({name} *ptr) {{\n{body}}}\n"));
+    }
+
+    /// The struct a host fills in to provide an imported interface.
+    fn host_struct(&mut self, iface: &Interface) {
+        let names = self.names;
+        let tag = format!("{}_{}", names.lib, iface.name.to_snake_case());
+        let mut code = format!(
+            "// The host's implementation of the \"{}\" interface.
+//
+// Arguments are only borrowed for the duration of a call. Results are owned
+// by the bindings, which release them after passing them to WebAssembly.
+typedef struct {tag} {{
+// Passed as the first argument to each function.
+void *data;
+",
+            iface.name
+        );
+
+        for func in &iface.functions {
+            push_docs(&mut code, &func.docs);
+            code.push_str(&format!(
+                "wasm_trap_t *(*{})(void *data{}{});\n",
+                field_name(&func.name),
+                names.params(iface, func),
+                names.ret(iface, func),
+            ));
+        }
+
+        code.push_str(&format!("}} {tag}_t;\n"));
+        self.push_header(&code);
+    }
+
+    fn instance_declarations(
+        &mut self,
+        exports: &Interface,
+        imports: &[&Interface],
+        interface_name: &str,
+    ) {
+        let lib = self.names.lib;
+        let mut code = format!(
+            "// An instance of the \"{interface_name}\" library.
+//
+// Arguments passed to its functions are borrowed, while results are owned by
+// the caller and must be released with the matching free function.
+typedef struct {lib} {lib}_t;
+
+// Instantiate the \"{interface_name}\" library.
+//
+// If `module` is NULL, the WebAssembly module bundled with this package is
+// loaded.
+"
+        );
+        if !imports.is_empty() {
+            code.push_str("//\n// The host's implementations of any imported interfaces are copied, so\n// their `data` must outlive the instance.\n");
+        }
+        code.push_str(&format!(
+            "wasm_trap_t *{lib}_new(wasm_store_t *store, const wasm_module_t *module{}, {lib}_t **ret);\n",
+            host_params(lib, imports)
+        ));
+        code.push_str(&format!(
+            "\n// Destroy an instance of the \"{interface_name}\" library.\nvoid {lib}_delete({lib}_t *lib);\n"
+        ));
+        self.push_header(&code);
+
+        for func in &exports.functions {
+            let mut code = String::new();
+            if func.docs.contents.is_some() {
+                push_docs(&mut code, &func.docs);
+            } else {
+                code.push_str(&format!("// Call the \"{}\" function.\n", func.name));
+            }
+            code.push_str(&format!("{};\n", self.export_signature(exports, func)));
+            self.push_header(&code);
+        }
+    }
+
+    fn export_signature(&self, iface: &Interface, func: &Function) -> String {
+        let names = self.names;
+        format!(
+            "wasm_trap_t *{lib}_{}({lib}_t *lib{}{})",
+            func.name.to_snake_case(),
+            names.params(iface, func),
+            names.ret(iface, func),
+            lib = names.lib,
+        )
+    }
+
+    fn instance_struct(&mut self, exports: &Interface, imports: &[&Interface]) {
+        let lib = self.names.lib;
+        let mut code = format!(
+            "struct {lib} {{\nabi_env_t env;\nwasi_env_t *wasi_env;\nwasm_instance_t *instance;\nwasm_extern_vec_t exports;\n"
+        );
+        for iface in imports {
+            code.push_str(&format!(
+                "{lib}_{}_t {};\n",
+                iface.name.to_snake_case(),
+                field_name(&iface.name)
+            ));
+        }
+        for func in &exports.functions {
+            code.push_str(&format!("wasm_func_t *{};\n", field_name(&func.name)));
+        }
+        code.push_str("};\n");
+        self.push_source(&code);
+    }
+
+    /// Adapt raw WebAssembly arguments to a call to the host's
+    /// implementation of each imported function.
+    fn import_callbacks(&mut self, iface: &Interface) {
+        let lib = self.names.lib;
+        let mut sizes = SizeAlign::default();
+        sizes.fill(iface);
+        let host = format!("lib->{}", field_name(&iface.name));
+
+        for func in &iface.functions {
+            let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+            let params = sig
+                .params
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| format!("args->data[{i}].of.{}", value_field(*ty)))
+                .collect();
+            let mut bindgen = FunctionBindgen::new(self.names, &sizes, params);
+            bindgen.callee = format!("{host}.{}", field_name(&func.name));
+            bindgen.callee_data = format!("{host}.data");
+            bindgen.wasm_results = sig.results.clone();
+            iface.call(
+                AbiVariant::GuestImport,
+                LiftLower::LiftArgsLowerResults,
+                func,
+                &mut bindgen,
+            );
+
+            let mut code = format!(
+                "static wasm_trap_t *{}(void *data, const wasm_val_vec_t *args, wasm_val_vec_t *results) {{\n{lib}_t *lib = data;\n",
+                callback_name(lib, iface, func),
+            );
+            if uses_ident(&bindgen.code.src, "env") {
+                code.push_str("abi_env_t *env = &lib->env;\n");
+            }
+            code.push_str(&bindgen.code.src);
+            code.push_str("return NULL;\n}\n");
+            self.push_source(&code);
+        }
+    }
+
+    /// Create the host functions a library imports, on demand.
+    fn import_resolver(&mut self, imports: &[&Interface]) {
+        let lib = self.names.lib;
+        let mut code = format!(
+            "static wasm_extern_t *{lib}_import(void *data, const wasm_name_t *module, const wasm_name_t *name) {{\n"
+        );
+
+        if imports.is_empty() {
+            code.push_str("(void) data;\n(void) module;\n(void) name;\n");
+        } else {
+            code.push_str(&format!("{lib}_t *lib = data;\n"));
+        }
+
+        for iface in imports {
+            for func in &iface.functions {
+                let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+                code.push_str(&format!(
+                    "if (name_is(module, {}) && name_is(name, {})) {{\n",
+                    quote(&iface.name),
+                    quote(&func.name)
+                ));
+                let params = valkinds(&mut code, "params", &sig.params);
+                let results = valkinds(&mut code, "results", &sig.results);
+                code.push_str(&format!(
+                    "wasm_func_t *func = abi_host_function(lib->env.store, {params}, {results}, {}, lib);\nreturn wasm_func_as_extern(func);\n}}\n",
+                    callback_name(lib, iface, func),
+                ));
+            }
+        }
+
+        code.push_str("return NULL;\n}\n");
+        self.push_source(&code);
+    }
+
+    fn constructor(&mut self, library: &Library, imports: &[&Interface], uses_volumes: bool) {
+        let lib = self.names.lib;
+        let wasm = super::wasm_ident(&library.module.wasm);
+        let mut code = format!(
+            "wasm_trap_t *{lib}_new(wasm_store_t *store, const wasm_module_t *module{}, {lib}_t **ret) {{
+wasm_trap_t *trap = NULL;
+wasm_module_t *bundled = NULL;
+if (module == NULL) {{
+trap = load_module(store, {wasm}, {wasm}_len, &bundled);
+if (trap != NULL) {{
+return trap;
+}}
+module = bundled;
+}}
+
+{lib}_t *lib = abi_alloc(1, sizeof({lib}_t));
+lib->env.store = store;
+",
+            host_params(lib, imports),
+        );
+        for iface in imports {
+            let field = field_name(&iface.name);
+            code.push_str(&format!("lib->{field} = *{field};\n"));
+        }
+
+        if library.requires_wasi() {
+            let config = format!(
+                "wasi_config_t *config = wasi_config_new({});\n",
+                quote(&library.interface_name().to_snake_case())
+            );
+            let wasi_env = "lib->wasi_env = wasi_env_new(store, config);
+if (lib->wasi_env == NULL) {
+trap = last_error_trap(store, \"Unable to create the WASI environment\");
+}
+";
+            if uses_volumes {
+                code.push_str(&format!(
+                    "\ntrap = extract_volumes(store);\nif (trap == NULL) {{\n{config}mount_volumes(config);\n{wasi_env}}}\n"
+                ));
+            } else {
+                code.push_str(&format!("\n{config}{wasi_env}"));
+            }
+        }
+
+        code.push_str(&format!(
+            "
+if (trap == NULL) {{
+trap = instantiate(store, module, lib->wasi_env, {lib}_import, lib, &lib->instance, &lib->exports);
+}}
+if (trap == NULL) {{
+abi_bind(&lib->env, module, &lib->exports);
+}}
+"
+        ));
+        for func in &library.exports.parsed.functions {
+            code.push_str(&format!(
+                "if (trap == NULL) {{\ntrap = abi_export_func(store, module, &lib->exports, {}, &lib->{});\n}}\n",
+                quote(&func.name),
+                field_name(&func.name)
+            ));
+        }
+        code.push_str(&format!(
+            "
+if (bundled != NULL) {{
+wasm_module_delete(bundled);
+}}
+if (trap != NULL) {{
+{lib}_delete(lib);
+return trap;
+}}
+
+*ret = lib;
+return NULL;
+}}
+"
+        ));
+
+        self.push_source(&code);
+    }
+
+    fn destructor(&mut self) {
+        let lib = self.names.lib;
+        self.push_source(&format!(
+            "void {lib}_delete({lib}_t *lib) {{
+if (lib == NULL) {{
+return;
+}}
+wasm_extern_vec_delete(&lib->exports);
+if (lib->instance != NULL) {{
+wasm_instance_delete(lib->instance);
+}}
+if (lib->wasi_env != NULL) {{
+wasi_env_delete(lib->wasi_env);
+}}
+free(lib);
+}}
+"
+        ));
+    }
+
+    fn exported_functions(&mut self, iface: &Interface) {
+        let mut sizes = SizeAlign::default();
+        sizes.fill(iface);
+
+        for func in &iface.functions {
+            let params = func
+                .params
+                .iter()
+                .map(|(name, ty)| {
+                    let name = param_name(name);
+                    if is_scalar(iface, ty) {
+                        name
+                    } else {
+                        format!("(*{name})")
+                    }
+                })
+                .collect();
+            let mut bindgen = FunctionBindgen::new(self.names, &sizes, params);
+            bindgen.callee = format!("lib->{}", field_name(&func.name));
+            iface.call(
+                AbiVariant::GuestExport,
+                LiftLower::LowerArgsLiftResults,
+                func,
+                &mut bindgen,
+            );
+
+            let mut code = format!("{} {{\n", self.export_signature(iface, func));
+            if uses_ident(&bindgen.code.src, "env") {
+                code.push_str("abi_env_t *env = &lib->env;\n");
+            }
+            code.push_str(&bindgen.code.src);
+            code.push_str("return NULL;\n}\n");
+            self.push_source(&code);
+        }
+    }
+}
+
+/// The parameters used to pass the host's implementation of each imported
+/// interface to a library's constructor.
+fn host_params(lib: &str, imports: &[&Interface]) -> String {
+    imports
+        .iter()
+        .map(|iface| {
+            format!(
+                ", const {lib}_{}_t *{}",
+                iface.name.to_snake_case(),
+                field_name(&iface.name)
+            )
+        })
+        .collect()
+}
+
+fn callback_name(lib: &str, iface: &Interface, func: &Function) -> String {
+    format!(
+        "{lib}_{}_{}_callback",
+        iface.name.to_snake_case(),
+        func.name.to_snake_case()
+    )
+}
+
+/// Declare a static array of `wasm_valkind_t`s, returning the arguments
+/// used to pass it to a function.
+fn valkinds(code: &mut String, name: &str, types: &[WasmType]) -> String {
+    if types.is_empty() {
+        return "NULL, 0".to_string();
+    }
+
+    let kinds: Vec<_> = types.iter().map(|ty| valkind(*ty)).collect();
+    code.push_str(&format!(
+        "static const wasm_valkind_t {name}[] = {{{}}};\n",
+        kinds.join(", ")
+    ));
+    format!("{name}, {}", types.len())
+}
+
+fn valkind(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "WASM_I32",
+        WasmType::I64 => "WASM_I64",
+        WasmType::F32 => "WASM_F32",
+        WasmType::F64 => "WASM_F64",
+    }
+}
+
+fn value_field(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "i32",
+        WasmType::I64 => "i64",
+        WasmType::F32 => "f32",
+        WasmType::F64 => "f64",
+    }
+}
+
+fn c_wasm_type(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "int32_t",
+        WasmType::I64 => "int64_t",
+        WasmType::F32 => "float",
+        WasmType::F64 => "double",
+    }
+}
+
+/// The smallest unsigned integer type which can hold `max`.
+fn uint_repr(max: usize) -> &'static str {
+    if max <= u8::MAX as usize {
+        "uint8_t"
+    } else if max <= u16::MAX as usize {
+        "uint16_t"
+    } else if max <= u32::MAX as usize {
+        "uint32_t"
+    } else {
+        "uint64_t"
+    }
+}
+
+/// Escape an identifier which would clash with a C or C++ keyword.
+pub(crate) fn ident(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
+
+/// The name of a struct field (i.e. for a function or host interface).
+fn field_name(name: &str) -> String {
+    ident(&name.to_snake_case())
+}
+
+/// Turn a WAI parameter name into a C identifier that won't clash with
+/// keywords or the names used by the glue code.
+fn param_name(name: &str) -> String {
+    codegen::avoid_reserved(ident(&name.to_snake_case()), &[GLUE_NAMES])
+}
+
+/// The identifier used when naming a type, where anonymous types are named
+/// after their contents (i.e. `list<u32>` becomes `list_u32`).
+fn type_ident(iface: &Interface, id: TypeId) -> String {
+    let def = &iface.types[id];
+    if let Some(name) = &def.name {
+        return name.to_snake_case();
+    }
+
+    match &def.kind {
+        TypeDefKind::List(ty) => format!("list_{}", mangle(iface, ty)),
+        TypeDefKind::Option(ty) => format!("option_{}", mangle(iface, ty)),
+        TypeDefKind::Expected(e) => {
+            format!(
+                "expected_{}_{}",
+                mangle(iface, &e.ok),
+                mangle(iface, &e.err)
+            )
+        }
+        TypeDefKind::Tuple(tuple) => {
+            let mut name = format!("tuple{}", tuple.types.len());
+            for ty in &tuple.types {
+                name.push('_');
+                name.push_str(&mangle(iface, ty));
+            }
+            name
+        }
+        TypeDefKind::Type(ty) => mangle(iface, ty),
+        other => unreachable!("anonymous {other:?} types aren't possible in WAI"),
+    }
+}
+
+fn mangle(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Unit => "unit".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::U8 => "u8".to_string(),
+        Type::U16 => "u16".to_string(),
+        Type::U32 => "u32".to_string(),
+        Type::U64 => "u64".to_string(),
+        Type::S8 => "s8".to_string(),
+        Type::S16 => "s16".to_string(),
+        Type::S32 => "s32".to_string(),
+        Type::S64 => "s64".to_string(),
+        Type::Float32 => "f32".to_string(),
+        Type::Float64 => "f64".to_string(),
+        Type::Char => "char".to_string(),
+        Type::String => "string".to_string(),
+        Type::Handle(_) => unreachable!("resources are rejected during validation"),
+        Type::Id(id) => type_ident(iface, *id),
+    }
+}
+
+/// The types stored inside a type.
+fn dependencies(kind: &TypeDefKind) -> Vec<&Type> {
+    match kind {
+        TypeDefKind::Record(record) => record.fields.iter().map(|f| &f.ty).collect(),
+        TypeDefKind::Tuple(tuple) => tuple.types.iter().collect(),
+        TypeDefKind::Variant(variant) => variant.cases.iter().map(|c| &c.ty).collect(),
+        TypeDefKind::Union(union) => union.cases.iter().map(|c| &c.ty).collect(),
+        TypeDefKind::Option(ty) | TypeDefKind::List(ty) | TypeDefKind::Type(ty) => vec![ty],
+        TypeDefKind::Expected(e) => vec![&e.ok, &e.err],
+        TypeDefKind::Flags(_)
+        | TypeDefKind::Enum(_)
+        | TypeDefKind::Future(_)
+        | TypeDefKind::Stream(_) => Vec::new(),
+    }
+}
+
+/// Does a value of this type own a string or list that needs to be freed?
+fn owns_memory(iface: &Interface, ty: &Type) -> bool {
+    let id = match ty {
+        Type::String => return true,
+        Type::Id(id) => *id,
+        _ => return false,
+    };
+
+    match &iface.types[id].kind {
+        TypeDefKind::List(_) => true,
+        TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => false,
+        kind => dependencies(kind)
+            .into_iter()
+            .any(|ty| owns_memory(iface, ty)),
+    }
+}
+
+/// Can a value be passed around by value, rather than by pointer?
+fn is_scalar(iface: &Interface, ty: &Type) -> bool {
+    match ty {
+        Type::String => false,
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Type(ty) => is_scalar(iface, ty),
+            TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => true,
+            _ => false,
+        },
+        _ => true,
+    }
+}
+
+fn push_docs(code: &mut String, docs: &Docs) {
+    if let Some(contents) = &docs.contents {
+        for line in contents.trim().lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                code.push_str("//\n");
+            } else {
+                code.push_str(&format!("// {line}\n"));
+            }
+        }
+    }
+}
+
+/// Variants and unions are a tag followed by a union containing each case's
+/// payload.
+fn push_tagged_union<'a>(
+    code: &mut String,
+    iface: &Interface,
+    names: Names<'_>,
+    tag: &str,
+    case_count: usize,
+    members: impl Iterator<Item = (String, &'a Type)>,
+) {
+    code.push_str(&format!(
+        "typedef struct {tag} {{\n{} tag;\n",
+        uint_repr(case_count.saturating_sub(1))
+    ));
+
+    let members: Vec<_> = members.filter(|(_, ty)| **ty != Type::Unit).collect();
+    if !members.is_empty() {
+        code.push_str("union {\n");
+        for (name, ty) in members {
+            code.push_str(&format!("{} {name};\n", names.c_type(iface, ty)));
+        }
+        code.push_str("} val;\n");
+    }
+}
+
+fn push_case_frees<'a>(
+    body: &mut String,
+    release: &dyn Fn(&Type, &str) -> String,
+    cases: impl Iterator<Item = (String, &'a Type)>,
+) {
+    body.push_str("switch (ptr->tag) {\n");
+    for (i, (member, ty)) in cases.enumerate() {
+        let free = release(ty, &format!("ptr->val.{member}"));
+        if !free.is_empty() {
+            body.push_str(&format!("case {i}: {{\n{free}break;\n}}\n"));
+        }
+    }
+    body.push_str("default: {\nbreak;\n}\n}\n");
+}
+
+/// Quote a string so it can be used as a C string literal.
+pub(crate) fn quote(s: &str) -> String {
+    // Octal escapes never consume more than 3 digits, unlike "\x"
+    codegen::quote(s, |c| {
+        c.is_ascii_control().then(|| format!("\\{:03o}", c as u32))
+    })
+}
+
+/// Indent C source code based on its braces, leaving preprocessor directives
+/// in the first column.
+pub(crate) fn indent(src: &str) -> String {
+    codegen::indent(src, "    ", |line| {
+        let is_code = !line.starts_with("//");
+        Layout {
+            closes: is_code && line.starts_with('}'),
+            opens: is_code && line.ends_with('{'),
+            unindented: line.starts_with('#'),
+            ..Layout::default()
+        }
+    })
+}
+
+/// Generates the C statements that pass a function's arguments and results
+/// across the canonical ABI.
+struct FunctionBindgen<'a> {
+    names: Names<'a>,
+    sizes: &'a SizeAlign,
+    params: Vec<String>,
+    /// The function to invoke for `CallWasm` or `CallInterface`.
+    callee: String,
+    /// The `data` pointer passed to the host's implementation of an import.
+    callee_data: String,
+    /// The WebAssembly results returned by a host function.
+    wasm_results: Vec<WasmType>,
+    code: FunctionCode,
+    /// Statements which release the host's results once they have been
+    /// lowered.
+    cleanup: Vec<String>,
+}
+
+impl<'a> FunctionBindgen<'a> {
+    fn new(names: Names<'a>, sizes: &'a SizeAlign, params: Vec<String>) -> Self {
+        FunctionBindgen {
+            names,
+            sizes,
+            params,
+            callee: String::new(),
+            callee_data: String::new(),
+            wasm_results: Vec::new(),
+            code: FunctionCode::default(),
+            cleanup: Vec::new(),
+        }
+    }
+
+    /// Declare a temporary of type `ty`, initialized with `expr`.
+    fn bind(&mut self, ty: &str, prefix: &str, expr: impl AsRef<str>) -> String {
+        let name = self.code.tmp(prefix);
+        self.code.line(format!("{ty} {name} = {};", expr.as_ref()));
+        name
+    }
+
+    /// Lower a variant-like value by switching on its discriminant, where
+    /// `payloads` is the expression for each case's payload.
+    fn lower_sum(
+        &mut self,
+        what: &str,
+        discriminant: &str,
+        payloads: &[String],
+        results: &mut Vec<String>,
+        result_types: &[WasmType],
+    ) {
+        let blocks = self.code.pop_blocks(payloads.len());
+        let names = self.code.pop_payloads(payloads.len());
+
+        let vars: Vec<_> = result_types
+            .iter()
+            .map(|ty| self.bind(c_wasm_type(*ty), "lowered", "0"))
+            .collect();
+
+        self.code.line(format!("switch ({discriminant}) {{"));
+        for (i, (((block, block_results), name), payload)) in
+            blocks.into_iter().zip(names).zip(payloads).enumerate()
+        {
+            self.code.line(format!("case {i}: {{"));
+            self.code
+                .src
+                .push_str(&replace_ident(&block, &name, payload));
+            for (var, value) in vars.iter().zip(&block_results) {
+                let value = replace_ident(value, &name, payload);
+                self.code.line(format!("{var} = {value};"));
+            }
+            self.code.line("break;\n}");
+        }
+        self.code.line(format!(
+            "default: {{\nreturn abi_trap(env, \"Invalid discriminant for {what}\");\n}}\n}}"
+        ));
+
+        results.extend(vars);
+    }
+
+    /// Lift a variant-like value into a tagged union (or option or expected)
+    /// of type `ty`.
+    fn lift_sum(
+        &mut self,
+        ty: String,
+        what: &str,
+        discriminant: &str,
+        cases: &[Lifted],
+        results: &mut Vec<String>,
+    ) {
+        let blocks = self.code.pop_blocks(cases.len());
+        let var = self.bind(&ty, "variant", "{0}");
+        self.code.line(format!("switch ({discriminant}) {{"));
+
+        for (i, (case, (block, block_results))) in cases.iter().zip(blocks).enumerate() {
+            self.code.line(format!("case {i}: {{"));
+            self.code.src.push_str(&block);
+            let value = &block_results[0];
+
+            match case {
+                Lifted::Case { name, has_value } => {
+                    self.code.line(format!("{var}.tag = {i};"));
+                    if *has_value {
+                        self.code.line(format!("{var}.val.{name} = {value};"));
+                    }
+                }
+                Lifted::Nothing => self.code.line(format!("{var}.is_some = false;")),
+                Lifted::Some { has_value } => {
+                    self.code.line(format!("{var}.is_some = true;"));
+                    if *has_value {
+                        self.code.line(format!("{var}.val = {value};"));
+                    }
+                }
+                Lifted::Ok { has_value } => {
+                    self.code.line(format!("{var}.is_err = false;"));
+                    if *has_value {
+                        self.code.line(format!("{var}.val.ok = {value};"));
+                    }
+                }
+                Lifted::Err { has_value } => {
+                    self.code.line(format!("{var}.is_err = true;"));
+                    if *has_value {
+                        self.code.line(format!("{var}.val.err = {value};"));
+                    }
+                }
+            }
+            self.code.line("break;\n}");
+        }
+
+        self.code.line(format!(
+            "default: {{\nreturn abi_trap(env, \"Invalid discriminant for {what}\");\n}}\n}}"
+        ));
+        results.push(var);
+    }
+
+    fn load(&mut self, func: &str, ty: WasmType, offset: i32, operands: &[String]) -> String {
+        self.bind(
+            c_wasm_type(ty),
+            "load",
+            format!("{func}(env, {}, {offset})", operands[0]),
+        )
+    }
+
+    fn store(&mut self, func: &str, offset: i32, operands: &[String]) {
+        self.code.line(format!(
+            "{func}(env, {}, {offset}, {});",
+            operands[1], operands[0]
+        ));
+    }
+
+    fn list_body(&mut self, body: &str, ptr: &str, size: usize, index: &str) -> String {
+        let base = self.code.tmp("base");
+        let mut src = String::new();
+        if uses_ident(body, ITER_BASE) {
+            src.push_str(&format!(
+                "int32_t {base} = {ptr} + (int32_t) {index} * {size};\n"
+            ));
+        }
+        src.push_str(&replace_ident(body, ITER_BASE, &base));
+        src
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_> {
+    type Operand = String;
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        let names = self.names;
+
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } => results.push(val.to_string()),
+            Instruction::ConstZero { tys } => {
+                for _ in tys.iter() {
+                    results.push("0".to_string());
+                }
+            }
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands.iter()) {
+                    let expr = match cast {
+                        Bitcast::None => op.clone(),
+                        Bitcast::I32ToI64 => format!("(int64_t) {}", parenthesize(op)),
+                        Bitcast::I64ToI32 => format!("(int32_t) {}", parenthesize(op)),
+                        Bitcast::F32ToI32 => format!("abi_f32_to_i32({op})"),
+                        Bitcast::F64ToI64 => format!("abi_f64_to_i64({op})"),
+                        Bitcast::F32ToI64 => format!("(int64_t) abi_f32_to_i32({op})"),
+                        Bitcast::I32ToF32 => format!("abi_i32_to_f32({op})"),
+                        Bitcast::I64ToF64 => format!("abi_i64_to_f64({op})"),
+                        Bitcast::I64ToF32 => {
+                            format!("abi_i32_to_f32((int32_t) {})", parenthesize(op))
+                        }
+                    };
+                    results.push(expr);
+                }
+            }
+
+            Instruction::I32Load { offset } => {
+                results.push(self.load("abi_load_i32", WasmType::I32, *offset, operands))
+            }
+            Instruction::I32Load8U { offset } => {
+                results.push(self.load("abi_load_u8", WasmType::I32, *offset, operands))
+            }
+            Instruction::I32Load8S { offset } => {
+                results.push(self.load("abi_load_s8", WasmType::I32, *offset, operands))
+            }
+            Instruction::I32Load16U { offset } => {
+                results.push(self.load("abi_load_u16", WasmType::I32, *offset, operands))
+            }
+            Instruction::I32Load16S { offset } => {
+                results.push(self.load("abi_load_s16", WasmType::I32, *offset, operands))
+            }
+            Instruction::I64Load { offset } => {
+                results.push(self.load("abi_load_i64", WasmType::I64, *offset, operands))
+            }
+            Instruction::F32Load { offset } => {
+                results.push(self.load("abi_load_f32", WasmType::F32, *offset, operands))
+            }
+            Instruction::F64Load { offset } => {
+                results.push(self.load("abi_load_f64", WasmType::F64, *offset, operands))
+            }
+            Instruction::I32Store { offset } => self.store("abi_store_i32", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("abi_store_i8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("abi_store_i16", *offset, operands),
+            Instruction::I64Store { offset } => self.store("abi_store_i64", *offset, operands),
+            Instruction::F32Store { offset } => self.store("abi_store_f32", *offset, operands),
+            Instruction::F64Store { offset } => self.store("abi_store_f64", *offset, operands),
+
+            Instruction::I32FromChar
+            | Instruction::I32FromU32
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromBool => {
+                results.push(format!("(int32_t) {}", parenthesize(&operands[0])))
+            }
+            Instruction::I64FromU64 => {
+                results.push(format!("(int64_t) {}", parenthesize(&operands[0])))
+            }
+            Instruction::I32FromS32
+            | Instruction::I64FromS64
+            | Instruction::F32FromFloat32
+            | Instruction::F64FromFloat64
+            | Instruction::S32FromI32
+            | Instruction::S64FromI64
+            | Instruction::Float32FromF32
+            | Instruction::Float64FromF64 => results.push(operands[0].clone()),
+            Instruction::S8FromI32 => {
+                results.push(format!("(int8_t) {}", parenthesize(&operands[0])))
+            }
+            Instruction::U8FromI32 => {
+                results.push(format!("(uint8_t) {}", parenthesize(&operands[0])))
+            }
+            Instruction::S16FromI32 => {
+                results.push(format!("(int16_t) {}", parenthesize(&operands[0])))
+            }
+            Instruction::U16FromI32 => {
+                results.push(format!("(uint16_t) {}", parenthesize(&operands[0])))
+            }
+            Instruction::U32FromI32 | Instruction::CharFromI32 => {
+                results.push(format!("(uint32_t) {}", parenthesize(&operands[0])))
+            }
+            Instruction::U64FromI64 => {
+                results.push(format!("(uint64_t) {}", parenthesize(&operands[0])))
+            }
+            Instruction::BoolFromI32 => {
+                results.push(format!("({} != 0)", parenthesize(&operands[0])))
+            }
+
+            Instruction::UnitLift => results.push("0".to_string()),
+            Instruction::UnitLower => {}
+
+            Instruction::I32FromBorrowedHandle { .. }
+            | Instruction::I32FromOwnedHandle { .. }
+            | Instruction::HandleOwnedFromI32 { .. }
+            | Instruction::HandleBorrowedFromI32 { .. } => {
+                unreachable!("resources are rejected during validation")
+            }
+
+            Instruction::ListCanonLower { .. } | Instruction::ListCanonLift { .. } => {
+                unreachable!("lists are never canonical")
+            }
+
+            Instruction::StringLower { .. } => {
+                let ptr = self.code.tmp("ptr");
+                let len = self.code.tmp("len");
+                self.code.line(format!("int32_t {ptr};\nint32_t {len};"));
+                self.code.line(format!(
+                    "ABI_TRY(abi_lower_string(env, {op}.ptr, {op}.len, &{ptr}, &{len}));",
+                    op = parenthesize(&operands[0])
+                ));
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::ListLower { element, .. } => {
+                let (body, body_results) = self.code.pop_block();
+                assert!(body_results.is_empty());
+                let size = self.sizes.size(element);
+                let align = self.sizes.align(element);
+                let list = parenthesize(&operands[0]);
+
+                let len = self.bind("int32_t", "len", format!("(int32_t) {list}.len"));
+                let ptr = self.code.tmp("ptr");
+                self.code.line(format!("int32_t {ptr};"));
+                self.code.line(format!(
+                    "ABI_TRY(abi_realloc(env, 0, 0, {align}, {len} * {size}, &{ptr}));"
+                ));
+
+                let i = self.code.tmp("i");
+                let body = replace_ident(&body, ITER_ELEM, &format!("{list}.ptr[{i}]"));
+                let body = self.list_body(&body, &ptr, size, &i);
+                self.code
+                    .line(format!("for (size_t {i} = 0; {i} < {list}.len; {i}++) {{"));
+                self.code.src.push_str(&body);
+                self.code.line("}");
+
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::StringLift { free } => {
+                let s = self.code.tmp("str");
+                self.code.line(format!("{}_string_t {s};", names.pkg));
+                self.code.line(format!(
+                    "abi_lift_string(env, {}, {}, &{s});",
+                    operands[0], operands[1]
+                ));
+                if free.is_some() {
+                    self.code.line(format!(
+                        "ABI_TRY(abi_free(env, {}, {}, 1));",
+                        operands[0], operands[1]
+                    ));
+                }
+                results.push(s);
+            }
+            Instruction::ListLift {
+                element, free, ty, ..
+            } => {
+                let (body, body_results) = self.code.pop_block();
+                let size = self.sizes.size(element);
+                let align = self.sizes.align(element);
+                let list_type = names.c_type(iface, &Type::Id(*ty));
+                let element_type = names.c_type(iface, element);
+
+                let ptr = self.bind("int32_t", "ptr", &operands[0]);
+                let len = self.bind("int32_t", "len", &operands[1]);
+                let list = self.code.tmp("list");
+                self.code.line(format!("{list_type} {list};"));
+                self.code
+                    .line(format!("{list}.len = (size_t) (uint32_t) {len};"));
+                self.code.line(format!(
+                    "{list}.ptr = abi_alloc({list}.len, sizeof({element_type}));"
+                ));
+                let i = self.code.tmp("i");
+                let body = self.list_body(&body, &ptr, size, &i);
+                self.code
+                    .line(format!("for (size_t {i} = 0; {i} < {list}.len; {i}++) {{"));
+                self.code.src.push_str(&body);
+                self.code
+                    .line(format!("{list}.ptr[{i}] = {};", body_results[0]));
+                self.code.line("}");
+                if free.is_some() {
+                    self.code.line(format!(
+                        "ABI_TRY(abi_free(env, {ptr}, {len} * {size}, {align}));"
+                    ));
+                }
+
+                results.push(list);
+            }
+            Instruction::IterElem { .. } => results.push(ITER_ELEM.to_string()),
+            Instruction::IterBasePointer => results.push(ITER_BASE.to_string()),
+
+            Instruction::RecordLower { record, .. } => {
+                for field in &record.fields {
+                    results.push(format!(
+                        "{}.{}",
+                        parenthesize(&operands[0]),
+                        ident(&field.name.to_snake_case())
+                    ));
+                }
+            }
+            Instruction::RecordLift { record, ty, .. } => {
+                let fields: Vec<_> = record
+                    .fields
+                    .iter()
+                    .zip(operands.iter())
+                    .map(|(f, op)| format!(".{} = {op}", ident(&f.name.to_snake_case())))
+                    .collect();
+                results.push(compound_literal(
+                    &names.c_type(iface, &Type::Id(*ty)),
+                    &fields,
+                ));
+            }
+            Instruction::TupleLower { tuple, .. } => {
+                for i in 0..tuple.types.len() {
+                    results.push(format!("{}.f{i}", parenthesize(&operands[0])));
+                }
+            }
+            Instruction::TupleLift { ty, .. } => {
+                let fields: Vec<_> = operands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, op)| format!(".f{i} = {op}"))
+                    .collect();
+                results.push(compound_literal(
+                    &names.c_type(iface, &Type::Id(*ty)),
+                    &fields,
+                ));
+            }
+
+            Instruction::FlagsLower { flags, .. } => {
+                if flags.repr().count() == 1 {
+                    results.push(format!("(int32_t) {}", parenthesize(&operands[0])));
+                } else {
+                    results.push(format!("(int32_t) (uint32_t) ({})", operands[0]));
+                    results.push(format!("(int32_t) (uint32_t) (({}) >> 32)", operands[0]));
+                }
+            }
+            Instruction::FlagsLift { flags, ty, .. } => {
+                let name = names.c_type(iface, &Type::Id(*ty));
+                if flags.repr().count() == 1 {
+                    results.push(format!("({name}) (uint32_t) ({})", operands[0]));
+                } else {
+                    results.push(format!(
+                        "({name}) ((uint64_t) (uint32_t) ({}) | ((uint64_t) (uint32_t) ({}) << 32))",
+                        operands[0], operands[1]
+                    ));
+                }
+            }
+
+            Instruction::VariantPayloadName => results.push(self.code.payload_name()),
+            Instruction::VariantLower {
+                variant,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let op = parenthesize(&operands[0]);
+                let payloads: Vec<_> = variant
+                    .cases
+                    .iter()
+                    .map(|c| format!("{op}.val.{}", ident(&c.name.to_snake_case())))
+                    .collect();
+                self.lower_sum(name, &format!("{op}.tag"), &payloads, results, result_types);
+            }
+            Instruction::UnionLower {
+                union,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let op = parenthesize(&operands[0]);
+                let payloads: Vec<_> = (0..union.cases.len())
+                    .map(|i| format!("{op}.val.f{i}"))
+                    .collect();
+                self.lower_sum(name, &format!("{op}.tag"), &payloads, results, result_types);
+            }
+            Instruction::VariantLift { variant, name, ty } => {
+                let cases: Vec<_> = variant
+                    .cases
+                    .iter()
+                    .map(|c| Lifted::case(ident(&c.name.to_snake_case()), &c.ty))
+                    .collect();
+                let ty = names.c_type(iface, &Type::Id(*ty));
+                self.lift_sum(ty, name, &operands[0], &cases, results);
+            }
+            Instruction::UnionLift { union, name, ty } => {
+                let cases: Vec<_> = union
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| Lifted::case(format!("f{i}"), &c.ty))
+                    .collect();
+                let ty = names.c_type(iface, &Type::Id(*ty));
+                self.lift_sum(ty, name, &operands[0], &cases, results);
+            }
+            Instruction::EnumLower { .. } => {
+                results.push(format!("(int32_t) {}", parenthesize(&operands[0])))
+            }
+            Instruction::EnumLift { ty, .. } => results.push(format!(
+                "({}) ({})",
+                names.c_type(iface, &Type::Id(*ty)),
+                operands[0]
+            )),
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let op = parenthesize(&operands[0]);
+                let payloads = ["0".to_string(), format!("{op}.val")];
+                self.lower_sum(
+                    "option",
+                    &format!("(int) {op}.is_some"),
+                    &payloads,
+                    results,
+                    result_types,
+                );
+            }
+            Instruction::OptionLift { payload, ty } => {
+                let cases = Lifted::option(payload);
+                let ty = names.c_type(iface, &Type::Id(*ty));
+                self.lift_sum(ty, "option", &operands[0], &cases, results);
+            }
+            Instruction::ExpectedLower {
+                results: result_types,
+                ..
+            } => {
+                let op = parenthesize(&operands[0]);
+                let payloads = [format!("{op}.val.ok"), format!("{op}.val.err")];
+                self.lower_sum(
+                    "expected",
+                    &format!("(int) {op}.is_err"),
+                    &payloads,
+                    results,
+                    result_types,
+                );
+            }
+            Instruction::ExpectedLift { expected, ty } => {
+                let cases = Lifted::expected(expected);
+                let ty = names.c_type(iface, &Type::Id(*ty));
+                self.lift_sum(ty, "expected", &operands[0], &cases, results);
+            }
+
+            Instruction::CallWasm { sig, .. } => {
+                let callee = self.callee.clone();
+                let args = if operands.is_empty() {
+                    "NULL, 0".to_string()
+                } else {
+                    let vals: Vec<_> = sig
+                        .params
+                        .iter()
+                        .zip(operands.iter())
+                        .map(|(ty, op)| {
+                            format!("WASM_{}_VAL({op})", value_field(*ty).to_uppercase())
+                        })
+                        .collect();
+                    let args = self.code.tmp("args");
+                    self.code
+                        .line(format!("wasm_val_t {args}[] = {{{}}};", vals.join(", ")));
+                    format!("{args}, {}", operands.len())
+                };
+
+                match codegen::single_result(&sig.results) {
+                    None => self
+                        .code
+                        .line(format!("ABI_TRY(abi_call({callee}, {args}, NULL, 0));")),
+                    Some(ty) => {
+                        let raw = self.code.tmp("results");
+                        self.code
+                            .line(format!("wasm_val_t {raw}[] = {{WASM_INIT_VAL}};"));
+                        self.code
+                            .line(format!("ABI_TRY(abi_call({callee}, {args}, {raw}, 1));"));
+                        let ret = self.bind(
+                            c_wasm_type(*ty),
+                            "ret",
+                            format!("{raw}[0].of.{}", value_field(*ty)),
+                        );
+                        results.push(ret);
+                    }
+                }
+            }
+            Instruction::CallInterface { func, .. } => {
+                let mut args = vec![self.callee_data.clone()];
+                let mut frees = Vec::new();
+                for ((_, ty), op) in func.params.iter().zip(operands.iter()) {
+                    if is_scalar(iface, ty) {
+                        args.push(op.clone());
+                    } else {
+                        let arg = if is_ident(op) {
+                            op.clone()
+                        } else {
+                            self.bind(&names.c_type(iface, ty), "arg", op)
+                        };
+                        args.push(format!("&{arg}"));
+                        if let Some(free) = names.free_fn(iface, ty) {
+                            frees.push(format!("{free}(&{arg});"));
+                        }
+                    }
+                }
+
+                let ret = if func.result == Type::Unit {
+                    None
+                } else {
+                    let ret = self.code.tmp("ret");
+                    self.code.line(format!(
+                        "{} {ret};\nmemset(&{ret}, 0, sizeof({ret}));",
+                        names.c_type(iface, &func.result)
+                    ));
+                    args.push(format!("&{ret}"));
+                    Some(ret)
+                };
+
+                let callee = self.callee.clone();
+                let trap = self.code.tmp("trap");
+                self.code.line(format!(
+                    "wasm_trap_t *{trap} = {callee}({});",
+                    args.join(", ")
+                ));
+                for free in frees {
+                    self.code.line(free);
+                }
+                self.code
+                    .line(format!("if ({trap} != NULL) {{\nreturn {trap};\n}}"));
+
+                match ret {
+                    Some(ret) => {
+                        if let Some(free) = names.free_fn(iface, &func.result) {
+                            self.cleanup.push(format!("{free}(&{ret});"));
+                        }
+                        results.push(ret);
+                    }
+                    None => results.push("0".to_string()),
+                }
+            }
+            Instruction::Return { func, .. } => {
+                if !self.wasm_results.is_empty() {
+                    for (i, (ty, op)) in self.wasm_results.clone().iter().zip(operands).enumerate()
+                    {
+                        self.code.line(format!(
+                            "results->data[{i}].kind = {};\nresults->data[{i}].of.{} = {op};",
+                            valkind(*ty),
+                            value_field(*ty)
+                        ));
+                    }
+                } else if !operands.is_empty() && func.result != Type::Unit {
+                    self.code.line(format!("*ret = {};", operands[0]));
+                }
+                for line in std::mem::take(&mut self.cleanup) {
+                    self.code.line(line);
+                }
+            }
+
+            Instruction::Malloc { size, align, .. } => {
+                let ptr = self.code.tmp("ptr");
+                self.code.line(format!("int32_t {ptr};"));
+                self.code.line(format!(
+                    "ABI_TRY(abi_realloc(env, 0, 0, {align}, {size}, &{ptr}));"
+                ));
+                results.push(ptr);
+            }
+            Instruction::Free { size, align, .. } => {
+                self.code.line(format!(
+                    "ABI_TRY(abi_free(env, {}, {size}, {align}));",
+                    operands[0]
+                ));
+            }
+
+            Instruction::CallWasmAsyncImport { .. }
+            | Instruction::CallWasmAsyncExport { .. }
+            | Instruction::ReturnAsyncExport { .. }
+            | Instruction::ReturnAsyncImport { .. } => {
+                unreachable!("async functions are rejected during validation")
+            }
+        }
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, _size: usize, _align: usize) -> String {
+        // Only called when a guest passes a return pointer to an import or
+        // returns one from an export, and we only ever generate host code
+        unreachable!("return pointers are only needed by guest code")
+    }
+
+    fn push_block(&mut self) {
+        self.code.push_block();
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        self.code.finish_block(operands);
+    }
+
+    fn sizes(&self) -> &SizeAlign {
+        self.sizes
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, _element: &Type) -> bool {
+        false
+    }
+}
+
+fn is_ident(expr: &str) -> bool {
+    expr.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// A compound literal initializing a struct's fields.
+fn compound_literal(ty: &str, fields: &[String]) -> String {
+    if fields.is_empty() {
+        format!("({ty}) {{0}}")
+    } else {
+        format!("({ty}) {{{}}}", fields.join(", "))
+    }
+}
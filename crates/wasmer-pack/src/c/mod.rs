@@ -0,0 +1,295 @@
+mod library;
+
+use std::{collections::BTreeSet, path::Path};
+
+use heck::{ToShoutySnakeCase, ToSnakeCase};
+use once_cell::sync::Lazy;
+
+use self::library::ident;
+pub(crate) use self::library::quote;
+use crate::{
+    codegen::{self, Templates},
+    types::{Abi, BindingsOptions, CommandKind, Package},
+    Files,
+};
+
+static TEMPLATES: Lazy<Templates> = Lazy::new(|| {
+    Templates::new(&[
+        ("CMakeLists.txt", include_str!("CMakeLists.txt.j2")),
+        ("header.h", include_str!("header.h.j2")),
+        ("source.c", include_str!("source.c.j2")),
+        ("bundled.h", include_str!("bundled.h.j2")),
+        ("abi.c", include_str!("abi.c.j2")),
+    ])
+});
+
+/// Generate a C library (with a header that can also be used from C++)
+/// which uses the Wasmer C API to run the package.
+pub fn generate_c(package: &Package, options: &BindingsOptions) -> Result<Files, crate::Error> {
+    crate::validation::check_abis(package.libraries(), "C", &[Abi::None, Abi::Wasi])?;
+    crate::validation::check_command_kinds(package.commands(), "C", &[CommandKind::Wasi])?;
+    crate::validation::deny_unsupported_interface_features(package.libraries(), "C")?;
+    if options.deny_extra_exports {
+        crate::validation::deny_extra_exports(package.libraries())?;
+    }
+
+    let metadata = package.metadata();
+    let name = match &options.name {
+        Some(name) => name.as_str(),
+        None => metadata.package_name.name(),
+    };
+    let prefix = ident(&name.to_snake_case());
+    let macro_prefix = prefix.to_shouty_snake_case();
+
+    let mounts = codegen::mounts(package, quote);
+    let uses_volumes = !mounts.is_empty();
+    let volumes = Volumes::for_package(package, uses_volumes);
+
+    let libraries: Vec<_> = package
+        .libraries()
+        .iter()
+        .map(|lib| library::generate(lib, &prefix, uses_volumes))
+        .collect();
+    let commands: Vec<_> = package
+        .commands()
+        .iter()
+        .map(CommandContext::from)
+        .collect();
+
+    let mut files = Files::new();
+
+    let mut doc = vec![format!(
+        "Bindings to the {} package, using the Wasmer C API.",
+        metadata.package_name
+    )];
+    if let Some(description) = &metadata.description {
+        doc.push(String::new());
+        doc.extend(description.lines().map(|line| line.trim_end().to_string()));
+    }
+
+    files.insert(
+        Path::new("include").join(format!("{prefix}.h")),
+        TEMPLATES.render(
+            "header.h",
+            minijinja::context! {
+                doc,
+                generator => crate::GENERATOR,
+                guard => format!("{macro_prefix}_H"),
+                prefix,
+                libraries => libraries.iter().map(|lib| lib.header.trim_end()).collect::<Vec<_>>(),
+                commands,
+            },
+        )
+        .map_err(crate::Error::Generate)?,
+    );
+
+    let mut embedded: Vec<_> = unique_modules(package)
+        .map(|(description, wasm)| EmbeddedContext::new(description, wasm_ident(wasm), wasm))
+        .collect();
+    embedded.extend(volumes.embedded);
+    if !embedded.is_empty() {
+        files.insert(
+            Path::new("src").join(format!("{prefix}_bundled.h")),
+            TEMPLATES
+                .render(
+                    "bundled.h",
+                    minijinja::context! {
+                        generator => crate::GENERATOR,
+                        guard => format!("{macro_prefix}_BUNDLED_H"),
+                        prefix,
+                        embedded,
+                    },
+                )
+                .map_err(crate::Error::Generate)?,
+        );
+    }
+
+    let abi = TEMPLATES
+        .render_to_string("abi.c", minijinja::context! { prefix })
+        .map_err(crate::Error::Generate)?;
+    files.insert(
+        Path::new("src").join(format!("{prefix}.c")),
+        TEMPLATES.render(
+            "source.c",
+            minijinja::context! {
+                generator => crate::GENERATOR,
+                prefix,
+                embedded => !embedded.is_empty(),
+                mounts,
+                volume_dirs => volumes.dirs,
+                volume_files => volumes.files,
+                abi => abi.trim_end(),
+                libraries => libraries.iter().map(|lib| lib.source.trim_end()).collect::<Vec<_>>(),
+                commands,
+            },
+        )
+        .map_err(crate::Error::Generate)?,
+    );
+
+    let version = &metadata.version;
+    files.insert(
+        "CMakeLists.txt",
+        TEMPLATES
+            .render(
+                "CMakeLists.txt",
+                minijinja::context! {
+                    generator => crate::GENERATOR,
+                    prefix,
+                    // CMake versions can only contain numbers
+                    version => format!("{}.{}.{}", version.major, version.minor, version.patch),
+                },
+            )
+            .map_err(crate::Error::Generate)?,
+    );
+
+    if let Some(readme) = &metadata.readme {
+        files.insert(codegen::README, readme.as_str().into());
+    }
+
+    Ok(files)
+}
+
+/// The name of the array a WebAssembly module is embedded in.
+///
+/// Modules are content-addressed, so libraries and commands which use the same
+/// binary will share a single copy.
+fn wasm_ident(wasm: &[u8]) -> String {
+    let filename = crate::files::wasm_filename(wasm);
+    format!("wasm_{}", filename.trim_end_matches(".wasm"))
+}
+
+/// Every distinct WebAssembly module used by the package, along with a
+/// description of where it came from.
+fn unique_modules(package: &Package) -> impl Iterator<Item = (String, &[u8])> {
+    let libraries = package.libraries().iter().map(|lib| {
+        (
+            format!("The {} library", quote(lib.interface_name())),
+            lib.module.wasm.as_slice(),
+        )
+    });
+    let commands = package.commands().iter().map(|cmd| {
+        (
+            format!("The {} command", quote(&cmd.name)),
+            cmd.wasm.as_slice(),
+        )
+    });
+
+    let mut seen = BTreeSet::new();
+    libraries
+        .chain(commands)
+        .filter(move |(_, wasm)| seen.insert(wasm_ident(wasm)))
+}
+
+/// A file which is embedded in the generated code as a byte array.
+#[derive(Debug, serde::Serialize)]
+struct EmbeddedContext {
+    /// What the file is, for use in a comment.
+    description: String,
+    ident: String,
+    /// The file's contents, formatted as the body of an array initializer.
+    bytes: String,
+    len: usize,
+}
+
+impl EmbeddedContext {
+    fn new(description: String, ident: String, contents: &[u8]) -> Self {
+        let bytes = if contents.is_empty() {
+            // C doesn't allow empty arrays, so add a byte that is never read
+            "    0x00, // (placeholder)".to_string()
+        } else {
+            contents
+                .chunks(16)
+                .map(|chunk| {
+                    let line: Vec<_> = chunk.iter().map(|b| format!("0x{b:02x},")).collect();
+                    format!("    {}", line.join(" "))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        EmbeddedContext {
+            description,
+            ident,
+            bytes,
+            len: contents.len(),
+        }
+    }
+}
+
+/// The directories bundled with the package, which get extracted to a
+/// temporary directory at runtime.
+#[derive(Debug, Default)]
+struct Volumes {
+    /// Every directory which needs to be created, parents first, as string
+    /// literals.
+    dirs: Vec<String>,
+    /// The `{path, data, len}` initializer for each file.
+    files: Vec<String>,
+    /// The contents of each file.
+    embedded: Vec<EmbeddedContext>,
+}
+
+impl Volumes {
+    fn for_package(package: &Package, uses_volumes: bool) -> Self {
+        if !uses_volumes {
+            return Volumes::default();
+        }
+
+        let mut dirs: BTreeSet<String> =
+            (0..package.mounts().len()).map(|i| i.to_string()).collect();
+        let mut volumes = Volumes::default();
+
+        for (i, (path, file)) in codegen::volume_files(package).into_iter().enumerate() {
+            // Every parent directory needs to exist before we can write the
+            // file. Sorting means parents come before their children.
+            let mut parent = path.as_str();
+            while let Some((dir, _)) = parent.rsplit_once('/') {
+                dirs.insert(dir.to_string());
+                parent = dir;
+            }
+
+            let ident = format!("volume_file_{i}");
+            volumes
+                .files
+                .push(format!("{{{}, {ident}, {ident}_len}}", quote(&path)));
+            volumes
+                .embedded
+                .push(EmbeddedContext::new(quote(&path), ident, file.contents()));
+        }
+
+        volumes.dirs = dirs.iter().map(|dir| quote(dir)).collect();
+        volumes
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct CommandContext {
+    name: String,
+    ident: String,
+    /// The array the command's WebAssembly module is embedded in.
+    wasm: String,
+    /// The name passed to the command as `argv[0]`, as a string literal.
+    program_name: String,
+    /// The command's default arguments, as string literals.
+    main_args: Vec<String>,
+    /// The command's default environment variables, as `{key, value}`
+    /// initializers.
+    env: Vec<String>,
+}
+
+impl From<&crate::Command> for CommandContext {
+    fn from(cmd: &crate::Command) -> Self {
+        CommandContext {
+            name: cmd.name.clone(),
+            ident: ident(&cmd.name.to_snake_case()),
+            wasm: wasm_ident(&cmd.wasm),
+            program_name: quote(cmd.program_name()),
+            main_args: cmd.main_args.iter().map(|arg| quote(arg)).collect(),
+            env: cmd
+                .env
+                .iter()
+                .map(|(key, value)| format!("{{{}, {}}}", quote(key), quote(value)))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,962 @@
+//! Helpers shared by the generators which render source code from templates
+//! and walk each function's canonical ABI instructions themselves.
+
+use std::collections::BTreeMap;
+
+use anyhow::Error;
+use heck::ToSnakeCase;
+use minijinja::{AutoEscape, Environment};
+use wai_parser::{Expected, Type};
+
+use crate::{Command, Files, Library, Package, SourceFile};
+
+/// The name used when saving the package's README.
+pub(crate) const README: &str = "README.md";
+
+/// A set of templates for generating source code.
+#[derive(Debug)]
+pub(crate) struct Templates(Environment<'static>);
+
+impl Templates {
+    /// Load templates from `(name, source)` pairs, panicking if any of them
+    /// are invalid.
+    pub(crate) fn new(templates: &[(&'static str, &'static str)]) -> Self {
+        let mut env = Environment::new();
+        // We're generating source code, so values are inserted verbatim
+        // (string literals are quoted before they get to the template)
+        env.set_auto_escape_callback(|_| AutoEscape::None);
+
+        for (name, source) in templates {
+            env.add_template(name, source).unwrap();
+        }
+
+        Templates(env)
+    }
+
+    pub(crate) fn render(
+        &self,
+        template: &str,
+        ctx: impl serde::Serialize,
+    ) -> Result<SourceFile, Error> {
+        self.render_to_string(template, ctx).map(SourceFile::from)
+    }
+
+    pub(crate) fn render_to_string(
+        &self,
+        template: &str,
+        ctx: impl serde::Serialize,
+    ) -> Result<String, Error> {
+        let rendered = self.0.get_template(template).unwrap().render(ctx)?;
+        Ok(rendered)
+    }
+}
+
+/// Save a single copy of every WebAssembly module used by the package's
+/// libraries and commands.
+pub(crate) fn wasm_modules(package: &Package) -> Files {
+    let mut files = Files::new();
+
+    let modules = package
+        .libraries()
+        .iter()
+        .map(|lib| &lib.module.wasm)
+        .chain(package.commands().iter().map(|cmd| &cmd.wasm));
+
+    for wasm in modules {
+        files.insert(crate::files::wasm_filename(wasm), SourceFile::from(wasm));
+    }
+
+    files
+}
+
+/// A directory bundled with the package.
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct MountContext {
+    /// Where the directory is mounted inside the guest, as a string literal.
+    pub guest_path: String,
+    /// Where the directory's contents are saved, relative to the `volumes`
+    /// directory, as a string literal.
+    pub dir: String,
+}
+
+/// The directories to bundle with the package, using `quote` to turn strings
+/// into literals.
+pub(crate) fn mounts(package: &Package, quote: impl Fn(&str) -> String) -> Vec<MountContext> {
+    // Mounts are only ever used by WASI, so there's no point bundling them if
+    // nothing will see them
+    if !package.requires_wasi() {
+        return Vec::new();
+    }
+
+    package
+        .mounts()
+        .iter()
+        .enumerate()
+        .map(|(i, mount)| MountContext {
+            guest_path: quote(&mount.guest_path),
+            dir: quote(&i.to_string()),
+        })
+        .collect()
+}
+
+/// How a generator turns names used by the package into identifiers and
+/// literals.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Naming {
+    /// Quote a string so it can be used as a string literal.
+    pub quote: fn(&str) -> String,
+    /// The identifier used to access a library.
+    pub library_ident: fn(&str) -> String,
+    /// The identifier used to run a command.
+    pub command_ident: fn(&str) -> String,
+    /// The name of the type wrapping a library or the interface a host
+    /// implements (i.e. `WasmerPack`).
+    pub class_name: fn(&str) -> String,
+    /// The parameter used to pass in the host's implementation of an
+    /// interface.
+    pub param_name: fn(&str) -> String,
+    /// Format a command's default environment variables as map entries.
+    pub env: fn(&BTreeMap<String, String>) -> Vec<String>,
+}
+
+/// Everything needed when rendering templates for a package.
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct Context {
+    /// The Go module, Ruby module, or PHP namespace containing the generated
+    /// code.
+    pub module: String,
+    pub commands: Vec<CommandContext>,
+    pub libraries: Vec<LibraryContext>,
+    pub mounts: Vec<MountContext>,
+    /// Do any libraries need to mount the bundled directories?
+    pub library_mounts: bool,
+}
+
+impl Context {
+    pub(crate) fn for_package(pkg: &Package, module: &str, naming: &Naming) -> Self {
+        let commands = pkg
+            .commands()
+            .iter()
+            .map(|cmd| CommandContext::new(cmd, naming))
+            .collect();
+        let libraries: Vec<_> = pkg
+            .libraries()
+            .iter()
+            .map(|lib| LibraryContext::new(lib, naming))
+            .collect();
+
+        let mounts = mounts(pkg, naming.quote);
+
+        Context {
+            module: module.to_string(),
+            commands,
+            library_mounts: !mounts.is_empty() && libraries.iter().any(|lib| lib.wasi),
+            libraries,
+            mounts,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct LibraryContext {
+    /// The identifier used to access the library, which is also the name of
+    /// the file or package containing its bindings.
+    pub ident: String,
+    pub interface_name: String,
+    /// The name of the type wrapping an instance (i.e. `WasmerPack`), which
+    /// is also the namespace its types live in.
+    pub class_name: String,
+    /// The name passed to the library as `argv[0]`, as a string literal.
+    pub program_name: String,
+    /// The library's WebAssembly module, relative to wherever modules are
+    /// saved, as a string literal.
+    pub wasm: String,
+    pub wasi: bool,
+    pub imports: Vec<InterfaceContext>,
+}
+
+impl LibraryContext {
+    fn new(lib: &Library, naming: &Naming) -> Self {
+        let quote = naming.quote;
+
+        LibraryContext {
+            ident: (naming.library_ident)(lib.interface_name()),
+            interface_name: lib.interface_name().to_string(),
+            class_name: (naming.class_name)(lib.interface_name()),
+            program_name: quote(&lib.interface_name().to_snake_case()),
+            wasm: quote(&crate::files::wasm_filename(&lib.module.wasm)),
+            wasi: lib.requires_wasi(),
+            imports: lib
+                .imports
+                .iter()
+                .map(|interface| InterfaceContext {
+                    param: (naming.param_name)(interface.name()),
+                    interface_name: interface.name().to_string(),
+                    class_name: (naming.class_name)(interface.name()),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// An interface imported by a library.
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct InterfaceContext {
+    /// The parameter used to pass in the host's implementation.
+    pub param: String,
+    pub interface_name: String,
+    /// The interface the host implements (i.e. `Browser`).
+    pub class_name: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct CommandContext {
+    pub name: String,
+    pub ident: String,
+    /// The command's WebAssembly module, relative to wherever modules are
+    /// saved, as a string literal.
+    pub wasm: String,
+    /// The name passed to the command as `argv[0]`, as a string literal.
+    pub program_name: String,
+    /// The command's default arguments, as string literals.
+    pub main_args: Vec<String>,
+    /// The command's default environment variables, as map entries.
+    pub env: Vec<String>,
+}
+
+impl CommandContext {
+    fn new(cmd: &Command, naming: &Naming) -> Self {
+        let quote = naming.quote;
+
+        CommandContext {
+            name: cmd.name.clone(),
+            ident: (naming.command_ident)(&cmd.name),
+            wasm: quote(&crate::files::wasm_filename(&cmd.wasm)),
+            program_name: quote(cmd.program_name()),
+            main_args: cmd.main_args.iter().map(|arg| quote(arg)).collect(),
+            env: (naming.env)(&cmd.env),
+        }
+    }
+}
+
+/// The contents of each bundled directory, saved to a directory named after
+/// its index.
+pub(crate) fn volumes(package: &Package) -> Files {
+    let mut files = Files::new();
+
+    for (i, mount) in package.mounts().iter().enumerate() {
+        files.insert_child_directory(i.to_string(), mount.files.clone());
+    }
+
+    files
+}
+
+/// Every file in the bundled directories, along with where it is saved
+/// relative to the `volumes` directory.
+///
+/// The paths always use forward slashes so they can be used on any platform
+/// (e.g. `0/python/os.py`).
+pub(crate) fn volume_files(package: &Package) -> Vec<(String, &SourceFile)> {
+    let mut files = Vec::new();
+
+    for (i, mount) in package.mounts().iter().enumerate() {
+        for (path, file) in mount.files.iter() {
+            let mut relative_path = i.to_string();
+            for component in path.iter() {
+                relative_path.push('/');
+                relative_path.push_str(&component.to_string_lossy());
+            }
+
+            files.push((relative_path, file));
+        }
+    }
+
+    files
+}
+
+/// Placeholder for the current element when lifting or lowering a list,
+/// replaced once the loop variable is known.
+pub(crate) const ITER_ELEM: &str = "ITER_ELEM";
+/// Placeholder for the current element's address when lifting or lowering a
+/// list.
+pub(crate) const ITER_BASE: &str = "ITER_BASE";
+
+/// Quote a string as a double-quoted literal, using the escapes most
+/// languages agree on for quotes, backslashes, and whitespace.
+///
+/// Every other character is passed to `escape`, which returns its escaped
+/// form if it has one (e.g. control characters, or anything which would
+/// trigger interpolation).
+pub(crate) fn quote(s: &str, escape: impl Fn(char) -> Option<String>) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => match escape(c) {
+                Some(escaped) => quoted.push_str(&escaped),
+                None => quoted.push(c),
+            },
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// How a line of source code affects indentation, as decided by the
+/// callback passed to [`indent()`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Layout {
+    /// The line ends a block, so it is outdented along with everything after
+    /// it.
+    pub(crate) closes: bool,
+    /// The line starts a block, so everything after it is indented.
+    pub(crate) opens: bool,
+    /// The line belongs to the enclosing statement (e.g. `else` or `case`),
+    /// so only it is outdented.
+    pub(crate) continues: bool,
+    /// The line always starts in the first column.
+    pub(crate) unindented: bool,
+    /// Text to insert between the indentation and the line.
+    pub(crate) padding: &'static str,
+}
+
+/// Indent source code one line at a time, where `layout` says how each
+/// (trimmed) line affects the nesting.
+pub(crate) fn indent(src: &str, unit: &str, layout: impl Fn(&str) -> Layout) -> String {
+    let mut indented = String::new();
+    let mut depth = 0_usize;
+
+    for line in src.lines() {
+        let line = line.trim_start();
+        if line.is_empty() {
+            indented.push('\n');
+            continue;
+        }
+
+        let layout = layout(line);
+        if layout.closes {
+            depth = depth.saturating_sub(1);
+        }
+        let level = if layout.unindented {
+            0
+        } else if layout.continues {
+            depth.saturating_sub(1)
+        } else {
+            depth
+        };
+
+        for _ in 0..level {
+            indented.push_str(unit);
+        }
+        indented.push_str(layout.padding);
+        indented.push_str(line);
+        indented.push('\n');
+
+        if layout.opens {
+            depth += 1;
+        }
+    }
+
+    indented
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Find each place `ident` is used as a standalone identifier.
+///
+/// Identifiers may start with a sigil (e.g. PHP's `$`), as long as the rest
+/// of the identifier is made up of word characters.
+fn ident_indices<'a>(src: &'a str, ident: &'a str) -> impl Iterator<Item = usize> + 'a {
+    src.match_indices(ident).map(|(i, _)| i).filter(move |&i| {
+        let before = src[..i].chars().next_back();
+        let after = src[i + ident.len()..].chars().next();
+        !before.map_or(false, is_ident_char) && !after.map_or(false, is_ident_char)
+    })
+}
+
+/// Does `src` mention `ident` as a standalone identifier?
+pub(crate) fn uses_ident(src: &str, ident: &str) -> bool {
+    ident_indices(src, ident).next().is_some()
+}
+
+/// How many times is `ident` mentioned as a standalone identifier?
+pub(crate) fn count_ident(src: &str, ident: &str) -> usize {
+    ident_indices(src, ident).count()
+}
+
+/// Replace every standalone occurrence of an identifier.
+pub(crate) fn replace_ident(src: &str, ident: &str, replacement: &str) -> String {
+    let mut replaced = String::with_capacity(src.len());
+    let mut last = 0;
+
+    for i in ident_indices(src, ident) {
+        replaced.push_str(&src[last..i]);
+        replaced.push_str(replacement);
+        last = i + ident.len();
+    }
+    replaced.push_str(&src[last..]);
+
+    replaced
+}
+
+/// Add a trailing underscore to an identifier if it is one of the `reserved`
+/// names (e.g. a keyword, or something referenced by the glue code).
+pub(crate) fn avoid_reserved(ident: String, reserved: &[&[&str]]) -> String {
+    if reserved.iter().any(|names| names.contains(&ident.as_str())) {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+/// Parenthesize an expression containing operators, so it can be used as an
+/// operand.
+pub(crate) fn parenthesize(expr: &str) -> String {
+    if expr.contains(' ') {
+        format!("({expr})")
+    } else {
+        expr.to_string()
+    }
+}
+
+/// The single result of a core WebAssembly function, if it has one.
+///
+/// The canonical ABI returns anything bigger than a single value through
+/// memory, so core functions never have multiple results.
+pub(crate) fn single_result<T>(results: &[T]) -> Option<&T> {
+    assert!(results.len() <= 1, "multi-value returns");
+    results.first()
+}
+
+/// What to do with the payload of each case when lifting a variant-like
+/// value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Lifted {
+    /// An option's `none` case.
+    Nothing,
+    /// A variant or union case, where `name` is whatever the generated code
+    /// uses to construct it (e.g. a class or union member).
+    Case { name: String, has_value: bool },
+    /// An option's `some` case.
+    Some { has_value: bool },
+    /// An expected's `ok` case.
+    Ok { has_value: bool },
+    /// An expected's `err` case.
+    Err { has_value: bool },
+}
+
+impl Lifted {
+    pub(crate) fn case(name: impl Into<String>, payload: &Type) -> Self {
+        Lifted::Case {
+            name: name.into(),
+            has_value: *payload != Type::Unit,
+        }
+    }
+
+    /// The cases of an `option<T>`.
+    pub(crate) fn option(payload: &Type) -> [Lifted; 2] {
+        [
+            Lifted::Nothing,
+            Lifted::Some {
+                has_value: *payload != Type::Unit,
+            },
+        ]
+    }
+
+    /// The cases of an `expected<T, E>`.
+    pub(crate) fn expected(expected: &Expected) -> [Lifted; 2] {
+        [
+            Lifted::Ok {
+                has_value: expected.ok != Type::Unit,
+            },
+            Lifted::Err {
+                has_value: expected.err != Type::Unit,
+            },
+        ]
+    }
+}
+
+/// The statements generated for a function, plus the bookkeeping every
+/// generator's `Bindgen` implementation needs for temporaries and nested
+/// blocks.
+#[derive(Debug, Default)]
+pub(crate) struct FunctionCode {
+    /// The statements for the block currently being generated.
+    pub(crate) src: String,
+    /// Prepended to the name of every temporary (e.g. PHP's `$`).
+    sigil: &'static str,
+    tmp: usize,
+    /// The enclosing blocks' statements, while a nested block is generated.
+    block_storage: Vec<String>,
+    /// Finished blocks and their results, waiting for the instruction which
+    /// uses them.
+    blocks: Vec<(String, Vec<String>)>,
+    /// The names given to each variant case's payload while lowering.
+    payloads: Vec<String>,
+}
+
+impl FunctionCode {
+    /// Generate code for a language where variable names start with a
+    /// sigil.
+    pub(crate) fn with_sigil(sigil: &'static str) -> Self {
+        FunctionCode {
+            sigil,
+            ..Default::default()
+        }
+    }
+
+    /// Get a unique name for a temporary.
+    pub(crate) fn tmp(&mut self, prefix: &str) -> String {
+        let name = format!("{}{prefix}{}", self.sigil, self.tmp);
+        self.tmp += 1;
+        name
+    }
+
+    pub(crate) fn line(&mut self, line: impl AsRef<str>) {
+        self.src.push_str(line.as_ref());
+        self.src.push('\n');
+    }
+
+    pub(crate) fn push_block(&mut self) {
+        let prev = std::mem::take(&mut self.src);
+        self.block_storage.push(prev);
+    }
+
+    pub(crate) fn finish_block(&mut self, operands: &mut Vec<String>) {
+        let to_restore = self.block_storage.pop().unwrap();
+        let src = std::mem::replace(&mut self.src, to_restore);
+        self.blocks.push((src, std::mem::take(operands)));
+    }
+
+    /// Take the most recently finished block and its results.
+    pub(crate) fn pop_block(&mut self) -> (String, Vec<String>) {
+        self.blocks.pop().expect("a finished block")
+    }
+
+    /// Take the blocks for each of a variant-like value's cases, in order.
+    pub(crate) fn pop_blocks(&mut self, cases: usize) -> Vec<(String, Vec<String>)> {
+        self.blocks.drain(self.blocks.len() - cases..).collect()
+    }
+
+    /// Name the payload of a variant case (i.e. for
+    /// `Instruction::VariantPayloadName`).
+    pub(crate) fn payload_name(&mut self) -> String {
+        let payload = self.tmp("payload");
+        self.payloads.push(payload.clone());
+        payload
+    }
+
+    pub(crate) fn pop_payload(&mut self) -> String {
+        self.payloads.pop().expect("a payload name")
+    }
+
+    /// Take the payload names for each of a variant-like value's cases, in
+    /// order.
+    pub(crate) fn pop_payloads(&mut self, cases: usize) -> Vec<String> {
+        self.payloads.drain(self.payloads.len() - cases..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, path::Path};
+
+    use insta::Settings;
+
+    use super::*;
+    use crate::{Abi, BindingsOptions, Command, Interface, Library, Metadata, Module, Mount};
+
+    const WASMER_PACK_EXPORTS: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../wasm/wasmer-pack.exports.wai"
+    ));
+
+    const CALCULATOR_EXPORTS: &str = r#"
+        record point { x: float32, y: float32 }
+        flags permissions { read, write, execute }
+        enum color { red, green, blue }
+        variant shape { circle(float32), rectangle(tuple<point, point>), nothing }
+        union number { s32, float64 }
+
+        /// Calculate a shape's area.
+        area: func(s: shape) -> float64
+        mix: func(colors: list<color>, perms: permissions) -> expected<string, u32>
+        lookup: func(name: string, fallback: option<u32>) -> option<list<point>>
+        sum: func(numbers: list<number>) -> number
+    "#;
+
+    /// A generator built on top of these helpers, and what we expect it to
+    /// produce.
+    struct Generator {
+        language: &'static str,
+        generate: fn(&Package, &BindingsOptions) -> Result<Files, crate::Error>,
+        quote: fn(&str) -> String,
+        /// Strings and their quoted versions.
+        quoted: &'static [(&'static str, &'static str)],
+        /// Every file generated for [`calculator()`], except the WebAssembly
+        /// modules.
+        calculator_files: &'static [&'static str],
+        /// Where WebAssembly modules are saved, unless they are embedded in
+        /// the generated code.
+        wasm_dir: Option<&'static str>,
+        calculator_snapshots: &'static [&'static str],
+        /// Where the files mounted by [`python_with_mounts()`] are saved,
+        /// unless they are embedded in the generated code.
+        volumes_dir: Option<&'static str>,
+        mounts_snapshots: &'static [&'static str],
+        /// The error for packages exporting resources, if they aren't
+        /// supported.
+        resources_error: Option<&'static str>,
+    }
+
+    const GENERATORS: &[Generator] = &[
+        Generator {
+            language: "rust",
+            generate: crate::generate_rust,
+            quote: crate::rust::literal,
+            quoted: &[
+                ("plain", "\"plain\""),
+                ("a \"quote\"\n", "\"a \\\"quote\\\"\\n\""),
+                ("back\\slash\u{7}", "\"back\\\\slash\\u{7}\""),
+            ],
+            calculator_files: &[
+                "Cargo.toml",
+                "src/lib.rs",
+                "src/bindings.rs",
+                "src/commands.rs",
+                "wai/calculator/calculator.exports.wai",
+                "wai/calculator/logger.wai",
+            ],
+            wasm_dir: Some("wasm"),
+            calculator_snapshots: &[
+                "Cargo.toml",
+                "src/lib.rs",
+                "src/bindings.rs",
+                "src/commands.rs",
+            ],
+            volumes_dir: Some("volumes"),
+            mounts_snapshots: &["src/mounts.rs", "src/bindings.rs"],
+            resources_error: None,
+        },
+        Generator {
+            language: "go",
+            generate: crate::generate_go,
+            quote: crate::go::quote,
+            quoted: &[
+                ("plain", "\"plain\""),
+                ("a \"quote\"\n", "\"a \\\"quote\\\"\\n\""),
+                ("back\\slash\u{7}", "\"back\\\\slash\\u0007\""),
+            ],
+            calculator_files: &[
+                "go.mod",
+                "calculator.go",
+                "internal/abi/abi.go",
+                "bindings/bindings.go",
+                "bindings/calculator/calculator.go",
+                "commands/commands.go",
+                "wasm/wasm.go",
+            ],
+            wasm_dir: Some("wasm"),
+            calculator_snapshots: &[
+                "go.mod",
+                "calculator.go",
+                "bindings/bindings.go",
+                "bindings/calculator/calculator.go",
+                "commands/commands.go",
+            ],
+            volumes_dir: Some("volumes"),
+            mounts_snapshots: &["volumes/volumes.go", "bindings/bindings.go"],
+            resources_error: Some(
+                "The \"wasmer-pack\" library uses resources, which isn't supported by the Go bindings",
+            ),
+        },
+        Generator {
+            language: "c",
+            generate: crate::generate_c,
+            quote: crate::c::quote,
+            quoted: &[
+                ("plain", "\"plain\""),
+                ("a \"quote\"\n", "\"a \\\"quote\\\"\\n\""),
+                ("back\\slash\u{7}1", "\"back\\\\slash\\0071\""),
+            ],
+            calculator_files: &[
+                "CMakeLists.txt",
+                "include/calculator.h",
+                "src/calculator.c",
+                "src/calculator_bundled.h",
+            ],
+            wasm_dir: None,
+            calculator_snapshots: &["CMakeLists.txt", "include/calculator.h", "src/calculator.c"],
+            volumes_dir: None,
+            mounts_snapshots: &["src/python.c", "src/python_bundled.h"],
+            resources_error: Some(
+                "The \"wasmer-pack\" library uses resources, which isn't supported by the C bindings",
+            ),
+        },
+        Generator {
+            language: "ruby",
+            generate: crate::generate_ruby,
+            quote: crate::ruby::quote,
+            quoted: &[
+                ("plain", "\"plain\""),
+                ("a \"quote\"\n", "\"a \\\"quote\\\"\\n\""),
+                ("#{interpolated}", "\"\\#{interpolated}\""),
+                ("back\\slash\u{7}", "\"back\\\\slash\\u0007\""),
+            ],
+            calculator_files: &[
+                "calculator.gemspec",
+                "lib/calculator.rb",
+                "lib/calculator/version.rb",
+                "lib/calculator/abi.rb",
+                "lib/calculator/bindings.rb",
+                "lib/calculator/bindings/calculator.rb",
+                "lib/calculator/commands.rb",
+            ],
+            wasm_dir: Some("lib/calculator/wasm"),
+            calculator_snapshots: &[
+                "calculator.gemspec",
+                "lib/calculator.rb",
+                "lib/calculator/bindings.rb",
+                "lib/calculator/bindings/calculator.rb",
+                "lib/calculator/commands.rb",
+            ],
+            volumes_dir: Some("lib/python/volumes"),
+            mounts_snapshots: &["lib/python/mounts.rb", "lib/python/bindings.rb"],
+            resources_error: None,
+        },
+        Generator {
+            language: "php",
+            generate: crate::generate_php,
+            quote: crate::php::quote,
+            quoted: &[
+                ("plain", "'plain'"),
+                ("it's a \\path", r"'it\'s a \\path'"),
+                ("$var \"quoted\"\n", r#""\$var \"quoted\"\n""#),
+                ("bell\u{7}", r#""bell\u{7}""#),
+            ],
+            calculator_files: &[
+                "composer.json",
+                "src/Package.php",
+                "src/Internal/Abi.php",
+                "src/Bindings.php",
+                "src/Bindings/Calculator/Calculator.php",
+                "src/Bindings/Calculator/Color.php",
+                "src/Bindings/Calculator/Logger.php",
+                "src/Bindings/Calculator/Number.php",
+                "src/Bindings/Calculator/Permissions.php",
+                "src/Bindings/Calculator/Point.php",
+                "src/Bindings/Calculator/Shape.php",
+                "src/Commands.php",
+                "src/ExitStatus.php",
+                "src/Expected.php",
+            ],
+            wasm_dir: Some("wasm"),
+            calculator_snapshots: &[
+                "composer.json",
+                "src/Package.php",
+                "src/Bindings.php",
+                "src/Bindings/Calculator/Calculator.php",
+                "src/Bindings/Calculator/Shape.php",
+                "src/Bindings/Calculator/Logger.php",
+                "src/Commands.php",
+            ],
+            volumes_dir: Some("volumes"),
+            mounts_snapshots: &["src/Mounts.php", "src/Bindings.php"],
+            resources_error: None,
+        },
+    ];
+
+    /// A library using most WAI types and a host interface, plus a couple
+    /// of commands.
+    fn calculator() -> Package {
+        let metadata = Metadata::new(
+            "wasmer/calculator".parse().unwrap(),
+            "1.2.3-beta.1".parse().unwrap(),
+        )
+        .with_description("Do some maths.")
+        .with_license("MIT")
+        .with_authors(["Wasmer Engineering Team <engineering@wasmer.io>"]);
+        let exports = Interface::from_wit("calculator.exports.wai", CALCULATOR_EXPORTS).unwrap();
+        let logger = Interface::from_wit(
+            "logger.wai",
+            "log: func(message: string, level: u8)\nprompt: func(question: string) -> option<string>",
+        )
+        .unwrap();
+        let libraries = vec![Library {
+            module: Module {
+                name: "calculator.wasm".to_string(),
+                abi: Abi::None,
+                wasm: crate::exports::stub_module(&exports),
+            },
+            exports,
+            imports: vec![logger],
+        }];
+        // Both commands use the same (empty) binary, so they share a module
+        let commands = vec![
+            Command::new("first", [])
+                .with_main_args(["--verbose"])
+                .with_env([("RUST_LOG", "info"), ("PATH", "/bin")]),
+            Command::new("second-with-dashes", []),
+        ];
+
+        Package::new(metadata, libraries, commands)
+    }
+
+    /// A WASI library with a directory mounted at `/lib`.
+    fn python_with_mounts() -> Package {
+        let metadata = Metadata::new("wasmer/python".parse().unwrap(), "1.2.3".parse().unwrap());
+        let lib = Library {
+            module: Module {
+                name: "python.wasm".to_string(),
+                abi: Abi::Wasi,
+                wasm: wat::parse_str("(module)").unwrap(),
+            },
+            exports: Interface::from_wit("python.exports.wai", "").unwrap(),
+            imports: Vec::new(),
+        };
+        let mut volume = Files::new();
+        volume.insert("python/os.py", "import sys".into());
+
+        Package::new(metadata, vec![lib], Vec::new()).with_mounts([Mount::new("/lib", volume)])
+    }
+
+    /// A library which exports resources.
+    fn wasmer_pack() -> Package {
+        let metadata = Metadata::new(
+            "wasmer/wasmer-pack".parse().unwrap(),
+            "1.2.3".parse().unwrap(),
+        );
+        let exports = Interface::from_wit("wasmer-pack.exports.wai", WASMER_PACK_EXPORTS).unwrap();
+        let lib = Library {
+            module: Module {
+                name: "wasmer_pack_wasm.wasm".to_string(),
+                abi: Abi::None,
+                wasm: crate::exports::stub_module(&exports),
+            },
+            exports,
+            imports: Vec::new(),
+        };
+
+        Package::new(metadata, vec![lib], Vec::new())
+    }
+
+    fn snapshot_settings() -> Settings {
+        let mut settings = Settings::clone_current();
+        settings.add_filter(r"[0-9a-f]{64}\.wasm", "[hash].wasm");
+        settings.add_filter(r"wasm_[0-9a-f]{64}", "wasm_[hash]");
+        settings
+    }
+
+    fn assert_snapshots(generator: &Generator, files: &Files, paths: &[&str], test: &str) {
+        snapshot_settings().bind(|| {
+            for path in paths {
+                let name = format!("{}__{test}__{}", generator.language, path.replace('/', "_"));
+                insta::assert_display_snapshot!(
+                    name,
+                    files[*path]
+                        .utf8_contents()
+                        .unwrap()
+                        .replace(crate::GENERATOR, "XXX")
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn generated_files() {
+        let package = calculator();
+        let modules = [
+            crate::files::wasm_filename(&package.libraries()[0].module.wasm),
+            crate::files::wasm_filename(&[]),
+        ];
+
+        for generator in GENERATORS {
+            let mut expected: BTreeSet<_> = generator
+                .calculator_files
+                .iter()
+                .map(|path| Path::new(path).to_path_buf())
+                .collect();
+            if let Some(wasm_dir) = generator.wasm_dir {
+                expected.extend(modules.iter().map(|wasm| Path::new(wasm_dir).join(wasm)));
+            }
+
+            let files = (generator.generate)(&package, &BindingsOptions::default()).unwrap();
+
+            let actual: BTreeSet<_> = files.iter().map(|(p, _)| p.to_path_buf()).collect();
+            assert_eq!(actual, expected, "{}", generator.language);
+            assert_snapshots(
+                generator,
+                &files,
+                generator.calculator_snapshots,
+                "calculator",
+            );
+        }
+    }
+
+    #[test]
+    fn generated_files_with_wasi_libraries_and_mounts() {
+        let package = python_with_mounts();
+
+        for generator in GENERATORS {
+            let files = (generator.generate)(&package, &BindingsOptions::default()).unwrap();
+
+            let os_py = files
+                .iter()
+                .find(|(path, _)| path.ends_with("python/os.py"))
+                .map(|(path, file)| (path.to_path_buf(), file.utf8_contents()));
+            let expected = generator
+                .volumes_dir
+                .map(|dir| (Path::new(dir).join("0/python/os.py"), Some("import sys")));
+            assert_eq!(os_py, expected, "{}", generator.language);
+            assert_snapshots(generator, &files, generator.mounts_snapshots, "mounts");
+        }
+    }
+
+    #[test]
+    fn exported_resources() {
+        let package = wasmer_pack();
+
+        for generator in GENERATORS {
+            let result = (generator.generate)(&package, &BindingsOptions::default());
+
+            match generator.resources_error {
+                Some(expected) => {
+                    let err = result.unwrap_err();
+                    assert_eq!(err.to_string(), expected, "{}", generator.language);
+                }
+                None => {
+                    assert!(result.is_ok(), "{}: {:?}", generator.language, result.err());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn identifiers_are_only_matched_as_whole_words() {
+        let src = "base0 = base + ITER_BASE * 2; $base = xbase_ITER_BASE;";
+
+        assert!(uses_ident(src, "base"));
+        assert!(!uses_ident(src, "base1"));
+        assert!(uses_ident(src, "$base"));
+        assert_eq!(count_ident(src, "base"), 2);
+        assert_eq!(
+            replace_ident(src, ITER_BASE, "base1"),
+            "base0 = base + base1 * 2; $base = xbase_ITER_BASE;"
+        );
+    }
+
+    #[test]
+    fn quoted_strings_are_valid_literals() {
+        for generator in GENERATORS {
+            for (s, expected) in generator.quoted {
+                assert_eq!((generator.quote)(s), *expected, "{}", generator.language);
+            }
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! A front end for the component model's WIT dialect.
+//!
+//! Rather than maintaining a second set of generators, the file is parsed
+//! with `wit-parser` and the subset of WIT which has a WAI equivalent is
+//! lowered to WAI source code and handed to `wai-parser`. Anything else
+//! (worlds, `use` statements, constructors, `future`s and `stream`s, etc.) is
+//! rejected with an error.
+//!
+//! Only the interface definition is translated. The guest still needs to be a
+//! core WebAssembly module using the WAI canonical ABI.
+
+use std::{fmt::Write, path::Path};
+
+use anyhow::{Context, Error};
+use wit_parser::{
+    Docs, Function, FunctionKind, Handle, Interface, Resolve, Results, Type, TypeDefKind, TypeId,
+    UnresolvedPackage,
+};
+
+/// WAI keywords which need to be escaped with a `%` when used as identifiers.
+const WAI_KEYWORDS: &[&str] = &[
+    "use",
+    "type",
+    "resource",
+    "func",
+    "u8",
+    "u16",
+    "u32",
+    "u64",
+    "s8",
+    "s16",
+    "s32",
+    "s64",
+    "float32",
+    "float64",
+    "char",
+    "handle",
+    "record",
+    "flags",
+    "variant",
+    "enum",
+    "union",
+    "bool",
+    "string",
+    "option",
+    "expected",
+    "future",
+    "stream",
+    "list",
+    "as",
+    "from",
+    "static",
+    "interface",
+    "tuple",
+    "async",
+    "unit",
+    "implements",
+];
+
+/// An interface which has been lowered from component-model WIT to WAI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lowered {
+    /// The name of the `interface` being lowered.
+    pub(crate) name: String,
+    /// The equivalent WAI source code.
+    pub(crate) wai: String,
+}
+
+/// Lower a WIT file containing a single `interface` to WAI.
+pub(crate) fn lower(path: &str, src: &str) -> Result<Lowered, Error> {
+    let pkg = UnresolvedPackage::parse(Path::new(path), src)?;
+
+    if let Some((_, world)) = pkg.worlds.iter().next() {
+        anyhow::bail!(
+            "worlds aren't supported, move the exports of \"{}\" into an interface",
+            world.name
+        );
+    }
+    if let Some(dependency) = pkg.foreign_deps.keys().next() {
+        anyhow::bail!(
+            "`use` statements aren't supported, define the types from \"{dependency}\" in this interface instead"
+        );
+    }
+
+    let mut resolve = Resolve::new();
+    let id = resolve.push(pkg)?;
+
+    let mut interfaces = resolve.packages[id].interfaces.iter();
+    let (name, &interface) = interfaces.next().context("no interface was defined")?;
+    if let Some((second, _)) = interfaces.next() {
+        anyhow::bail!(
+            "only one interface per file is supported, but both \"{name}\" and \"{second}\" were defined"
+        );
+    }
+
+    let mut lowering = Lowering {
+        resolve: &resolve,
+        out: String::new(),
+    };
+    lowering.interface(&resolve.interfaces[interface])?;
+
+    Ok(Lowered {
+        name: name.clone(),
+        wai: lowering.out,
+    })
+}
+
+struct Lowering<'a> {
+    resolve: &'a Resolve,
+    out: String,
+}
+
+impl Lowering<'_> {
+    fn interface(&mut self, interface: &Interface) -> Result<(), Error> {
+        let resolve = self.resolve;
+
+        for (name, &id) in &interface.types {
+            self.separator();
+            self.docs(&resolve.types[id].docs, "");
+            self.type_def(interface, name, id)?;
+        }
+
+        for func in interface.functions.values() {
+            // Resource methods are lowered as part of their resource
+            if func.kind == FunctionKind::Freestanding {
+                self.separator();
+                self.docs(&func.docs, "");
+                self.function(func, "")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn type_def(&mut self, interface: &Interface, name: &str, id: TypeId) -> Result<(), Error> {
+        let name = escape(name);
+
+        match &self.resolve.types[id].kind {
+            TypeDefKind::Record(record) => {
+                writeln!(self.out, "record {name} {{")?;
+                for field in &record.fields {
+                    let ty = self.ty(&field.ty)?;
+                    self.docs(&field.docs, "    ");
+                    writeln!(self.out, "    {}: {ty},", escape(&field.name))?;
+                }
+                self.out.push_str("}\n");
+            }
+            TypeDefKind::Variant(variant) => {
+                writeln!(self.out, "variant {name} {{")?;
+                for case in &variant.cases {
+                    self.docs(&case.docs, "    ");
+                    match &case.ty {
+                        Some(ty) => {
+                            let ty = self.ty(ty)?;
+                            writeln!(self.out, "    {}({ty}),", escape(&case.name))?;
+                        }
+                        None => writeln!(self.out, "    {},", escape(&case.name))?,
+                    }
+                }
+                self.out.push_str("}\n");
+            }
+            TypeDefKind::Enum(e) => {
+                writeln!(self.out, "enum {name} {{")?;
+                for case in &e.cases {
+                    self.docs(&case.docs, "    ");
+                    writeln!(self.out, "    {},", escape(&case.name))?;
+                }
+                self.out.push_str("}\n");
+            }
+            TypeDefKind::Flags(flags) => {
+                writeln!(self.out, "flags {name} {{")?;
+                for flag in &flags.flags {
+                    self.docs(&flag.docs, "    ");
+                    writeln!(self.out, "    {},", escape(&flag.name))?;
+                }
+                self.out.push_str("}\n");
+            }
+            TypeDefKind::Resource => self.resource(interface, &name, id)?,
+            kind => {
+                let ty = self.anonymous_type(kind)?;
+                writeln!(self.out, "type {name} = {ty}")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resource(&mut self, interface: &Interface, name: &str, id: TypeId) -> Result<(), Error> {
+        let methods: Vec<&Function> = interface
+            .functions
+            .values()
+            .filter(|f| match f.kind {
+                FunctionKind::Method(r)
+                | FunctionKind::Static(r)
+                | FunctionKind::Constructor(r) => r == id,
+                FunctionKind::Freestanding => false,
+            })
+            .collect();
+
+        if methods.is_empty() {
+            writeln!(self.out, "resource {name}")?;
+            return Ok(());
+        }
+
+        writeln!(self.out, "resource {name} {{")?;
+        for method in methods {
+            if let FunctionKind::Constructor(_) = method.kind {
+                anyhow::bail!(
+                    "resource constructors aren't supported, give \"{name}\" a static function instead"
+                );
+            }
+            self.docs(&method.docs, "    ");
+            self.function(method, "    ")?;
+        }
+        self.out.push_str("}\n");
+
+        Ok(())
+    }
+
+    fn function(&mut self, func: &Function, indent: &str) -> Result<(), Error> {
+        let name = func.item_name();
+
+        let params = match func.kind {
+            // WAI methods take "self" implicitly
+            FunctionKind::Method(_) => &func.params[1..],
+            _ => &func.params[..],
+        };
+        let params = params
+            .iter()
+            .map(|(param, ty)| Ok(format!("{}: {}", escape(param), self.ty(ty)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let result = match &func.results {
+            Results::Anon(ty) => Some(self.ty(ty)?),
+            Results::Named(results) if results.is_empty() => None,
+            Results::Named(_) => anyhow::bail!(
+                "\"{name}\" has named or multiple results, which aren't supported. Return a record instead"
+            ),
+        };
+
+        let prefix = match func.kind {
+            FunctionKind::Static(_) => "static ",
+            _ => "",
+        };
+        write!(
+            self.out,
+            "{indent}{prefix}{}: func({})",
+            escape(name),
+            params.join(", ")
+        )?;
+        if let Some(result) = result {
+            write!(self.out, " -> {result}")?;
+        }
+        self.out.push('\n');
+
+        Ok(())
+    }
+
+    fn ty(&self, ty: &Type) -> Result<String, Error> {
+        let ty = match ty {
+            Type::Bool => "bool",
+            Type::U8 => "u8",
+            Type::U16 => "u16",
+            Type::U32 => "u32",
+            Type::U64 => "u64",
+            Type::S8 => "s8",
+            Type::S16 => "s16",
+            Type::S32 => "s32",
+            Type::S64 => "s64",
+            Type::Float32 => "float32",
+            Type::Float64 => "float64",
+            Type::Char => "char",
+            Type::String => "string",
+            Type::Id(id) => {
+                let def = &self.resolve.types[*id];
+                return match (&def.name, &def.kind) {
+                    (Some(name), TypeDefKind::Resource) => Ok(format!("handle {}", escape(name))),
+                    (Some(name), _) => Ok(escape(name)),
+                    (None, kind) => self.anonymous_type(kind),
+                };
+            }
+        };
+
+        Ok(ty.to_string())
+    }
+
+    fn anonymous_type(&self, kind: &TypeDefKind) -> Result<String, Error> {
+        let ty = match kind {
+            TypeDefKind::List(element) => format!("list<{}>", self.ty(element)?),
+            TypeDefKind::Option(inner) => format!("option<{}>", self.ty(inner)?),
+            TypeDefKind::Tuple(tuple) => {
+                let elements = tuple
+                    .types
+                    .iter()
+                    .map(|ty| self.ty(ty))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                format!("tuple<{}>", elements.join(", "))
+            }
+            TypeDefKind::Result(result) => format!(
+                "expected<{}, {}>",
+                self.optional_ty(result.ok.as_ref())?,
+                self.optional_ty(result.err.as_ref())?,
+            ),
+            TypeDefKind::Handle(Handle::Own(resource) | Handle::Borrow(resource)) => {
+                self.ty(&Type::Id(*resource))?
+            }
+            TypeDefKind::Type(ty) => self.ty(ty)?,
+            TypeDefKind::Future(_) => anyhow::bail!("`future` types aren't supported"),
+            TypeDefKind::Stream(_) => anyhow::bail!("`stream` types aren't supported"),
+            other => anyhow::bail!("{other:?} can't be used as an anonymous type"),
+        };
+
+        Ok(ty)
+    }
+
+    /// A type which may be omitted (e.g. the `_` in `result<_, string>`).
+    fn optional_ty(&self, ty: Option<&Type>) -> Result<String, Error> {
+        match ty {
+            Some(ty) => self.ty(ty),
+            None => Ok("unit".to_string()),
+        }
+    }
+
+    fn separator(&mut self) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+    }
+
+    fn docs(&mut self, docs: &Docs, indent: &str) {
+        for line in docs.contents.iter().flat_map(|d| d.lines()) {
+            let _ = writeln!(self.out, "{indent}/// {line}");
+        }
+    }
+}
+
+/// Escape an identifier if it would clash with a WAI keyword.
+fn escape(name: &str) -> String {
+    if WAI_KEYWORDS.contains(&name) {
+        format!("%{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lower_an_interface() {
+        let src = r#"
+            package wasmer:geometry@0.1.0;
+
+            /// Shapes and things.
+            interface geometry {
+                /// A point in space.
+                record point {
+                    x: f32,
+                    /// The vertical position.
+                    y: f32,
+                }
+
+                enum colour { red, green, blue }
+
+                flags permissions { read, write }
+
+                variant shape {
+                    circle(f64),
+                    polygon(list<point>),
+                    empty,
+                }
+
+                type points = list<tuple<point, option<string>>>;
+
+                resource canvas {
+                    draw: func(shape: shape) -> result<_, string>;
+                    merge: static func(a: borrow<canvas>, b: own<canvas>) -> canvas;
+                }
+
+                area: func(s: shape) -> result<f64>;
+                clear: func(%type: u32);
+            }
+        "#;
+
+        let Lowered { name, wai } = lower("geometry.wit", src).unwrap();
+
+        assert_eq!(name, "geometry");
+        insta::assert_display_snapshot!(wai);
+        crate::Interface::from_wit("geometry.wai", &wai).unwrap();
+    }
+
+    #[test]
+    fn unsupported_constructs_are_rejected() {
+        let inputs = [
+            (
+                "package a:b;\nworld w { export f: func(); }",
+                "worlds aren't supported",
+            ),
+            (
+                "package a:b;\ninterface a {}\ninterface b {}",
+                "only one interface per file is supported",
+            ),
+            (
+                "package a:b;\ninterface a {\n    use c:d/e.{f};\n}",
+                "`use` statements aren't supported",
+            ),
+            (
+                "package a:b;\ninterface a {\n    resource r {\n        constructor();\n    }\n}",
+                "resource constructors aren't supported",
+            ),
+            (
+                "package a:b;\ninterface a {\n    f: func() -> stream<u8>;\n}",
+                "`stream` types aren't supported",
+            ),
+            (
+                "package a:b;\ninterface a {\n    f: func() -> (a: u32, b: u32);\n}",
+                "\"f\" has named or multiple results",
+            ),
+            ("package a:b;", "no interface was defined"),
+        ];
+
+        for (src, expected) in inputs {
+            let err = lower("a.wit", src).unwrap_err().to_string();
+            assert!(
+                err.starts_with(expected),
+                "\"{err}\" doesn't start with \"{expected}\""
+            );
+        }
+    }
+
+    #[test]
+    fn syntax_errors_come_from_wit_parser() {
+        let err = lower("a.wit", "package a:b;\ninterface a {\n    f: func(;\n}").unwrap_err();
+
+        assert!(format!("{err:?}").contains("a.wit:3"), "{err:?}");
+    }
+}
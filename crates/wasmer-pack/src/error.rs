@@ -0,0 +1,115 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::ValidationError;
+
+/// The errors that may be returned by `wasmer-pack`.
+///
+/// Each variant's [`Display`] implementation is the same as the error it
+/// wraps, so this can be used as a drop-in replacement for the underlying
+/// error message.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The package couldn't be loaded (e.g. the WEBC file is invalid or a file
+    /// couldn't be read).
+    Load(anyhow::Error),
+    /// An interface definition couldn't be parsed.
+    Parse(anyhow::Error),
+    /// The package failed validation.
+    Validation(ValidationError),
+    /// The bindings couldn't be generated (e.g. because a template failed to
+    /// render).
+    Generate(anyhow::Error),
+}
+
+impl Error {
+    /// Attach some extra context to this error without changing what kind of
+    /// error it is.
+    pub(crate) fn context<C>(self, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        match self {
+            Error::Load(e) => Error::Load(e.context(context)),
+            Error::Parse(e) => Error::Parse(e.context(context)),
+            Error::Generate(e) => Error::Generate(e.context(context)),
+            Error::Validation(e) => Error::Validation(e.with_context(context)),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Load(e) | Error::Parse(e) | Error::Generate(e) => Display::fmt(e, f),
+            Error::Validation(e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Load(e) | Error::Parse(e) | Error::Generate(e) => e.source(),
+            Error::Validation(e) => e.source(),
+        }
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(e: ValidationError) -> Self {
+        Error::Validation(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_preserves_the_error_kind() {
+        let err = crate::Interface::from_wit("broken.wai", "this isn't valid WAI").unwrap_err();
+        let err = err.context("Unable to load the exports interface");
+
+        assert!(matches!(err, Error::Parse(_)));
+        let chain: Vec<_> = anyhow::Error::from(err)
+            .chain()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(chain[0], "Unable to load the exports interface");
+        assert_eq!(chain[1], "Unable to parse the WIT file");
+    }
+
+    #[test]
+    fn context_is_kept_for_validation_errors() {
+        let problem = crate::Problem::DuplicateName {
+            kind: crate::ItemKind::Command,
+            name: "first".to_string(),
+        };
+        let err = Error::from(
+            crate::validation::ValidationError::from_problems(vec![problem]).unwrap_err(),
+        );
+
+        let err = err
+            .context("Unable to load the \"first\" command")
+            .context("Unable to load the package");
+
+        let Error::Validation(e) = &err else {
+            panic!("Expected a validation error, found {err:?}");
+        };
+        assert_eq!(
+            e.context(),
+            [
+                "Unable to load the package",
+                "Unable to load the \"first\" command"
+            ]
+        );
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Unable to load the package: Unable to load the \"first\" command: {}",
+                e.problems()[0]
+            )
+        );
+    }
+}
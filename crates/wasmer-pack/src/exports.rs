@@ -0,0 +1,370 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+use anyhow::{Context, Error};
+use wai_parser::abi::{AbiVariant, WasmType};
+use wasmparser::{ExternalKind, Payload, TypeRef, ValType};
+
+use crate::{Interface, Library};
+
+/// The name of the function `wai-bindgen` uses to allocate memory inside the
+/// guest.
+const REALLOC: &str = "canonical_abi_realloc";
+
+/// The results of comparing the functions a [`Library`]'s WebAssembly module
+/// exports with the ones declared by its exports [`Interface`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    /// Exports the interface requires, but the module doesn't provide.
+    pub missing: Vec<String>,
+    /// Functions exported by the module which aren't part of the interface
+    /// (ignoring intrinsics like `_start` or `__heap_base`).
+    pub extra: Vec<String>,
+    /// Exports whose type doesn't match the canonical ABI signature.
+    pub mistyped: Vec<MistypedExport>,
+}
+
+impl ExportReport {
+    /// Are there any missing or mistyped exports?
+    ///
+    /// Extra exports are harmless, so they aren't considered errors.
+    pub fn has_errors(&self) -> bool {
+        !self.missing.is_empty() || !self.mistyped.is_empty()
+    }
+}
+
+/// An export with the wrong type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MistypedExport {
+    pub name: String,
+    /// The expected type (e.g. `func(i32, i32) -> i32`).
+    pub expected: String,
+    /// The type the module actually exports (e.g. `func(i32)`).
+    pub actual: String,
+}
+
+impl Library {
+    /// Check that this library's WebAssembly module exports everything its
+    /// exports [`Interface`] declares, using the canonical ABI signatures.
+    pub fn check_exports(&self) -> Result<ExportReport, crate::Error> {
+        let actual = module_exports(&self.module.wasm)
+            .with_context(|| format!("Unable to parse \"{}\"", self.module.name))
+            .map_err(crate::Error::Load)?;
+        let expected = expected_exports(&self.exports);
+
+        Ok(compare(&expected, &actual))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Export {
+    Memory,
+    Func {
+        params: Vec<ValType>,
+        results: Vec<ValType>,
+    },
+    Other,
+}
+
+impl Display for Export {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Export::Memory => write!(f, "memory"),
+            Export::Func { params, results } => {
+                write!(f, "func(")?;
+                write_list(f, params)?;
+                write!(f, ")")?;
+
+                match results.as_slice() {
+                    [] => Ok(()),
+                    [ty] => write!(f, " -> {ty}"),
+                    _ => {
+                        write!(f, " -> (")?;
+                        write_list(f, results)?;
+                        write!(f, ")")
+                    }
+                }
+            }
+            Export::Other => write!(f, "a non-function export"),
+        }
+    }
+}
+
+fn write_list(f: &mut Formatter<'_>, types: &[ValType]) -> fmt::Result {
+    for (i, ty) in types.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{ty}")?;
+    }
+
+    Ok(())
+}
+
+fn compare(expected: &BTreeMap<String, Export>, actual: &BTreeMap<String, Export>) -> ExportReport {
+    let mut report = ExportReport::default();
+
+    for (name, expected_export) in expected {
+        match actual.get(name) {
+            None => report.missing.push(name.clone()),
+            Some(actual_export) if actual_export != expected_export => {
+                report.mistyped.push(MistypedExport {
+                    name: name.clone(),
+                    expected: expected_export.to_string(),
+                    actual: actual_export.to_string(),
+                });
+            }
+            Some(_) => {}
+        }
+    }
+
+    report.extra = actual
+        .iter()
+        .filter(|(name, export)| {
+            matches!(export, Export::Func { .. })
+                && !expected.contains_key(name.as_str())
+                && !is_intrinsic(name)
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    report
+}
+
+/// Exports added by the compiler, `wai-bindgen`, or WASI which will never be
+/// mentioned in an interface definition.
+fn is_intrinsic(name: &str) -> bool {
+    name.starts_with("canonical_abi_")
+        || name.starts_with("__")
+        || matches!(name, "_start" | "_initialize")
+}
+
+/// Work out which exports a module implementing this interface would need,
+/// mirroring the names and signatures used by `wai-bindgen`.
+fn expected_exports(interface: &Interface) -> BTreeMap<String, Export> {
+    let iface = &interface.parsed;
+    let mut exports = BTreeMap::new();
+
+    for func in &iface.functions {
+        let name = match &iface.module {
+            Some(module) => format!("{module}#{}", func.name),
+            None => func.name.clone(),
+        };
+        let sig = iface.wasm_signature(AbiVariant::GuestExport, func);
+        let export = Export::Func {
+            params: sig.params.iter().map(val_type).collect(),
+            results: sig.results.iter().map(val_type).collect(),
+        };
+        exports.insert(name, export);
+    }
+
+    for (_, resource) in iface.resources.iter() {
+        if resource.foreign_module.is_none() {
+            exports.insert(
+                format!("canonical_abi_drop_{}", resource.name),
+                Export::Func {
+                    params: vec![ValType::I32],
+                    results: Vec::new(),
+                },
+            );
+        }
+    }
+
+    if !iface.functions.is_empty() {
+        exports.insert("memory".to_string(), Export::Memory);
+        exports.insert(
+            REALLOC.to_string(),
+            Export::Func {
+                params: vec![ValType::I32; 4],
+                results: vec![ValType::I32],
+            },
+        );
+    }
+
+    exports
+}
+
+fn val_type(ty: &WasmType) -> ValType {
+    match ty {
+        WasmType::I32 => ValType::I32,
+        WasmType::I64 => ValType::I64,
+        WasmType::F32 => ValType::F32,
+        WasmType::F64 => ValType::F64,
+    }
+}
+
+/// Get the name and type of everything exported by a WebAssembly module.
+fn module_exports(wasm: &[u8]) -> Result<BTreeMap<String, Export>, Error> {
+    let mut types = Vec::new();
+    // Imported functions come first in the function index space
+    let mut functions = Vec::new();
+    let mut exports = Vec::new();
+
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::TypeSection(section) => {
+                for ty in section {
+                    types.push(match ty? {
+                        wasmparser::Type::Func(func) => Some(func),
+                        _ => None,
+                    });
+                }
+            }
+            Payload::ImportSection(section) => {
+                for import in section {
+                    if let TypeRef::Func(ty) = import?.ty {
+                        functions.push(ty);
+                    }
+                }
+            }
+            Payload::FunctionSection(section) => {
+                for ty in section {
+                    functions.push(ty?);
+                }
+            }
+            Payload::ExportSection(section) => {
+                for export in section {
+                    let export = export?;
+                    exports.push((export.name.to_string(), export.kind, export.index));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    exports
+        .into_iter()
+        .map(|(name, kind, index)| {
+            let export = match kind {
+                ExternalKind::Memory => Export::Memory,
+                ExternalKind::Func => {
+                    let func = functions
+                        .get(index as usize)
+                        .and_then(|&ty| types.get(ty as usize)?.as_ref())
+                        .with_context(|| format!("The \"{name}\" export has an invalid type"))?;
+                    Export::Func {
+                        params: func.params().to_vec(),
+                        results: func.results().to_vec(),
+                    }
+                }
+                _ => Export::Other,
+            };
+            Ok((name, export))
+        })
+        .collect()
+}
+
+/// Generate a WebAssembly module with the exports required by an interface,
+/// where every function just traps.
+#[cfg(test)]
+pub(crate) fn stub_module(interface: &Interface) -> Vec<u8> {
+    let mut wat = String::from("(module\n");
+
+    for (name, export) in expected_exports(interface) {
+        match export {
+            Export::Memory => wat.push_str("  (memory (export \"memory\") 1)\n"),
+            Export::Func { params, results } => {
+                let params: Vec<_> = params.iter().map(|p| format!(" {p}")).collect();
+                let results: Vec<_> = results.iter().map(|r| format!(" {r}")).collect();
+                wat.push_str(&format!(
+                    "  (func (export \"{name}\") (param{}) (result{}) unreachable)\n",
+                    params.concat(),
+                    results.concat(),
+                ));
+            }
+            Export::Other => unreachable!(),
+        }
+    }
+
+    wat.push(')');
+
+    wat::parse_str(wat).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Abi, Module};
+
+    const CALC: &str = r#"
+        add: func(a: float32, b: float32) -> float32
+        describe: func(value: float32) -> string
+        resource calculator {
+            static new: func() -> calculator
+            reset: func()
+        }
+    "#;
+
+    fn library(wat: &str) -> Library {
+        Library {
+            module: Module {
+                name: "calc.wasm".to_string(),
+                abi: Abi::None,
+                wasm: wat::parse_str(wat).unwrap(),
+            },
+            exports: Interface::from_wit("calc.exports.wai", CALC).unwrap(),
+            imports: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn stub_modules_are_valid() {
+        let exports = Interface::from_wit("calc.exports.wai", CALC).unwrap();
+        let lib = Library {
+            module: Module {
+                name: "calc.wasm".to_string(),
+                abi: Abi::None,
+                wasm: stub_module(&exports),
+            },
+            exports,
+            imports: Vec::new(),
+        };
+
+        let report = lib.check_exports().unwrap();
+
+        assert_eq!(report, ExportReport::default());
+    }
+
+    #[test]
+    fn report_missing_extra_and_mistyped_exports() {
+        let lib = library(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "add") (param i32 i32) (result i32) unreachable)
+                (func (export "calculator::new") (result i32) unreachable)
+                (func (export "calculator::reset") (param i32) unreachable)
+                (func (export "canonical_abi_drop_calculator") (param i32) unreachable)
+                (func (export "_start") unreachable)
+                (func (export "subtract") (param f32 f32) (result f32) unreachable)
+            )"#,
+        );
+
+        let report = lib.check_exports().unwrap();
+
+        assert_eq!(
+            report,
+            ExportReport {
+                missing: vec!["canonical_abi_realloc".to_string(), "describe".to_string()],
+                extra: vec!["subtract".to_string()],
+                mistyped: vec![MistypedExport {
+                    name: "add".to_string(),
+                    expected: "func(f32, f32) -> f32".to_string(),
+                    actual: "func(i32, i32) -> i32".to_string(),
+                }],
+            }
+        );
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn invalid_modules_are_a_load_error() {
+        let mut lib = library("(module)");
+        lib.module.wasm = b"not wasm".to_vec();
+
+        let err = lib.check_exports().unwrap_err();
+
+        assert!(matches!(err, crate::Error::Load(_)));
+    }
+}
@@ -6,6 +6,7 @@ use std::{
 };
 
 use anyhow::{Context, Error};
+use sha2::{Digest, Sha256};
 
 /// A set of files loaded into memory.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -111,6 +112,14 @@ impl<P: AsRef<Path>> Index<P> for Files {
     }
 }
 
+/// The filename a WebAssembly module is saved under.
+///
+/// Modules are content-addressed, so libraries and commands which use the same
+/// binary will share a single copy.
+pub(crate) fn wasm_filename(wasm: &[u8]) -> String {
+    format!("{:x}.wasm", Sha256::digest(wasm))
+}
+
 /// A file in memory.
 #[derive(Clone, Default, PartialEq, Eq)]
 pub struct SourceFile(pub Vec<u8>);
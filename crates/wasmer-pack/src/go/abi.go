@@ -0,0 +1,141 @@
+// Package abi implements the canonical ABI used to pass values between Go
+// and WebAssembly.
+package abi
+
+import (
+	"encoding/binary"
+	"errors"
+	"math"
+
+	"github.com/wasmerio/wasmer-go/wasmer"
+)
+
+// Env gives the generated bindings access to an instance's linear memory and
+// allocator.
+type Env struct {
+	memory  *wasmer.Memory
+	realloc *wasmer.Function
+	free    *wasmer.Function
+}
+
+// Bind looks up the exports used by the canonical ABI.
+//
+// This must be called after the instance is created and before any of its
+// functions are called.
+func (e *Env) Bind(instance *wasmer.Instance) {
+	if memory, err := instance.Exports.GetMemory("memory"); err == nil {
+		e.memory = memory
+	}
+	if realloc, err := instance.Exports.GetRawFunction("canonical_abi_realloc"); err == nil {
+		e.realloc = realloc
+	}
+	if free, err := instance.Exports.GetRawFunction("canonical_abi_free"); err == nil {
+		e.free = free
+	}
+}
+
+func (e *Env) bytes(ptr int32, offset int32, size int) []byte {
+	start := int(uint32(ptr)) + int(offset)
+	return e.memory.Data()[start : start+size]
+}
+
+// Realloc calls the instance's "canonical_abi_realloc" function.
+func (e *Env) Realloc(ptr, oldSize, align, newSize int32) (int32, error) {
+	if e.realloc == nil {
+		return 0, errors.New("the module doesn't export \"canonical_abi_realloc\"")
+	}
+	ret, err := e.realloc.Call(ptr, oldSize, align, newSize)
+	if err != nil {
+		return 0, err
+	}
+	return ret.(int32), nil
+}
+
+// Free calls the instance's "canonical_abi_free" function.
+func (e *Env) Free(ptr, size, align int32) error {
+	if e.free == nil {
+		return errors.New("the module doesn't export \"canonical_abi_free\"")
+	}
+	_, err := e.free.Call(ptr, size, align)
+	return err
+}
+
+// LowerString copies a string into linear memory, returning its pointer and
+// length.
+func (e *Env) LowerString(s string) (int32, int32, error) {
+	ptr, err := e.Realloc(0, 0, 1, int32(len(s)))
+	if err != nil {
+		return 0, 0, err
+	}
+	copy(e.bytes(ptr, 0, len(s)), s)
+	return ptr, int32(len(s)), nil
+}
+
+// LiftString copies a string out of linear memory.
+func (e *Env) LiftString(ptr, length int32) string {
+	return string(e.bytes(ptr, 0, int(uint32(length))))
+}
+
+func (e *Env) I32Load(ptr, offset int32) int32 {
+	return int32(binary.LittleEndian.Uint32(e.bytes(ptr, offset, 4)))
+}
+
+func (e *Env) I32Load8U(ptr, offset int32) int32 {
+	return int32(e.bytes(ptr, offset, 1)[0])
+}
+
+func (e *Env) I32Load8S(ptr, offset int32) int32 {
+	return int32(int8(e.bytes(ptr, offset, 1)[0]))
+}
+
+func (e *Env) I32Load16U(ptr, offset int32) int32 {
+	return int32(binary.LittleEndian.Uint16(e.bytes(ptr, offset, 2)))
+}
+
+func (e *Env) I32Load16S(ptr, offset int32) int32 {
+	return int32(int16(binary.LittleEndian.Uint16(e.bytes(ptr, offset, 2))))
+}
+
+func (e *Env) I64Load(ptr, offset int32) int64 {
+	return int64(binary.LittleEndian.Uint64(e.bytes(ptr, offset, 8)))
+}
+
+func (e *Env) F32Load(ptr, offset int32) float32 {
+	return math.Float32frombits(binary.LittleEndian.Uint32(e.bytes(ptr, offset, 4)))
+}
+
+func (e *Env) F64Load(ptr, offset int32) float64 {
+	return math.Float64frombits(binary.LittleEndian.Uint64(e.bytes(ptr, offset, 8)))
+}
+
+func (e *Env) I32Store(ptr, offset int32, value int32) {
+	binary.LittleEndian.PutUint32(e.bytes(ptr, offset, 4), uint32(value))
+}
+
+func (e *Env) I32Store8(ptr, offset int32, value int32) {
+	e.bytes(ptr, offset, 1)[0] = byte(value)
+}
+
+func (e *Env) I32Store16(ptr, offset int32, value int32) {
+	binary.LittleEndian.PutUint16(e.bytes(ptr, offset, 2), uint16(value))
+}
+
+func (e *Env) I64Store(ptr, offset int32, value int64) {
+	binary.LittleEndian.PutUint64(e.bytes(ptr, offset, 8), uint64(value))
+}
+
+func (e *Env) F32Store(ptr, offset int32, value float32) {
+	binary.LittleEndian.PutUint32(e.bytes(ptr, offset, 4), math.Float32bits(value))
+}
+
+func (e *Env) F64Store(ptr, offset int32, value float64) {
+	binary.LittleEndian.PutUint64(e.bytes(ptr, offset, 8), math.Float64bits(value))
+}
+
+// BoolToI32 converts a bool to its canonical ABI representation.
+func BoolToI32(b bool) int32 {
+	if b {
+		return 1
+	}
+	return 0
+}
@@ -0,0 +1,1315 @@
+//! Generate the Go package for a single library.
+//!
+//! Unlike the other generators, there is no `wai-bindgen` backend for
+//! `wasmer-go`, so this module walks each function's canonical ABI
+//! instructions itself.
+
+use heck::{ToLowerCamelCase, ToPascalCase};
+use wai_parser::{
+    abi::{AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType},
+    Docs, Function, Interface, SizeAlign, Type, TypeDefKind,
+};
+
+use crate::{
+    codegen::{self, count_ident, uses_ident, FunctionCode, Layout, Lifted},
+    Library,
+};
+
+/// Go's reserved words.
+pub(crate) const GO_KEYWORDS: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "fallthrough",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "type",
+    "var",
+];
+
+/// Identifiers referenced by the glue code, which a parameter mustn't
+/// shadow.
+const GLUE_NAMES: &[&str] = &[
+    "abi", "append", "args", "base", "bool", "byte", "e", "env", "err", "error", "false",
+    "float32", "float64", "fmt", "host", "i", "int", "int16", "int32", "int64", "int8", "len",
+    "lib", "make", "math", "new", "nil", "result", "results", "rune", "string", "true", "uint",
+    "uint16", "uint32", "uint64", "uint8", "wasmer",
+];
+
+/// Generate the source code for a library's Go package.
+pub(crate) fn generate(lib: &Library, package_ident: &str, module_path: &str) -> String {
+    let mut gen = LibraryGenerator::default();
+    let class_name = lib.class_name();
+
+    let interfaces: Vec<&Interface> = std::iter::once(&lib.exports)
+        .chain(&lib.imports)
+        .map(|i| &i.parsed)
+        .collect();
+
+    for iface in &interfaces {
+        gen.type_definitions(iface);
+    }
+    if interfaces.iter().any(|iface| uses_expected(iface)) {
+        gen.expected_definition();
+    }
+
+    for iface in &lib.imports {
+        gen.host_interface(&iface.parsed);
+    }
+
+    gen.library_struct(&lib.exports.parsed, &class_name, lib.interface_name());
+    gen.instantiate(
+        &lib.exports.parsed,
+        &lib.imports.iter().map(|i| &i.parsed).collect::<Vec<_>>(),
+        &class_name,
+        lib.interface_name(),
+    );
+    gen.exported_functions(&lib.exports.parsed, &class_name);
+
+    for iface in &lib.imports {
+        gen.import_registration(&iface.parsed);
+    }
+
+    let mut std_imports = Vec::new();
+    if gen.needs_fmt {
+        std_imports.push("fmt");
+    }
+    if gen.needs_math {
+        std_imports.push("math");
+    }
+
+    let mut src = String::new();
+    src.push_str(&format!(
+        "// Package {package_ident} contains bindings to the \"{}\" library.\n",
+        lib.interface_name()
+    ));
+    src.push_str(&format!("package {package_ident}\n\nimport (\n"));
+    for import in &std_imports {
+        src.push_str(&format!("\"{import}\"\n"));
+    }
+    if !std_imports.is_empty() {
+        src.push('\n');
+    }
+    src.push_str("\"github.com/wasmerio/wasmer-go/wasmer\"\n\n");
+    src.push_str(&format!("\"{module_path}/internal/abi\"\n)\n"));
+    src.push_str(&gen.src);
+
+    indent(&src)
+}
+
+#[derive(Default)]
+struct LibraryGenerator {
+    src: String,
+    needs_fmt: bool,
+    needs_math: bool,
+}
+
+impl LibraryGenerator {
+    fn push(&mut self, code: &str) {
+        self.src.push('\n');
+        self.src.push_str(code);
+        if !code.ends_with('\n') {
+            self.src.push('\n');
+        }
+    }
+
+    fn type_definitions(&mut self, iface: &Interface) {
+        for (_, def) in iface.types.iter() {
+            let name = match &def.name {
+                Some(name) => name.to_pascal_case(),
+                None => continue,
+            };
+            let mut code = String::new();
+            push_docs(&mut code, &def.docs);
+
+            match &def.kind {
+                TypeDefKind::Record(record) => {
+                    if record.fields.is_empty() {
+                        code.push_str(&format!("type {name} struct{{}}\n"));
+                    } else {
+                        let fields: Vec<_> = record
+                            .fields
+                            .iter()
+                            .map(|f| (f.name.to_pascal_case(), go_type(iface, &f.ty), &f.docs))
+                            .collect();
+                        let width = fields.iter().map(|f| f.0.len()).max().unwrap_or(0);
+                        code.push_str(&format!("type {name} struct {{\n"));
+                        for (field, ty, docs) in fields {
+                            push_docs(&mut code, docs);
+                            code.push_str(&format!("{field:width$} {ty}\n"));
+                        }
+                        code.push_str("}\n");
+                    }
+                }
+                TypeDefKind::Flags(flags) => {
+                    let repr = if flags.flags.len() > 32 {
+                        "uint64"
+                    } else {
+                        "uint32"
+                    };
+                    code.push_str(&format!("type {name} {repr}\n"));
+                    let consts: Vec<_> = flags
+                        .flags
+                        .iter()
+                        .enumerate()
+                        .map(|(i, flag)| {
+                            let ident = format!("{name}{}", flag.name.to_pascal_case());
+                            (ident, format!("{name} = 1 << {i}"), &flag.docs)
+                        })
+                        .collect();
+                    push_consts(&mut code, consts);
+                }
+                TypeDefKind::Enum(e) => {
+                    code.push_str(&format!("type {name} uint32\n"));
+                    let consts: Vec<_> = e
+                        .cases
+                        .iter()
+                        .enumerate()
+                        .map(|(i, case)| {
+                            let ident = format!("{name}{}", case.name.to_pascal_case());
+                            (ident, format!("{name} = {i}"), &case.docs)
+                        })
+                        .collect();
+                    push_consts(&mut code, consts);
+                }
+                TypeDefKind::Variant(variant) => {
+                    let cases = variant
+                        .cases
+                        .iter()
+                        .map(|c| (case_name(&name, &c.name), &c.ty, &c.docs));
+                    push_sum_type(&mut code, iface, &name, cases);
+                }
+                TypeDefKind::Union(union) => {
+                    let cases = union
+                        .cases
+                        .iter()
+                        .enumerate()
+                        .map(|(i, c)| (format!("{name}{i}"), &c.ty, &c.docs));
+                    push_sum_type(&mut code, iface, &name, cases);
+                }
+                TypeDefKind::Tuple(tuple) => {
+                    code.push_str(&format!(
+                        "type {name} = {}\n",
+                        tuple_type(iface, &tuple.types)
+                    ));
+                }
+                TypeDefKind::Option(ty) => {
+                    code.push_str(&format!("type {name} = *{}\n", go_type(iface, ty)));
+                }
+                TypeDefKind::Expected(e) => {
+                    code.push_str(&format!(
+                        "type {name} = Expected[{}, {}]\n",
+                        go_type(iface, &e.ok),
+                        go_type(iface, &e.err)
+                    ));
+                }
+                TypeDefKind::List(ty) => {
+                    code.push_str(&format!("type {name} = []{}\n", go_type(iface, ty)));
+                }
+                TypeDefKind::Type(ty) => {
+                    code.push_str(&format!("type {name} = {}\n", go_type(iface, ty)));
+                }
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                    unreachable!("futures and streams are rejected during validation")
+                }
+            }
+
+            self.push(&code);
+        }
+    }
+
+    fn expected_definition(&mut self) {
+        self.push(
+            "// Expected holds either the result of a successful operation or an error.
+type Expected[T any, E any] struct {
+Ok    T
+Err   E
+IsErr bool
+}",
+        );
+    }
+
+    /// The Go interface a host implements to provide an imported interface.
+    fn host_interface(&mut self, iface: &Interface) {
+        let name = iface.name.to_pascal_case();
+        let mut code = format!(
+            "// {name} is implemented by the host to provide the \"{}\" interface.\n",
+            iface.name
+        );
+        code.push_str(&format!("type {name} interface {{\n"));
+
+        for func in &iface.functions {
+            push_docs(&mut code, &func.docs);
+            code.push_str(&format!(
+                "{}({}) {}\n",
+                func.name.to_pascal_case(),
+                params(iface, func),
+                result_signature(iface, &func.result, false),
+            ));
+        }
+
+        code.push_str("}\n");
+        self.push(&code);
+    }
+
+    fn library_struct(&mut self, exports: &Interface, class_name: &str, interface_name: &str) {
+        let mut fields = vec![
+            ("env".to_string(), "*abi.Env"),
+            ("instance".to_string(), "*wasmer.Instance"),
+        ];
+        for func in &exports.functions {
+            fields.push((function_field(func), "*wasmer.Function"));
+        }
+        let width = fields.iter().map(|f| f.0.len()).max().unwrap_or(0);
+
+        let mut code = format!(
+            "// {class_name} gives access to the functions exported by the\n// \"{interface_name}\" library.\n"
+        );
+        code.push_str(&format!("type {class_name} struct {{\n"));
+        for (field, ty) in fields {
+            code.push_str(&format!("{field:width$} {ty}\n"));
+        }
+        code.push_str("}\n");
+
+        self.push(&code);
+    }
+
+    fn instantiate(
+        &mut self,
+        exports: &Interface,
+        imports: &[&Interface],
+        class_name: &str,
+        interface_name: &str,
+    ) {
+        let mut code = format!(
+            "// Instantiate creates a new instance of the \"{interface_name}\" library.
+//
+// Any functions the library imports from the host are added to imports
+// before the module is instantiated.
+func Instantiate(store *wasmer.Store, module *wasmer.Module, imports *wasmer.ImportObject"
+        );
+        for iface in imports {
+            code.push_str(&format!(
+                ", {} {}",
+                param_name(&iface.name),
+                iface.name.to_pascal_case()
+            ));
+        }
+        code.push_str(&format!(
+            ") (*{class_name}, error) {{\nenv := &abi.Env{{}}\n"
+        ));
+        for iface in imports {
+            code.push_str(&format!(
+                "add{}ToImports(store, imports, env, {})\n",
+                iface.name.to_pascal_case(),
+                param_name(&iface.name)
+            ));
+        }
+        if !imports.is_empty() {
+            code.push('\n');
+        }
+        code.push_str(
+            "instance, err := wasmer.NewInstance(module, imports)
+if err != nil {
+return nil, err
+}
+env.Bind(instance)
+
+",
+        );
+        code.push_str(&format!(
+            "lib := &{class_name}{{env: env, instance: instance}}\n"
+        ));
+        for func in &exports.functions {
+            code.push_str(&format!(
+                "if lib.{}, err = instance.Exports.GetRawFunction({}); err != nil {{\nreturn nil, err\n}}\n",
+                function_field(func),
+                quote(&func.name)
+            ));
+        }
+        code.push_str("\nreturn lib, nil\n}\n");
+
+        self.push(&code);
+    }
+
+    fn exported_functions(&mut self, iface: &Interface, class_name: &str) {
+        let mut sizes = SizeAlign::default();
+        sizes.fill(iface);
+
+        for func in &iface.functions {
+            let param_names = func.params.iter().map(|(n, _)| param_name(n)).collect();
+            let mut bindgen = FunctionBindgen::new(&sizes, param_names);
+            bindgen.callee = format!("lib.{}", function_field(func));
+            iface.call(
+                AbiVariant::GuestExport,
+                LiftLower::LowerArgsLiftResults,
+                func,
+                &mut bindgen,
+            );
+            let body = bindgen.finish();
+            self.needs_fmt |= bindgen.needs_fmt;
+            self.needs_math |= bindgen.needs_math;
+
+            let mut code = String::new();
+            if func.docs.contents.is_some() {
+                push_docs(&mut code, &func.docs);
+            } else {
+                code.push_str(&format!(
+                    "// {} calls the \"{}\" function.\n",
+                    func.name.to_pascal_case(),
+                    func.name
+                ));
+            }
+            code.push_str(&format!(
+                "func (lib *{class_name}) {}({}) {} {{\n",
+                func.name.to_pascal_case(),
+                params(iface, func),
+                result_signature(iface, &func.result, true),
+            ));
+            if uses_ident(&body, "env") {
+                code.push_str("env := lib.env\n");
+            }
+            code.push_str(&body);
+            code.push_str("return\n}\n");
+
+            self.push(&code);
+        }
+    }
+
+    /// Register the host's implementation of an imported interface with the
+    /// import object.
+    fn import_registration(&mut self, iface: &Interface) {
+        let name = iface.name.to_pascal_case();
+        let mut sizes = SizeAlign::default();
+        sizes.fill(iface);
+
+        let mut code = format!(
+            "func add{name}ToImports(store *wasmer.Store, imports *wasmer.ImportObject, env *abi.Env, host {name}) {{\n"
+        );
+        code.push_str(&format!(
+            "imports.Register({}, map[string]wasmer.IntoExtern{{\n",
+            quote(&iface.name)
+        ));
+        for func in &iface.functions {
+            let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+            code.push_str(&format!(
+                "{}: wasmer.NewFunction(
+store,
+wasmer.NewFunctionType(
+wasmer.NewValueTypes({}),
+wasmer.NewValueTypes({}),
+),
+func(args []wasmer.Value) ([]wasmer.Value, error) {{
+return {}(env, host, args)
+}},
+),
+",
+                quote(&func.name),
+                value_types(&sig.params),
+                value_types(&sig.results),
+                adapter_name(iface, func),
+            ));
+        }
+        code.push_str("})\n}\n");
+        self.push(&code);
+
+        for func in &iface.functions {
+            let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+            let params = sig
+                .params
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| format!("args[{i}].{}()", value_getter(*ty)))
+                .collect();
+            let mut bindgen = FunctionBindgen::new(&sizes, params);
+            bindgen.callee = format!("host.{}", func.name.to_pascal_case());
+            bindgen.wasm_results = sig.results.clone();
+            iface.call(
+                AbiVariant::GuestImport,
+                LiftLower::LiftArgsLowerResults,
+                func,
+                &mut bindgen,
+            );
+            let body = bindgen.finish();
+            self.needs_fmt |= bindgen.needs_fmt;
+            self.needs_math |= bindgen.needs_math;
+
+            let code = format!(
+                "func {}(env *abi.Env, host {name}, args []wasmer.Value) (results []wasmer.Value, err error) {{\n{body}return\n}}\n",
+                adapter_name(iface, func),
+            );
+            self.push(&code);
+        }
+    }
+}
+
+/// The function which adapts raw WebAssembly arguments to a call to the
+/// host's implementation of an imported function.
+fn adapter_name(iface: &Interface, func: &Function) -> String {
+    format!(
+        "{}{}",
+        iface.name.to_lower_camel_case(),
+        func.name.to_pascal_case()
+    )
+}
+
+/// The field a library's struct uses to hold an exported function.
+fn function_field(func: &Function) -> String {
+    format!("fn{}", func.name.to_pascal_case())
+}
+
+fn value_types(types: &[WasmType]) -> String {
+    types
+        .iter()
+        .map(|ty| match ty {
+            WasmType::I32 => "wasmer.I32",
+            WasmType::I64 => "wasmer.I64",
+            WasmType::F32 => "wasmer.F32",
+            WasmType::F64 => "wasmer.F64",
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn value_getter(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "I32",
+        WasmType::I64 => "I64",
+        WasmType::F32 => "F32",
+        WasmType::F64 => "F64",
+    }
+}
+
+fn go_wasm_type(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "int32",
+        WasmType::I64 => "int64",
+        WasmType::F32 => "float32",
+        WasmType::F64 => "float64",
+    }
+}
+
+/// Turn a WAI parameter name into a Go identifier that won't clash with
+/// keywords or the names used by the glue code.
+pub(crate) fn param_name(name: &str) -> String {
+    codegen::avoid_reserved(name.to_lower_camel_case(), &[GO_KEYWORDS, GLUE_NAMES])
+}
+
+fn params(iface: &Interface, func: &Function) -> String {
+    func.params
+        .iter()
+        .map(|(name, ty)| format!("{} {}", param_name(name), go_type(iface, ty)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The results of a Go function returning a WAI type, optionally using
+/// named results.
+fn result_signature(iface: &Interface, result: &Type, named: bool) -> String {
+    match (result, named) {
+        (Type::Unit, true) => "(err error)".to_string(),
+        (Type::Unit, false) => "error".to_string(),
+        (ty, true) => format!("(result {}, err error)", go_type(iface, ty)),
+        (ty, false) => format!("({}, error)", go_type(iface, ty)),
+    }
+}
+
+/// The Go type used to represent a WAI type.
+fn go_type(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Unit => "struct{}".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::U8 => "uint8".to_string(),
+        Type::U16 => "uint16".to_string(),
+        Type::U32 => "uint32".to_string(),
+        Type::U64 => "uint64".to_string(),
+        Type::S8 => "int8".to_string(),
+        Type::S16 => "int16".to_string(),
+        Type::S32 => "int32".to_string(),
+        Type::S64 => "int64".to_string(),
+        Type::Float32 => "float32".to_string(),
+        Type::Float64 => "float64".to_string(),
+        Type::Char => "rune".to_string(),
+        Type::String => "string".to_string(),
+        Type::Handle(_) => unreachable!("resources are rejected during validation"),
+        Type::Id(id) => {
+            let def = &iface.types[*id];
+            if let Some(name) = &def.name {
+                return name.to_pascal_case();
+            }
+
+            match &def.kind {
+                TypeDefKind::Type(ty) => go_type(iface, ty),
+                TypeDefKind::List(ty) => format!("[]{}", go_type(iface, ty)),
+                TypeDefKind::Option(ty) => format!("*{}", go_type(iface, ty)),
+                TypeDefKind::Expected(e) => format!(
+                    "Expected[{}, {}]",
+                    go_type(iface, &e.ok),
+                    go_type(iface, &e.err)
+                ),
+                TypeDefKind::Tuple(tuple) => tuple_type(iface, &tuple.types),
+                other => unreachable!("anonymous {other:?} types aren't possible in WAI"),
+            }
+        }
+    }
+}
+
+fn tuple_type(iface: &Interface, types: &[Type]) -> String {
+    if types.is_empty() {
+        return "struct{}".to_string();
+    }
+
+    let fields: Vec<_> = types
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| format!("F{i} {}", go_type(iface, ty)))
+        .collect();
+    format!("struct{{ {} }}", fields.join("; "))
+}
+
+fn uses_expected(iface: &Interface) -> bool {
+    iface
+        .types
+        .iter()
+        .any(|(_, def)| matches!(def.kind, TypeDefKind::Expected(_)))
+}
+
+fn case_name(type_name: &str, case: &str) -> String {
+    format!("{type_name}{}", case.to_pascal_case())
+}
+
+fn push_docs(code: &mut String, docs: &Docs) {
+    if let Some(contents) = &docs.contents {
+        for line in contents.trim().lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                code.push_str("//\n");
+            } else {
+                code.push_str(&format!("// {line}\n"));
+            }
+        }
+    }
+}
+
+fn push_consts(code: &mut String, consts: Vec<(String, String, &Docs)>) {
+    if consts.is_empty() {
+        return;
+    }
+
+    let width = consts.iter().map(|c| c.0.len()).max().unwrap_or(0);
+    code.push_str("\nconst (\n");
+    for (ident, value, docs) in consts {
+        push_docs(code, docs);
+        code.push_str(&format!("{ident:width$} {value}\n"));
+    }
+    code.push_str(")\n");
+}
+
+/// Variants and unions are represented as an interface with a private marker
+/// method, implemented by one struct per case.
+fn push_sum_type<'a>(
+    code: &mut String,
+    iface: &Interface,
+    name: &str,
+    cases: impl Iterator<Item = (String, &'a Type, &'a Docs)>,
+) {
+    code.push_str(&format!("type {name} interface {{\nis{name}()\n}}\n"));
+
+    for (case, ty, docs) in cases {
+        code.push('\n');
+        push_docs(code, docs);
+        match ty {
+            Type::Unit => code.push_str(&format!("type {case} struct{{}}\n")),
+            ty => code.push_str(&format!(
+                "type {case} struct {{\nValue {}\n}}\n",
+                go_type(iface, ty)
+            )),
+        }
+        code.push_str(&format!("\nfunc ({case}) is{name}() {{}}\n"));
+    }
+}
+
+/// Quote a string so it can be used as a Go string literal.
+pub(crate) fn quote(s: &str) -> String {
+    codegen::quote(s, |c| {
+        c.is_control().then(|| format!("\\u{:04x}", c as u32))
+    })
+}
+
+/// Indent Go source code based on its braces and parentheses, the same way
+/// `gofmt` would.
+pub(crate) fn indent(src: &str) -> String {
+    codegen::indent(src, "\t", |line| {
+        let is_code = !line.starts_with("//");
+        Layout {
+            closes: is_code && (line.starts_with('}') || line.starts_with(')')),
+            opens: is_code && (line.ends_with('{') || line.ends_with('(')),
+            continues: line.starts_with("case ") || line == "default:",
+            ..Layout::default()
+        }
+    })
+}
+
+/// Generates the Go statements that pass a function's arguments and results
+/// across the canonical ABI.
+struct FunctionBindgen<'a> {
+    sizes: &'a SizeAlign,
+    params: Vec<String>,
+    /// The function to invoke for `CallWasm` or `CallInterface`.
+    callee: String,
+    /// The WebAssembly results returned by a host function.
+    wasm_results: Vec<WasmType>,
+    code: FunctionCode,
+    /// Temporaries declared with `:=`, which Go will complain about if they
+    /// are never used.
+    declared: Vec<String>,
+    needs_fmt: bool,
+    needs_math: bool,
+}
+
+impl<'a> FunctionBindgen<'a> {
+    fn new(sizes: &'a SizeAlign, params: Vec<String>) -> Self {
+        FunctionBindgen {
+            sizes,
+            params,
+            callee: String::new(),
+            wasm_results: Vec::new(),
+            code: FunctionCode::default(),
+            declared: Vec::new(),
+            needs_fmt: false,
+            needs_math: false,
+        }
+    }
+
+    /// Declare a temporary with `:=`, keeping track of it so
+    /// [`FunctionBindgen::finish()`] can discard it if it is never read.
+    fn bind(&mut self, prefix: &str, expr: impl AsRef<str>) -> String {
+        let name = self.code.tmp(prefix);
+        self.code.line(format!("{name} := {}", expr.as_ref()));
+        self.declared.push(name.clone());
+        name
+    }
+
+    fn check_err(&mut self) {
+        self.code.line("if err != nil {\nreturn\n}");
+    }
+
+    /// Take the generated statements, discarding any temporaries that were
+    /// never read.
+    fn finish(&self) -> String {
+        let mut src = self.code.src.clone();
+
+        for name in &self.declared {
+            if count_ident(&src, name) == 1 {
+                src = src.replace(&format!("{name} := "), "_ = ");
+            }
+        }
+
+        src
+    }
+
+    /// Lower a variant-like value, where `cases` contains the Go type to
+    /// match against for each case and whether it carries a `Value`.
+    fn lower_sum(
+        &mut self,
+        name: &str,
+        operand: &str,
+        cases: &[(String, bool)],
+        results: &mut Vec<String>,
+        result_types: &[WasmType],
+    ) {
+        let blocks = self.code.pop_blocks(cases.len());
+        let payloads = self.code.pop_payloads(cases.len());
+        let vars = self.declare_results(result_types);
+
+        let any_payload = cases.iter().any(|(_, has_value)| *has_value);
+        let bound = self.code.tmp("variant");
+        if any_payload {
+            self.code
+                .line(format!("switch {bound} := {operand}.(type) {{"));
+        } else {
+            self.code.line(format!("switch {operand}.(type) {{"));
+        }
+
+        for (((case, has_value), (block, block_results)), payload) in
+            cases.iter().zip(blocks).zip(payloads)
+        {
+            self.code.line(format!("case {case}:"));
+            if *has_value {
+                self.code.line(format!("{payload} := {bound}.Value"));
+                self.declared.push(payload);
+            }
+            self.code.src.push_str(&block);
+            self.assign(&vars, &block_results);
+        }
+
+        self.needs_fmt = true;
+        self.code.line(format!(
+            "default:\nerr = fmt.Errorf(\"invalid value for {name}: %v\", {operand})\nreturn\n}}"
+        ));
+
+        results.extend(vars);
+    }
+
+    /// Lift a variant-like value into a variable of type `ty` using a type
+    /// switch on its discriminant.
+    fn lift_sum(
+        &mut self,
+        ty: String,
+        what: &str,
+        discriminant: &str,
+        cases: &[Lifted],
+        results: &mut Vec<String>,
+    ) {
+        let blocks = self.code.pop_blocks(cases.len());
+        let var = self.code.tmp("variant");
+        self.code.line(format!("var {var} {ty}"));
+        self.code.line(format!("switch {discriminant} {{"));
+
+        for (i, (case, (block, block_results))) in cases.iter().zip(blocks).enumerate() {
+            self.code.line(format!("case {i}:"));
+            self.code.src.push_str(&block);
+            let value = &block_results[0];
+
+            match case {
+                Lifted::Nothing => {}
+                Lifted::Case { name, has_value } if *has_value => {
+                    self.code.line(format!("{var} = {name}{{Value: {value}}}"))
+                }
+                Lifted::Case { name, .. } => self.code.line(format!("{var} = {name}{{}}")),
+                Lifted::Some { .. } => {
+                    let value = self.bind("value", value);
+                    self.code.line(format!("{var} = &{value}"));
+                }
+                Lifted::Ok { .. } => self.code.line(format!("{var}.Ok = {value}")),
+                Lifted::Err { .. } => {
+                    self.code.line(format!("{var}.Err = {value}"));
+                    self.code.line(format!("{var}.IsErr = true"));
+                }
+            }
+        }
+
+        self.needs_fmt = true;
+        self.code.line(format!(
+            "default:\nerr = fmt.Errorf(\"invalid discriminant for {what}: %d\", {discriminant})\nreturn\n}}"
+        ));
+        results.push(var);
+    }
+
+    fn declare_results(&mut self, types: &[WasmType]) -> Vec<String> {
+        types
+            .iter()
+            .map(|ty| {
+                let var = self.code.tmp("lowered");
+                self.code.line(format!("var {var} {}", go_wasm_type(*ty)));
+                var
+            })
+            .collect()
+    }
+
+    fn assign(&mut self, vars: &[String], values: &[String]) {
+        for (var, value) in vars.iter().zip(values) {
+            self.code.line(format!("{var} = {value}"));
+        }
+    }
+
+    fn load(&mut self, method: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        let value = self.bind("load", format!("env.{method}({}, {offset})", operands[0]));
+        results.push(value);
+    }
+
+    fn store(&mut self, method: &str, offset: i32, operands: &[String]) {
+        self.code.line(format!(
+            "env.{method}({}, {offset}, {})",
+            operands[1], operands[0]
+        ));
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_> {
+    type Operand = String;
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        match inst {
+            Instruction::GetArg { nth } => {
+                let param = self.params[*nth].clone();
+                if param.contains('[') {
+                    // Raw WebAssembly arguments get pulled out of the slice
+                    // once, up front
+                    let arg = self.bind("arg", param);
+                    self.params[*nth] = arg.clone();
+                    results.push(arg);
+                } else {
+                    results.push(param);
+                }
+            }
+            Instruction::I32Const { val } => results.push(format!("int32({val})")),
+            Instruction::ConstZero { tys } => {
+                for ty in tys.iter() {
+                    results.push(format!("{}(0)", go_wasm_type(*ty)));
+                }
+            }
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands.iter()) {
+                    let expr = match cast {
+                        Bitcast::None => op.clone(),
+                        Bitcast::I32ToI64 => format!("int64({op})"),
+                        Bitcast::I64ToI32 => format!("int32({op})"),
+                        Bitcast::F32ToI32 => format!("int32(math.Float32bits({op}))"),
+                        Bitcast::F64ToI64 => format!("int64(math.Float64bits({op}))"),
+                        Bitcast::F32ToI64 => format!("int64(math.Float32bits({op}))"),
+                        Bitcast::I32ToF32 => format!("math.Float32frombits(uint32({op}))"),
+                        Bitcast::I64ToF64 => format!("math.Float64frombits(uint64({op}))"),
+                        Bitcast::I64ToF32 => format!("math.Float32frombits(uint32({op}))"),
+                    };
+                    if expr.contains("math.") {
+                        self.needs_math = true;
+                    }
+                    results.push(expr);
+                }
+            }
+
+            Instruction::I32Load { offset } => self.load("I32Load", *offset, operands, results),
+            Instruction::I32Load8U { offset } => self.load("I32Load8U", *offset, operands, results),
+            Instruction::I32Load8S { offset } => self.load("I32Load8S", *offset, operands, results),
+            Instruction::I32Load16U { offset } => {
+                self.load("I32Load16U", *offset, operands, results)
+            }
+            Instruction::I32Load16S { offset } => {
+                self.load("I32Load16S", *offset, operands, results)
+            }
+            Instruction::I64Load { offset } => self.load("I64Load", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("F32Load", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("F64Load", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("I32Store", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("I32Store8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("I32Store16", *offset, operands),
+            Instruction::I64Store { offset } => self.store("I64Store", *offset, operands),
+            Instruction::F32Store { offset } => self.store("F32Store", *offset, operands),
+            Instruction::F64Store { offset } => self.store("F64Store", *offset, operands),
+
+            Instruction::I32FromChar
+            | Instruction::I32FromU32
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU8
+            | Instruction::I32FromS8 => results.push(format!("int32({})", operands[0])),
+            Instruction::I64FromU64 => results.push(format!("int64({})", operands[0])),
+            Instruction::I32FromS32
+            | Instruction::I64FromS64
+            | Instruction::F32FromFloat32
+            | Instruction::F64FromFloat64
+            | Instruction::S32FromI32
+            | Instruction::S64FromI64
+            | Instruction::Float32FromF32
+            | Instruction::Float64FromF64 => results.push(operands[0].clone()),
+            Instruction::S8FromI32 => results.push(format!("int8({})", operands[0])),
+            Instruction::U8FromI32 => results.push(format!("uint8({})", operands[0])),
+            Instruction::S16FromI32 => results.push(format!("int16({})", operands[0])),
+            Instruction::U16FromI32 => results.push(format!("uint16({})", operands[0])),
+            Instruction::U32FromI32 => results.push(format!("uint32({})", operands[0])),
+            Instruction::U64FromI64 => results.push(format!("uint64({})", operands[0])),
+            Instruction::CharFromI32 => results.push(format!("rune({})", operands[0])),
+            Instruction::BoolFromI32 => results.push(format!("({} != 0)", operands[0])),
+            Instruction::I32FromBool => results.push(format!("abi.BoolToI32({})", operands[0])),
+
+            Instruction::UnitLift => results.push("struct{}{}".to_string()),
+            Instruction::UnitLower => {}
+
+            Instruction::I32FromBorrowedHandle { .. }
+            | Instruction::I32FromOwnedHandle { .. }
+            | Instruction::HandleOwnedFromI32 { .. }
+            | Instruction::HandleBorrowedFromI32 { .. } => {
+                unreachable!("resources are rejected during validation")
+            }
+
+            Instruction::ListCanonLower { .. } | Instruction::ListCanonLift { .. } => {
+                unreachable!("lists are never canonical")
+            }
+
+            Instruction::StringLower { .. } => {
+                let ptr = self.code.tmp("ptr");
+                let len = self.code.tmp("len");
+                self.code.line(format!("var {ptr}, {len} int32"));
+                self.code.line(format!(
+                    "{ptr}, {len}, err = env.LowerString({})",
+                    operands[0]
+                ));
+                self.check_err();
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::ListLower { element, .. } => {
+                let (body, body_results) = self.code.pop_block();
+                assert!(body_results.is_empty());
+                let size = self.sizes.size(element);
+                let align = self.sizes.align(element);
+
+                let vec = self.bind("vec", &operands[0]);
+                let len = self.bind("len", format!("int32(len({vec}))"));
+                let ptr = self.code.tmp("ptr");
+                self.code.line(format!("var {ptr} int32"));
+                self.code.line(format!(
+                    "{ptr}, err = env.Realloc(0, 0, {align}, {len}*{size})"
+                ));
+                self.check_err();
+
+                if uses_ident(&body, "e") {
+                    self.code.line(format!("for i, e := range {vec} {{"));
+                } else {
+                    self.code.line(format!("for i := range {vec} {{"));
+                }
+                if uses_ident(&body, "base") {
+                    self.code.line(format!("base := {ptr} + int32(i)*{size}"));
+                } else {
+                    self.code.line("_ = i");
+                }
+                self.code.src.push_str(&body);
+                self.code.line("}");
+
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::StringLift { free } => {
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", &operands[1]);
+                let s = self.bind("str", format!("env.LiftString({ptr}, {len})"));
+                if free.is_some() {
+                    self.code.line(format!("err = env.Free({ptr}, {len}, 1)"));
+                    self.check_err();
+                }
+                results.push(s);
+            }
+            Instruction::ListLift {
+                element, free, ty, ..
+            } => {
+                let (body, body_results) = self.code.pop_block();
+                let size = self.sizes.size(element);
+                let align = self.sizes.align(element);
+                let list_type = go_type(iface, &Type::Id(*ty));
+
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", &operands[1]);
+                let list = self.bind("list", format!("make({list_type}, {len})"));
+                self.code.line(format!("for i := range {list} {{"));
+                if uses_ident(&body, "base") {
+                    self.code.line(format!("base := {ptr} + int32(i)*{size}"));
+                }
+                self.code.src.push_str(&body);
+                self.code.line(format!("{list}[i] = {}", body_results[0]));
+                self.code.line("}");
+                if free.is_some() {
+                    self.code
+                        .line(format!("err = env.Free({ptr}, {len}*{size}, {align})"));
+                    self.check_err();
+                }
+
+                results.push(list);
+            }
+            Instruction::IterElem { .. } => results.push("e".to_string()),
+            Instruction::IterBasePointer => results.push("base".to_string()),
+
+            Instruction::RecordLower { record, .. } => {
+                let rec = self.bind("record", &operands[0]);
+                for field in &record.fields {
+                    results.push(format!("{rec}.{}", field.name.to_pascal_case()));
+                }
+            }
+            Instruction::RecordLift { record, ty, .. } => {
+                let fields: Vec<_> = record
+                    .fields
+                    .iter()
+                    .zip(operands.iter())
+                    .map(|(field, op)| format!("{}: {op}", field.name.to_pascal_case()))
+                    .collect();
+                results.push(format!(
+                    "{}{{{}}}",
+                    go_type(iface, &Type::Id(*ty)),
+                    fields.join(", ")
+                ));
+            }
+            Instruction::TupleLower { tuple, .. } => {
+                let t = self.bind("tuple", &operands[0]);
+                for i in 0..tuple.types.len() {
+                    results.push(format!("{t}.F{i}"));
+                }
+            }
+            Instruction::TupleLift { ty, .. } => {
+                let fields: Vec<_> = operands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, op)| format!("F{i}: {op}"))
+                    .collect();
+                results.push(format!(
+                    "{}{{{}}}",
+                    go_type(iface, &Type::Id(*ty)),
+                    fields.join(", ")
+                ));
+            }
+
+            Instruction::FlagsLower { flags, .. } => {
+                if flags.repr().count() == 1 {
+                    results.push(format!("int32({})", operands[0]));
+                } else {
+                    let f = self.bind("flags", &operands[0]);
+                    results.push(format!("int32(uint32({f}))"));
+                    results.push(format!("int32(uint32({f} >> 32))"));
+                }
+            }
+            Instruction::FlagsLift { flags, ty, .. } => {
+                let name = go_type(iface, &Type::Id(*ty));
+                if flags.repr().count() == 1 {
+                    results.push(format!("{name}(uint32({}))", operands[0]));
+                } else {
+                    results.push(format!(
+                        "{name}(uint64(uint32({})) | uint64(uint32({}))<<32)",
+                        operands[0], operands[1]
+                    ));
+                }
+            }
+
+            Instruction::VariantPayloadName => results.push(self.code.payload_name()),
+            Instruction::VariantLower {
+                variant,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let type_name = name.to_pascal_case();
+                let cases: Vec<_> = variant
+                    .cases
+                    .iter()
+                    .map(|c| (case_name(&type_name, &c.name), c.ty != Type::Unit))
+                    .collect();
+                self.lower_sum(&type_name, &operands[0], &cases, results, result_types);
+            }
+            Instruction::UnionLower {
+                union,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let type_name = name.to_pascal_case();
+                let cases: Vec<_> = union
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (format!("{type_name}{i}"), c.ty != Type::Unit))
+                    .collect();
+                self.lower_sum(&type_name, &operands[0], &cases, results, result_types);
+            }
+            Instruction::VariantLift { variant, name, ty } => {
+                let type_name = name.to_pascal_case();
+                let cases: Vec<_> = variant
+                    .cases
+                    .iter()
+                    .map(|c| Lifted::case(case_name(&type_name, &c.name), &c.ty))
+                    .collect();
+                self.lift_sum(
+                    go_type(iface, &Type::Id(*ty)),
+                    name,
+                    &operands[0],
+                    &cases,
+                    results,
+                );
+            }
+            Instruction::UnionLift { union, name, ty } => {
+                let type_name = name.to_pascal_case();
+                let cases: Vec<_> = union
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| Lifted::case(format!("{type_name}{i}"), &c.ty))
+                    .collect();
+                self.lift_sum(
+                    go_type(iface, &Type::Id(*ty)),
+                    name,
+                    &operands[0],
+                    &cases,
+                    results,
+                );
+            }
+            Instruction::EnumLower { .. } => results.push(format!("int32({})", operands[0])),
+            Instruction::EnumLift { ty, .. } => results.push(format!(
+                "{}({})",
+                go_type(iface, &Type::Id(*ty)),
+                operands[0]
+            )),
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let (some, some_results) = self.code.pop_block();
+                let (none, none_results) = self.code.pop_block();
+                let some_payload = self.code.pop_payload();
+                let _none_payload = self.code.pop_payload();
+                let vars = self.declare_results(result_types);
+
+                let option = self.bind("option", &operands[0]);
+                self.code.line(format!("if {option} == nil {{"));
+                self.code.src.push_str(&none);
+                self.assign(&vars, &none_results);
+                self.code.line("} else {");
+                self.code.line(format!("{some_payload} := *{option}"));
+                self.declared.push(some_payload);
+                self.code.src.push_str(&some);
+                self.assign(&vars, &some_results);
+                self.code.line("}");
+
+                results.extend(vars);
+            }
+            Instruction::OptionLift { payload, ty } => {
+                let cases = Lifted::option(payload);
+                let option_type = go_type(iface, &Type::Id(*ty));
+                self.lift_sum(option_type, "option", &operands[0], &cases, results);
+            }
+            Instruction::ExpectedLower {
+                results: result_types,
+                ..
+            } => {
+                let (err_block, err_results) = self.code.pop_block();
+                let (ok_block, ok_results) = self.code.pop_block();
+                let err_payload = self.code.pop_payload();
+                let ok_payload = self.code.pop_payload();
+                let vars = self.declare_results(result_types);
+
+                let expected = self.bind("expected", &operands[0]);
+                self.code.line(format!("if {expected}.IsErr {{"));
+                self.code.line(format!("{err_payload} := {expected}.Err"));
+                self.declared.push(err_payload);
+                self.code.src.push_str(&err_block);
+                self.assign(&vars, &err_results);
+                self.code.line("} else {");
+                self.code.line(format!("{ok_payload} := {expected}.Ok"));
+                self.declared.push(ok_payload);
+                self.code.src.push_str(&ok_block);
+                self.assign(&vars, &ok_results);
+                self.code.line("}");
+
+                results.extend(vars);
+            }
+            Instruction::ExpectedLift { expected, ty } => {
+                let cases = Lifted::expected(expected);
+                let expected_type = go_type(iface, &Type::Id(*ty));
+                self.lift_sum(expected_type, "expected", &operands[0], &cases, results);
+            }
+
+            Instruction::CallWasm { sig, .. } => {
+                let callee = self.callee.clone();
+                let args = operands.join(", ");
+                match codegen::single_result(&sig.results) {
+                    None => {
+                        self.code.line(format!("_, err = {callee}.Call({args})"));
+                        self.check_err();
+                    }
+                    Some(ty) => {
+                        let raw = self.code.tmp("raw");
+                        self.code.line(format!("var {raw} interface{{}}"));
+                        self.code
+                            .line(format!("{raw}, err = {callee}.Call({args})"));
+                        self.check_err();
+                        let ret = self.bind("ret", format!("{raw}.({})", go_wasm_type(*ty)));
+                        results.push(ret);
+                    }
+                }
+            }
+            Instruction::CallInterface { func, .. } => {
+                let callee = self.callee.clone();
+                let args = operands.join(", ");
+                if func.result == Type::Unit {
+                    self.code.line(format!("err = {callee}({args})"));
+                    self.check_err();
+                    results.push("struct{}{}".to_string());
+                } else {
+                    let ret = self.code.tmp("ret");
+                    self.code
+                        .line(format!("var {ret} {}", go_type(iface, &func.result)));
+                    self.code.line(format!("{ret}, err = {callee}({args})"));
+                    self.check_err();
+                    results.push(ret);
+                }
+            }
+            Instruction::Return { func, .. } => {
+                if !self.wasm_results.is_empty() {
+                    let values: Vec<_> = self
+                        .wasm_results
+                        .iter()
+                        .zip(operands.iter())
+                        .map(|(ty, op)| format!("wasmer.New{}({op})", value_getter(*ty)))
+                        .collect();
+                    self.code
+                        .line(format!("results = []wasmer.Value{{{}}}", values.join(", ")));
+                } else if !operands.is_empty() && func.result != Type::Unit {
+                    self.code.line(format!("result = {}", operands[0]));
+                }
+            }
+
+            Instruction::Malloc { size, align, .. } => {
+                let ptr = self.code.tmp("ptr");
+                self.code.line(format!("var {ptr} int32"));
+                self.code
+                    .line(format!("{ptr}, err = env.Realloc(0, 0, {align}, {size})"));
+                self.check_err();
+                results.push(ptr);
+            }
+            Instruction::Free { size, align, .. } => {
+                self.code
+                    .line(format!("err = env.Free({}, {size}, {align})", operands[0]));
+                self.check_err();
+            }
+
+            Instruction::CallWasmAsyncImport { .. }
+            | Instruction::CallWasmAsyncExport { .. }
+            | Instruction::ReturnAsyncExport { .. }
+            | Instruction::ReturnAsyncImport { .. } => {
+                unreachable!("async functions are rejected during validation")
+            }
+        }
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, _size: usize, _align: usize) -> String {
+        // Only called when a guest passes a return pointer to an import or
+        // returns one from an export, and we only ever generate host code
+        unreachable!("return pointers are only needed by guest code")
+    }
+
+    fn push_block(&mut self) {
+        self.code.push_block();
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        self.code.finish_block(operands);
+    }
+
+    fn sizes(&self) -> &SizeAlign {
+        self.sizes
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, _element: &Type) -> bool {
+        false
+    }
+}
@@ -0,0 +1,204 @@
+mod library;
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Error;
+use heck::ToPascalCase;
+use once_cell::sync::Lazy;
+
+pub(crate) use self::library::quote;
+use self::library::GO_KEYWORDS;
+use crate::{
+    codegen::{self, Context, Naming, Templates},
+    types::{Abi, BindingsOptions, CommandKind, Package},
+    Files, SourceFile,
+};
+
+/// The version of the `wasmer-go` module used by the generated code.
+const WASMER_GO_VERSION: &str = "v1.0.4";
+
+/// The minimum Go version required by the generated module (generics were
+/// added in Go 1.18).
+const GO_VERSION: &str = "1.18";
+
+static TEMPLATES: Lazy<Templates> = Lazy::new(|| {
+    Templates::new(&[
+        ("go.mod", include_str!("go.mod.j2")),
+        ("package.go", include_str!("package.go.j2")),
+        ("wasm.go", include_str!("wasm.go.j2")),
+        ("bindings.go", include_str!("bindings.go.j2")),
+        ("commands.go", include_str!("commands.go.j2")),
+        ("volumes.go", include_str!("volumes.go.j2")),
+    ])
+});
+
+/// Generate a Go module which embeds the package and uses `wasmer-go` to run
+/// it.
+pub fn generate_go(package: &Package, options: &BindingsOptions) -> Result<Files, crate::Error> {
+    crate::validation::check_abis(package.libraries(), "Go", &[Abi::None, Abi::Wasi])?;
+    crate::validation::check_command_kinds(package.commands(), "Go", &[CommandKind::Wasi])?;
+    crate::validation::deny_unsupported_interface_features(package.libraries(), "Go")?;
+    if options.deny_extra_exports {
+        crate::validation::deny_extra_exports(package.libraries())?;
+    }
+
+    let metadata = package.metadata();
+
+    // Go modules are named after the path they are imported from, so we
+    // default to something like "wasmer/wasmer-pack"
+    let module = match &options.name {
+        Some(name) => name.clone(),
+        None => metadata.package_name.to_string(),
+    };
+    let package_name = package_ident(module.rsplit('/').next().unwrap_or(&module));
+
+    let ctx = Context::for_package(package, &module, &NAMING);
+    let mut files = Files::new();
+
+    files.insert(
+        "go.mod",
+        TEMPLATES
+            .render(
+                "go.mod",
+                minijinja::context! {
+                    module,
+                    go_version => GO_VERSION,
+                    wasmer_go_version => WASMER_GO_VERSION,
+                },
+            )
+            .map_err(crate::Error::Generate)?,
+    );
+
+    files.insert(
+        format!("{package_name}.go"),
+        package_go(package, &package_name, &ctx).map_err(crate::Error::Generate)?,
+    );
+
+    if !ctx.libraries.is_empty() {
+        files.insert(
+            Path::new("internal").join("abi").join("abi.go"),
+            include_str!("abi.go").into(),
+        );
+        files.insert(
+            Path::new("bindings").join("bindings.go"),
+            TEMPLATES
+                .render("bindings.go", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+        for (lib, lib_ctx) in package.libraries().iter().zip(&ctx.libraries) {
+            let src = library::generate(lib, &lib_ctx.ident, &module);
+            files.insert(
+                Path::new("bindings")
+                    .join(&lib_ctx.ident)
+                    .join(format!("{}.go", lib_ctx.ident)),
+                src.into(),
+            );
+        }
+    }
+
+    if !ctx.commands.is_empty() {
+        files.insert(
+            Path::new("commands").join("commands.go"),
+            TEMPLATES
+                .render("commands.go", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+    }
+
+    if !ctx.mounts.is_empty() {
+        let embedded: Vec<_> = package
+            .mounts()
+            .iter()
+            .enumerate()
+            .filter(|(_, mount)| mount.files.iter().next().is_some())
+            .map(|(i, _)| format!("all:{i}"))
+            .collect();
+        files.insert(
+            Path::new("volumes").join("volumes.go"),
+            TEMPLATES
+                .render(
+                    "volumes.go",
+                    minijinja::context! { embedded, mounts => &ctx.mounts },
+                )
+                .map_err(crate::Error::Generate)?,
+        );
+        files.insert_child_directory(Path::new("volumes"), codegen::volumes(package));
+    }
+
+    let wasm = codegen::wasm_modules(package);
+    if wasm.iter().next().is_some() {
+        files.insert(
+            Path::new("wasm").join("wasm.go"),
+            TEMPLATES
+                .render("wasm.go", ())
+                .map_err(crate::Error::Generate)?,
+        );
+        files.insert_child_directory("wasm", wasm);
+    }
+
+    if let Some(readme) = &metadata.readme {
+        files.insert(codegen::README, readme.as_str().into());
+    }
+
+    Ok(files)
+}
+
+/// Turn a name into a Go package name (all lowercase, no underscores).
+fn package_ident(name: &str) -> String {
+    let ident: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+
+    if GO_KEYWORDS.contains(&ident.as_str()) {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+const NAMING: Naming = Naming {
+    quote,
+    library_ident: package_ident,
+    command_ident: str::to_pascal_case,
+    class_name: str::to_pascal_case,
+    param_name: library::param_name,
+    env: env_entries,
+};
+
+/// Format environment variables as aligned `key: value` map entries.
+fn env_entries(env: &BTreeMap<String, String>) -> Vec<String> {
+    let keys: Vec<_> = env.keys().map(|key| quote(key) + ":").collect();
+    let width = keys.iter().map(|key| key.len()).max().unwrap_or(0);
+
+    keys.iter()
+        .zip(env.values())
+        .map(|(key, value)| format!("{key:width$} {}", quote(value)))
+        .collect()
+}
+
+fn package_go(package: &Package, package_name: &str, ctx: &Context) -> Result<SourceFile, Error> {
+    let metadata = package.metadata();
+    let mut doc = vec![format!(
+        "Package {package_name} provides bindings to the {} package.",
+        metadata.package_name
+    )];
+
+    if let Some(description) = &metadata.description {
+        doc.push(String::new());
+        doc.extend(description.lines().map(|line| line.trim_end().to_string()));
+    }
+
+    TEMPLATES.render(
+        "package.go",
+        minijinja::context! {
+            doc,
+            generator => crate::GENERATOR,
+            package => package_name,
+            module => &ctx.module,
+            libraries => !ctx.libraries.is_empty(),
+            commands => !ctx.commands.is_empty(),
+        },
+    )
+}
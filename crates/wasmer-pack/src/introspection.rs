@@ -0,0 +1,512 @@
+//! An owned, stable view of the items defined by an [`crate::Interface`].
+//!
+//! These types deliberately don't expose anything from `wai-parser`, so tools
+//! built on top of them don't need to depend on a matching version.
+
+use std::fmt::{self, Display, Formatter};
+
+use wai_parser::{Docs, TypeDefKind};
+
+/// A function defined by an interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Function {
+    /// The function's name, as written in the interface definition.
+    pub name: String,
+    pub docs: Option<String>,
+    pub kind: FunctionKind,
+    pub is_async: bool,
+    /// The function's parameters, not including the implicit `self` for
+    /// [`FunctionKind::Method`]s.
+    pub params: Vec<Param>,
+    pub result: Type,
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Function {
+            name,
+            kind,
+            is_async,
+            params,
+            result,
+            ..
+        } = self;
+
+        write!(f, "{name}: ")?;
+
+        if let FunctionKind::Static { .. } = kind {
+            write!(f, "static ")?;
+        }
+        if *is_async {
+            write!(f, "async ")?;
+        }
+
+        write!(f, "func(")?;
+        for (i, Param { name, ty }) in params.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{name}: {ty}")?;
+        }
+        write!(f, ")")?;
+
+        if *result != Type::Unit {
+            write!(f, " -> {result}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Where a [`Function`] is defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FunctionKind {
+    /// A top-level function.
+    Freestanding,
+    /// A static function attached to a [`Resource`].
+    Static { resource: String },
+    /// A method which is called on an instance of a [`Resource`].
+    Method { resource: String },
+}
+
+/// A named [`Function`] parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub name: String,
+    pub ty: Type,
+}
+
+/// A resource (i.e. an opaque handle to an object) defined by an interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Resource {
+    pub name: String,
+    pub docs: Option<String>,
+    pub supertype: Option<String>,
+}
+
+/// A named type defined by an interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TypeDefinition {
+    pub name: String,
+    pub docs: Option<String>,
+    pub kind: TypeDefinitionKind,
+}
+
+/// The different kinds of [`TypeDefinition`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TypeDefinitionKind {
+    Record(Vec<Field>),
+    Flags(Vec<Flag>),
+    Variant(Vec<Case>),
+    Enum(Vec<EnumCase>),
+    Union(Vec<UnionCase>),
+    /// A new name for an existing type (e.g. `type bytes = list<u8>`).
+    Alias(Type),
+}
+
+/// A field in a [`TypeDefinitionKind::Record`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub docs: Option<String>,
+    pub ty: Type,
+}
+
+/// A flag in a [`TypeDefinitionKind::Flags`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flag {
+    pub name: String,
+    pub docs: Option<String>,
+}
+
+/// A case in a [`TypeDefinitionKind::Variant`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    pub name: String,
+    pub docs: Option<String>,
+    /// The data associated with this case, or [`Type::Unit`] if there is none.
+    pub ty: Type,
+}
+
+/// A case in a [`TypeDefinitionKind::Enum`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumCase {
+    pub name: String,
+    pub docs: Option<String>,
+}
+
+/// A case in a [`TypeDefinitionKind::Union`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnionCase {
+    pub docs: Option<String>,
+    pub ty: Type,
+}
+
+/// A reference to a type.
+///
+/// The [`Display`] implementation uses the same syntax as a WAI file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Type {
+    Unit,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    S8,
+    S16,
+    S32,
+    S64,
+    Float32,
+    Float64,
+    Char,
+    String,
+    /// A handle to the named [`Resource`].
+    Handle(String),
+    /// A reference to the named [`TypeDefinition`].
+    Named(String),
+    Option(Box<Type>),
+    List(Box<Type>),
+    Tuple(Vec<Type>),
+    Expected {
+        ok: Box<Type>,
+        err: Box<Type>,
+    },
+    Future(Box<Type>),
+    Stream {
+        element: Box<Type>,
+        end: Box<Type>,
+    },
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Unit => write!(f, "unit"),
+            Type::Bool => write!(f, "bool"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::S8 => write!(f, "s8"),
+            Type::S16 => write!(f, "s16"),
+            Type::S32 => write!(f, "s32"),
+            Type::S64 => write!(f, "s64"),
+            Type::Float32 => write!(f, "float32"),
+            Type::Float64 => write!(f, "float64"),
+            Type::Char => write!(f, "char"),
+            Type::String => write!(f, "string"),
+            Type::Handle(name) | Type::Named(name) => write!(f, "{name}"),
+            Type::Option(ty) => write!(f, "option<{ty}>"),
+            Type::List(ty) => write!(f, "list<{ty}>"),
+            Type::Tuple(types) => {
+                write!(f, "tuple<")?;
+                for (i, ty) in types.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{ty}")?;
+                }
+                write!(f, ">")
+            }
+            Type::Expected { ok, err } => {
+                write!(f, "expected<{}, {}>", Placeholder(ok), Placeholder(err))
+            }
+            Type::Future(ty) => write!(f, "future<{}>", Placeholder(ty)),
+            Type::Stream { element, end } => {
+                write!(f, "stream<{}, {}>", Placeholder(element), Placeholder(end))
+            }
+        }
+    }
+}
+
+/// Generic parameters use `_` instead of `unit`.
+struct Placeholder<'a>(&'a Type);
+
+impl Display for Placeholder<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Type::Unit => write!(f, "_"),
+            other => write!(f, "{other}"),
+        }
+    }
+}
+
+pub(crate) fn functions(interface: &wai_parser::Interface) -> Vec<Function> {
+    let resource_name = |id: wai_parser::ResourceId| interface.resources[id].name.clone();
+
+    interface
+        .functions
+        .iter()
+        .map(|func| Function {
+            name: func.item_name().to_string(),
+            docs: docs(&func.docs),
+            kind: match func.kind {
+                wai_parser::FunctionKind::Freestanding => FunctionKind::Freestanding,
+                wai_parser::FunctionKind::Static { resource, .. } => FunctionKind::Static {
+                    resource: resource_name(resource),
+                },
+                wai_parser::FunctionKind::Method { resource, .. } => FunctionKind::Method {
+                    resource: resource_name(resource),
+                },
+            },
+            is_async: func.is_async,
+            params: func
+                .params
+                .iter()
+                // Methods have an implicit "self" parameter
+                .skip(usize::from(matches!(
+                    func.kind,
+                    wai_parser::FunctionKind::Method { .. }
+                )))
+                .map(|(name, ty)| Param {
+                    name: name.clone(),
+                    ty: ty_ref(interface, *ty),
+                })
+                .collect(),
+            result: ty_ref(interface, func.result),
+        })
+        .collect()
+}
+
+pub(crate) fn resources(interface: &wai_parser::Interface) -> Vec<Resource> {
+    interface
+        .resources
+        .iter()
+        .map(|(_, resource)| Resource {
+            name: resource.name.clone(),
+            docs: docs(&resource.docs),
+            supertype: resource.supertype.clone(),
+        })
+        .collect()
+}
+
+pub(crate) fn types(interface: &wai_parser::Interface) -> Vec<TypeDefinition> {
+    interface
+        .types
+        .iter()
+        .filter_map(|(_, def)| {
+            let name = def.name.clone()?;
+            Some(TypeDefinition {
+                name,
+                docs: docs(&def.docs),
+                kind: definition_kind(interface, &def.kind),
+            })
+        })
+        .collect()
+}
+
+fn definition_kind(interface: &wai_parser::Interface, kind: &TypeDefKind) -> TypeDefinitionKind {
+    let ty = |ty: &wai_parser::Type| ty_ref(interface, *ty);
+
+    match kind {
+        TypeDefKind::Record(record) => TypeDefinitionKind::Record(
+            record
+                .fields
+                .iter()
+                .map(|field| Field {
+                    name: field.name.clone(),
+                    docs: docs(&field.docs),
+                    ty: ty(&field.ty),
+                })
+                .collect(),
+        ),
+        TypeDefKind::Flags(flags) => TypeDefinitionKind::Flags(
+            flags
+                .flags
+                .iter()
+                .map(|flag| Flag {
+                    name: flag.name.clone(),
+                    docs: docs(&flag.docs),
+                })
+                .collect(),
+        ),
+        TypeDefKind::Variant(variant) => TypeDefinitionKind::Variant(
+            variant
+                .cases
+                .iter()
+                .map(|case| Case {
+                    name: case.name.clone(),
+                    docs: docs(&case.docs),
+                    ty: ty(&case.ty),
+                })
+                .collect(),
+        ),
+        TypeDefKind::Enum(enum_) => TypeDefinitionKind::Enum(
+            enum_
+                .cases
+                .iter()
+                .map(|case| EnumCase {
+                    name: case.name.clone(),
+                    docs: docs(&case.docs),
+                })
+                .collect(),
+        ),
+        TypeDefKind::Union(union) => TypeDefinitionKind::Union(
+            union
+                .cases
+                .iter()
+                .map(|case| UnionCase {
+                    docs: docs(&case.docs),
+                    ty: ty(&case.ty),
+                })
+                .collect(),
+        ),
+        other => TypeDefinitionKind::Alias(anonymous_type(interface, other)),
+    }
+}
+
+fn ty_ref(interface: &wai_parser::Interface, ty: wai_parser::Type) -> Type {
+    match ty {
+        wai_parser::Type::Unit => Type::Unit,
+        wai_parser::Type::Bool => Type::Bool,
+        wai_parser::Type::U8 => Type::U8,
+        wai_parser::Type::U16 => Type::U16,
+        wai_parser::Type::U32 => Type::U32,
+        wai_parser::Type::U64 => Type::U64,
+        wai_parser::Type::S8 => Type::S8,
+        wai_parser::Type::S16 => Type::S16,
+        wai_parser::Type::S32 => Type::S32,
+        wai_parser::Type::S64 => Type::S64,
+        wai_parser::Type::Float32 => Type::Float32,
+        wai_parser::Type::Float64 => Type::Float64,
+        wai_parser::Type::Char => Type::Char,
+        wai_parser::Type::String => Type::String,
+        wai_parser::Type::Handle(id) => Type::Handle(interface.resources[id].name.clone()),
+        wai_parser::Type::Id(id) => {
+            let def = &interface.types[id];
+            match &def.name {
+                Some(name) => Type::Named(name.clone()),
+                None => anonymous_type(interface, &def.kind),
+            }
+        }
+    }
+}
+
+fn anonymous_type(interface: &wai_parser::Interface, kind: &TypeDefKind) -> Type {
+    let ty = |ty: &wai_parser::Type| Box::new(ty_ref(interface, *ty));
+
+    match kind {
+        TypeDefKind::Option(inner) => Type::Option(ty(inner)),
+        TypeDefKind::List(inner) => Type::List(ty(inner)),
+        TypeDefKind::Tuple(tuple) => {
+            Type::Tuple(tuple.types.iter().map(|t| ty_ref(interface, *t)).collect())
+        }
+        TypeDefKind::Expected(expected) => Type::Expected {
+            ok: ty(&expected.ok),
+            err: ty(&expected.err),
+        },
+        TypeDefKind::Future(inner) => Type::Future(ty(inner)),
+        TypeDefKind::Stream(stream) => Type::Stream {
+            element: ty(&stream.element),
+            end: ty(&stream.end),
+        },
+        TypeDefKind::Type(inner) => ty_ref(interface, *inner),
+        TypeDefKind::Record(_)
+        | TypeDefKind::Flags(_)
+        | TypeDefKind::Variant(_)
+        | TypeDefKind::Enum(_)
+        | TypeDefKind::Union(_) => {
+            unreachable!(
+                "The WAI syntax requires records, flags, variants, enums, and unions to be named"
+            )
+        }
+    }
+}
+
+fn docs(docs: &Docs) -> Option<String> {
+    docs.contents.as_deref().map(|d| d.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Interface;
+
+    use super::*;
+
+    const WAI: &str = r#"
+        /// A point in 2D space.
+        record point {
+            /// The horizontal position.
+            x: float32,
+            y: float32,
+        }
+
+        flags permissions { read, write }
+
+        variant shape {
+            circle(float32),
+            /// An empty shape.
+            nothing,
+        }
+
+        enum colour { red, green }
+
+        union number { u32, float64 }
+
+        type points = list<point>
+
+        /// A drawing surface.
+        resource canvas {
+            static new: func(width: u32, height: u32) -> canvas
+            /// Draw a shape.
+            draw: func(s: shape, at: option<point>) -> expected<unit, string>
+        }
+
+        /// Calculate the centroid.
+        centroid: func(points: points) -> tuple<float32, float32>
+        reset: func()
+    "#;
+
+    #[test]
+    fn function_signatures() {
+        let interface = Interface::from_wit("shapes.exports.wai", WAI).unwrap();
+
+        let signatures: Vec<_> = interface
+            .functions()
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+
+        assert_eq!(
+            signatures,
+            [
+                "new: static func(width: u32, height: u32) -> canvas",
+                "draw: func(s: shape, at: option<point>) -> expected<_, string>",
+                "centroid: func(points: points) -> tuple<float32, float32>",
+                "reset: func()",
+            ]
+        );
+        let functions = interface.functions();
+        assert_eq!(
+            functions[1].kind,
+            FunctionKind::Method {
+                resource: "canvas".to_string()
+            }
+        );
+        assert_eq!(functions[1].docs.as_deref(), Some("Draw a shape."));
+        assert_eq!(
+            interface.resources(),
+            [Resource {
+                name: "canvas".to_string(),
+                docs: Some("A drawing surface.".to_string()),
+                supertype: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn type_definitions() {
+        let interface = Interface::from_wit("shapes.exports.wai", WAI).unwrap();
+
+        let types = interface.types();
+
+        insta::assert_debug_snapshot!(types);
+    }
+}
@@ -1,15 +1,16 @@
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
 
 use anyhow::Error;
 use heck::{ToPascalCase, ToSnakeCase};
-use minijinja::Environment;
+use minijinja::{AutoEscape, Environment};
 use once_cell::sync::Lazy;
 use wai_bindgen_gen_core::Generator;
 use wai_bindgen_gen_js::Js;
 use wai_parser::Interface;
 
 use crate::{
-    types::BindingsOptions, types::Command, Files, Library, Metadata, Package, SourceFile,
+    types::BindingsOptions, types::Command, Abi, CommandKind, Files, Library, Metadata, Package,
+    SourceFile,
 };
 
 /// The version of `@wasmer/wasi` pulled in when using a WASI library.
@@ -20,6 +21,9 @@ const WASMER_WASI_VERSION: &str = "^1.2.2";
 
 static TEMPLATES: Lazy<Environment> = Lazy::new(|| {
     let mut env = Environment::new();
+    // We're generating source code, so values are inserted verbatim (use the
+    // "tojson" filter for literals)
+    env.set_auto_escape_callback(|_| AutoEscape::None);
     env.add_template("bindings.index.js", include_str!("bindings.index.js.j2"))
         .unwrap();
     env.add_template(
@@ -33,6 +37,12 @@ static TEMPLATES: Lazy<Environment> = Lazy::new(|| {
         .unwrap();
     env.add_template("top-level.index.js", include_str!("top-level.index.js.j2"))
         .unwrap();
+    env.add_template("mounts.js", include_str!("mounts.js.j2"))
+        .unwrap();
+    env.add_template("emscripten.js", include_str!("emscripten.js.j2"))
+        .unwrap();
+    env.add_template("wcgi.js", include_str!("wcgi.js.j2"))
+        .unwrap();
     env.add_template(
         "top-level.index.d.ts",
         include_str!("top-level.index.d.ts.j2"),
@@ -43,18 +53,56 @@ static TEMPLATES: Lazy<Environment> = Lazy::new(|| {
 });
 
 /// Generate JavaScript bindings for a package.
-pub fn generate_javascript(package: &Package, options: &BindingsOptions) -> Result<Files, Error> {
+pub fn generate_javascript(
+    package: &Package,
+    options: &BindingsOptions,
+) -> Result<Files, crate::Error> {
+    crate::validation::check_abis(package.libraries(), "JavaScript", &[Abi::None, Abi::Wasi])?;
+    crate::validation::check_emscripten_commands(package.commands(), "JavaScript")?;
+    if options.deny_extra_exports {
+        crate::validation::deny_extra_exports(package.libraries())?;
+    }
+
     let mut files = Files::new();
 
     let ctx = Context::for_package(package);
 
-    files.insert_child_directory(Path::new("src").join("bindings"), library_bindings(&ctx)?);
+    files.insert_child_directory(
+        Path::new("src").join("bindings"),
+        library_bindings(&ctx).map_err(crate::Error::Generate)?,
+    );
 
-    for cmd in &ctx.commands {
-        files.insert_child_directory(Path::new("src").join("commands"), command_bindings(cmd)?);
+    for cmd in ctx.commands.iter().chain(&ctx.servers) {
+        files.insert_child_directory(
+            Path::new("src").join("commands"),
+            command_bindings(cmd).map_err(crate::Error::Generate)?,
+        );
     }
 
-    files.insert_child_directory("src", top_level(&ctx)?);
+    files.insert_child_directory(
+        Path::new("src").join("wasm"),
+        crate::codegen::wasm_modules(package),
+    );
+    files.insert_child_directory("src", top_level(&ctx).map_err(crate::Error::Generate)?);
+
+    if !ctx.mounts.is_empty() {
+        files.insert_child_directory(
+            "src",
+            mounts(&ctx, package).map_err(crate::Error::Generate)?,
+        );
+    }
+    if ctx.has_emscripten_commands {
+        files.insert(
+            Path::new("src").join("emscripten.js"),
+            emscripten_helpers(&ctx).map_err(crate::Error::Generate)?,
+        );
+    }
+    if !ctx.servers.is_empty() {
+        files.insert(
+            Path::new("src").join("wcgi.js"),
+            wcgi_helpers(&ctx).map_err(crate::Error::Generate)?,
+        );
+    }
     let mut metadata = package.metadata().clone();
     if let Some(package_name) = &options.name {
         metadata.package_name.set_name(package_name);
@@ -62,6 +110,10 @@ pub fn generate_javascript(package: &Package, options: &BindingsOptions) -> Resu
     let package_json = generate_package_json(package.requires_wasi(), &metadata);
     files.insert("package.json", package_json);
 
+    if let Some(readme) = &metadata.readme {
+        files.insert("README.md", readme.as_str().into());
+    }
+
     // Note: We need to wrap the generated files in an extra folder because
     // that's how "npm pack" works
     let mut f = Files::new();
@@ -74,9 +126,14 @@ pub fn generate_javascript(package: &Package, options: &BindingsOptions) -> Resu
 struct Context {
     libraries: Vec<LibraryContext>,
     commands: Vec<CommandContext>,
+    /// Commands which handle HTTP requests using WCGI.
+    servers: Vec<CommandContext>,
     generator: String,
     wasi: bool,
     has_wasi_libraries: bool,
+    /// Do any commands need the Emscripten helpers?
+    has_emscripten_commands: bool,
+    mounts: Vec<MountContext>,
 }
 
 impl Context {
@@ -86,40 +143,77 @@ impl Context {
             .iter()
             .map(LibraryContext::for_lib)
             .collect();
-        let commands: Vec<_> = pkg.commands().iter().map(CommandContext::for_cmd).collect();
+        let has_mounts = !pkg.mounts().is_empty();
+        let (servers, commands): (Vec<_>, Vec<_>) = pkg
+            .commands()
+            .iter()
+            .map(|cmd| CommandContext::for_cmd(cmd, has_mounts))
+            .partition(|cmd| cmd.wcgi);
+        let mounts = pkg
+            .mounts()
+            .iter()
+            .enumerate()
+            .map(|(i, mount)| MountContext {
+                guest_path: mount.guest_path.clone(),
+                dir: format!("volumes/{i}"),
+            })
+            .collect();
 
         let has_wasi_libraries = libraries.iter().any(|lib| lib.wasi);
+        let has_emscripten_commands = commands.iter().any(|cmd| cmd.emscripten);
 
-        let wasi = !commands.is_empty() || has_wasi_libraries;
+        let wasi = !commands.is_empty() || !servers.is_empty() || has_wasi_libraries;
 
         Context {
             libraries,
             commands,
+            servers,
             generator: crate::GENERATOR.to_string(),
             wasi,
             has_wasi_libraries,
+            has_emscripten_commands,
+            mounts,
         }
     }
 }
 
+#[derive(Debug, serde::Serialize)]
+struct MountContext {
+    /// Where the directory is mounted inside the guest.
+    guest_path: String,
+    /// Where the directory's contents are saved, relative to `src/`.
+    dir: String,
+}
+
 #[derive(Debug, serde::Serialize)]
 struct CommandContext {
     name: String,
     ident: String,
     module_filename: String,
-    #[serde(skip)]
-    wasm: Vec<u8>,
+    /// The name passed to the command as `argv[0]`.
+    program_name: String,
+    main_args: Vec<String>,
+    env: BTreeMap<String, String>,
+    /// Does the package have any directories which need to be mounted?
+    has_mounts: bool,
+    /// Was the command compiled with Emscripten?
+    emscripten: bool,
+    /// Does the command handle HTTP requests using WCGI?
+    wcgi: bool,
 }
 
 impl CommandContext {
-    fn for_cmd(cmd: &Command) -> CommandContext {
-        let module_filename = Path::new(&cmd.name).with_extension("wasm");
-
+    fn for_cmd(cmd: &Command, has_mounts: bool) -> CommandContext {
         CommandContext {
             name: cmd.name.clone(),
             ident: cmd.name.to_snake_case(),
-            module_filename: module_filename.display().to_string(),
-            wasm: cmd.wasm.clone(),
+            module_filename: crate::files::wasm_filename(&cmd.wasm),
+            program_name: cmd.program_name().to_string(),
+            main_args: cmd.main_args.clone(),
+            env: cmd.env.clone(),
+            has_mounts,
+            emscripten: cmd.kind == CommandKind::Emscripten,
+            wcgi: cmd.kind == CommandKind::Wcgi,
         }
     }
 }
@@ -128,26 +222,23 @@ impl CommandContext {
 struct LibraryContext {
     /// The identifier that should be used when accessing this library.
     ident: String,
-    /// The filename of the WebAssembly module (e.g. `wasmer-pack.wasm`).
+    /// The filename of the WebAssembly module, relative to `src/wasm/`.
     module_filename: String,
     /// Does this library require WASI?
     wasi: bool,
     exports: InterfaceContext,
     imports: Vec<InterfaceContext>,
-    #[serde(skip)]
-    wasm: Vec<u8>,
 }
 
 impl LibraryContext {
     fn for_lib(lib: &Library) -> Self {
-        let module_filename = Path::new(lib.module_filename()).with_extension("wasm");
         let interface_name = lib.interface_name();
         let ident = interface_name.to_snake_case();
 
         let exports = InterfaceContext {
             interface_name: lib.exports.name().to_string(),
             class_name: lib.exports.name().to_pascal_case(),
-            interface: lib.exports.0.clone(),
+            interface: lib.exports.parsed.clone(),
         };
         let imports = lib
             .imports
@@ -155,17 +246,16 @@ impl LibraryContext {
             .map(|interface| InterfaceContext {
                 interface_name: interface.name().to_string(),
                 class_name: interface.name().to_pascal_case(),
-                interface: interface.0.clone(),
+                interface: interface.parsed.clone(),
             })
             .collect();
 
         LibraryContext {
             ident,
-            module_filename: module_filename.display().to_string(),
+            module_filename: crate::files::wasm_filename(&lib.module.wasm),
             wasi: lib.requires_wasi(),
             exports,
             imports,
-            wasm: lib.module.wasm.clone(),
         }
     }
 }
@@ -183,7 +273,6 @@ struct InterfaceContext {
 
 fn command_bindings(cmd: &CommandContext) -> Result<Files, Error> {
     let mut files = Files::new();
-    let module_filename = Path::new(&cmd.name).with_extension("wasm");
 
     files.insert(
         Path::new(&cmd.name).with_extension("js"),
@@ -202,7 +291,6 @@ fn command_bindings(cmd: &CommandContext) -> Result<Files, Error> {
             .render(cmd)?
             .into(),
     );
-    files.insert(module_filename, SourceFile::from(&cmd.wasm));
 
     Ok(files)
 }
@@ -231,19 +319,46 @@ fn top_level(ctx: &Context) -> Result<Files, Error> {
     Ok(files)
 }
 
+/// Save each mounted directory alongside a helper for loading them into the
+/// WASI filesystem.
+fn mounts(ctx: &Context, pkg: &Package) -> Result<Files, Error> {
+    let mut files = Files::new();
+
+    for (mount, mount_ctx) in pkg.mounts().iter().zip(&ctx.mounts) {
+        files.insert_child_directory(&mount_ctx.dir, mount.files.clone());
+    }
+
+    let mounts_js = TEMPLATES.get_template("mounts.js").unwrap().render(ctx)?;
+    files.insert("mounts.js", mounts_js.into());
+
+    Ok(files)
+}
+
+/// The host functions needed by commands compiled with Emscripten.
+fn emscripten_helpers(ctx: &Context) -> Result<SourceFile, Error> {
+    let emscripten_js = TEMPLATES
+        .get_template("emscripten.js")
+        .unwrap()
+        .render(ctx)?;
+
+    Ok(emscripten_js.into())
+}
+
+/// The glue code for running WCGI commands inside a HTTP server.
+fn wcgi_helpers(ctx: &Context) -> Result<SourceFile, Error> {
+    let wcgi_js = TEMPLATES.get_template("wcgi.js").unwrap().render(ctx)?;
+
+    Ok(wcgi_js.into())
+}
+
 fn library_bindings(ctx: &Context) -> Result<Files, Error> {
     let mut files = Files::new();
 
     for LibraryContext {
-        module_filename,
-        exports,
-        imports,
-        wasm,
-        ..
+        exports, imports, ..
     } in &ctx.libraries
     {
-        let mut bindings = generate_bindings(exports, imports);
-        bindings.insert(module_filename, wasm.into());
+        let bindings = generate_bindings(exports, imports);
         files.insert_child_directory(&exports.interface_name, bindings);
     }
 
@@ -263,23 +378,63 @@ fn library_bindings(ctx: &Context) -> Result<Files, Error> {
 }
 
 fn generate_package_json(needs_wasi: bool, metadata: &Metadata) -> SourceFile {
-    let dependencies = if needs_wasi {
-        serde_json::json!({
-            "@wasmer/wasi": WASMER_WASI_VERSION,
-        })
-    } else {
-        serde_json::json!({})
-    };
-
-    let package_json = serde_json::json!({
+    let mut dependencies = serde_json::Map::new();
+
+    // Each WAPM dependency should have its own generated package
+    for dep in &metadata.dependencies {
+        dependencies.insert(
+            dep.package_name.javascript_package(),
+            dep.javascript_version().into(),
+        );
+    }
+
+    // Note: this goes last because the generated code relies on this
+    // particular version, even if a WAPM dependency (e.g. "wasmer/wasi")
+    // happens to map to the same npm package
+    if needs_wasi {
+        dependencies.insert("@wasmer/wasi".to_string(), WASMER_WASI_VERSION.into());
+    }
+
+    let mut package_json = serde_json::json!({
         "name": metadata.package_name.javascript_package(),
-        "version": &metadata.version,
+        "version": metadata.javascript_version(),
         "main": format!("src/index.js"),
         "types": format!("src/index.d.ts"),
         "type": "commonjs",
         "dependencies": dependencies,
     });
 
+    let Metadata {
+        description,
+        license,
+        homepage,
+        repository,
+        authors,
+        ..
+    } = metadata;
+    let fields = package_json
+        .as_object_mut()
+        .expect("package.json is always an object");
+
+    if let Some(description) = description {
+        fields.insert("description".to_string(), description.as_str().into());
+    }
+    if let Some(license) = license {
+        fields.insert("license".to_string(), license.as_str().into());
+    }
+    if let Some(homepage) = homepage {
+        fields.insert("homepage".to_string(), homepage.as_str().into());
+    }
+    if let Some(repository) = repository {
+        fields.insert(
+            "repository".to_string(),
+            serde_json::json!({ "type": "git", "url": repository }),
+        );
+    }
+    if !authors.is_empty() {
+        fields.insert("contributors".to_string(), authors.as_slice().into());
+    }
+
     format!("{package_json:#}").into()
 }
 
@@ -306,13 +461,16 @@ mod tests {
 
     use insta::Settings;
 
-    use crate::{Metadata, Module};
+    use crate::{Dependency, Metadata, Module};
 
     use super::*;
 
     #[test]
     fn package_json() {
-        let metadata = Metadata::new("wasmerio/wasmer-pack".parse().unwrap(), "0.0.0");
+        let metadata = Metadata::new(
+            "wasmerio/wasmer-pack".parse().unwrap(),
+            "0.0.0".parse().unwrap(),
+        );
 
         let got = generate_package_json(false, &metadata);
 
@@ -321,13 +479,61 @@ mod tests {
 
     #[test]
     fn package_json_wasi() {
-        let metadata = Metadata::new("wasmerio/wabt".parse().unwrap(), "0.0.0");
+        let metadata = Metadata::new("wasmerio/wabt".parse().unwrap(), "0.0.0".parse().unwrap());
 
         let got = generate_package_json(true, &metadata);
 
         insta::assert_display_snapshot!(got.utf8_contents().unwrap());
     }
 
+    #[test]
+    fn package_json_with_extended_metadata() {
+        let metadata = Metadata::new(
+            "wasmer/wasmer-pack".parse().unwrap(),
+            "0.0.0".parse().unwrap(),
+        )
+        .with_description("A code generator")
+        .with_license("MIT")
+        .with_homepage("https://wasmer.io/")
+        .with_repository("https://github.com/wasmerio/wasmer-pack")
+        .with_authors(["Wasmer Engineering Team <engineering@wasmer.io>"]);
+
+        let got = generate_package_json(false, &metadata);
+
+        insta::assert_display_snapshot!(got.utf8_contents().unwrap());
+    }
+
+    #[test]
+    fn package_json_with_dependencies() {
+        let metadata = Metadata::new("wasmer/tools".parse().unwrap(), "0.0.0".parse().unwrap())
+            .with_dependencies([
+                Dependency::new("wasmer/coreutils".parse().unwrap(), "^1.2".parse().unwrap()),
+                Dependency::new("python".parse().unwrap(), "*".parse().unwrap()),
+            ]);
+
+        let got = generate_package_json(true, &metadata);
+
+        insta::assert_display_snapshot!(got.utf8_contents().unwrap());
+    }
+
+    #[test]
+    fn wasmer_wasi_takes_precedence_over_wapm_dependencies() {
+        let metadata = Metadata::new("wasmer/tools".parse().unwrap(), "0.0.0".parse().unwrap())
+            .with_dependencies([Dependency::new(
+                "wasmer/wasi".parse().unwrap(),
+                "^0.1".parse().unwrap(),
+            )]);
+
+        let got = generate_package_json(true, &metadata);
+
+        let package_json: serde_json::Value =
+            serde_json::from_str(got.utf8_contents().unwrap()).unwrap();
+        assert_eq!(
+            package_json["dependencies"],
+            serde_json::json!({ "@wasmer/wasi": WASMER_WASI_VERSION })
+        );
+    }
+
     const WASMER_PACK_EXPORTS: &str = include_str!(concat!(
         env!("CARGO_MANIFEST_DIR"),
         "/../wasm/wasmer-pack.exports.wai"
@@ -335,38 +541,46 @@ mod tests {
 
     #[test]
     fn generated_files() {
-        let expected: BTreeSet<&Path> = [
+        let mut expected: BTreeSet<&Path> = [
             "package/package.json",
             "package/src/bindings/index.d.ts",
             "package/src/bindings/index.js",
             "package/src/bindings/wasmer-pack/browser.d.ts",
             "package/src/bindings/wasmer-pack/browser.js",
             "package/src/bindings/wasmer-pack/intrinsics.js",
-            "package/src/bindings/wasmer-pack/wasmer_pack_wasm.wasm",
             "package/src/bindings/wasmer-pack/wasmer-pack.d.ts",
             "package/src/bindings/wasmer-pack/wasmer-pack.js",
             "package/src/commands/first.d.ts",
             "package/src/commands/first.js",
-            "package/src/commands/first.wasm",
             "package/src/commands/second-with-dashes.d.ts",
             "package/src/commands/second-with-dashes.js",
-            "package/src/commands/second-with-dashes.wasm",
             "package/src/index.d.ts",
             "package/src/index.js",
         ]
         .iter()
         .map(Path::new)
         .collect();
-        let metadata = Metadata::new("wasmer/wasmer-pack".parse().unwrap(), "1.2.3");
+        let metadata = Metadata::new(
+            "wasmer/wasmer-pack".parse().unwrap(),
+            "1.2.3".parse().unwrap(),
+        );
+        let exports =
+            crate::Interface::from_wit("wasmer-pack.exports.wit", WASMER_PACK_EXPORTS).unwrap();
         let module = Module {
             name: "wasmer_pack_wasm.wasm".to_string(),
             abi: crate::Abi::None,
-            wasm: Vec::new(),
+            wasm: crate::exports::stub_module(&exports),
         };
-        let exports =
-            crate::Interface::from_wit("wasmer-pack.exports.wit", WASMER_PACK_EXPORTS).unwrap();
+        let library_wasm =
+            Path::new("package/src/wasm").join(crate::files::wasm_filename(&module.wasm));
+        expected.insert(&library_wasm);
+        // Both commands use the same (empty) binary, so they share a file
+        let command_wasm = Path::new("package/src/wasm").join(crate::files::wasm_filename(&[]));
+        expected.insert(&command_wasm);
         let commands = vec![
-            Command::new("first", []),
+            Command::new("first", [])
+                .with_main_args(["--verbose"])
+                .with_env([("RUST_LOG", "info")]),
             Command::new("second-with-dashes", []),
         ];
         let browser =
@@ -390,6 +604,7 @@ mod tests {
             r"Generated by wasmer-pack v\d+\.\d+\.\d+(-\w+(\.\d+)?)?",
             "Generated by XXX",
         );
+        settings.add_filter(r"[0-9a-f]{64}\.wasm", "[hash].wasm");
         settings.bind(|| {
             insta::assert_display_snapshot!(files["package/package.json"].utf8_contents().unwrap());
             insta::assert_display_snapshot!(files["package/src/commands/first.d.ts"]
@@ -422,4 +637,95 @@ mod tests {
                 .unwrap());
         });
     }
+
+    #[test]
+    fn generated_files_with_mounts() {
+        let metadata = Metadata::new("wasmer/python".parse().unwrap(), "1.2.3".parse().unwrap());
+        let mut lib = Files::new();
+        lib.insert("python/os.py", "import sys".into());
+        let pkg = Package::new(metadata, Vec::new(), vec![Command::new("python", [])])
+            .with_mounts([crate::Mount::new("/lib", lib)]);
+
+        let files = generate_javascript(&pkg, &BindingsOptions::default()).unwrap();
+
+        assert_eq!(
+            files["package/src/volumes/0/python/os.py"].utf8_contents(),
+            Some("import sys")
+        );
+        insta::assert_display_snapshot!(files["package/src/mounts.js"].utf8_contents().unwrap());
+        insta::assert_display_snapshot!(files["package/src/commands/python.js"]
+            .utf8_contents()
+            .unwrap());
+    }
+
+    #[test]
+    fn commands_use_their_exec_name_as_argv0() {
+        let metadata = Metadata::new("wasmer/python".parse().unwrap(), "3.12.0".parse().unwrap());
+        let python = Command::new("python", []).with_exec_name("python3");
+        let wasm = Command::new("wasm", []);
+        let pkg = Package::new(metadata, Vec::new(), vec![python, wasm]);
+
+        let files = generate_javascript(&pkg, &BindingsOptions::default()).unwrap();
+
+        let python = files["package/src/commands/python.js"]
+            .utf8_contents()
+            .unwrap();
+        assert!(python.contains("const PROGRAM_NAME = \"python3\";"));
+        let wasm = files["package/src/commands/wasm.js"]
+            .utf8_contents()
+            .unwrap();
+        assert!(wasm.contains("const PROGRAM_NAME = \"wasm\";"));
+    }
+
+    #[test]
+    fn generated_files_with_emscripten_commands() {
+        let metadata = Metadata::new("wasmer/lua".parse().unwrap(), "1.2.3".parse().unwrap());
+        // Compiled with -sSTANDALONE_WASM, so it imports WASI and has a _start
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+                (func (export "_start"))
+            )"#,
+        )
+        .unwrap();
+        let lua = Command::new("lua", wasm).with_kind(CommandKind::Emscripten);
+        let pkg = Package::new(metadata, Vec::new(), vec![lua]);
+
+        let files = generate_javascript(&pkg, &BindingsOptions::default()).unwrap();
+
+        insta::assert_display_snapshot!(files["package/src/emscripten.js"]
+            .utf8_contents()
+            .unwrap());
+        insta::assert_display_snapshot!(files["package/src/commands/lua.js"]
+            .utf8_contents()
+            .unwrap());
+    }
+
+    #[test]
+    fn generated_files_with_wcgi_commands() {
+        let metadata = Metadata::new("wasmer/hello".parse().unwrap(), "1.2.3".parse().unwrap());
+        let serve = Command::new("serve", []).with_kind(CommandKind::Wcgi);
+        let pkg = Package::new(metadata, Vec::new(), vec![serve]);
+
+        let files = generate_javascript(&pkg, &BindingsOptions::default()).unwrap();
+
+        insta::assert_display_snapshot!(files["package/src/wcgi.js"]
+            .utf8_contents()
+            .unwrap()
+            .replace(crate::GENERATOR, "XXX"));
+        insta::assert_display_snapshot!(files["package/src/commands/serve.js"]
+            .utf8_contents()
+            .unwrap());
+        insta::assert_display_snapshot!(files["package/src/commands/serve.d.ts"]
+            .utf8_contents()
+            .unwrap());
+        insta::assert_display_snapshot!(files["package/src/index.js"]
+            .utf8_contents()
+            .unwrap()
+            .replace(crate::GENERATOR, "XXX"));
+        insta::assert_display_snapshot!(files["package/src/index.d.ts"]
+            .utf8_contents()
+            .unwrap()
+            .replace(crate::GENERATOR, "XXX"));
+    }
 }
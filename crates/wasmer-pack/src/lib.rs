@@ -11,7 +11,8 @@
 //!
 //! // First, we need to give the package some metadata
 //! let package_name = "username/my-package".parse()?;
-//! let metadata = Metadata::new(package_name, "1.2.3");
+//! let version = "1.2.3".parse()?;
+//! let metadata = Metadata::new(package_name, version);
 //!
 //! // Then we'll load the libraries from disk (this example only uses one)
 //! let module = Module::from_path("./module.wasm", Abi::None)?;
@@ -43,20 +44,55 @@
 #[cfg(test)]
 extern crate pretty_assertions;
 
+mod c;
+mod codegen;
+mod component_wit;
+mod error;
+mod exports;
 mod files;
+mod go;
+mod introspection;
 mod js;
+mod manifest;
+mod php;
 mod pirita;
 mod py;
+mod resolver;
+mod ruby;
+mod rust;
 mod types;
+mod validation;
 mod versions;
+mod warnings;
 
 pub use crate::{
+    c::generate_c,
+    error::Error,
+    exports::{ExportReport, MistypedExport},
     files::{Files, SourceFile},
+    go::generate_go,
+    introspection::{
+        Case, EnumCase, Field, Flag, Function, FunctionKind, Param, Resource, Type, TypeDefinition,
+        TypeDefinitionKind, UnionCase,
+    },
     js::generate_javascript,
+    manifest::{
+        Manifest, ManifestCommand, ManifestInterface, ManifestLibrary, ManifestMetadata,
+        ManifestModule, ManifestMount, MANIFEST_VERSION,
+    },
+    php::generate_php,
     py::generate_python,
+    resolver::{Resolver, WebcDirectory},
+    ruby::generate_ruby,
+    rust::generate_rust,
     types::BindingsOptions,
-    types::{Abi, Command, Interface, Library, Metadata, Module, Package, PackageName},
+    types::{
+        Abi, Command, CommandKind, Dependency, Interface, Library, Metadata, Module, Mount,
+        Package, PackageName,
+    },
+    validation::{ItemKind, Problem, ValidationError},
     versions::WAI_PARSER_VERSION,
+    warnings::Warning,
 };
 
 /// The generator name that will be mentioned at the top level of each generated
@@ -0,0 +1,605 @@
+//! A stable, versioned JSON representation of a [`Package`].
+//!
+//! This lets tools that already have their WebAssembly modules and WAI files
+//! on hand describe a package to `wasmer-pack` without needing to assemble a
+//! WEBC file first.
+//!
+//! ```json
+//! {
+//!   "manifest_version": 1,
+//!   "metadata": {
+//!     "name": "wasmer/wasmer-pack",
+//!     "version": "0.7.0"
+//!   },
+//!   "libraries": [
+//!     {
+//!       "module": { "path": "wasmer_pack_wasm.wasm" },
+//!       "abi": "none",
+//!       "exports": { "path": "wasmer-pack.exports.wai" }
+//!     }
+//!   ],
+//!   "commands": [
+//!     { "name": "wasmer-pack", "module": { "path": "wasmer-pack-cli.wasm" } }
+//!   ]
+//! }
+//! ```
+//!
+//! Relative paths are resolved relative to a base directory (normally the
+//! directory containing the manifest). Files may also be embedded directly,
+//! with WebAssembly modules encoded as base64.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Abi, Command, CommandKind, Dependency, Files, Interface, Library, Metadata, Module, Mount,
+    Package, SourceFile,
+};
+
+/// The version of the manifest format understood by this version of
+/// `wasmer-pack`.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// The top-level JSON manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// The version of the manifest format (see [`MANIFEST_VERSION`]).
+    pub manifest_version: u32,
+    pub metadata: ManifestMetadata,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub libraries: Vec<ManifestLibrary>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<ManifestCommand>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mounts: Vec<ManifestMount>,
+}
+
+/// The manifest's equivalent of [`Metadata`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestMetadata {
+    /// The package name (e.g. `wasmer/wasmer-pack`).
+    pub name: String,
+    /// The package's version number, which must be valid semver.
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// The README's contents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readme: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// Other packages this package depends on, mapping package names to
+    /// version requirements (e.g. `"wasmer/python": "^3.12"`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, String>,
+}
+
+/// The manifest's equivalent of [`Library`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestLibrary {
+    pub module: ManifestModule,
+    /// The ABI used by the module, detected automatically if not provided.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abi: Option<Abi>,
+    pub exports: ManifestInterface,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imports: Vec<ManifestInterface>,
+}
+
+/// The manifest's equivalent of [`Command`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestCommand {
+    pub name: String,
+    pub module: ManifestModule,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub main_args: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec_name: Option<String>,
+    /// The environment the command runs in, defaulting to WASI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<CommandKind>,
+}
+
+/// The manifest's equivalent of [`Mount`], either referencing a directory on
+/// disk or embedding its files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum ManifestMount {
+    Path {
+        /// Where the directory will be mounted in the guest (e.g. `/lib`).
+        guest_path: String,
+        path: PathBuf,
+    },
+    Embedded {
+        /// Where the directory will be mounted in the guest (e.g. `/lib`).
+        guest_path: String,
+        /// The directory's files, keyed by their path relative to the
+        /// directory and encoded as base64.
+        files: BTreeMap<String, String>,
+    },
+}
+
+/// A WebAssembly module, either referenced by path or embedded in the
+/// manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum ManifestModule {
+    Path {
+        path: PathBuf,
+    },
+    Embedded {
+        /// The module's filename (e.g. `wasmer_pack_wasm.wasm`).
+        name: String,
+        /// The WebAssembly binary, encoded as base64.
+        base64: String,
+    },
+}
+
+/// A WAI interface definition, either referenced by path or embedded in the
+/// manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum ManifestInterface {
+    Path {
+        path: PathBuf,
+    },
+    Embedded {
+        /// The interface's filename (e.g. `wasmer-pack.exports.wai`).
+        filename: String,
+        contents: String,
+        /// The source code for any files pulled in with a `use` statement,
+        /// keyed by the name they were imported with.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        dependencies: BTreeMap<String, String>,
+    },
+}
+
+impl Manifest {
+    /// Create a [`Manifest`] which embeds everything in the [`Package`].
+    pub fn from_package(pkg: &Package) -> Self {
+        let Metadata {
+            package_name,
+            version,
+            description,
+            license,
+            homepage,
+            repository,
+            readme,
+            authors,
+            dependencies,
+        } = pkg.metadata().clone();
+
+        Manifest {
+            manifest_version: MANIFEST_VERSION,
+            metadata: ManifestMetadata {
+                name: package_name.to_string(),
+                version: version.to_string(),
+                description,
+                license,
+                homepage,
+                repository,
+                readme,
+                authors,
+                dependencies: dependencies
+                    .into_iter()
+                    .map(|dep| (dep.package_name.to_string(), dep.version.to_string()))
+                    .collect(),
+            },
+            libraries: pkg
+                .libraries()
+                .iter()
+                .map(|lib| ManifestLibrary {
+                    module: ManifestModule::embedded(&lib.module.name, &lib.module.wasm),
+                    abi: Some(lib.module.abi),
+                    exports: ManifestInterface::embedded(&lib.exports),
+                    imports: lib
+                        .imports
+                        .iter()
+                        .map(ManifestInterface::embedded)
+                        .collect(),
+                })
+                .collect(),
+            commands: pkg
+                .commands()
+                .iter()
+                .map(|cmd| ManifestCommand {
+                    name: cmd.name.clone(),
+                    module: ManifestModule::embedded(format!("{}.wasm", cmd.name), &cmd.wasm),
+                    main_args: cmd.main_args.clone(),
+                    env: cmd.env.clone(),
+                    exec_name: cmd.exec_name.clone(),
+                    kind: (cmd.kind != CommandKind::Wasi).then_some(cmd.kind),
+                })
+                .collect(),
+            mounts: pkg.mounts().iter().map(ManifestMount::embedded).collect(),
+        }
+    }
+
+    /// Turn the [`Manifest`] into a [`Package`], resolving any relative paths
+    /// against `base_dir`.
+    pub fn into_package(self, base_dir: &Path) -> Result<Package, crate::Error> {
+        let Manifest {
+            manifest_version,
+            metadata,
+            libraries,
+            commands,
+            mounts,
+        } = self;
+
+        if manifest_version != MANIFEST_VERSION {
+            return Err(crate::Error::Load(anyhow::anyhow!(
+                "Unsupported manifest version {manifest_version} (expected {MANIFEST_VERSION})"
+            )));
+        }
+
+        let metadata = metadata.load().map_err(crate::Error::Load)?;
+
+        let libraries = libraries
+            .into_iter()
+            .map(|lib| lib.load(base_dir))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let commands = commands
+            .into_iter()
+            .map(|cmd| {
+                let ManifestCommand {
+                    name,
+                    module,
+                    main_args,
+                    env,
+                    exec_name,
+                    kind,
+                } = cmd;
+                let (_, wasm) = module
+                    .load(base_dir)
+                    .with_context(|| format!("Unable to load the \"{name}\" command"))
+                    .map_err(crate::Error::Load)?;
+                Ok(Command {
+                    main_args,
+                    env,
+                    exec_name,
+                    kind: kind.unwrap_or_default(),
+                    ..Command::new(name, wasm)
+                })
+            })
+            .collect::<Result<Vec<_>, crate::Error>>()?;
+
+        let mounts = mounts
+            .into_iter()
+            .map(|mount| mount.load(base_dir))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(crate::Error::Load)?;
+
+        Ok(Package::try_new(metadata, libraries, commands)?.with_mounts(mounts))
+    }
+}
+
+impl ManifestMetadata {
+    fn load(self) -> Result<Metadata, Error> {
+        let ManifestMetadata {
+            name,
+            version,
+            description,
+            license,
+            homepage,
+            repository,
+            readme,
+            authors,
+            dependencies,
+        } = self;
+
+        let package_name = name
+            .parse()
+            .with_context(|| format!("\"{name}\" is not a valid package name"))?;
+        let version = version
+            .parse()
+            .with_context(|| format!("\"{version}\" is not a valid semver version number"))?;
+
+        let dependencies = dependencies
+            .into_iter()
+            .map(|(name, version)| {
+                let package_name = name
+                    .parse()
+                    .with_context(|| format!("\"{name}\" is not a valid package name"))?;
+                let version = version.parse().with_context(|| {
+                    format!("\"{version}\" is not a valid version requirement for \"{name}\"")
+                })?;
+                Ok(Dependency::new(package_name, version))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut metadata = Metadata::new(package_name, version)
+            .with_authors(authors)
+            .with_dependencies(dependencies);
+        metadata.description = description;
+        metadata.license = license;
+        metadata.homepage = homepage;
+        metadata.repository = repository;
+        metadata.readme = readme;
+
+        Ok(metadata)
+    }
+}
+
+impl ManifestLibrary {
+    fn load(self, base_dir: &Path) -> Result<Library, crate::Error> {
+        let ManifestLibrary {
+            module,
+            abi,
+            exports,
+            imports,
+        } = self;
+
+        let exports = exports
+            .load(base_dir)
+            .map_err(|e| e.context("Unable to load the exports interface"))?;
+
+        let (name, wasm) = module
+            .load(base_dir)
+            .with_context(|| format!("Unable to load the \"{}\" library", exports.name()))
+            .map_err(crate::Error::Load)?;
+
+        let imports = imports
+            .into_iter()
+            .map(|import| import.load(base_dir))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.context("Unable to load the imports interfaces"))?;
+
+        let abi = abi.unwrap_or_else(|| Abi::detect(&wasm));
+
+        Ok(Library {
+            module: Module { name, abi, wasm },
+            exports,
+            imports,
+        })
+    }
+}
+
+impl ManifestMount {
+    fn embedded(mount: &Mount) -> Self {
+        ManifestMount::Embedded {
+            guest_path: mount.guest_path.clone(),
+            files: mount
+                .files
+                .iter()
+                .map(|(path, file)| (path.display().to_string(), STANDARD.encode(file.contents())))
+                .collect(),
+        }
+    }
+
+    fn load(self, base_dir: &Path) -> Result<Mount, Error> {
+        match self {
+            ManifestMount::Path { guest_path, path } => {
+                let path = base_dir.join(path);
+                let files = read_dir_recursive(&path)
+                    .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+                Ok(Mount::new(guest_path, files))
+            }
+            ManifestMount::Embedded { guest_path, files } => {
+                let mut contents = Files::new();
+                for (path, base64) in files {
+                    let data = STANDARD.decode(base64).with_context(|| {
+                        format!("Unable to decode \"{path}\" in \"{guest_path}\" as base64")
+                    })?;
+                    contents.insert(path, SourceFile::new(data));
+                }
+                Ok(Mount::new(guest_path, contents))
+            }
+        }
+    }
+}
+
+fn read_dir_recursive(dir: &Path) -> Result<Files, Error> {
+    let mut files = Files::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+
+        if entry.file_type()?.is_dir() {
+            files.insert_child_directory(name, read_dir_recursive(&path)?);
+        } else {
+            let contents = std::fs::read(&path)
+                .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+            files.insert(name, SourceFile::new(contents));
+        }
+    }
+
+    Ok(files)
+}
+
+impl ManifestModule {
+    fn embedded(name: impl Into<String>, wasm: &[u8]) -> Self {
+        ManifestModule::Embedded {
+            name: name.into(),
+            base64: STANDARD.encode(wasm),
+        }
+    }
+
+    /// Get the module's filename and WebAssembly code.
+    fn load(self, base_dir: &Path) -> Result<(String, Vec<u8>), Error> {
+        match self {
+            ManifestModule::Path { path } => {
+                let path = base_dir.join(path);
+                let Module { name, wasm, .. } = Module::from_path(path, Abi::None)?;
+                Ok((name, wasm))
+            }
+            ManifestModule::Embedded { name, base64 } => {
+                let wasm = STANDARD
+                    .decode(base64)
+                    .with_context(|| format!("Unable to decode \"{name}\" as base64"))?;
+                Ok((name, wasm))
+            }
+        }
+    }
+}
+
+impl ManifestInterface {
+    fn embedded(interface: &Interface) -> Self {
+        let source = interface.source.clone();
+
+        ManifestInterface::Embedded {
+            filename: source.filename,
+            contents: source.contents,
+            dependencies: source.dependencies,
+        }
+    }
+
+    fn load(self, base_dir: &Path) -> Result<Interface, crate::Error> {
+        match self {
+            ManifestInterface::Path { path } => Interface::from_path(base_dir.join(path)),
+            ManifestInterface::Embedded {
+                filename,
+                contents,
+                dependencies,
+            } => Interface::parse_with(&filename, &contents, |dependency| {
+                let contents = dependencies.get(dependency).with_context(|| {
+                    format!("The \"{dependency}\" dependency wasn't embedded in the manifest")
+                })?;
+                Ok((PathBuf::from(dependency), contents.clone()))
+            })
+            .with_context(|| format!("Unable to parse \"{filename}\""))
+            .map_err(crate::Error::Parse),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT_ROOT: &str = env!("CARGO_MANIFEST_DIR");
+
+    #[test]
+    fn round_trip_an_embedded_package() {
+        let calc = Interface::from_wit("calc.exports.wai", "add: func(a: u32, b: u32) -> u32");
+        let wasm = crate::exports::stub_module(&calc.unwrap());
+        let exports = ManifestInterface::Embedded {
+            filename: "calc.exports.wai".to_string(),
+            contents: "use * from types\nadd: func(a: number, b: number) -> number".to_string(),
+            dependencies: [("types".to_string(), "type number = u32".to_string())]
+                .into_iter()
+                .collect(),
+        };
+        let manifest = Manifest {
+            manifest_version: MANIFEST_VERSION,
+            metadata: ManifestMetadata {
+                name: "wasmer/calc".to_string(),
+                version: "1.2.3".to_string(),
+                description: Some("A calculator".to_string()),
+                license: Some("MIT".to_string()),
+                homepage: None,
+                repository: None,
+                readme: None,
+                authors: vec!["Wasmer <hello@wasmer.io>".to_string()],
+                dependencies: [("wasmer/math".to_string(), "^1.2".to_string())]
+                    .into_iter()
+                    .collect(),
+            },
+            libraries: vec![ManifestLibrary {
+                module: ManifestModule::embedded("calc.wasm", &wasm),
+                abi: Some(Abi::None),
+                exports,
+                imports: Vec::new(),
+            }],
+            commands: vec![ManifestCommand {
+                name: "calc".to_string(),
+                module: ManifestModule::embedded("calc.wasm", b"\0asm\x01\0\0\0"),
+                main_args: vec!["--verbose".to_string()],
+                env: [("RUST_LOG".to_string(), "info".to_string())]
+                    .into_iter()
+                    .collect(),
+                exec_name: Some("calculator".to_string()),
+                kind: Some(CommandKind::Emscripten),
+            }],
+            mounts: vec![ManifestMount::Embedded {
+                guest_path: "/lib".to_string(),
+                files: [("python/os.py".to_string(), STANDARD.encode("import sys"))]
+                    .into_iter()
+                    .collect(),
+            }],
+        };
+
+        let json = serde_json::to_string(&manifest).unwrap();
+        let pkg = Package::from_json_manifest(&json, Path::new(".")).unwrap();
+
+        assert_eq!(pkg.libraries()[0].interface_name(), "calc");
+        assert_eq!(pkg.libraries()[0].module.wasm, wasm);
+        assert_eq!(pkg.commands()[0].name, "calc");
+        assert_eq!(pkg.metadata().license.as_deref(), Some("MIT"));
+        assert_eq!(Manifest::from_package(&pkg), manifest);
+    }
+
+    #[test]
+    fn load_files_relative_to_the_base_directory() {
+        let temp = tempfile::tempdir().unwrap();
+        let exports_wai = Path::new(PROJECT_ROOT)
+            .join("..")
+            .join("wasm")
+            .join("wasmer-pack.exports.wai");
+        let wasm = crate::exports::stub_module(&Interface::from_path(&exports_wai).unwrap());
+        std::fs::write(temp.path().join("wasmer_pack_wasm.wasm"), wasm).unwrap();
+        let lib_dir = temp.path().join("lib").join("python");
+        std::fs::create_dir_all(&lib_dir).unwrap();
+        std::fs::write(lib_dir.join("os.py"), "import sys").unwrap();
+        let json = serde_json::json!({
+            "manifest_version": 1,
+            "metadata": { "name": "wasmer/wasmer-pack", "version": "0.0.0" },
+            "libraries": [
+                {
+                    "module": { "path": "wasmer_pack_wasm.wasm" },
+                    "exports": { "path": exports_wai },
+                }
+            ],
+            "mounts": [{ "guest_path": "/lib", "path": "lib" }],
+        });
+
+        let pkg = Package::from_json_manifest(&json.to_string(), temp.path()).unwrap();
+
+        let lib = &pkg.libraries()[0];
+        assert_eq!(lib.interface_name(), "wasmer-pack");
+        assert_eq!(lib.module.name, "wasmer_pack_wasm.wasm");
+        assert_eq!(lib.module.abi, Abi::None);
+        let mount = &pkg.mounts()[0];
+        assert_eq!(mount.guest_path, "/lib");
+        assert_eq!(
+            mount.files["python/os.py"].utf8_contents(),
+            Some("import sys")
+        );
+    }
+
+    #[test]
+    fn unknown_manifest_versions_are_rejected() {
+        let json = r#"{
+            "manifest_version": 2,
+            "metadata": { "name": "wasmer/wasmer-pack", "version": "0.0.0" }
+        }"#;
+
+        let err = Package::from_json_manifest(json, Path::new(".")).unwrap_err();
+
+        assert!(matches!(err, crate::Error::Load(_)));
+        assert_eq!(
+            err.to_string(),
+            "Unsupported manifest version 2 (expected 1)"
+        );
+    }
+}
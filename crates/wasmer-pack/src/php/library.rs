@@ -0,0 +1,1519 @@
+//! Generate the PHP classes for a single library.
+//!
+//! PSR-4 requires one class per file, so each library produces its own
+//! class, one class per WAI type, and one interface per imported interface.
+//! Like the Go and Ruby generators, the glue code is generated by walking
+//! each function's canonical ABI instructions.
+
+use heck::{ToLowerCamelCase, ToPascalCase, ToShoutySnakeCase};
+use wai_parser::{
+    abi::{AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType},
+    Docs, Function, FunctionKind, Interface, ResourceId, SizeAlign, Type, TypeDefKind,
+};
+
+use crate::{
+    codegen::{
+        self, parenthesize, replace_ident, uses_ident, FunctionCode, Layout, Lifted, ITER_BASE,
+        ITER_ELEM,
+    },
+    Library,
+};
+
+/// Names which can't be used for a PHP class or interface.
+const RESERVED_CLASS_NAMES: &[&str] = &[
+    "abstract",
+    "and",
+    "array",
+    "as",
+    "bool",
+    "break",
+    "callable",
+    "case",
+    "catch",
+    "class",
+    "clone",
+    "const",
+    "continue",
+    "declare",
+    "default",
+    "do",
+    "echo",
+    "else",
+    "elseif",
+    "empty",
+    "enddeclare",
+    "endfor",
+    "endforeach",
+    "endif",
+    "endswitch",
+    "endwhile",
+    "enum",
+    "eval",
+    "exit",
+    "extends",
+    "false",
+    "final",
+    "finally",
+    "float",
+    "fn",
+    "for",
+    "foreach",
+    "function",
+    "global",
+    "goto",
+    "if",
+    "implements",
+    "include",
+    "include_once",
+    "instanceof",
+    "insteadof",
+    "int",
+    "interface",
+    "isset",
+    "iterable",
+    "list",
+    "match",
+    "mixed",
+    "namespace",
+    "never",
+    "new",
+    "null",
+    "numeric",
+    "object",
+    "or",
+    "parent",
+    "print",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "require",
+    "require_once",
+    "resource",
+    "return",
+    "self",
+    "static",
+    "string",
+    "switch",
+    "throw",
+    "trait",
+    "true",
+    "try",
+    "unset",
+    "use",
+    "var",
+    "void",
+    "while",
+    "xor",
+    "yield",
+];
+
+/// Variables referenced by the glue code, which a parameter mustn't shadow.
+const GLUE_NAMES: &[&str] = &[
+    "this", "host", "store", "module", "wasmEnv", "wasiEnv", "imports",
+];
+
+/// Methods that a generated method mustn't override.
+const RESERVED_METHODS: &[&str] = &["instance"];
+
+/// Methods every resource inherits from the `Resource` helper.
+const RESOURCE_METHODS: &[&str] = &["handle", "cloneRef", "drop"];
+
+/// A PHP source file, relative to the library's directory.
+pub(crate) struct ClassFile {
+    pub(crate) class_name: String,
+    pub(crate) src: String,
+}
+
+/// Generate the source code for a library's classes.
+pub(crate) fn generate(lib: &Library, namespace: &str) -> Vec<ClassFile> {
+    let class_name = class_ident(lib.interface_name());
+    let lib_namespace = format!("{namespace}\\Bindings\\{class_name}");
+    let exports = &lib.exports.parsed;
+    let interfaces: Vec<&Interface> = std::iter::once(exports)
+        .chain(lib.imports.iter().map(|i| &i.parsed))
+        .collect();
+
+    // The helpers are normally imported with a "use" statement, but a type
+    // with the same name would shadow them
+    let defined_names: Vec<String> = interfaces
+        .iter()
+        .flat_map(|iface| iface.types.iter())
+        .filter_map(|(_, def)| def.name.as_deref().map(class_ident))
+        .chain(
+            exports
+                .resources
+                .iter()
+                .map(|(_, resource)| class_ident(&resource.name)),
+        )
+        .collect();
+    let qualify = |name: &str, path: String| {
+        if defined_names.iter().any(|n| n == name) {
+            (format!("\\{path}"), None)
+        } else {
+            (name.to_string(), Some(path))
+        }
+    };
+    let (abi, abi_use) = qualify("Abi", format!("{namespace}\\Internal\\Abi"));
+    let (expected, expected_use) = qualify("Expected", format!("{namespace}\\Expected"));
+    let (resource, resource_use) = qualify("Resource", format!("{namespace}\\Internal\\Resource"));
+    let (slab, slab_use) = qualify("Slab", format!("{namespace}\\Internal\\Slab"));
+    let names = Names {
+        abi,
+        expected,
+        resource,
+        slab,
+    };
+
+    let header = |uses: &[Option<&String>]| {
+        let mut src = format!("<?php\n\ndeclare(strict_types=1);\n\nnamespace {lib_namespace};\n");
+        let uses: Vec<_> = uses.iter().flatten().collect();
+        if !uses.is_empty() {
+            src.push('\n');
+            for path in uses {
+                src.push_str(&format!("use {path};\n"));
+            }
+        }
+        src.push('\n');
+        src
+    };
+
+    let mut files = Vec::new();
+    let mut defined = Vec::new();
+
+    for iface in &interfaces {
+        for (_, def) in iface.types.iter() {
+            let name = match &def.name {
+                Some(name) => class_ident(name),
+                None => continue,
+            };
+            if defined.contains(&name) {
+                continue;
+            }
+            let body = match type_definition(iface, &def.kind, &def.docs, &name, &names) {
+                Some(body) => body,
+                None => continue,
+            };
+            let uses_expected = body.contains(&format!("{}::", names.expected))
+                || body.contains(&format!("{} ", names.expected));
+            let src = header(&[expected_use.as_ref().filter(|_| uses_expected)]) + &body;
+            files.push(ClassFile {
+                class_name: name.clone(),
+                src: indent(&src),
+            });
+            defined.push(name);
+        }
+    }
+
+    for (id, resource) in exports.resources.iter() {
+        let name = class_ident(&resource.name);
+        let body = resource_class(exports, id, &class_name, &names);
+        let uses_expected = body.contains(&format!("{} ", names.expected))
+            || body.contains(&format!("): {}", names.expected));
+        let src = header(&[
+            resource_use.as_ref(),
+            expected_use.as_ref().filter(|_| uses_expected),
+        ]) + &body;
+        files.push(ClassFile {
+            class_name: name,
+            src: indent(&src),
+        });
+    }
+
+    for iface in &lib.imports {
+        let iface = &iface.parsed;
+        let body = host_interface(iface, &names);
+        let uses_expected = body.contains(&names.expected);
+        let src = header(&[expected_use.as_ref().filter(|_| uses_expected)]) + &body;
+        files.push(ClassFile {
+            class_name: class_ident(&iface.name),
+            src: indent(&src),
+        });
+    }
+
+    let body = library_class(lib, &class_name, &names);
+    let uses_expected = body.contains(&format!("{}::", names.expected))
+        || body.contains(&format!("): {}", names.expected));
+    let src = header(&[
+        abi_use.as_ref(),
+        expected_use.as_ref().filter(|_| uses_expected),
+        slab_use.as_ref().filter(|_| exports.resources.len() > 0),
+    ]) + &body;
+    files.push(ClassFile {
+        class_name,
+        src: indent(&src),
+    });
+
+    files
+}
+
+/// Does any of the library's interfaces use `expected<T, E>`?
+pub(crate) fn uses_expected(lib: &Library) -> bool {
+    std::iter::once(&lib.exports)
+        .chain(&lib.imports)
+        .flat_map(|iface| iface.parsed.types.iter())
+        .any(|(_, def)| matches!(def.kind, TypeDefKind::Expected(_)))
+}
+
+/// How the generated code refers to the package's shared classes.
+struct Names {
+    abi: String,
+    expected: String,
+    resource: String,
+    slab: String,
+}
+
+fn type_definition(
+    iface: &Interface,
+    kind: &TypeDefKind,
+    docs: &Docs,
+    name: &str,
+    names: &Names,
+) -> Option<String> {
+    let mut code = String::new();
+
+    match kind {
+        TypeDefKind::Record(record) => {
+            let mut doc = doc_lines(docs);
+            let mut params = Vec::new();
+            for field in &record.fields {
+                let field_name = field.name.to_lower_camel_case();
+                let php = php_type(iface, &field.ty, names);
+                let documented = doc_type(iface, &field.ty, names);
+                let field_docs = field
+                    .docs
+                    .contents
+                    .as_deref()
+                    .map(|d| {
+                        d.trim()
+                            .lines()
+                            .map(str::trim)
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    })
+                    .unwrap_or_default();
+                if documented != php || !field_docs.is_empty() {
+                    if doc.last().map_or(false, |l| !l.starts_with("@param")) {
+                        doc.push(String::new());
+                    }
+                    doc.push(
+                        format!("@param {documented} ${field_name} {field_docs}")
+                            .trim_end()
+                            .to_string(),
+                    );
+                }
+                params.push(format!("public {php} ${field_name},"));
+            }
+            push_doc_comment(&mut code, &doc);
+            code.push_str(&format!("final class {name}\n{{\n"));
+            if params.is_empty() {
+                code.push_str("}\n");
+            } else {
+                code.push_str("public function __construct(\n");
+                for param in params {
+                    code.push_str(&param);
+                    code.push('\n');
+                }
+                code.push_str(") {\n}\n}\n");
+            }
+        }
+        TypeDefKind::Flags(flags) => {
+            push_doc_comment(&mut code, &doc_lines(docs));
+            code.push_str(&format!("final class {name}\n{{\n"));
+            for (i, flag) in flags.flags.iter().enumerate() {
+                push_doc_comment(&mut code, &doc_lines(&flag.docs));
+                code.push_str(&format!(
+                    "public const {} = 1 << {i};\n",
+                    constant_ident(&flag.name)
+                ));
+            }
+            code.push_str("}\n");
+        }
+        TypeDefKind::Enum(e) => {
+            push_doc_comment(&mut code, &doc_lines(docs));
+            code.push_str(&format!("final class {name}\n{{\n"));
+            for (i, case) in e.cases.iter().enumerate() {
+                push_doc_comment(&mut code, &doc_lines(&case.docs));
+                code.push_str(&format!(
+                    "public const {} = {i};\n",
+                    constant_ident(&case.name)
+                ));
+            }
+            code.push_str("}\n");
+        }
+        TypeDefKind::Variant(variant) => {
+            let cases: Vec<_> = variant
+                .cases
+                .iter()
+                .map(|c| SumCase {
+                    constant: constant_ident(&c.name),
+                    constructor: c.name.to_lower_camel_case(),
+                    ty: c.ty,
+                    docs: &c.docs,
+                })
+                .collect();
+            push_sum_type(&mut code, iface, name, docs, &cases, names);
+        }
+        TypeDefKind::Union(union) => {
+            let cases: Vec<_> = union
+                .cases
+                .iter()
+                .enumerate()
+                .map(|(i, c)| SumCase {
+                    constant: format!("CASE{i}"),
+                    constructor: format!("case{i}"),
+                    ty: c.ty,
+                    docs: &c.docs,
+                })
+                .collect();
+            push_sum_type(&mut code, iface, name, docs, &cases, names);
+        }
+        // PHP doesn't need a class for these
+        TypeDefKind::Tuple(_)
+        | TypeDefKind::Option(_)
+        | TypeDefKind::Expected(_)
+        | TypeDefKind::List(_)
+        | TypeDefKind::Type(_) => return None,
+        TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+            unreachable!("futures and streams are rejected during validation")
+        }
+    }
+
+    Some(code)
+}
+
+struct SumCase<'a> {
+    constant: String,
+    constructor: String,
+    ty: Type,
+    docs: &'a Docs,
+}
+
+/// Variants and unions are represented as a class with a constant for each
+/// case's tag and a named constructor for each case.
+fn push_sum_type(
+    code: &mut String,
+    iface: &Interface,
+    name: &str,
+    docs: &Docs,
+    cases: &[SumCase<'_>],
+    names: &Names,
+) {
+    push_doc_comment(code, &doc_lines(docs));
+    code.push_str(&format!("final class {name}\n{{\n"));
+    for (i, case) in cases.iter().enumerate() {
+        code.push_str(&format!("public const {} = {i};\n", case.constant));
+    }
+    code.push_str(
+        "\nprivate function __construct(public int $tag, public mixed $value = null)\n{\n}\n",
+    );
+    for case in cases {
+        code.push('\n');
+        let mut doc = doc_lines(case.docs);
+        if case.ty == Type::Unit {
+            push_doc_comment(code, &doc);
+            code.push_str(&format!(
+                "public static function {}(): self\n{{\nreturn new self(self::{});\n}}\n",
+                case.constructor, case.constant
+            ));
+        } else {
+            let php = php_type(iface, &case.ty, names);
+            let documented = doc_type(iface, &case.ty, names);
+            if documented != php {
+                if !doc.is_empty() {
+                    doc.push(String::new());
+                }
+                doc.push(format!("@param {documented} $value"));
+            }
+            push_doc_comment(code, &doc);
+            code.push_str(&format!(
+                "public static function {}({php} $value): self\n{{\nreturn new self(self::{}, $value);\n}}\n",
+                case.constructor, case.constant
+            ));
+        }
+    }
+    code.push_str("}\n");
+}
+
+/// An interface the host implements to provide an imported interface.
+fn host_interface(iface: &Interface, names: &Names) -> String {
+    let mut code = String::new();
+    push_doc_comment(
+        &mut code,
+        &[format!(
+            "The host's implementation of the \"{}\" interface.",
+            iface.name
+        )],
+    );
+    code.push_str(&format!("interface {}\n{{\n", class_ident(&iface.name)));
+
+    for (i, func) in iface.functions.iter().enumerate() {
+        if i > 0 {
+            code.push('\n');
+        }
+        let (signature, doc) = signature(iface, func, names, doc_lines(&func.docs));
+        push_doc_comment(&mut code, &doc);
+        code.push_str(&format!("public {signature};\n"));
+    }
+
+    code.push_str("}\n");
+    code
+}
+
+/// A class wrapping a resource the library exports, with a method for each of
+/// the resource's own functions.
+fn resource_class(iface: &Interface, id: ResourceId, lib_class: &str, names: &Names) -> String {
+    let resource = &iface.resources[id];
+    let mut code = String::new();
+
+    push_doc_comment(&mut code, &doc_lines(&resource.docs));
+    code.push_str(&format!(
+        "final class {} extends {}\n{{\n",
+        class_ident(&resource.name),
+        names.resource
+    ));
+    push_doc_comment(&mut code, &["@internal".to_string()]);
+    code.push_str(&format!(
+        "public function __construct(private {lib_class} $lib, int $handle, \\Closure $destructor)\n{{\nparent::__construct($handle, $destructor);\n}}\n"
+    ));
+
+    for func in &iface.functions {
+        let name = match &func.kind {
+            FunctionKind::Method { resource, name } if *resource == id => name,
+            _ => continue,
+        };
+        let params = &func.params[1..];
+        let (signature, doc) = method_signature(
+            iface,
+            &resource_method_ident(name),
+            params,
+            &func.result,
+            names,
+            doc_lines(&func.docs),
+        );
+        let args: Vec<_> = std::iter::once("$this".to_string())
+            .chain(
+                params
+                    .iter()
+                    .map(|(name, _)| format!("${}", param_name(name))),
+            )
+            .collect();
+        let call = format!(
+            "$this->lib->{}({})",
+            method_ident(&func.name),
+            args.join(", ")
+        );
+        let body = if func.result == Type::Unit {
+            format!("{call};")
+        } else {
+            format!("return {call};")
+        };
+
+        code.push('\n');
+        push_doc_comment(&mut code, &doc);
+        code.push_str(&format!("public {signature}\n{{\n{body}\n}}\n"));
+    }
+
+    code.push_str("}\n");
+    code
+}
+
+fn library_class(lib: &Library, class_name: &str, names: &Names) -> String {
+    let exports = &lib.exports.parsed;
+    let abi = &names.abi;
+    let mut code = String::new();
+
+    push_doc_comment(
+        &mut code,
+        &[format!(
+            "Bindings to the \"{}\" library.",
+            lib.interface_name()
+        )],
+    );
+    code.push_str(&format!(
+        "final class {class_name}\n{{\nprivate {abi} $abi;\n"
+    ));
+    for (_, resource) in exports.resources.iter() {
+        code.push_str(&format!(
+            "private {} ${};\n",
+            names.slab,
+            handles(&resource.name)
+        ));
+    }
+    code.push('\n');
+
+    // The constructor
+    let imports: Vec<&Interface> = lib.imports.iter().map(|i| &i.parsed).collect();
+    let mut params = vec![
+        "mixed $store".to_string(),
+        "mixed $module".to_string(),
+        "mixed $wasiEnv".to_string(),
+    ];
+    params.extend(
+        imports
+            .iter()
+            .map(|iface| format!("{} ${}", class_ident(&iface.name), param_name(&iface.name))),
+    );
+    push_doc_comment(
+        &mut code,
+        &["@internal Use the package's `Bindings` class instead.".to_string()],
+    );
+    code.push_str(&format!(
+        "public function __construct({})\n{{\n",
+        params.join(", ")
+    ));
+    for (_, resource) in exports.resources.iter() {
+        code.push_str(&format!(
+            "$this->{} = new {}();\n",
+            handles(&resource.name),
+            names.slab
+        ));
+    }
+    if imports.is_empty() && exports.resources.len() == 0 {
+        code.push_str(&format!(
+            "$this->abi = {abi}::instantiate($store, $module, [], $wasiEnv);\n"
+        ));
+    } else {
+        code.push_str("$imports = [\n");
+        for iface in &imports {
+            code.push_str(&format!("{} => [\n", quote(&iface.name)));
+            for func in &iface.functions {
+                let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+                let args: Vec<_> = (0..sig.params.len()).map(|i| format!("$arg{i}")).collect();
+                code.push_str(&format!(
+                    "{} => {abi}::func($store, [{}], [{}], fn ({}) => $this->{}(${}{})),\n",
+                    quote(&func.name),
+                    value_types(&sig.params),
+                    value_types(&sig.results),
+                    args.join(", "),
+                    adapter_name(iface, func),
+                    param_name(&iface.name),
+                    args.iter().map(|a| format!(", {a}")).collect::<String>(),
+                ));
+            }
+            code.push_str("],\n");
+        }
+        if exports.resources.len() > 0 {
+            code.push_str(&resource_intrinsics(exports, names));
+        }
+        code.push_str("];\n");
+        code.push_str(&format!(
+            "$this->abi = {abi}::instantiate($store, $module, $imports, $wasiEnv);\n"
+        ));
+    }
+    code.push_str("}\n\n");
+
+    push_doc_comment(
+        &mut code,
+        &["The underlying WebAssembly instance.".to_string()],
+    );
+    code.push_str("public function instance(): mixed\n{\nreturn $this->abi->instance();\n}\n");
+
+    // Exported functions
+    let mut sizes = SizeAlign::default();
+    sizes.fill(exports);
+    for func in &exports.functions {
+        let param_names: Vec<_> = func.params.iter().map(|(n, _)| param_name(n)).collect();
+        let mut bindgen = FunctionBindgen::new(&sizes, names, param_names);
+        bindgen.callee = quote(&func.name);
+        bindgen.returns_void = func.result == Type::Unit;
+        exports.call(
+            AbiVariant::GuestExport,
+            LiftLower::LowerArgsLiftResults,
+            func,
+            &mut bindgen,
+        );
+
+        let doc = if func.docs.contents.is_some() {
+            doc_lines(&func.docs)
+        } else {
+            vec![format!("Call the \"{}\" function.", func.name)]
+        };
+        let (signature, doc) = signature(exports, func, names, doc);
+        code.push('\n');
+        push_doc_comment(&mut code, &doc);
+        code.push_str(&format!("public {signature}\n{{\n{}}}\n", bindgen.code.src));
+    }
+
+    // Adapters which are called by WebAssembly and forward to the host
+    for iface in &imports {
+        let mut sizes = SizeAlign::default();
+        sizes.fill(iface);
+
+        for func in &iface.functions {
+            let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+            let args: Vec<_> = (0..sig.params.len()).map(|i| format!("arg{i}")).collect();
+            let mut bindgen = FunctionBindgen::new(&sizes, names, args.clone());
+            bindgen.callee = format!("$host->{}", method_ident(&func.name));
+            bindgen.returns_void = sig.results.is_empty();
+            iface.call(
+                AbiVariant::GuestImport,
+                LiftLower::LiftArgsLowerResults,
+                func,
+                &mut bindgen,
+            );
+
+            let params: Vec<_> = std::iter::once(format!("{} $host", class_ident(&iface.name)))
+                .chain(args.iter().map(|a| format!("int|float ${a}")))
+                .collect();
+            let ret = if sig.results.is_empty() {
+                "void"
+            } else {
+                "int|float"
+            };
+            code.push_str(&format!(
+                "\nprivate function {}({}): {ret}\n{{\n{}}}\n",
+                adapter_name(iface, func),
+                params.join(", "),
+                bindgen.code.src
+            ));
+        }
+    }
+
+    code.push_str("}\n");
+    code
+}
+
+/// The intrinsics WebAssembly uses to create, copy, and destroy the resources
+/// it exports, as entries in the constructor's `$imports` array.
+fn resource_intrinsics(iface: &Interface, names: &Names) -> String {
+    let abi = &names.abi;
+    let mut code = String::from("'canonical_abi' => [\n");
+
+    for (_, resource) in iface.resources.iter() {
+        let name = &resource.name;
+        let handles = format!("$this->{}", handles(name));
+        code.push_str(&format!(
+            "'resource_drop_{name}' => {abi}::func($store, [WASM_I32], [], fn (int $index) => {handles}->remove($index)->drop()),
+'resource_clone_{name}' => {abi}::func($store, [WASM_I32], [WASM_I32], fn (int $index) => {handles}->insert({handles}->get($index)->cloneRef())),
+'resource_get_{name}' => {abi}::func($store, [WASM_I32], [WASM_I32], fn (int $index) => {handles}->get($index)->handle()),
+'resource_new_{name}' => {abi}::func($store, [WASM_I32], [WASM_I32], fn (int $handle) => {handles}->insert(new {}($this, $handle, {}))),
+",
+            class_ident(name),
+            destructor(name),
+        ));
+    }
+
+    code.push_str("],\n");
+    code
+}
+
+/// A closure which destroys the WebAssembly side of a resource.
+fn destructor(resource: &str) -> String {
+    format!(
+        "fn (int $handle) => $this->abi->call({}, [WASM_I32], [$handle])",
+        quote(&format!("canonical_abi_drop_{resource}"))
+    )
+}
+
+/// The property holding the slab for a resource's handles.
+fn handles(resource: &str) -> String {
+    format!("{}Handles", resource.to_lower_camel_case())
+}
+
+/// A function's PHP signature, plus its doc-comment with any types PHP's
+/// native type declarations can't express.
+fn signature(
+    iface: &Interface,
+    func: &Function,
+    names: &Names,
+    doc: Vec<String>,
+) -> (String, Vec<String>) {
+    method_signature(
+        iface,
+        &method_ident(&func.name),
+        &func.params,
+        &func.result,
+        names,
+        doc,
+    )
+}
+
+fn method_signature(
+    iface: &Interface,
+    method: &str,
+    func_params: &[(String, Type)],
+    result: &Type,
+    names: &Names,
+    mut doc: Vec<String>,
+) -> (String, Vec<String>) {
+    let mut params = Vec::new();
+    let mut tags = Vec::new();
+
+    for (name, ty) in func_params {
+        let name = param_name(name);
+        let php = php_type(iface, ty, names);
+        let documented = doc_type(iface, ty, names);
+        if documented != php {
+            tags.push(format!("@param {documented} ${name}"));
+        }
+        params.push(format!("{php} ${name}"));
+    }
+
+    let ret = if *result == Type::Unit {
+        "void".to_string()
+    } else {
+        let php = php_type(iface, result, names);
+        let documented = doc_type(iface, result, names);
+        if documented != php {
+            tags.push(format!("@return {documented}"));
+        }
+        php
+    };
+
+    if !tags.is_empty() {
+        if !doc.is_empty() {
+            doc.push(String::new());
+        }
+        doc.extend(tags);
+    }
+
+    let signature = format!("function {method}({}): {ret}", params.join(", "));
+    (signature, doc)
+}
+
+/// The PHP type declaration used for a WAI type.
+fn php_type(iface: &Interface, ty: &Type, names: &Names) -> String {
+    match ty {
+        Type::Unit => "mixed".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::U8
+        | Type::U16
+        | Type::U32
+        | Type::U64
+        | Type::S8
+        | Type::S16
+        | Type::S32
+        | Type::S64 => "int".to_string(),
+        Type::Float32 | Type::Float64 => "float".to_string(),
+        Type::Char | Type::String => "string".to_string(),
+        Type::Handle(id) => class_ident(&iface.resources[*id].name),
+        Type::Id(id) => {
+            let def = &iface.types[*id];
+            match &def.kind {
+                TypeDefKind::Record(_) | TypeDefKind::Variant(_) | TypeDefKind::Union(_) => {
+                    class_ident(def.name.as_deref().expect("records and variants are named"))
+                }
+                TypeDefKind::Enum(_) | TypeDefKind::Flags(_) => "int".to_string(),
+                TypeDefKind::Tuple(_) | TypeDefKind::List(_) => "array".to_string(),
+                TypeDefKind::Option(inner) => match php_type(iface, inner, names) {
+                    inner if inner == "mixed" || inner.starts_with('?') => "mixed".to_string(),
+                    inner => format!("?{inner}"),
+                },
+                TypeDefKind::Expected(_) => names.expected.clone(),
+                TypeDefKind::Type(inner) => php_type(iface, inner, names),
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                    unreachable!("futures and streams are rejected during validation")
+                }
+            }
+        }
+    }
+}
+
+/// The type used in doc-comments, which can describe a list's elements and
+/// a tuple's members.
+fn doc_type(iface: &Interface, ty: &Type, names: &Names) -> String {
+    match ty {
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::List(element) => format!("list<{}>", doc_type(iface, element, names)),
+            TypeDefKind::Tuple(tuple) => {
+                let members: Vec<_> = tuple
+                    .types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| format!("{i}: {}", doc_type(iface, ty, names)))
+                    .collect();
+                format!("array{{{}}}", members.join(", "))
+            }
+            TypeDefKind::Option(inner) => match doc_type(iface, inner, names) {
+                inner if inner.starts_with('?') || inner == "mixed" => "mixed".to_string(),
+                inner if inner.contains('<') || inner.contains('{') => format!("{inner}|null"),
+                inner => format!("?{inner}"),
+            },
+            TypeDefKind::Type(inner) => doc_type(iface, inner, names),
+            _ => php_type(iface, ty, names),
+        },
+        _ => php_type(iface, ty, names),
+    }
+}
+
+fn value_types(types: &[WasmType]) -> String {
+    types
+        .iter()
+        .map(|ty| match ty {
+            WasmType::I32 => "WASM_I32",
+            WasmType::I64 => "WASM_I64",
+            WasmType::F32 => "WASM_F32",
+            WasmType::F64 => "WASM_F64",
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The private method which adapts raw WebAssembly arguments to a call to
+/// the host's implementation of an imported function.
+fn adapter_name(iface: &Interface, func: &Function) -> String {
+    format!("{}_{}", iface.name, func.name).to_lower_camel_case()
+}
+
+/// Turn a WAI name into a PHP class name.
+pub(crate) fn class_ident(name: &str) -> String {
+    let ident = name.to_pascal_case();
+    if RESERVED_CLASS_NAMES.contains(&ident.to_lowercase().as_str()) {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+/// Turn a WAI name into a variable name (without the `$`).
+pub(crate) fn param_name(name: &str) -> String {
+    codegen::avoid_reserved(name.to_lower_camel_case(), &[GLUE_NAMES])
+}
+
+/// Turn a WAI name into a method name.
+///
+/// PHP allows keywords to be used as method names, so we only need to avoid
+/// the methods the generated classes define.
+pub(crate) fn method_ident(name: &str) -> String {
+    let ident = name.to_lower_camel_case();
+    if RESERVED_METHODS.contains(&ident.as_str()) {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+/// Turn the name of a resource's method into a PHP method name, avoiding the
+/// methods inherited from the `Resource` helper.
+fn resource_method_ident(name: &str) -> String {
+    let ident = name.to_lower_camel_case();
+    if RESOURCE_METHODS.contains(&ident.as_str()) || ident == "__construct" {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+fn constant_ident(name: &str) -> String {
+    let ident = name.to_shouty_snake_case();
+    // "class" is the only name a class constant can't have
+    if ident == "CLASS" {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+fn doc_lines(docs: &Docs) -> Vec<String> {
+    match &docs.contents {
+        Some(contents) => contents
+            .trim()
+            .lines()
+            .map(|line| line.trim_end().to_string())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn push_doc_comment(code: &mut String, lines: &[String]) {
+    if lines.is_empty() {
+        return;
+    }
+
+    code.push_str("/**\n");
+    for line in lines {
+        if line.is_empty() {
+            code.push_str("*\n");
+        } else {
+            // Make sure the documentation can't end the comment early
+            code.push_str(&format!("* {}\n", line.replace("*/", "*\\/")));
+        }
+    }
+    code.push_str("*/\n");
+}
+
+/// Quote a string so it can be used as a PHP string literal.
+///
+/// Single-quoted strings are used where possible because they don't support
+/// interpolation, falling back to double quotes when the string contains
+/// characters that need escaping.
+pub(crate) fn quote(s: &str) -> String {
+    if !s.chars().any(|c| c.is_control()) {
+        return format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"));
+    }
+
+    codegen::quote(s, |c| match c {
+        '$' => Some("\\$".to_string()),
+        c if c.is_control() => Some(format!("\\u{{{:x}}}", c as u32)),
+        _ => None,
+    })
+}
+
+/// Indent PHP source code based on its brackets, following PSR-12.
+pub(crate) fn indent(src: &str) -> String {
+    codegen::indent(src, "    ", |line| Layout {
+        closes: line.starts_with('}') || line.starts_with(')') || line.starts_with(']'),
+        opens: line.ends_with('{') || line.ends_with('(') || line.ends_with('['),
+        // Align the asterisks in doc-comments
+        padding: if line.starts_with('*') { " " } else { "" },
+        ..Layout::default()
+    })
+}
+
+/// Generates the PHP statements that pass a function's arguments and results
+/// across the canonical ABI.
+struct FunctionBindgen<'a> {
+    sizes: &'a SizeAlign,
+    names: &'a Names,
+    params: Vec<String>,
+    /// The exported function's name (as a string literal), or the host
+    /// method to invoke.
+    callee: String,
+    /// Should the generated function return nothing?
+    returns_void: bool,
+    code: FunctionCode,
+}
+
+impl<'a> FunctionBindgen<'a> {
+    fn new(sizes: &'a SizeAlign, names: &'a Names, params: Vec<String>) -> Self {
+        FunctionBindgen {
+            sizes,
+            names,
+            params: params.into_iter().map(|p| format!("${p}")).collect(),
+            callee: String::new(),
+            returns_void: false,
+            code: FunctionCode::with_sigil("$"),
+        }
+    }
+
+    /// Assign an expression to a new `$` variable.
+    fn bind(&mut self, prefix: &str, expr: impl AsRef<str>) -> String {
+        let name = self.code.tmp(prefix);
+        self.code.line(format!("{name} = {};", expr.as_ref()));
+        name
+    }
+
+    fn assign(&mut self, vars: &[String], values: &[String]) {
+        for (var, value) in vars.iter().zip(values) {
+            self.code.line(format!("{var} = {value};"));
+        }
+    }
+
+    /// Lower a variant-like value, where `cases` contains the constant for
+    /// each case's tag and whether it carries a value.
+    fn lower_sum(
+        &mut self,
+        name: &str,
+        operand: &str,
+        cases: &[(String, bool)],
+        results: &mut Vec<String>,
+        result_count: usize,
+    ) {
+        let blocks = self.code.pop_blocks(cases.len());
+        let payloads = self.code.pop_payloads(cases.len());
+        let vars: Vec<_> = (0..result_count)
+            .map(|_| self.code.tmp("lowered"))
+            .collect();
+
+        let variant = self.bind("variant", operand);
+        for (i, (((constant, has_value), (block, block_results)), payload)) in
+            cases.iter().zip(blocks).zip(payloads).enumerate()
+        {
+            let keyword = if i == 0 { "if" } else { "} elseif" };
+            self.code
+                .line(format!("{keyword} ({variant}->tag === {constant}) {{"));
+            let uses_payload = uses_ident(&block, &payload)
+                || block_results.iter().any(|r| uses_ident(r, &payload));
+            if *has_value && uses_payload {
+                self.code.line(format!("{payload} = {variant}->value;"));
+            }
+            self.code.src.push_str(&block);
+            self.assign(&vars, &block_results);
+        }
+        self.code.line(format!(
+            "}} else {{\nthrow new \\InvalidArgumentException('Invalid value for {name}');\n}}"
+        ));
+
+        results.extend(vars);
+    }
+
+    /// Lift a variant-like value with an `if`/`elseif` chain comparing its
+    /// discriminant.
+    fn lift_sum(
+        &mut self,
+        what: &str,
+        discriminant: &str,
+        cases: &[Lifted],
+        results: &mut Vec<String>,
+    ) {
+        let blocks = self.code.pop_blocks(cases.len());
+        let var = self.code.tmp("variant");
+        let discriminant = self.bind("discriminant", discriminant);
+
+        for (i, (case, (block, block_results))) in cases.iter().zip(blocks).enumerate() {
+            let keyword = if i == 0 { "if" } else { "} elseif" };
+            self.code
+                .line(format!("{keyword} ({discriminant} === {i}) {{"));
+            self.code.src.push_str(&block);
+            let value = &block_results[0];
+
+            let lifted = match case {
+                Lifted::Nothing => "null".to_string(),
+                Lifted::Case { name, has_value } if *has_value => format!("{name}({value})"),
+                Lifted::Case { name, .. } => format!("{name}()"),
+                Lifted::Some { .. } => value.clone(),
+                Lifted::Ok { .. } => format!("{}::ok({value})", self.names.expected),
+                Lifted::Err { .. } => format!("{}::err({value})", self.names.expected),
+            };
+            self.code.line(format!("{var} = {lifted};"));
+        }
+
+        self.code.line(format!(
+            "}} else {{\nthrow new \\UnexpectedValueException(\"Invalid discriminant for {what}: {{{discriminant}}}\");\n}}"
+        ));
+        results.push(var);
+    }
+
+    fn load(&mut self, method: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        let value = self.bind(
+            "load",
+            format!("$this->abi->{method}({}, {offset})", operands[0]),
+        );
+        results.push(value);
+    }
+
+    fn store(&mut self, method: &str, offset: i32, operands: &[String]) {
+        self.code.line(format!(
+            "$this->abi->{method}({}, {offset}, {});",
+            operands[1], operands[0]
+        ));
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_> {
+    type Operand = String;
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        let abi = self.names.abi.clone();
+
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } => results.push(val.to_string()),
+            Instruction::ConstZero { tys } => {
+                for ty in tys.iter() {
+                    match ty {
+                        WasmType::I32 | WasmType::I64 => results.push("0".to_string()),
+                        WasmType::F32 | WasmType::F64 => results.push("0.0".to_string()),
+                    }
+                }
+            }
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands.iter()) {
+                    let expr = match cast {
+                        Bitcast::None | Bitcast::I32ToI64 => op.clone(),
+                        Bitcast::I64ToI32 => format!("{abi}::i32({op})"),
+                        Bitcast::F32ToI32 | Bitcast::F32ToI64 => format!("{abi}::f32ToI32({op})"),
+                        Bitcast::F64ToI64 => format!("{abi}::f64ToI64({op})"),
+                        Bitcast::I32ToF32 => format!("{abi}::i32ToF32({op})"),
+                        Bitcast::I64ToF64 => format!("{abi}::i64ToF64({op})"),
+                        Bitcast::I64ToF32 => format!("{abi}::i32ToF32({abi}::i32({op}))"),
+                    };
+                    results.push(expr);
+                }
+            }
+
+            Instruction::I32Load { offset } => self.load("loadI32", *offset, operands, results),
+            Instruction::I32Load8U { offset } => self.load("loadU8", *offset, operands, results),
+            Instruction::I32Load8S { offset } => self.load("loadS8", *offset, operands, results),
+            Instruction::I32Load16U { offset } => self.load("loadU16", *offset, operands, results),
+            Instruction::I32Load16S { offset } => self.load("loadS16", *offset, operands, results),
+            Instruction::I64Load { offset } => self.load("loadI64", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("loadF32", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("loadF64", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("storeI32", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("storeI8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("storeI16", *offset, operands),
+            Instruction::I64Store { offset } => self.store("storeI64", *offset, operands),
+            Instruction::F32Store { offset } => self.store("storeF32", *offset, operands),
+            Instruction::F64Store { offset } => self.store("storeF64", *offset, operands),
+
+            Instruction::I32FromChar => results.push(format!("{abi}::ord({})", operands[0])),
+            Instruction::CharFromI32 => results.push(format!("{abi}::chr({})", operands[0])),
+            Instruction::I32FromU32 => results.push(format!("{abi}::i32({})", operands[0])),
+            Instruction::U32FromI32 => results.push(format!("{abi}::u32({})", operands[0])),
+            Instruction::F32FromFloat32 | Instruction::F64FromFloat64 => {
+                results.push(format!("(float) {}", parenthesize(&operands[0])))
+            }
+            // PHP integers are 64-bit and signed, so a u64 keeps its bit
+            // pattern
+            Instruction::I64FromU64
+            | Instruction::U64FromI64
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromS32
+            | Instruction::I64FromS64
+            | Instruction::S8FromI32
+            | Instruction::S16FromI32
+            | Instruction::S32FromI32
+            | Instruction::S64FromI64
+            | Instruction::Float32FromF32
+            | Instruction::Float64FromF64 => results.push(operands[0].clone()),
+            Instruction::U8FromI32 => {
+                results.push(format!("{} & 0xFF", parenthesize(&operands[0])))
+            }
+            Instruction::U16FromI32 => {
+                results.push(format!("{} & 0xFFFF", parenthesize(&operands[0])))
+            }
+            Instruction::BoolFromI32 => {
+                results.push(format!("{} !== 0", parenthesize(&operands[0])))
+            }
+            Instruction::I32FromBool => {
+                results.push(format!("{} ? 1 : 0", parenthesize(&operands[0])))
+            }
+
+            Instruction::UnitLift => results.push("null".to_string()),
+            Instruction::UnitLower => {}
+
+            // Handles for resources the library exports
+            Instruction::I32FromBorrowedHandle { ty } => {
+                let handles = handles(&iface.resources[*ty].name);
+                results.push(format!(
+                    "$this->{handles}->insert({}->cloneRef())",
+                    parenthesize(&operands[0])
+                ));
+            }
+            Instruction::HandleOwnedFromI32 { ty } => {
+                let handles = handles(&iface.resources[*ty].name);
+                let resource = self.bind(
+                    "resource",
+                    format!("$this->{handles}->remove({})", operands[0]),
+                );
+                results.push(resource);
+            }
+            Instruction::I32FromOwnedHandle { .. } | Instruction::HandleBorrowedFromI32 { .. } => {
+                unreachable!("resources in imported interfaces are rejected during validation")
+            }
+            Instruction::ListCanonLower { .. } | Instruction::ListCanonLift { .. } => {
+                unreachable!("lists are never canonical")
+            }
+
+            Instruction::StringLower { .. } => {
+                let ptr = self.code.tmp("ptr");
+                let len = self.code.tmp("len");
+                self.code.line(format!(
+                    "[{ptr}, {len}] = $this->abi->lowerString({});",
+                    operands[0]
+                ));
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::ListLower { element, .. } => {
+                let (body, body_results) = self.code.pop_block();
+                assert!(body_results.is_empty());
+                let size = self.sizes.size(element);
+                let align = self.sizes.align(element);
+
+                let vec = self.bind("vec", &operands[0]);
+                let len = self.bind("len", format!("\\count({vec})"));
+                let ptr = self.bind(
+                    "ptr",
+                    format!("$this->abi->realloc(0, 0, {align}, {len} * {size})"),
+                );
+                let elem = self.code.tmp("e");
+                let i = self.code.tmp("i");
+                let base = self.code.tmp("base");
+
+                self.code.line(format!(
+                    "foreach (\\array_values({vec}) as {i} => {elem}) {{"
+                ));
+                if uses_ident(&body, ITER_BASE) {
+                    self.code.line(format!("{base} = {ptr} + {i} * {size};"));
+                }
+                let body = replace_ident(&replace_ident(&body, ITER_ELEM, &elem), ITER_BASE, &base);
+                self.code.src.push_str(&body);
+                self.code.line("}");
+
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::StringLift { free } => {
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", &operands[1]);
+                let s = self.bind("str", format!("$this->abi->liftString({ptr}, {len})"));
+                if free.is_some() {
+                    self.code
+                        .line(format!("$this->abi->free({ptr}, {len}, 1);"));
+                }
+                results.push(s);
+            }
+            Instruction::ListLift { element, free, .. } => {
+                let (body, body_results) = self.code.pop_block();
+                let size = self.sizes.size(element);
+                let align = self.sizes.align(element);
+
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", format!("{abi}::u32({})", operands[1]));
+                let i = self.code.tmp("i");
+                let base = self.code.tmp("base");
+                let list = self.bind("list", "[]");
+
+                self.code
+                    .line(format!("for ({i} = 0; {i} < {len}; {i}++) {{"));
+                if uses_ident(&body, ITER_BASE) {
+                    self.code.line(format!("{base} = {ptr} + {i} * {size};"));
+                }
+                self.code
+                    .src
+                    .push_str(&replace_ident(&body, ITER_BASE, &base));
+                self.code.line(format!("{list}[] = {};", body_results[0]));
+                self.code.line("}");
+                if free.is_some() {
+                    self.code
+                        .line(format!("$this->abi->free({ptr}, {len} * {size}, {align});"));
+                }
+
+                results.push(list);
+            }
+            Instruction::IterElem { .. } => results.push(ITER_ELEM.to_string()),
+            Instruction::IterBasePointer => results.push(ITER_BASE.to_string()),
+
+            Instruction::RecordLower { record, .. } => {
+                let rec = self.bind("record", &operands[0]);
+                for field in &record.fields {
+                    results.push(format!("{rec}->{}", field.name.to_lower_camel_case()));
+                }
+            }
+            Instruction::RecordLift { name, .. } => {
+                results.push(format!(
+                    "new {}({})",
+                    class_ident(name),
+                    operands.join(", ")
+                ));
+            }
+            Instruction::TupleLower { tuple, .. } => {
+                let t = self.bind("tuple", &operands[0]);
+                for i in 0..tuple.types.len() {
+                    results.push(format!("{t}[{i}]"));
+                }
+            }
+            Instruction::TupleLift { .. } => {
+                results.push(format!("[{}]", operands.join(", ")));
+            }
+
+            Instruction::FlagsLower { flags, .. } => {
+                if flags.repr().count() == 1 {
+                    results.push(format!("{abi}::i32({})", operands[0]));
+                } else {
+                    let f = self.bind("flags", &operands[0]);
+                    results.push(format!("{abi}::i32({f} & 0xFFFFFFFF)"));
+                    results.push(format!("{abi}::i32({f} >> 32)"));
+                }
+            }
+            Instruction::FlagsLift { flags, .. } => {
+                if flags.repr().count() == 1 {
+                    results.push(format!("{abi}::u32({})", operands[0]));
+                } else {
+                    results.push(format!(
+                        "{abi}::u32({}) | ({} << 32)",
+                        operands[0], operands[1]
+                    ));
+                }
+            }
+
+            Instruction::VariantPayloadName => results.push(self.code.payload_name()),
+            Instruction::VariantLower {
+                variant,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let class = class_ident(name);
+                let cases: Vec<_> = variant
+                    .cases
+                    .iter()
+                    .map(|c| {
+                        (
+                            format!("{class}::{}", constant_ident(&c.name)),
+                            c.ty != Type::Unit,
+                        )
+                    })
+                    .collect();
+                self.lower_sum(&class, &operands[0], &cases, results, result_types.len());
+            }
+            Instruction::UnionLower {
+                union,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let class = class_ident(name);
+                let cases: Vec<_> = union
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (format!("{class}::CASE{i}"), c.ty != Type::Unit))
+                    .collect();
+                self.lower_sum(&class, &operands[0], &cases, results, result_types.len());
+            }
+            Instruction::VariantLift { variant, name, .. } => {
+                let class = class_ident(name);
+                let cases: Vec<_> = variant
+                    .cases
+                    .iter()
+                    .map(|c| {
+                        Lifted::case(format!("{class}::{}", c.name.to_lower_camel_case()), &c.ty)
+                    })
+                    .collect();
+                self.lift_sum(name, &operands[0], &cases, results);
+            }
+            Instruction::UnionLift { union, name, .. } => {
+                let class = class_ident(name);
+                let cases: Vec<_> = union
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| Lifted::case(format!("{class}::case{i}"), &c.ty))
+                    .collect();
+                self.lift_sum(name, &operands[0], &cases, results);
+            }
+            Instruction::EnumLower { enum_, name, .. }
+            | Instruction::EnumLift { enum_, name, .. } => {
+                results.push(format!(
+                    "{abi}::enumCase({}, {}, '{}')",
+                    operands[0],
+                    enum_.cases.len(),
+                    class_ident(name)
+                ));
+            }
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let (some, some_results) = self.code.pop_block();
+                let (none, none_results) = self.code.pop_block();
+                let some_payload = self.code.pop_payload();
+                let _none_payload = self.code.pop_payload();
+                let vars: Vec<_> = (0..result_types.len())
+                    .map(|_| self.code.tmp("lowered"))
+                    .collect();
+
+                let option = self.bind("option", &operands[0]);
+                self.code.line(format!("if ({option} === null) {{"));
+                self.code.src.push_str(&none);
+                self.assign(&vars, &none_results);
+                self.code.line("} else {");
+                self.code.line(format!("{some_payload} = {option};"));
+                self.code.src.push_str(&some);
+                self.assign(&vars, &some_results);
+                self.code.line("}");
+
+                results.extend(vars);
+            }
+            Instruction::OptionLift { payload, .. } => {
+                let cases = Lifted::option(payload);
+                self.lift_sum("option", &operands[0], &cases, results);
+            }
+            Instruction::ExpectedLower {
+                results: result_types,
+                ..
+            } => {
+                let expected = &self.names.expected;
+                let cases = [
+                    (format!("{expected}::OK"), true),
+                    (format!("{expected}::ERR"), true),
+                ];
+                self.lower_sum(
+                    "Expected",
+                    &operands[0],
+                    &cases,
+                    results,
+                    result_types.len(),
+                );
+            }
+            Instruction::ExpectedLift { expected, .. } => {
+                let cases = Lifted::expected(expected);
+                self.lift_sum("expected", &operands[0], &cases, results);
+            }
+
+            Instruction::CallWasm { sig, .. } => {
+                let call = format!(
+                    "$this->abi->call({}, [{}], [{}])",
+                    self.callee,
+                    value_types(&sig.params),
+                    operands.join(", ")
+                );
+                match codegen::single_result(&sig.results) {
+                    None => self.code.line(format!("{call};")),
+                    Some(_) => {
+                        let ret = self.bind("ret", format!("{call}[0]"));
+                        results.push(ret);
+                    }
+                }
+            }
+            Instruction::CallInterface { func, .. } => {
+                let call = format!("{}({})", self.callee, operands.join(", "));
+                if func.result == Type::Unit {
+                    self.code.line(format!("{call};"));
+                    results.push("null".to_string());
+                } else {
+                    let ret = self.bind("ret", call);
+                    results.push(ret);
+                }
+            }
+            Instruction::Return { .. } => match codegen::single_result(operands) {
+                Some(value) if !self.returns_void => self.code.line(format!("return {value};")),
+                _ => {}
+            },
+
+            Instruction::Malloc { size, align, .. } => {
+                let ptr = self.bind("ptr", format!("$this->abi->realloc(0, 0, {align}, {size})"));
+                results.push(ptr);
+            }
+            Instruction::Free { size, align, .. } => {
+                self.code.line(format!(
+                    "$this->abi->free({}, {size}, {align});",
+                    operands[0]
+                ));
+            }
+
+            Instruction::CallWasmAsyncImport { .. }
+            | Instruction::CallWasmAsyncExport { .. }
+            | Instruction::ReturnAsyncExport { .. }
+            | Instruction::ReturnAsyncImport { .. } => {
+                unreachable!("async functions are rejected during validation")
+            }
+        }
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, _size: usize, _align: usize) -> String {
+        // Only called when a guest passes a return pointer to an import or
+        // returns one from an export, and we only ever generate host code
+        unreachable!("return pointers are only needed by guest code")
+    }
+
+    fn push_block(&mut self) {
+        self.code.push_block();
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        self.code.finish_block(operands);
+    }
+
+    fn sizes(&self) -> &SizeAlign {
+        self.sizes
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, _element: &Type) -> bool {
+        false
+    }
+}
@@ -0,0 +1,408 @@
+mod library;
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Error;
+use heck::ToLowerCamelCase;
+use once_cell::sync::Lazy;
+
+use self::library::class_ident;
+pub(crate) use self::library::quote;
+use crate::{
+    codegen::{self, Context, Naming, Templates},
+    types::{Abi, BindingsOptions, CommandKind, Metadata, Package},
+    Files, SourceFile,
+};
+
+/// The minimum PHP version supported by the generated code.
+const PHP_VERSION: &str = ">=8.1";
+
+/// Methods the `Bindings` and `Commands` classes use internally, which a
+/// library or command mustn't override.
+const PRIVATE_METHODS: &[&str] = &["__construct", "loadModule", "run"];
+
+static TEMPLATES: Lazy<Templates> = Lazy::new(|| {
+    Templates::new(&[
+        ("Package.php", include_str!("Package.php.j2")),
+        ("Abi.php", include_str!("Abi.php.j2")),
+        ("Resource.php", include_str!("Resource.php.j2")),
+        ("Slab.php", include_str!("Slab.php.j2")),
+        ("Bindings.php", include_str!("Bindings.php.j2")),
+        ("Expected.php", include_str!("Expected.php.j2")),
+        ("Commands.php", include_str!("Commands.php.j2")),
+        ("ExitStatus.php", include_str!("ExitStatus.php.j2")),
+        ("Mounts.php", include_str!("Mounts.php.j2")),
+    ])
+});
+
+/// Generate a Composer package which embeds the package and uses
+/// `wasmer-php` to run it.
+pub fn generate_php(package: &Package, options: &BindingsOptions) -> Result<Files, crate::Error> {
+    crate::validation::check_abis(package.libraries(), "PHP", &[Abi::None, Abi::Wasi])?;
+    crate::validation::check_command_kinds(package.commands(), "PHP", &[CommandKind::Wasi])?;
+    crate::validation::deny_unsupported_interface_features_except_exported_resources(
+        package.libraries(),
+        "PHP",
+    )?;
+    // wasmer-php takes all of a WASI module's imports from the WASI
+    // environment, so there's nowhere to put the host's functions or the
+    // intrinsics for exported resources
+    crate::validation::deny_imports_in_wasi_libraries(package.libraries(), "PHP")?;
+    if options.deny_extra_exports {
+        crate::validation::deny_extra_exports(package.libraries())?;
+    }
+
+    let metadata = package.metadata();
+    let name = composer_name(metadata, options.name.as_deref());
+    // Composer packages conventionally map "vendor/package" to the
+    // "Vendor\Package" namespace
+    let namespace = name
+        .split('/')
+        .map(class_ident)
+        .collect::<Vec<_>>()
+        .join("\\");
+
+    let ctx = Context::for_package(package, &namespace, &NAMING);
+    let src = Path::new("src");
+    let mut files = Files::new();
+
+    files.insert(
+        "composer.json",
+        composer_json(metadata, &name, &namespace).into(),
+    );
+    files.insert(
+        src.join("Package.php"),
+        package_class(package, &ctx).map_err(crate::Error::Generate)?,
+    );
+
+    if !ctx.libraries.is_empty() {
+        files.insert(
+            src.join("Internal").join("Abi.php"),
+            TEMPLATES
+                .render("Abi.php", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+        files.insert(
+            src.join("Bindings.php"),
+            TEMPLATES
+                .render("Bindings.php", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+        if package
+            .libraries()
+            .iter()
+            .any(|lib| lib.exports.parsed.resources.len() > 0)
+        {
+            for class in ["Resource", "Slab"] {
+                let filename = format!("{class}.php");
+                files.insert(
+                    src.join("Internal").join(&filename),
+                    TEMPLATES
+                        .render(&filename, &ctx)
+                        .map_err(crate::Error::Generate)?,
+                );
+            }
+        }
+        for (lib, lib_ctx) in package.libraries().iter().zip(&ctx.libraries) {
+            let dir = src.join("Bindings").join(&lib_ctx.class_name);
+            for class in library::generate(lib, &namespace) {
+                files.insert(
+                    dir.join(format!("{}.php", class.class_name)),
+                    class.src.into(),
+                );
+            }
+        }
+        if package.libraries().iter().any(library::uses_expected) {
+            files.insert(
+                src.join("Expected.php"),
+                TEMPLATES
+                    .render("Expected.php", &ctx)
+                    .map_err(crate::Error::Generate)?,
+            );
+        }
+    }
+
+    if !ctx.commands.is_empty() {
+        files.insert(
+            src.join("Commands.php"),
+            TEMPLATES
+                .render("Commands.php", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+        files.insert(
+            src.join("ExitStatus.php"),
+            TEMPLATES
+                .render("ExitStatus.php", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+    }
+
+    if !ctx.mounts.is_empty() {
+        files.insert(
+            src.join("Mounts.php"),
+            TEMPLATES
+                .render("Mounts.php", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+        files.insert_child_directory(Path::new("volumes"), codegen::volumes(package));
+    }
+
+    files.insert_child_directory("wasm", codegen::wasm_modules(package));
+
+    if let Some(readme) = &metadata.readme {
+        files.insert(codegen::README, readme.as_str().into());
+    }
+
+    Ok(files)
+}
+
+/// Composer requires package names to be in the `vendor/package` form, so
+/// packages without a namespace use their name for both.
+fn composer_name(metadata: &Metadata, name: Option<&str>) -> String {
+    let package_name = &metadata.package_name;
+    let name = name.unwrap_or_else(|| package_name.name());
+
+    if name.contains('/') {
+        return name.to_lowercase();
+    }
+
+    let vendor = package_name.namespace().as_str().unwrap_or(name);
+    format!("{vendor}/{name}").to_lowercase()
+}
+
+/// Turn a library or command name into the method used to access it.
+fn method_name(name: &str) -> String {
+    let ident = name.to_lower_camel_case();
+    if PRIVATE_METHODS.contains(&ident.as_str()) {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+const NAMING: Naming = Naming {
+    quote,
+    library_ident: method_name,
+    command_ident: method_name,
+    class_name: class_ident,
+    param_name: library::param_name,
+    env: env_entries,
+};
+
+/// Format environment variables as `key => value` array entries.
+fn env_entries(env: &BTreeMap<String, String>) -> Vec<String> {
+    env.iter()
+        .map(|(key, value)| format!("{} => {}", quote(key), quote(value)))
+        .collect()
+}
+
+fn composer_json(metadata: &Metadata, name: &str, namespace: &str) -> String {
+    // Composer doesn't allow build metadata in version numbers
+    let mut version = metadata.version.clone();
+    version.build = semver::BuildMetadata::EMPTY;
+
+    let composer = ComposerJson {
+        name,
+        description: metadata.description.as_deref().map(str::trim),
+        kind: "library",
+        version: version.to_string(),
+        license: metadata.license.as_deref(),
+        homepage: metadata.homepage.as_deref(),
+        authors: metadata.authors.iter().map(|a| Author::parse(a)).collect(),
+        require: [
+            ("php", PHP_VERSION),
+            ("ext-wasm", "*"),
+            ("ext-mbstring", "*"),
+        ]
+        .into_iter()
+        .collect(),
+        autoload: Autoload {
+            psr_4: [(format!("{namespace}\\"), "src/")].into_iter().collect(),
+        },
+    };
+
+    let mut json = serde_json::to_string_pretty(&composer).expect("Serializing is infallible");
+    json.push('\n');
+    json
+}
+
+/// The `composer.json` file.
+///
+/// See [the schema](https://getcomposer.org/doc/04-schema.md) for more.
+#[derive(Debug, serde::Serialize)]
+struct ComposerJson<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    homepage: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<Author<'a>>,
+    require: std::collections::BTreeMap<&'a str, &'a str>,
+    autoload: Autoload<'a>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Autoload<'a> {
+    #[serde(rename = "psr-4")]
+    psr_4: std::collections::BTreeMap<String, &'a str>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Author<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<&'a str>,
+}
+
+impl<'a> Author<'a> {
+    /// Parse an author in the `Name <email>` format used by `Cargo.toml` and
+    /// `package.json`.
+    fn parse(author: &'a str) -> Self {
+        match author
+            .trim()
+            .strip_suffix('>')
+            .and_then(|a| a.split_once('<'))
+        {
+            Some((name, email)) => Author {
+                name: name.trim(),
+                email: Some(email.trim()),
+            },
+            None => Author {
+                name: author.trim(),
+                email: None,
+            },
+        }
+    }
+}
+
+fn package_class(package: &Package, ctx: &Context) -> Result<SourceFile, Error> {
+    let metadata = package.metadata();
+    let mut doc = vec![format!(
+        "Bindings to the {} package.",
+        metadata.package_name
+    )];
+
+    if let Some(description) = &metadata.description {
+        doc.push(String::new());
+        doc.extend(
+            description
+                .lines()
+                .map(|line| line.trim_end().replace("*/", "*\\/")),
+        );
+    }
+
+    TEMPLATES.render(
+        "Package.php",
+        minijinja::context! {
+            doc,
+            module => &ctx.module,
+            generator => crate::GENERATOR,
+            version => quote(&metadata.version.to_string()),
+            libraries => !ctx.libraries.is_empty(),
+            commands => !ctx.commands.is_empty(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Library, Module};
+
+    const WASMER_PACK_EXPORTS: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../wasm/wasmer-pack.exports.wai"
+    ));
+
+    #[test]
+    fn exported_resources_are_supported() {
+        let metadata = Metadata::new(
+            "wasmer/wasmer-pack".parse().unwrap(),
+            "1.2.3".parse().unwrap(),
+        );
+        let exports =
+            crate::Interface::from_wit("wasmer-pack.exports.wai", WASMER_PACK_EXPORTS).unwrap();
+        let lib = Library {
+            module: Module {
+                name: "wasmer_pack_wasm.wasm".to_string(),
+                abi: Abi::None,
+                wasm: crate::exports::stub_module(&exports),
+            },
+            exports,
+            imports: Vec::new(),
+        };
+        let pkg = Package::new(metadata, vec![lib], Vec::new());
+
+        let files = generate_php(&pkg, &BindingsOptions::default()).unwrap();
+
+        for helper in ["src/Internal/Resource.php", "src/Internal/Slab.php"] {
+            assert!(files.iter().any(|(path, _)| path == Path::new(helper)));
+        }
+        let package = files["src/Bindings/WasmerPack/Package.php"]
+            .utf8_contents()
+            .unwrap();
+        assert!(package.contains("final class Package extends Resource\n"));
+        let src = files["src/Bindings/WasmerPack/WasmerPack.php"]
+            .utf8_contents()
+            .unwrap();
+        // The "abi" enum shadows the helpers, so they need to be fully qualified
+        assert!(src.contains("private \\Wasmer\\WasmerPack\\Internal\\Abi $abi;"));
+        assert!(src.contains("'resource_drop_package' => "));
+        insta::assert_display_snapshot!(src);
+    }
+
+    #[test]
+    fn imported_resources_are_rejected() {
+        let metadata = Metadata::new("wasmer/host".parse().unwrap(), "1.2.3".parse().unwrap());
+        let exports = crate::Interface::from_wit("host.exports.wai", "").unwrap();
+        let browser = crate::Interface::from_wit(
+            "browser.wai",
+            "resource window { title: func() -> string }",
+        )
+        .unwrap();
+        let lib = Library {
+            module: Module {
+                name: "host.wasm".to_string(),
+                abi: Abi::None,
+                wasm: wat::parse_str("(module)").unwrap(),
+            },
+            exports,
+            imports: vec![browser],
+        };
+        let pkg = Package::new(metadata, vec![lib], Vec::new());
+
+        let err = generate_php(&pkg, &BindingsOptions::default()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The \"host\" library uses resources in imported interfaces, which isn't supported by the PHP bindings"
+        );
+    }
+
+    #[test]
+    fn library_and_command_names_are_valid_methods() {
+        assert_eq!(method_name("second-with-dashes"), "secondWithDashes");
+        assert_eq!(method_name("class"), "class");
+        assert_eq!(method_name("run"), "run_");
+    }
+
+    #[test]
+    fn composer_names_always_have_a_vendor() {
+        let metadata = Metadata::new(
+            "wasmer/Calculator".parse().unwrap(),
+            "1.0.0".parse().unwrap(),
+        );
+        assert_eq!(composer_name(&metadata, None), "wasmer/calculator");
+        assert_eq!(composer_name(&metadata, Some("maths")), "wasmer/maths");
+        assert_eq!(composer_name(&metadata, Some("acme/maths")), "acme/maths");
+
+        let metadata = Metadata::new("calculator".parse().unwrap(), "1.0.0".parse().unwrap());
+        assert_eq!(composer_name(&metadata, None), "calculator/calculator");
+    }
+}
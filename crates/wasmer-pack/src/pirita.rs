@@ -1,87 +1,488 @@
-use std::path::Path;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Context, Error};
+use semver::Version;
 use webc::{
-    compat::Container,
+    compat::{Container, Volume},
     metadata::{
         self,
-        annotations::{Atom, Wapm},
+        annotations::{
+            Atom, Emscripten, FileSystemMapping, FileSystemMappings, VolumeSpecificPath, Wapm,
+            Wasi, Wcgi, EMSCRIPTEN_RUNNER_URI, WASI_RUNNER_URI, WASM4_RUNNER_URI, WCGI_RUNNER_URI,
+        },
     },
 };
 
-use crate::{Abi, Command, Interface, Library, Metadata, Module, Package, PackageName};
+use crate::{
+    Abi, Command, CommandKind, Dependency, Files, Interface, Library, Metadata, Module, Mount,
+    Package, PackageName, Resolver, SourceFile, Warning,
+};
+
+/// Runners which are well-known, but that `wasmer-pack` can't generate
+/// bindings for.
+const UNSUPPORTED_RUNNERS: &[&str] = &[WASM4_RUNNER_URI];
 
-pub(crate) fn load_webc_binary(webc: &Container) -> Result<Package, Error> {
-    let metadata = metadata(webc.manifest())?;
+pub(crate) fn load_webc_binary(
+    webc: &Container,
+    resolver: &dyn Resolver,
+) -> Result<(Package, Vec<Warning>), crate::Error> {
+    let mut warnings = Vec::new();
+
+    let metadata = metadata(webc)
+        .map_err(crate::Error::Load)?
+        .with_dependencies(dependencies(webc, &mut warnings));
     let libraries = libraries(webc)?;
-    let commands = commands(webc)?;
+    let commands = commands(webc, resolver, &mut warnings)?;
+    ignored_package_annotations(webc, &mut warnings);
+    unused_atoms(webc, &mut warnings);
+
+    let mounts = mounts(webc, &mut warnings).map_err(crate::Error::Load)?;
 
-    Ok(Package::new(metadata, libraries, commands))
+    let pkg = Package::try_new(metadata, libraries, commands)?.with_mounts(mounts);
+
+    Ok((pkg, warnings))
 }
 
-fn commands(webc: &Container) -> Result<Vec<Command>, Error> {
+fn commands(
+    webc: &Container,
+    resolver: &dyn Resolver,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<Command>, crate::Error> {
     let mut commands = Vec::new();
 
     for (name, command) in &webc.manifest().commands {
-        if command
-            .runner
-            .starts_with(webc::metadata::annotations::WASI_RUNNER_URI)
-        {
-            let Atom {
-                name: atom_name,
-                dependency,
-                ..
-            } = command
-                .atom()
-                .ok()
-                .flatten()
-                .unwrap_or_else(|| webc::metadata::annotations::Atom::new(name, None));
-
-            if let Some(dependency) = &dependency {
-                anyhow::bail!("The \"{name}\" command uses the \"{atom_name}\" atom from its \"{dependency}\" dependency, but wasmer-pack doesn't support dependency resolution");
+        let Some(kind) = command_kind(&command.runner) else {
+            let runner = command.runner.clone();
+            let command = name.to_string();
+
+            if UNSUPPORTED_RUNNERS
+                .iter()
+                .any(|uri| runner.starts_with(uri))
+            {
+                warnings.push(Warning::UnsupportedRunner { command, runner });
+            } else {
+                warnings.push(Warning::UnknownRunner { command, runner });
             }
 
-            let wasm = webc.get_atom(&atom_name).with_context(|| {
+            continue;
+        };
+
+        let Atom {
+            name: atom_name,
+            dependency,
+            ..
+        } = command_atom(name, command);
+
+        let wasm = match &dependency {
+            Some(dependency) => match dependency_atom(webc, resolver, dependency, &atom_name)? {
+                Ok(wasm) => wasm,
+                Err(reason) => {
+                    warnings.push(Warning::SkippedCommand {
+                        command: name.to_string(),
+                        reason,
+                    });
+                    continue;
+                }
+            },
+            None => webc.get_atom(&atom_name).map(Vec::from),
+        };
+        let wasm = wasm
+            .with_context(|| {
                 format!("Unable to get the \"{atom_name}\" atom for the \"{name}\" command")
-            })?;
-            commands.push(Command {
-                name: name.to_string(),
-                wasm: wasm.into(),
-            });
+            })
+            .map_err(crate::Error::Load)?;
+        ignored_command_annotations(name, command, kind, warnings);
+        let key = defaults_annotation(kind);
+        let RunnerDefaults {
+            main_args,
+            env,
+            exec_name,
+        } = command
+            .annotation(key)
+            .with_context(|| {
+                format!("Unable to parse the \"{name}\" command's \"{key}\" annotation")
+            })
+            .map_err(crate::Error::Load)?
+            .unwrap_or_default();
+        let env = env.iter().map(|var| match var.split_once('=') {
+            Some((key, value)) => (key, value),
+            None => (var.as_str(), ""),
+        });
+
+        let mut cmd = Command::new(name, wasm)
+            .with_kind(kind)
+            .with_main_args(main_args)
+            .with_env(env);
+        if let Some(exec_name) = exec_name {
+            cmd = cmd.with_exec_name(exec_name);
         }
+        commands.push(cmd);
     }
 
     Ok(commands)
 }
 
-fn libraries(webc: &Container) -> Result<Vec<Library>, Error> {
+/// Figure out which kind of [`Command`] a runner corresponds to, if it is
+/// supported.
+fn command_kind(runner: &str) -> Option<CommandKind> {
+    if runner.starts_with(WASI_RUNNER_URI) {
+        Some(CommandKind::Wasi)
+    } else if runner.starts_with(EMSCRIPTEN_RUNNER_URI) {
+        Some(CommandKind::Emscripten)
+    } else if runner.starts_with(WCGI_RUNNER_URI) {
+        Some(CommandKind::Wcgi)
+    } else {
+        None
+    }
+}
+
+/// The annotation containing a command's default arguments and environment
+/// variables.
+///
+/// WCGI commands are configured using the same `wasi` annotation as normal
+/// WASI commands.
+fn defaults_annotation(kind: CommandKind) -> &'static str {
+    match kind {
+        CommandKind::Wasi | CommandKind::Wcgi => Wasi::KEY,
+        CommandKind::Emscripten => Emscripten::KEY,
+    }
+}
+
+/// The parts of a command's `wasi` or `emscripten` annotation that are
+/// applied by the generated bindings.
+///
+/// This is deserialized separately from [`Wasi`] because it doesn't know
+/// about the `exec-name` field yet. Unset fields may also be serialized as
+/// `null` (the [`Emscripten`] annotation does this), so we treat `null` the
+/// same as a missing field.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RunnerDefaults {
+    #[serde(default, deserialize_with = "null_as_default")]
+    main_args: Vec<String>,
+    /// Environment variables in the `KEY=VALUE` format.
+    #[serde(default, deserialize_with = "null_as_default")]
+    env: Vec<String>,
+    #[serde(default)]
+    exec_name: Option<String>,
+}
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + serde::Deserialize<'de>,
+{
+    let value: Option<T> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(value.unwrap_or_default())
+}
+
+/// Load the directories that the package's `fs` annotation says should be
+/// mounted into the WASI filesystem.
+fn mounts(webc: &Container, warnings: &mut Vec<Warning>) -> Result<Vec<Mount>, Error> {
+    let Some(mappings) = webc
+        .manifest()
+        .package_annotation::<FileSystemMappings>(FileSystemMappings::KEY)
+        .context("Unable to parse the filesystem mappings")?
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut mounts = Vec::new();
+
+    for mapping in mappings {
+        let FileSystemMapping {
+            from,
+            volume_name,
+            original_path,
+            mount_path,
+        } = mapping;
+
+        if let Some(dependency) = from {
+            warnings.push(Warning::SkippedMount {
+                mount_path,
+                reason: format!(
+                    "mounting volumes from the \"{dependency}\" dependency isn't supported"
+                ),
+            });
+            continue;
+        }
+
+        let volume = webc
+            .get_volume(&volume_name)
+            .with_context(|| format!("The container doesn't have a \"{volume_name}\" volume"))?;
+        let files = read_volume_dir(&volume, &original_path).with_context(|| {
+            format!("Unable to read \"{original_path}\" from the \"{volume_name}\" volume")
+        })?;
+
+        mounts.push(Mount::new(mount_path, files));
+    }
+
+    Ok(mounts)
+}
+
+fn read_volume_dir(volume: &Volume, dir: &str) -> Result<Files, Error> {
+    let entries = volume
+        .read_dir(dir)
+        .with_context(|| format!("\"{dir}\" isn't a directory"))?;
+    let mut files = Files::new();
+
+    for (name, metadata) in entries {
+        let path = format!("{}/{name}", dir.trim_end_matches('/'));
+
+        if metadata.is_dir() {
+            files.insert_child_directory(name.as_ref(), read_volume_dir(volume, &path)?);
+        } else {
+            let contents = volume
+                .read_file(path.as_str())
+                .with_context(|| format!("Unable to read \"{path}\""))?;
+            files.insert(name.as_ref(), SourceFile::from(contents.as_ref()));
+        }
+    }
+
+    Ok(files)
+}
+
+fn dependencies(webc: &Container, warnings: &mut Vec<Warning>) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+
+    for (alias, specifier) in &webc.manifest().use_map {
+        match Dependency::from_use_map(alias, specifier) {
+            Ok(dependency) => dependencies.push(dependency),
+            Err(e) => warnings.push(Warning::SkippedDependency {
+                dependency: alias.clone(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    // The use map's order depends on how the WEBC file was built, so sort
+    // dependencies to keep the generated packages reproducible
+    dependencies.sort_by(|a, b| a.alias.cmp(&b.alias));
+
+    dependencies
+}
+
+/// Look up an atom from one of the package's dependencies.
+///
+/// The inner `Err` explains why the command should be skipped.
+fn dependency_atom(
+    webc: &Container,
+    resolver: &dyn Resolver,
+    alias: &str,
+    atom_name: &str,
+) -> Result<Result<Option<Vec<u8>>, String>, crate::Error> {
+    let Some(specifier) = webc.manifest().use_map.get(alias) else {
+        return Ok(Err(format!(
+            "the \"{alias}\" dependency isn't declared by the package"
+        )));
+    };
+
+    let dependency = match Dependency::from_use_map(alias, specifier) {
+        Ok(d) => d,
+        Err(e) => return Ok(Err(format!("unable to resolve \"{alias}\": {e}"))),
+    };
+
+    let Some(dep) = resolver.resolve(&dependency)? else {
+        return Ok(Err(format!(
+            "unable to find a copy of {}@{} for the \"{atom_name}\" atom",
+            dependency.package_name, dependency.version,
+        )));
+    };
+
+    Ok(Ok(dep.get_atom(atom_name).map(Vec::from)))
+}
+
+/// Figure out which atom a command uses, falling back to an atom with the
+/// same name as the command.
+fn command_atom(name: &str, command: &metadata::Command) -> Atom {
+    command
+        .atom()
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| Atom::new(name, None))
+}
+
+fn ignored_command_annotations(
+    name: &str,
+    command: &metadata::Command,
+    kind: CommandKind,
+    warnings: &mut Vec<Warning>,
+) {
+    let ignored = |annotation: String| Warning::IgnoredAnnotation {
+        command: Some(name.to_string()),
+        annotation,
+    };
+    let defaults_key = defaults_annotation(kind);
+
+    for key in command.annotations.keys() {
+        let is_wcgi_key = kind == CommandKind::Wcgi && key == Wcgi::KEY;
+        if key != Atom::KEY && key != defaults_key && !is_wcgi_key {
+            warnings.push(ignored(key.clone()));
+        }
+    }
+
+    if kind == CommandKind::Wcgi {
+        // The generated servers always follow the CGI convention, so there
+        // is no way to honour a different dialect
+        if let Ok(Some(Wcgi {
+            dialect: Some(_), ..
+        })) = command.annotation::<Wcgi>(Wcgi::KEY)
+        {
+            warnings.push(ignored(format!("{}.dialect", Wcgi::KEY)));
+        }
+    }
+
+    let (package, mount_atom_in_volume) = match kind {
+        CommandKind::Wasi | CommandKind::Wcgi => match command.wasi() {
+            Ok(Some(wasi)) => (wasi.package, wasi.mount_atom_in_volume),
+            _ => return,
+        },
+        CommandKind::Emscripten => match command.annotation::<Emscripten>(Emscripten::KEY) {
+            Ok(Some(emscripten)) => (emscripten.package, emscripten.mount_atom_in_volume),
+            _ => return,
+        },
+    };
+    let fields = [
+        ("package", package.is_some()),
+        ("mount-atom-in-volume", mount_atom_in_volume.is_some()),
+    ];
+
+    for (field, is_set) in fields {
+        if is_set {
+            warnings.push(ignored(format!("{defaults_key}.{field}")));
+        }
+    }
+}
+
+fn ignored_package_annotations(webc: &Container, warnings: &mut Vec<Warning>) {
+    for key in webc.manifest().package.keys() {
+        if key != Wapm::KEY && key != FileSystemMappings::KEY {
+            warnings.push(Warning::IgnoredAnnotation {
+                command: None,
+                annotation: key.clone(),
+            });
+        }
+    }
+}
+
+fn unused_atoms(webc: &Container, warnings: &mut Vec<Warning>) {
+    let manifest = webc.manifest();
+
+    let command_atoms = manifest
+        .commands
+        .iter()
+        .map(|(name, command)| command_atom(name, command))
+        .filter(|atom| atom.dependency.is_none())
+        .map(|atom| atom.name);
+    let library_atoms = manifest
+        .bindings
+        .iter()
+        .filter_map(|b| b.get_bindings())
+        .map(|b| b.module().trim_start_matches("atoms://").to_string());
+    let used: BTreeSet<String> = command_atoms.chain(library_atoms).collect();
+
+    for atom in webc.atoms().into_keys() {
+        if !used.contains(&atom) {
+            warnings.push(Warning::UnusedAtom { atom });
+        }
+    }
+}
+
+fn libraries(webc: &Container) -> Result<Vec<Library>, crate::Error> {
     let metadata::Manifest { bindings, .. } = webc.manifest();
     let libraries = bindings
         .iter()
-        .map(|b| load_library(webc, b))
+        .map(|b| {
+            load_library(webc, b)
+                .map_err(|e| e.context(format!("Unable to load the \"{}\" bindings", b.name)))
+        })
         .collect::<Result<Vec<_>, _>>()?;
 
     Ok(libraries)
 }
 
-fn metadata(manifest: &metadata::Manifest) -> Result<Metadata, Error> {
-    let Wapm { name, version, .. } = manifest
+/// Get the name and version of the package in a WEBC file.
+pub(crate) fn package_id(webc: &Container) -> Result<(PackageName, Version), Error> {
+    let Wapm { name, version, .. } = webc
+        .manifest()
         .wapm()?
         .context("Unable to find the wapm metadata")?;
     let package_name = PackageName::parse(&name).context("Unable to parse the package name")?;
-    Ok(Metadata::new(package_name, version))
+    let version = version
+        .parse()
+        .with_context(|| format!("\"{version}\" is not a valid semver version number"))?;
+
+    Ok((package_name, version))
+}
+
+fn metadata(webc: &Container) -> Result<Metadata, Error> {
+    let manifest = webc.manifest();
+    let Wapm {
+        description,
+        license,
+        readme,
+        repository,
+        homepage,
+        ..
+    } = manifest
+        .wapm()?
+        .context("Unable to find the wapm metadata")?;
+    let (package_name, version) = package_id(webc)?;
+
+    let mut metadata = Metadata::new(package_name, version);
+
+    if !description.is_empty() {
+        metadata = metadata.with_description(description);
+    }
+    if let Some(license) = license {
+        metadata = metadata.with_license(license);
+    }
+    if let Some(homepage) = homepage {
+        metadata = metadata.with_homepage(homepage);
+    }
+    if let Some(repository) = repository {
+        metadata = metadata.with_repository(repository);
+    }
+    if let Some(VolumeSpecificPath { volume, path }) = readme {
+        let readme =
+            get_file_from_volume(webc, &volume, &path).context("Unable to load the README")?;
+        metadata = metadata.with_readme(String::from_utf8_lossy(&readme));
+    }
+
+    // Note: The Wapm annotation doesn't have a dedicated field for authors,
+    // but older packages may still include them.
+    let authors = manifest
+        .package_annotation::<Authors>(Wapm::KEY)?
+        .map(|a| a.authors)
+        .unwrap_or_default();
+    if !authors.is_empty() {
+        metadata = metadata.with_authors(authors);
+    }
+
+    Ok(metadata)
 }
 
-fn load_library(webc: &Container, bindings: &metadata::Binding) -> Result<Library, Error> {
+#[derive(Debug, Default, serde::Deserialize)]
+struct Authors {
+    #[serde(default)]
+    authors: Vec<String>,
+}
+
+fn load_library(webc: &Container, bindings: &metadata::Binding) -> Result<Library, crate::Error> {
     let bindings = bindings
         .get_bindings()
-        .context("Unable to read the bindings metadata")?;
+        .context("Unable to read the bindings metadata")
+        .map_err(crate::Error::Load)?;
 
     let exports_path = bindings
         .exports()
-        .context("The library doesn't have any exports")?;
-    let exports =
-        load_interface(webc, exports_path).context("Unable to load the exports interface")?;
+        .context("The library doesn't have any exports")
+        .map_err(crate::Error::Load)?;
+    let exports = load_interface(webc, exports_path)
+        .map_err(|e| e.context("Unable to load the exports interface"))?;
 
     let imports_paths = match &bindings {
         metadata::BindingsExtended::Wit(_) => &[],
@@ -90,19 +491,21 @@ fn load_library(webc: &Container, bindings: &metadata::Binding) -> Result<Librar
     let imports = imports_paths
         .iter()
         .map(|path| load_interface(webc, path))
-        .collect::<Result<Vec<_>, Error>>()?;
+        .collect::<Result<Vec<_>, _>>()?;
 
     let module_name = bindings.module().trim_start_matches("atoms://");
     let module = webc
         .get_atom(module_name)
-        .with_context(|| format!("Unable to get the \"{}\" atom", bindings.module()))?;
+        .with_context(|| format!("Unable to get the \"{}\" atom", bindings.module()))
+        .map_err(crate::Error::Load)?;
     let module = Module {
         name: Path::new(module_name)
             .file_stem()
             .and_then(|s| s.to_str())
-            .context("Unable to determine the module's name")?
+            .context("Unable to determine the module's name")
+            .map_err(crate::Error::Load)?
             .to_string(),
-        abi: wasm_abi(&module),
+        abi: Abi::detect(&module),
         wasm: module.to_vec(),
     };
 
@@ -113,11 +516,53 @@ fn load_library(webc: &Container, bindings: &metadata::Binding) -> Result<Librar
     })
 }
 
-fn load_interface(webc: &Container, exports_path: &str) -> Result<Interface, Error> {
-    let (volume, exports_path) = exports_path.split_once("://").unwrap();
-    let exports = get_file_from_volume(webc, volume, exports_path)?;
-    let exports = std::str::from_utf8(&exports).context("The WIT file should be a UTF-8 string")?;
-    Interface::from_wit(exports_path, exports).context("Unable to parse the WIT file")
+fn load_interface(webc: &Container, exports_path: &str) -> Result<Interface, crate::Error> {
+    let (volume, exports_path) = exports_path
+        .split_once("://")
+        .with_context(|| format!("\"{exports_path}\" should be in the form \"volume://path\""))
+        .map_err(crate::Error::Load)?;
+    let exports = get_file_from_volume(webc, volume, exports_path).map_err(crate::Error::Load)?;
+    let exports = std::str::from_utf8(&exports)
+        .context("The WIT file should be a UTF-8 string")
+        .map_err(crate::Error::Parse)?;
+
+    // Any files pulled in with a "use" statement should be next to the
+    // original file in the same volume
+    let parent = Path::new(exports_path)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+
+    Interface::parse_with(exports_path, exports, |dependency| {
+        load_dependency(webc, volume, parent, dependency)
+    })
+    .with_context(|| format!("Unable to parse \"{exports_path}\""))
+    .map_err(crate::Error::Parse)
+}
+
+/// Read a file referenced by a `use` statement from a volume, using the same
+/// lookup rules as [`wai_parser::Interface::parse_file()`].
+fn load_dependency(
+    webc: &Container,
+    volume: &str,
+    parent: &Path,
+    dependency: &str,
+) -> Result<(PathBuf, String), Error> {
+    let wai = parent.join(dependency).with_extension("wai");
+    let wai_md = wai.with_extension("wai.md");
+
+    for path in [wai, wai_md] {
+        let Some(filename) = path.to_str() else {
+            continue;
+        };
+
+        if let Ok(contents) = get_file_from_volume(webc, volume, filename) {
+            let contents = String::from_utf8(contents.to_vec())
+                .with_context(|| format!("\"{filename}\" should be a UTF-8 string"))?;
+            return Ok((path, contents));
+        }
+    }
+
+    anyhow::bail!("Unable to find \"{dependency}\" in the \"{volume}\" volume")
 }
 
 fn get_file_from_volume(
@@ -134,25 +579,392 @@ fn get_file_from_volume(
     })
 }
 
-/// Try to automatically detec the ABI for a WebAssembly module based in its
-/// imports.
-fn wasm_abi(module: &[u8]) -> Abi {
-    let wasi_namespaces = &["wasi_unstable", "wasi_snapshot_preview1"];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resolver::NoDependencies, Package, WebcDirectory};
+    use webc::v2::{
+        write::{DirEntry, Directory, FileEntry, Writer},
+        SignatureAlgorithm,
+    };
 
-    let imported_modules = wasmparser::Parser::new(0)
-        .parse_all(module)
-        .filter_map(|p| match p {
-            Ok(wasmparser::Payload::ImportSection(imports)) => Some(imports),
-            _ => None,
-        })
-        .flat_map(|s| s.into_iter().filter_map(|result| result.ok()))
-        .map(|import| import.module);
+    #[test]
+    fn resolve_use_statements_inside_the_volume() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let wasmer_toml = r#"
+            [package]
+            name = "wasmer/calc"
+            version = "0.1.0"
+            description = "A calculator"
+
+            [[module]]
+            name = "calc"
+            source = "calc.wasm"
+            abi = "none"
+            bindings = { wai-version = "0.2.0", exports = "wai/calc.exports.wai" }
+        "#;
+        let exports = "use { number } from types\nadd: func(a: number, b: number) -> number";
+        std::fs::write(dir.join("wasmer.toml"), wasmer_toml).unwrap();
+        std::fs::create_dir(dir.join("wai")).unwrap();
+        std::fs::write(dir.join("wai").join("calc.exports.wai"), exports).unwrap();
+        std::fs::write(dir.join("wai").join("types.wai"), "type number = u32").unwrap();
+        let calc = Interface::from_path(dir.join("wai").join("calc.exports.wai")).unwrap();
+        std::fs::write(dir.join("calc.wasm"), crate::exports::stub_module(&calc)).unwrap();
+        let pkg = webc::wasmer_package::Package::from_manifest(dir.join("wasmer.toml")).unwrap();
+        // wasmer-toml only copies the exports file into the metadata volume,
+        // so write the container ourselves with the whole "wai/" directory
+        // and nothing in the manifest mentioning "types.wai"
+        let atoms = pkg
+            .atoms()
+            .iter()
+            .map(|(name, wasm)| (name.parse().unwrap(), FileEntry::Borrowed(wasm)))
+            .collect();
+        let wai = Directory::from_path(dir.join("wai")).unwrap();
+        let metadata = Directory {
+            children: [("wai".parse().unwrap(), DirEntry::Dir(wai))].into(),
+        };
+        let serialized = Writer::default()
+            .write_manifest(pkg.manifest())
+            .unwrap()
+            .write_atoms(atoms)
+            .unwrap()
+            .with_volume("metadata", metadata)
+            .unwrap()
+            .finish(SignatureAlgorithm::None)
+            .unwrap();
+        let webc = Container::from_bytes(serialized).unwrap();
+
+        let (pkg, _) = load_webc_binary(&webc, &NoDependencies).unwrap();
+
+        let exports = &pkg.libraries()[0].exports;
+        assert_eq!(exports.name(), "calc");
+        assert_eq!(
+            exports.functions()[0].to_string(),
+            "add: func(a: number, b: number) -> number"
+        );
+    }
+
+    #[test]
+    fn warn_about_skipped_content() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let wasmer_toml = r#"
+            [package]
+            name = "wasmer/tools"
+            version = "0.1.0"
+            description = "Some tools"
 
-    for imported_module in imported_modules {
-        if wasi_namespaces.contains(&imported_module) {
-            return Abi::Wasi;
+            [[module]]
+            name = "hello"
+            source = "empty.wasm"
+            abi = "wasi"
+
+            [[module]]
+            name = "server"
+            source = "empty.wasm"
+            abi = "wasi"
+
+            [[module]]
+            name = "unused"
+            source = "empty.wasm"
+            abi = "none"
+
+            [[command]]
+            name = "hello"
+            module = "hello"
+            runner = "wasi"
+            annotations = { wasi = { main-args = ["--verbose"], env = ["RUST_LOG=info", "NO_COLOR"] }, custom = 42 }
+
+            [[command]]
+            name = "play"
+            module = "server"
+            runner = "wasm4"
+
+            [[command]]
+            name = "custom"
+            module = "server"
+            runner = "https://example.com/runner"
+        "#;
+        std::fs::write(dir.join("wasmer.toml"), wasmer_toml).unwrap();
+        std::fs::write(dir.join("empty.wasm"), wat::parse_str("(module)").unwrap()).unwrap();
+        let pkg = webc::wasmer_package::Package::from_manifest(dir.join("wasmer.toml")).unwrap();
+        let webc = Container::from(pkg);
+
+        let (pkg, warnings) = load_webc_binary(&webc, &NoDependencies).unwrap();
+
+        assert_eq!(pkg.commands().len(), 1);
+        let hello = &pkg.commands()[0];
+        assert_eq!(hello.main_args, ["--verbose"]);
+        assert_eq!(hello.env["RUST_LOG"], "info");
+        assert_eq!(hello.env["NO_COLOR"], "");
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::IgnoredAnnotation {
+                    command: Some("hello".to_string()),
+                    annotation: "custom".to_string(),
+                },
+                Warning::UnsupportedRunner {
+                    command: "play".to_string(),
+                    runner: WASM4_RUNNER_URI.to_string(),
+                },
+                Warning::UnknownRunner {
+                    command: "custom".to_string(),
+                    runner: "https://example.com/runner".to_string(),
+                },
+                Warning::UnusedAtom {
+                    atom: "unused".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn load_emscripten_commands() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let wasmer_toml = r#"
+            [package]
+            name = "wasmer/lua"
+            version = "0.1.0"
+            description = "Lua, compiled with Emscripten"
+
+            [[module]]
+            name = "lua"
+            source = "lua.wasm"
+            abi = "emscripten"
+
+            [[command]]
+            name = "lua"
+            module = "lua"
+            runner = "emscripten"
+            annotations = { emscripten = { main-args = ["-i"], package = "wasmer/lua" } }
+        "#;
+        std::fs::write(dir.join("wasmer.toml"), wasmer_toml).unwrap();
+        std::fs::write(dir.join("lua.wasm"), wat::parse_str("(module)").unwrap()).unwrap();
+        let pkg = webc::wasmer_package::Package::from_manifest(dir.join("wasmer.toml")).unwrap();
+        let webc = Container::from(pkg);
+
+        let (pkg, warnings) = load_webc_binary(&webc, &NoDependencies).unwrap();
+
+        assert_eq!(pkg.commands().len(), 1);
+        let lua = &pkg.commands()[0];
+        assert_eq!(lua.kind, CommandKind::Emscripten);
+        assert_eq!(lua.main_args, ["-i"]);
+        assert_eq!(
+            warnings,
+            vec![Warning::IgnoredAnnotation {
+                command: Some("lua".to_string()),
+                annotation: "emscripten.package".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn load_wcgi_commands() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let wasmer_toml = r#"
+            [package]
+            name = "wasmer/hello-server"
+            version = "0.1.0"
+            description = "A CGI script"
+
+            [[module]]
+            name = "server"
+            source = "server.wasm"
+            abi = "wasi"
+
+            [[command]]
+            name = "serve"
+            module = "server"
+            runner = "wcgi"
+            annotations = { wasi = { env = ["GREETING=hello"] }, wcgi = { dialect = "rfc-3875" } }
+        "#;
+        std::fs::write(dir.join("wasmer.toml"), wasmer_toml).unwrap();
+        std::fs::write(dir.join("server.wasm"), wat::parse_str("(module)").unwrap()).unwrap();
+        let pkg = webc::wasmer_package::Package::from_manifest(dir.join("wasmer.toml")).unwrap();
+        let webc = Container::from(pkg);
+
+        let (pkg, warnings) = load_webc_binary(&webc, &NoDependencies).unwrap();
+
+        assert_eq!(pkg.commands().len(), 1);
+        let serve = &pkg.commands()[0];
+        assert_eq!(serve.kind, CommandKind::Wcgi);
+        assert_eq!(serve.env["GREETING"], "hello");
+        assert_eq!(
+            warnings,
+            vec![Warning::IgnoredAnnotation {
+                command: Some("serve".to_string()),
+                annotation: "wcgi.dialect".to_string(),
+            }]
+        );
+    }
+
+    /// Create a serialized `wasmer/coreutils` package with an `ls` atom.
+    fn coreutils(version: &str, wat: &str) -> Vec<u8> {
+        let temp = tempfile::tempdir().unwrap();
+        let wasmer_toml = format!(
+            r#"
+            [package]
+            name = "wasmer/coreutils"
+            version = "{version}"
+            description = "Core utilities"
+
+            [[module]]
+            name = "ls"
+            source = "ls.wasm"
+            abi = "wasi"
+            "#
+        );
+        std::fs::write(temp.path().join("wasmer.toml"), wasmer_toml).unwrap();
+        std::fs::write(temp.path().join("ls.wasm"), wat::parse_str(wat).unwrap()).unwrap();
+        let pkg =
+            webc::wasmer_package::Package::from_manifest(temp.path().join("wasmer.toml")).unwrap();
+
+        pkg.serialize().unwrap().to_vec()
+    }
+
+    #[test]
+    fn resolve_command_atoms_from_dependencies() {
+        let deps = tempfile::tempdir().unwrap();
+        for (filename, version, wat) in [
+            ("old.webc", "1.0.0", "(module)"),
+            (
+                "latest.webc",
+                "1.1.0",
+                r#"(module (func (export "latest")))"#,
+            ),
+            ("incompatible.webc", "2.0.0", "(module)"),
+        ] {
+            std::fs::write(deps.path().join(filename), coreutils(version, wat)).unwrap();
         }
+        std::fs::write(deps.path().join("README.md"), "Not a WEBC file").unwrap();
+        let temp = tempfile::tempdir().unwrap();
+        let wasmer_toml = r#"
+            [package]
+            name = "wasmer/tools"
+            version = "0.1.0"
+            description = "Some tools"
+
+            [dependencies]
+            "wasmer/coreutils" = "^1"
+            "wasmer/missing" = "1"
+
+            [[command]]
+            name = "ls"
+            module = "wasmer/coreutils:ls"
+            runner = "wasi"
+
+            [[command]]
+            name = "missing"
+            module = "wasmer/missing:missing"
+            runner = "wasi"
+        "#;
+        std::fs::write(temp.path().join("wasmer.toml"), wasmer_toml).unwrap();
+        let pkg =
+            webc::wasmer_package::Package::from_manifest(temp.path().join("wasmer.toml")).unwrap();
+        let webc = Container::from(pkg);
+
+        let (pkg, warnings) = load_webc_binary(&webc, &WebcDirectory::new(deps.path())).unwrap();
+
+        let dependencies: Vec<_> = pkg
+            .metadata()
+            .dependencies
+            .iter()
+            .map(|dep| format!("{}@{}", dep.package_name, dep.version))
+            .collect();
+        assert_eq!(dependencies, ["wasmer/coreutils@^1", "wasmer/missing@^1"]);
+        assert_eq!(pkg.commands().len(), 1);
+        let ls = &pkg.commands()[0];
+        assert_eq!(ls.name, "ls");
+        assert_eq!(
+            ls.wasm,
+            wat::parse_str(r#"(module (func (export "latest")))"#).unwrap()
+        );
+        assert_eq!(
+            warnings,
+            vec![Warning::SkippedCommand {
+                command: "missing".to_string(),
+                reason: "unable to find a copy of wasmer/missing@^1 for the \"missing\" atom"
+                    .to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn resolve_packages_from_a_cache_directory() {
+        let cache = tempfile::tempdir().unwrap();
+        let dir = cache.path().join("wasmer").join("coreutils");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("1.0.0.webc"), coreutils("1.0.0", "(module)")).unwrap();
+        std::fs::write(
+            cache.path().join("latest.bin"),
+            coreutils("1.1.0", "(module)"),
+        )
+        .unwrap();
+        let resolver = WebcDirectory::new(cache.path());
+
+        let (pkg, _) =
+            Package::resolve(&"wasmer/coreutils@=1.0.0".parse().unwrap(), &resolver).unwrap();
+        assert_eq!(pkg.metadata().version.to_string(), "1.0.0");
+        let (pkg, _) = Package::resolve(&"wasmer/coreutils".parse().unwrap(), &resolver).unwrap();
+        assert_eq!(pkg.metadata().version.to_string(), "1.1.0");
+
+        let err = Package::resolve(&"wasmer/coreutils@2".parse().unwrap(), &resolver).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unable to find a copy of wasmer/coreutils@^2"
+        );
+
+        let missing = WebcDirectory::new(cache.path().join("missing"));
+        let err = Package::resolve(&"wasmer/coreutils".parse().unwrap(), &missing).unwrap_err();
+        assert!(err.to_string().contains("doesn't exist"));
     }
 
-    Abi::None
+    #[test]
+    fn load_mounted_directories() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let wasmer_toml = r#"
+            [package]
+            name = "wasmer/python"
+            version = "0.1.0"
+            description = "Python"
+
+            [[module]]
+            name = "python"
+            source = "python.wasm"
+            abi = "wasi"
+
+            [[command]]
+            name = "python"
+            module = "python"
+            runner = "wasi"
+
+            [fs]
+            "/lib" = "lib"
+        "#;
+        std::fs::write(dir.join("wasmer.toml"), wasmer_toml).unwrap();
+        std::fs::write(dir.join("python.wasm"), wat::parse_str("(module)").unwrap()).unwrap();
+        std::fs::create_dir_all(dir.join("lib").join("json")).unwrap();
+        std::fs::write(dir.join("lib").join("os.py"), "import sys").unwrap();
+        std::fs::write(dir.join("lib").join("json").join("__init__.py"), "").unwrap();
+        let pkg = webc::wasmer_package::Package::from_manifest(dir.join("wasmer.toml")).unwrap();
+        let webc = Container::from(pkg);
+
+        let (pkg, warnings) = load_webc_binary(&webc, &NoDependencies).unwrap();
+
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let mounts = pkg.mounts();
+        assert_eq!(mounts.len(), 1);
+        assert_eq!(mounts[0].guest_path, "/lib");
+        let files: Vec<_> = mounts[0].files.iter().map(|(path, _)| path).collect();
+        assert_eq!(files, [Path::new("json/__init__.py"), Path::new("os.py")]);
+        assert_eq!(
+            mounts[0].files["os.py"].utf8_contents().unwrap(),
+            "import sys"
+        );
+    }
 }
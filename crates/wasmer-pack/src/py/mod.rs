@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
 
 use anyhow::Error;
 use heck::{ToPascalCase, ToSnakeCase};
@@ -8,10 +8,13 @@ use wai_bindgen_gen_core::Generator;
 use wai_bindgen_gen_wasmer_py::WasmerPy;
 
 use crate::{
-    types::{BindingsOptions, Interface, Package},
-    Files, Metadata, Module, SourceFile,
+    types::{Abi, BindingsOptions, CommandKind, Interface, Package},
+    Files, Metadata, SourceFile,
 };
 
+/// The name used when saving the package's README.
+const README: &str = "README.md";
+
 static TEMPLATES: Lazy<Environment> = Lazy::new(|| {
     let mut env = Environment::new();
     env.add_template(
@@ -26,6 +29,17 @@ static TEMPLATES: Lazy<Environment> = Lazy::new(|| {
     .unwrap();
     env.add_template("MANIFEST.in", include_str!("MANIFEST.in.j2"))
         .unwrap();
+    env.add_template("_mounts.py", include_str!("_mounts.py.j2"))
+        .unwrap();
+    env.add_template("_emscripten.py", include_str!("_emscripten.py.j2"))
+        .unwrap();
+    env.add_template("_wcgi.py", include_str!("_wcgi.py.j2"))
+        .unwrap();
+    env.add_template(
+        "servers.__init__.py",
+        include_str!("servers.__init__.py.j2"),
+    )
+    .unwrap();
     env.add_template(
         "commands.__init__.py",
         include_str!("commands.__init__.py.j2"),
@@ -36,7 +50,16 @@ static TEMPLATES: Lazy<Environment> = Lazy::new(|| {
 });
 
 /// Generate Python bindings.
-pub fn generate_python(package: &Package, options: &BindingsOptions) -> Result<Files, Error> {
+pub fn generate_python(
+    package: &Package,
+    options: &BindingsOptions,
+) -> Result<Files, crate::Error> {
+    crate::validation::check_abis(package.libraries(), "Python", &[Abi::None, Abi::Wasi])?;
+    crate::validation::check_emscripten_commands(package.commands(), "Python")?;
+    if options.deny_extra_exports {
+        crate::validation::deny_extra_exports(package.libraries())?;
+    }
+
     let metadata = package.metadata();
 
     // make sure the name is in snake-case
@@ -53,20 +76,46 @@ pub fn generate_python(package: &Package, options: &BindingsOptions) -> Result<F
     if !ctx.libraries.is_empty() {
         files.insert_child_directory(
             Path::new(&package_name).join("bindings"),
-            library_bindings(&ctx)?,
+            library_bindings(&ctx).map_err(crate::Error::Generate)?,
         );
     }
 
     if !ctx.commands.is_empty() {
         files.insert_child_directory(
             Path::new(&package_name).join("commands"),
-            command_bindings(&ctx)?,
+            command_bindings(&ctx).map_err(crate::Error::Generate)?,
+        );
+    }
+
+    if !ctx.servers.is_empty() {
+        files.insert_child_directory(
+            &package_name,
+            server_bindings(&ctx).map_err(crate::Error::Generate)?,
+        );
+    }
+
+    files.insert_child_directory(
+        Path::new(&package_name).join("wasm"),
+        crate::codegen::wasm_modules(package),
+    );
+
+    if !ctx.mounts.is_empty() {
+        files.insert_child_directory(
+            &package_name,
+            mounts(&ctx, package).map_err(crate::Error::Generate)?,
+        );
+    }
+
+    if ctx.has_emscripten_commands {
+        files.insert(
+            Path::new(&package_name).join("_emscripten.py"),
+            emscripten_helpers(&ctx).map_err(crate::Error::Generate)?,
         );
     }
 
     files.insert(
         Path::new(&package_name).join("__init__.py"),
-        top_level_dunder_init(package)?,
+        top_level_dunder_init(package).map_err(crate::Error::Generate)?,
     );
     // Indicate that we use type hints
     files.insert(
@@ -76,10 +125,17 @@ pub fn generate_python(package: &Package, options: &BindingsOptions) -> Result<F
 
     files.insert(
         "pyproject.toml",
-        generate_pyproject_toml(metadata, &package_name)?,
+        generate_pyproject_toml(metadata, &package_name).map_err(crate::Error::Generate)?,
     );
 
-    files.insert("MANIFEST.in", generate_manifest(package, &package_name)?);
+    files.insert(
+        "MANIFEST.in",
+        generate_manifest(package, &package_name).map_err(crate::Error::Generate)?,
+    );
+
+    if let Some(readme) = &metadata.readme {
+        files.insert(README, readme.as_str().into());
+    }
 
     Ok(files)
 }
@@ -87,17 +143,23 @@ pub fn generate_python(package: &Package, options: &BindingsOptions) -> Result<F
 #[derive(Debug, serde::Serialize)]
 struct Context {
     commands: Vec<CommandContext>,
+    /// Commands which handle HTTP requests using WCGI.
+    servers: Vec<CommandContext>,
     libraries: Vec<LibraryContext>,
+    mounts: Vec<MountContext>,
+    /// Do any commands need the Emscripten helpers?
+    has_emscripten_commands: bool,
 }
 
 impl Context {
     fn for_package(pkg: &Package) -> Self {
-        let commands = pkg
+        let (servers, commands): (Vec<CommandContext>, Vec<_>) = pkg
             .commands()
             .iter()
             .cloned()
             .map(CommandContext::from)
-            .collect();
+            .partition(|cmd| cmd.wcgi);
+        let has_emscripten_commands = commands.iter().any(|cmd| cmd.emscripten);
 
         let libraries = pkg
             .libraries()
@@ -106,35 +168,54 @@ impl Context {
             .map(LibraryContext::from)
             .collect();
 
+        let mounts = pkg
+            .mounts()
+            .iter()
+            .enumerate()
+            .map(|(i, mount)| MountContext {
+                guest_path: mount.guest_path.clone(),
+                dir: i.to_string(),
+            })
+            .collect();
+
         Context {
             commands,
+            servers,
             libraries,
+            mounts,
+            has_emscripten_commands,
         }
     }
 }
 
+#[derive(Debug, serde::Serialize)]
+struct MountContext {
+    /// Where the directory is mounted inside the guest.
+    guest_path: String,
+    /// Where the directory's contents are saved, relative to `volumes/`.
+    dir: String,
+}
+
 #[derive(Debug, serde::Serialize)]
 struct LibraryContext {
     ident: String,
     class_name: String,
+    /// The filename of the WebAssembly module, relative to `wasm/`.
     module_filename: String,
     wasi: bool,
     exports: InterfaceContext,
     imports: Vec<InterfaceContext>,
-    #[serde(skip)]
-    module: Module,
 }
 
 impl From<crate::Library> for LibraryContext {
     fn from(lib: crate::Library) -> Self {
-        let module_filename = Path::new(lib.module_filename()).with_extension("wasm");
         let ident = lib.interface_name().to_snake_case();
         let class_name = lib.class_name();
 
         LibraryContext {
             ident,
             class_name,
-            module_filename: module_filename.display().to_string(),
+            module_filename: crate::files::wasm_filename(&lib.module.wasm),
             wasi: lib.requires_wasi(),
             exports: lib.exports.into(),
             imports: lib
@@ -142,7 +223,6 @@ impl From<crate::Library> for LibraryContext {
                 .into_iter()
                 .map(InterfaceContext::from)
                 .collect(),
-            module: lib.module,
         }
     }
 }
@@ -176,19 +256,27 @@ struct CommandContext {
     name: String,
     ident: String,
     module_filename: String,
-    #[serde(skip)]
-    wasm: Vec<u8>,
+    /// The name passed to the command as `argv[0]`.
+    program_name: String,
+    main_args: Vec<String>,
+    env: BTreeMap<String, String>,
+    /// Was the command compiled with Emscripten?
+    emscripten: bool,
+    /// Does the command handle HTTP requests using WCGI?
+    wcgi: bool,
 }
 
 impl From<crate::Command> for CommandContext {
     fn from(cmd: crate::Command) -> CommandContext {
-        let ident = cmd.name.to_snake_case();
-        let module_filename = Path::new(&ident).with_extension("wasm");
         CommandContext {
             name: cmd.name.clone(),
-            ident,
-            module_filename: module_filename.display().to_string(),
-            wasm: cmd.wasm,
+            ident: cmd.name.to_snake_case(),
+            module_filename: crate::files::wasm_filename(&cmd.wasm),
+            program_name: cmd.program_name().to_string(),
+            main_args: cmd.main_args,
+            env: cmd.env,
+            emscripten: cmd.kind == CommandKind::Emscripten,
+            wcgi: cmd.kind == CommandKind::Wcgi,
         }
     }
 }
@@ -196,10 +284,6 @@ impl From<crate::Command> for CommandContext {
 fn command_bindings(ctx: &Context) -> Result<Files, Error> {
     let mut files = Files::new();
 
-    for cmd in &ctx.commands {
-        files.insert(&cmd.module_filename, SourceFile::from(&cmd.wasm));
-    }
-
     files.insert(
         "__init__.py",
         TEMPLATES
@@ -212,12 +296,61 @@ fn command_bindings(ctx: &Context) -> Result<Files, Error> {
     Ok(files)
 }
 
+/// The `Servers` class for running WCGI commands, plus the glue code for
+/// exposing them as WSGI and ASGI applications.
+fn server_bindings(ctx: &Context) -> Result<Files, Error> {
+    let mut files = Files::new();
+
+    let dunder_init = TEMPLATES
+        .get_template("servers.__init__.py")
+        .unwrap()
+        .render(ctx)?;
+    files.insert(Path::new("servers").join("__init__.py"), dunder_init.into());
+
+    let wcgi_py = TEMPLATES
+        .get_template("_wcgi.py")
+        .unwrap()
+        .render(minijinja::context! {
+            generator => crate::GENERATOR,
+        })?;
+    files.insert("_wcgi.py", wcgi_py.into());
+
+    Ok(files)
+}
+
+/// Save each mounted directory alongside a helper for adding them to a WASI
+/// environment.
+fn mounts(ctx: &Context, pkg: &Package) -> Result<Files, Error> {
+    let mut files = Files::new();
+
+    for (mount, mount_ctx) in pkg.mounts().iter().zip(&ctx.mounts) {
+        files.insert_child_directory(
+            Path::new("volumes").join(&mount_ctx.dir),
+            mount.files.clone(),
+        );
+    }
+
+    let mounts_py = TEMPLATES.get_template("_mounts.py").unwrap().render(ctx)?;
+    files.insert("_mounts.py", mounts_py.into());
+
+    Ok(files)
+}
+
+/// The host functions needed by commands compiled with Emscripten.
+fn emscripten_helpers(ctx: &Context) -> Result<SourceFile, Error> {
+    let emscripten_py = TEMPLATES
+        .get_template("_emscripten.py")
+        .unwrap()
+        .render(ctx)?;
+
+    Ok(emscripten_py.into())
+}
+
 fn library_bindings(ctx: &Context) -> Result<Files, Error> {
     let mut files = Files::new();
 
     for lib in &ctx.libraries {
-        let mut bindings = generate_bindings(lib);
-        bindings.insert(&lib.module_filename, lib.module.wasm.clone().into());
+        let bindings = generate_bindings(lib);
         files.insert_child_directory(&lib.ident, bindings);
     }
 
@@ -233,14 +366,16 @@ fn library_bindings(ctx: &Context) -> Result<Files, Error> {
 fn generate_manifest(package: &Package, package_name: &str) -> Result<SourceFile, Error> {
     let ctx = minijinja::context! {
         package_name,
-        libraries => package.libraries()
-        .iter()
-            .map(|lib| lib.interface_name())
-            .collect::<Vec<_>>(),
-        commands => package.commands()
+        wasm => !package.libraries().is_empty() || !package.commands().is_empty(),
+        mounts => !package.mounts().is_empty(),
+        emscripten => package
+            .commands()
             .iter()
-            .map(|cmd| cmd.name.as_str())
-            .collect::<Vec<_>>(),
+            .any(|cmd| cmd.kind == CommandKind::Emscripten),
+        wcgi => package
+            .commands()
+            .iter()
+            .any(|cmd| cmd.kind == CommandKind::Wcgi),
     };
     let rendered = TEMPLATES
         .get_template("MANIFEST.in")
@@ -252,19 +387,46 @@ fn generate_manifest(package: &Package, package_name: &str) -> Result<SourceFile
 
 fn generate_pyproject_toml(metadata: &Metadata, package_name: &str) -> Result<SourceFile, Error> {
     let Metadata {
-        version,
         description,
+        license,
+        homepage,
+        repository,
+        readme,
+        authors,
         ..
     } = metadata;
 
+    let mut urls = BTreeMap::new();
+    if let Some(homepage) = homepage {
+        urls.insert("Homepage", homepage.as_str());
+    }
+    if let Some(repository) = repository {
+        urls.insert("Repository", repository.as_str());
+    }
+
+    let version = metadata.python_version();
+    // Each WAPM dependency should have its own generated package
+    let requirements: Vec<String> = metadata
+        .dependencies
+        .iter()
+        .map(|dep| dep.python_requirement())
+        .collect();
+    let dependencies = ["wasmer", "wasmer_compiler_cranelift"]
+        .into_iter()
+        .chain(requirements.iter().map(String::as_str))
+        .collect();
+
     let project = PyProject {
         project: Project {
             name: package_name,
-            version,
+            version: &version,
             description: description.as_deref(),
-            readme: None,
+            readme: readme.as_ref().map(|_| Path::new(README)),
+            license: license.as_deref().map(|text| License { text }),
+            authors: authors.iter().map(|a| Author::parse(a)).collect(),
             keywords: Vec::new(),
-            dependencies: vec!["wasmer", "wasmer_compiler_cranelift"],
+            dependencies,
+            urls,
         },
         build_system: BuildSystem {
             requires: &["setuptools", "setuptools-scm"],
@@ -297,24 +459,70 @@ struct Project<'a> {
     version: &'a str,
     description: Option<&'a str>,
     readme: Option<&'a Path>,
+    license: Option<License<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<Author<'a>>,
     keywords: Vec<&'a str>,
     dependencies: Vec<&'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    urls: BTreeMap<&'a str, &'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+struct License<'a> {
+    text: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+struct Author<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<&'a str>,
+}
+
+impl<'a> Author<'a> {
+    /// Parse an author in the `Name <email>` format used by `Cargo.toml` and
+    /// `package.json`.
+    fn parse(author: &'a str) -> Self {
+        match author
+            .trim()
+            .strip_suffix('>')
+            .and_then(|a| a.split_once('<'))
+        {
+            Some((name, email)) => Author {
+                name: name.trim(),
+                email: Some(email.trim()),
+            },
+            None => Author {
+                name: author.trim(),
+                email: None,
+            },
+        }
+    }
 }
 
 fn top_level_dunder_init(package: &Package) -> Result<SourceFile, Error> {
+    let metadata = package.metadata();
     let Metadata {
-        version,
         description,
         package_name,
-    } = package.metadata();
+        ..
+    } = metadata;
 
     let ctx = minijinja::context! {
-        version,
+        version => metadata.python_version(),
         description,
         generator => crate::GENERATOR,
         package_name => package_name.to_string(),
         ident => package_name.name().to_pascal_case(),
-        commands => !package.commands().is_empty(),
+        commands => package
+            .commands()
+            .iter()
+            .any(|cmd| cmd.kind != CommandKind::Wcgi),
+        servers => package
+            .commands()
+            .iter()
+            .any(|cmd| cmd.kind == CommandKind::Wcgi),
         libraries => !package.libraries().is_empty(),
     };
 
@@ -331,11 +539,11 @@ fn generate_bindings(lib: &LibraryContext) -> Files {
     // guest, but we're generating bindings from the perspective of the host.
     // Hence the "host_imports = guest_exports" thing.
 
-    let imports = std::slice::from_ref(&lib.exports.interface.0);
+    let imports = std::slice::from_ref(&lib.exports.interface.parsed);
     let exports: Vec<_> = lib
         .imports
         .iter()
-        .map(|ctx| ctx.interface.0.clone())
+        .map(|ctx| ctx.interface.parsed.clone())
         .collect();
 
     let mut generated = wai_bindgen_gen_core::Files::default();
@@ -353,7 +561,7 @@ mod tests {
     use insta::Settings;
 
     use super::*;
-    use crate::{Command, Library, Module};
+    use crate::{Command, Dependency, Library, Module};
     use std::collections::BTreeSet;
 
     const WASMER_PACK_EXPORTS: &str = include_str!(concat!(
@@ -361,34 +569,76 @@ mod tests {
         "/../wasm/wasmer-pack.exports.wai"
     ));
 
+    #[test]
+    fn pyproject_with_extended_metadata() {
+        let metadata = Metadata::new(
+            "wasmer/wasmer-pack".parse().unwrap(),
+            "1.2.3".parse().unwrap(),
+        )
+        .with_description("A code generator")
+        .with_license("MIT")
+        .with_homepage("https://wasmer.io/")
+        .with_repository("https://github.com/wasmerio/wasmer-pack")
+        .with_readme("# Wasmer Pack")
+        .with_authors([
+            "Wasmer Engineering Team <engineering@wasmer.io>",
+            "Anonymous",
+        ]);
+
+        let got = generate_pyproject_toml(&metadata, "wasmer_pack").unwrap();
+
+        insta::assert_display_snapshot!(got.utf8_contents().unwrap());
+    }
+
+    #[test]
+    fn pyproject_with_dependencies() {
+        let metadata = Metadata::new("wasmer/tools".parse().unwrap(), "0.0.0".parse().unwrap())
+            .with_dependencies([Dependency::new(
+                "wasmer/coreutils".parse().unwrap(),
+                "^1.2".parse().unwrap(),
+            )]);
+
+        let got = generate_pyproject_toml(&metadata, "tools").unwrap();
+
+        insta::assert_display_snapshot!(got.utf8_contents().unwrap());
+    }
+
     #[test]
     fn generated_files() {
-        let expected: BTreeSet<&Path> = [
+        let mut expected: BTreeSet<&Path> = [
             "MANIFEST.in",
             "pyproject.toml",
             "wasmer_pack/__init__.py",
             "wasmer_pack/py.typed",
             "wasmer_pack/commands/__init__.py",
-            "wasmer_pack/commands/first.wasm",
-            "wasmer_pack/commands/second_with_dashes.wasm",
             "wasmer_pack/bindings/__init__.py",
             "wasmer_pack/bindings/wasmer_pack/__init__.py",
             "wasmer_pack/bindings/wasmer_pack/bindings.py",
-            "wasmer_pack/bindings/wasmer_pack/wasmer_pack_wasm.wasm",
         ]
         .iter()
         .map(Path::new)
         .collect();
-        let metadata = Metadata::new("wasmer/wasmer-pack".parse().unwrap(), "1.2.3");
+        let metadata = Metadata::new(
+            "wasmer/wasmer-pack".parse().unwrap(),
+            "1.2.3".parse().unwrap(),
+        );
+        let exports =
+            crate::Interface::from_wit("wasmer-pack.exports.wit", WASMER_PACK_EXPORTS).unwrap();
         let module = Module {
             name: "wasmer_pack_wasm.wasm".to_string(),
             abi: crate::Abi::None,
-            wasm: Vec::new(),
+            wasm: crate::exports::stub_module(&exports),
         };
-        let exports =
-            crate::Interface::from_wit("wasmer-pack.exports.wit", WASMER_PACK_EXPORTS).unwrap();
+        let library_wasm =
+            Path::new("wasmer_pack/wasm").join(crate::files::wasm_filename(&module.wasm));
+        expected.insert(&library_wasm);
+        // Both commands use the same (empty) binary, so they share a file
+        let command_wasm = Path::new("wasmer_pack/wasm").join(crate::files::wasm_filename(&[]));
+        expected.insert(&command_wasm);
         let commands = vec![
-            Command::new("first", []),
+            Command::new("first", [])
+                .with_main_args(["--verbose"])
+                .with_env([("RUST_LOG", "info")]),
             Command::new("second-with-dashes", []),
         ];
         let browser =
@@ -411,6 +661,7 @@ mod tests {
             r"Generated by wasmer-pack v\d+\.\d+\.\d+(-\w+(\.\d+)?)?",
             "Generated by XXX",
         );
+        settings.add_filter(r"[0-9a-f]{64}\.wasm", "[hash].wasm");
         settings.bind(|| {
             insta::assert_display_snapshot!(files["pyproject.toml"].utf8_contents().unwrap());
             insta::assert_display_snapshot!(files["MANIFEST.in"].utf8_contents().unwrap());
@@ -430,4 +681,71 @@ mod tests {
         let actual_files: BTreeSet<_> = files.iter().map(|(p, _)| p).collect();
         assert_eq!(actual_files, expected);
     }
+
+    #[test]
+    fn generated_files_with_mounts() {
+        let metadata = Metadata::new("wasmer/python".parse().unwrap(), "1.2.3".parse().unwrap());
+        let mut lib = Files::new();
+        lib.insert("python/os.py", "import sys".into());
+        let package = Package::new(metadata, Vec::new(), vec![Command::new("python", [])])
+            .with_mounts([crate::Mount::new("/lib", lib)]);
+
+        let files = generate_python(&package, &BindingsOptions::default()).unwrap();
+
+        assert_eq!(
+            files["python/volumes/0/python/os.py"].utf8_contents(),
+            Some("import sys")
+        );
+        insta::assert_display_snapshot!(files["python/_mounts.py"].utf8_contents().unwrap());
+        insta::assert_display_snapshot!(files["MANIFEST.in"].utf8_contents().unwrap());
+        insta::assert_display_snapshot!(files["python/commands/__init__.py"]
+            .utf8_contents()
+            .unwrap());
+    }
+
+    #[test]
+    fn generated_files_with_emscripten_commands() {
+        let metadata = Metadata::new("wasmer/lua".parse().unwrap(), "1.2.3".parse().unwrap());
+        // Compiled with -sSTANDALONE_WASM, so it imports WASI and has a _start
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+                (func (export "_start"))
+            )"#,
+        )
+        .unwrap();
+        let lua = Command::new("lua", wasm).with_kind(CommandKind::Emscripten);
+        let package = Package::new(metadata, Vec::new(), vec![lua]);
+
+        let files = generate_python(&package, &BindingsOptions::default()).unwrap();
+
+        insta::assert_display_snapshot!(files["lua/_emscripten.py"].utf8_contents().unwrap());
+        insta::assert_display_snapshot!(files["MANIFEST.in"].utf8_contents().unwrap());
+        insta::assert_display_snapshot!(files["lua/commands/__init__.py"].utf8_contents().unwrap());
+    }
+
+    #[test]
+    fn generated_files_with_wcgi_commands() {
+        let metadata = Metadata::new("wasmer/hello".parse().unwrap(), "1.2.3".parse().unwrap());
+        let serve = Command::new("serve", []).with_kind(CommandKind::Wcgi);
+        let package = Package::new(metadata, Vec::new(), vec![serve]);
+
+        let files = generate_python(&package, &BindingsOptions::default()).unwrap();
+
+        assert!(!files
+            .iter()
+            .any(|(path, _)| path.starts_with("hello/commands")));
+        insta::assert_display_snapshot!(files["hello/_wcgi.py"]
+            .utf8_contents()
+            .unwrap()
+            .replace(crate::GENERATOR, "XXX"));
+        insta::assert_display_snapshot!(files["hello/servers/__init__.py"]
+            .utf8_contents()
+            .unwrap());
+        insta::assert_display_snapshot!(files["hello/__init__.py"]
+            .utf8_contents()
+            .unwrap()
+            .replace(crate::GENERATOR, "XXX"));
+        insta::assert_display_snapshot!(files["MANIFEST.in"].utf8_contents().unwrap());
+    }
 }
@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use once_cell::sync::OnceCell;
+use semver::Version;
+use webc::compat::Container;
+
+use crate::{Dependency, PackageName};
+
+/// Something which can find the WEBC files a package depends on, letting
+/// commands use atoms from their dependencies.
+pub trait Resolver {
+    /// Look up a [`Dependency`], returning `None` if it isn't available.
+    fn resolve(&self, dependency: &Dependency) -> Result<Option<Container>, crate::Error>;
+}
+
+/// A [`Resolver`] which never finds any dependencies.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct NoDependencies;
+
+impl Resolver for NoDependencies {
+    fn resolve(&self, _dependency: &Dependency) -> Result<Option<Container>, crate::Error> {
+        Ok(None)
+    }
+}
+
+/// A [`Resolver`] which looks for dependencies in a directory of WEBC files.
+///
+/// Files are matched using the package name and version from their `wapm`
+/// annotation rather than their filename, so this also works with a local
+/// Wasmer cache (e.g. `~/.wasmer/cache/`). Subdirectories are searched too,
+/// letting you point it at a registry mirror laid out however you like. When
+/// several files match, the one with the highest version is used.
+///
+/// The directory is scanned the first time a dependency is looked up, so
+/// WEBC files added after that won't be found.
+#[derive(Debug, Clone)]
+pub struct WebcDirectory {
+    dir: PathBuf,
+    index: OnceCell<Vec<IndexEntry>>,
+}
+
+/// A WEBC file found while scanning a [`WebcDirectory`].
+#[derive(Debug, Clone)]
+struct IndexEntry {
+    package_name: PackageName,
+    version: Version,
+    path: PathBuf,
+}
+
+impl WebcDirectory {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        WebcDirectory {
+            dir: dir.into(),
+            index: OnceCell::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    fn index(&self) -> Result<&[IndexEntry], crate::Error> {
+        let index = self.index.get_or_try_init(|| {
+            if !self.dir.is_dir() {
+                anyhow::bail!("The \"{}\" directory doesn't exist", self.dir.display());
+            }
+
+            let mut index = Vec::new();
+
+            for path in files_recursive(&self.dir)? {
+                // Anything that isn't a valid WEBC file gets skipped
+                let Ok(webc) = Container::from_disk(&path) else {
+                    continue;
+                };
+                let Ok((package_name, version)) = crate::pirita::package_id(&webc) else {
+                    continue;
+                };
+
+                index.push(IndexEntry {
+                    package_name,
+                    version,
+                    path,
+                });
+            }
+
+            Ok(index)
+        });
+
+        index.map(Vec::as_slice).map_err(crate::Error::Load)
+    }
+}
+
+impl PartialEq for WebcDirectory {
+    fn eq(&self, other: &Self) -> bool {
+        self.dir == other.dir
+    }
+}
+
+impl Eq for WebcDirectory {}
+
+impl Resolver for WebcDirectory {
+    fn resolve(&self, dependency: &Dependency) -> Result<Option<Container>, crate::Error> {
+        let best_match = self
+            .index()?
+            .iter()
+            .filter(|entry| {
+                entry.package_name == dependency.package_name
+                    && dependency.version.matches(&entry.version)
+            })
+            .max_by(|a, b| a.version.cmp(&b.version));
+
+        let Some(entry) = best_match else {
+            return Ok(None);
+        };
+
+        let webc = Container::from_disk(&entry.path)
+            .with_context(|| format!("Unable to load \"{}\"", entry.path.display()))
+            .map_err(crate::Error::Load)?;
+
+        Ok(Some(webc))
+    }
+}
+
+/// Get the paths of all files inside a directory and its subdirectories.
+fn files_recursive(dir: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .with_context(|| format!("Unable to read the \"{}\" directory", dir.display()))?;
+
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
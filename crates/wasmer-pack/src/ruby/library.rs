@@ -0,0 +1,1272 @@
+//! Generate the Ruby class for a single library.
+//!
+//! There is no `wai-bindgen` backend for `wasmer-ruby`, so like the Go
+//! generator, this module walks each function's canonical ABI instructions
+//! itself.
+
+use heck::{ToPascalCase, ToShoutySnakeCase, ToSnakeCase};
+use wai_parser::{
+    abi::{AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType},
+    Docs, Function, FunctionKind, Interface, ResourceId, SizeAlign, Type, TypeDefKind,
+};
+
+use crate::{
+    codegen::{
+        self, parenthesize, replace_ident, uses_ident, FunctionCode, Layout, Lifted, ITER_BASE,
+        ITER_ELEM,
+    },
+    Library,
+};
+
+/// Ruby's reserved words.
+pub(crate) const RUBY_KEYWORDS: &[&str] = &[
+    "BEGIN",
+    "END",
+    "__ENCODING__",
+    "__FILE__",
+    "__LINE__",
+    "alias",
+    "and",
+    "begin",
+    "break",
+    "case",
+    "class",
+    "def",
+    "defined",
+    "do",
+    "else",
+    "elsif",
+    "end",
+    "ensure",
+    "false",
+    "for",
+    "if",
+    "in",
+    "module",
+    "next",
+    "nil",
+    "not",
+    "or",
+    "redo",
+    "rescue",
+    "retry",
+    "return",
+    "self",
+    "super",
+    "then",
+    "true",
+    "undef",
+    "unless",
+    "until",
+    "when",
+    "while",
+    "yield",
+];
+
+/// Locals and instance variables used by the generated methods, which a
+/// parameter mustn't shadow.
+const GLUE_NAMES: &[&str] = &["host", "store", "import_object"];
+
+/// Methods that a struct member or generated method mustn't override.
+const RESERVED_METHODS: &[&str] = &[
+    "class",
+    "clone",
+    "clone_ref",
+    "drop",
+    "dup",
+    "freeze",
+    "handle",
+    "hash",
+    "initialize",
+    "inspect",
+    "instance",
+    "method",
+    "object_id",
+    "send",
+    "to_s",
+];
+
+/// Generate the source code for a library's class.
+pub(crate) fn generate(lib: &Library, module: &str) -> String {
+    let class_name = lib.class_name();
+    let exports = &lib.exports.parsed;
+
+    let interfaces: Vec<&Interface> = std::iter::once(exports)
+        .chain(lib.imports.iter().map(|i| &i.parsed))
+        .collect();
+
+    // The helpers are normally accessible as "Abi", but a type with the same
+    // name would shadow them
+    let shadowed = interfaces.iter().any(|iface| {
+        iface
+            .types
+            .iter()
+            .any(|(_, def)| def.name.as_deref().map(type_name).as_deref() == Some("Abi"))
+    });
+    let mut gen = LibraryGenerator {
+        abi: if shadowed {
+            format!("::{module}::Abi")
+        } else {
+            "Abi".to_string()
+        },
+        ..Default::default()
+    };
+
+    for iface in &interfaces {
+        gen.type_definitions(iface);
+    }
+    if interfaces.iter().any(|iface| uses_expected(iface)) {
+        gen.expected_definition();
+    }
+    gen.resource_classes(exports);
+
+    for iface in &lib.imports {
+        gen.host_interface(&iface.parsed);
+    }
+
+    gen.initialize(
+        exports,
+        &lib.imports.iter().map(|i| &i.parsed).collect::<Vec<_>>(),
+    );
+    gen.exported_functions(exports);
+
+    gen.push("private");
+    for iface in &lib.imports {
+        gen.import_registration(&iface.parsed);
+    }
+    gen.resource_registration(exports);
+
+    let mut src = String::from(
+        "# frozen_string_literal: true\n\nrequire \"wasmer\"\n\nrequire_relative \"../abi\"\n\n",
+    );
+    src.push_str(&format!("module {module}\nclass Bindings\n"));
+    src.push_str(&format!(
+        "# Bindings to the \"{}\" library.\nclass {class_name}\n",
+        lib.interface_name()
+    ));
+    src.push_str(gen.src.trim_start());
+    src.push_str("end\nend\nend\n");
+
+    indent(&src)
+}
+
+#[derive(Default)]
+struct LibraryGenerator {
+    src: String,
+    /// The names of the types defined so far, so interfaces which define a
+    /// type with the same name don't redefine the constant.
+    defined: Vec<String>,
+    /// How to refer to the module containing the canonical ABI helpers.
+    abi: String,
+}
+
+impl LibraryGenerator {
+    fn push(&mut self, code: &str) {
+        self.src.push('\n');
+        self.src.push_str(code);
+        if !code.ends_with('\n') {
+            self.src.push('\n');
+        }
+    }
+
+    fn type_definitions(&mut self, iface: &Interface) {
+        for (_, def) in iface.types.iter() {
+            let name = match &def.name {
+                Some(name) => type_name(name),
+                None => continue,
+            };
+            if self.defined.contains(&name) {
+                continue;
+            }
+            let mut code = String::new();
+            push_docs(&mut code, &def.docs);
+
+            match &def.kind {
+                TypeDefKind::Record(record) if record.fields.is_empty() => {
+                    code.push_str(&format!("{name} = Class.new\n"));
+                }
+                TypeDefKind::Record(record) => {
+                    for field in &record.fields {
+                        if let Some(contents) = &field.docs.contents {
+                            if !code.is_empty() {
+                                code.push_str("#\n");
+                            }
+                            code.push_str(&format!("# @!attribute {}\n", field_name(&field.name)));
+                            for line in contents.trim().lines() {
+                                code.push_str(
+                                    &format!("#   {}\n", line.trim()).replace("#   \n", "#\n"),
+                                );
+                            }
+                        }
+                    }
+                    let members: Vec<_> = record
+                        .fields
+                        .iter()
+                        .map(|f| format!(":{}", field_name(&f.name)))
+                        .collect();
+                    code.push_str(&format!(
+                        "{name} = Struct.new({}, keyword_init: true)\n",
+                        members.join(", ")
+                    ));
+                }
+                TypeDefKind::Flags(flags) => {
+                    code.push_str(&format!("module {name}\n"));
+                    for (i, flag) in flags.flags.iter().enumerate() {
+                        push_docs(&mut code, &flag.docs);
+                        code.push_str(&format!(
+                            "{} = 1 << {i}\n",
+                            flag.name.to_shouty_snake_case()
+                        ));
+                    }
+                    code.push_str("end\n");
+                }
+                TypeDefKind::Enum(e) => {
+                    code.push_str(&format!("module {name}\n"));
+                    let mut constants = Vec::new();
+                    for case in &e.cases {
+                        let constant = case.name.to_shouty_snake_case();
+                        push_docs(&mut code, &case.docs);
+                        code.push_str(&format!("{constant} = :{}\n", case.name.to_snake_case()));
+                        constants.push(constant);
+                    }
+                    code.push_str(&format!(
+                        "\n# Every case, in the order used by the canonical ABI.\nCASES = [{}].freeze\nend\n",
+                        constants.join(", ")
+                    ));
+                }
+                TypeDefKind::Variant(variant) => {
+                    let cases = variant
+                        .cases
+                        .iter()
+                        .map(|c| (c.name.to_pascal_case(), &c.docs));
+                    push_sum_type(&mut code, &name, cases);
+                }
+                TypeDefKind::Union(union) => {
+                    let cases = union
+                        .cases
+                        .iter()
+                        .enumerate()
+                        .map(|(i, c)| (format!("Case{i}"), &c.docs));
+                    push_sum_type(&mut code, &name, cases);
+                }
+                // Ruby is dynamically typed, so there's no need to name
+                // these
+                TypeDefKind::Tuple(_)
+                | TypeDefKind::Option(_)
+                | TypeDefKind::Expected(_)
+                | TypeDefKind::List(_)
+                | TypeDefKind::Type(_) => continue,
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                    unreachable!("futures and streams are rejected during validation")
+                }
+            }
+
+            self.defined.push(name);
+            self.push(&code);
+        }
+    }
+
+    fn expected_definition(&mut self) {
+        self.push(
+            "# The result of an operation which succeeded.
+Ok = Struct.new(:value)
+
+# The error returned by an operation which failed.
+Err = Struct.new(:value)",
+        );
+    }
+
+    /// A class wrapping each resource the library exports, with a method for
+    /// each of the resource's own functions.
+    fn resource_classes(&mut self, iface: &Interface) {
+        let abi = self.abi.clone();
+        for (id, resource) in iface.resources.iter() {
+            let name = type_name(&resource.name);
+            let mut code = String::new();
+            push_docs(&mut code, &resource.docs);
+            code.push_str(&format!(
+                "class {name} < {abi}::Resource
+# @api private
+def initialize(lib, handle, destructor)
+super(handle, destructor)
+@lib = lib
+end
+"
+            ));
+
+            for func in &iface.functions {
+                match &func.kind {
+                    FunctionKind::Method { resource, name } if *resource == id => {
+                        let params: Vec<_> = func.params[1..]
+                            .iter()
+                            .map(|(name, _)| param_name(name))
+                            .collect();
+                        let args: Vec<_> = std::iter::once("self".to_string())
+                            .chain(params.iter().cloned())
+                            .collect();
+                        code.push('\n');
+                        push_function_docs(&mut code, func);
+                        code.push_str(&format!(
+                            "def {}{}\n@lib.{}({})\nend\n",
+                            method_ident(name),
+                            param_list(&params),
+                            function_ident(iface, func),
+                            args.join(", ")
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+
+            code.push_str("end\n");
+            self.push(&code);
+        }
+    }
+
+    /// A module documenting the methods the host needs to implement to
+    /// provide an imported interface.
+    fn host_interface(&mut self, iface: &Interface) {
+        let mut code = format!(
+            "# The host's implementation of the \"{}\" interface.
+#
+# Any object with these methods can be used, but including this module
+# documents the intent and reports missing methods clearly.
+module {}
+",
+            iface.name,
+            type_name(&iface.name)
+        );
+
+        for (i, func) in iface.functions.iter().enumerate() {
+            if i > 0 {
+                code.push('\n');
+            }
+            let params: Vec<_> = func.params.iter().map(|(n, _)| param_name(n)).collect();
+            push_docs(&mut code, &func.docs);
+            code.push_str(&format!(
+                "def {}{}\nraise NotImplementedError, \"{}#{} isn't implemented\"\nend\n",
+                method_ident(&func.name),
+                param_list(&params),
+                type_name(&iface.name),
+                method_ident(&func.name),
+            ));
+        }
+
+        code.push_str("end\n");
+        self.push(&code);
+    }
+
+    fn initialize(&mut self, exports: &Interface, imports: &[&Interface]) {
+        let abi = &self.abi;
+        let mut params = vec![
+            "store".to_string(),
+            "wasm_module".to_string(),
+            "import_object".to_string(),
+        ];
+        params.extend(imports.iter().map(|iface| param_name(&iface.name)));
+
+        let mut code = String::from(
+            "# Instantiate the library.
+#
+# Users should normally go through the package's +Bindings+, which
+# prepares the module and import object.
+#
+# @api private
+",
+        );
+        code.push_str(&format!("def initialize{}\n", param_list(&params)));
+        code.push_str(&format!("@env = {abi}::Env.new\n"));
+        for (_, resource) in exports.resources.iter() {
+            code.push_str(&format!("{} = {abi}::Slab.new\n", handles(&resource.name)));
+        }
+        for iface in imports {
+            code.push_str(&format!(
+                "add_{}_to_imports(store, import_object, {})\n",
+                iface.name.to_snake_case(),
+                param_name(&iface.name)
+            ));
+        }
+        if exports.resources.len() > 0 {
+            code.push_str("add_resources_to_imports(store, import_object)\n");
+        }
+        code.push_str(
+            "\n@instance = Wasmer::Instance.new(wasm_module, import_object)\n@env.bind(@instance)\n",
+        );
+        for func in &exports.functions {
+            code.push_str(&format!(
+                "{} = {abi}.required_export(@instance, {})\n",
+                function_field(exports, func),
+                quote(&func.name)
+            ));
+        }
+        for (_, resource) in exports.resources.iter() {
+            code.push_str(&format!(
+                "{} = {abi}.required_export(@instance, {})\n",
+                destructor_field(&resource.name),
+                quote(&format!("canonical_abi_drop_{}", resource.name))
+            ));
+        }
+        code.push_str("end\n\n# The underlying WebAssembly instance.\nattr_reader :instance\n");
+
+        self.push(&code);
+    }
+
+    fn exported_functions(&mut self, iface: &Interface) {
+        let mut sizes = SizeAlign::default();
+        sizes.fill(iface);
+
+        for func in &iface.functions {
+            let param_names: Vec<_> = func.params.iter().map(|(n, _)| param_name(n)).collect();
+            let mut bindgen = FunctionBindgen::new(&sizes, param_names.clone());
+            bindgen.callee = function_field(iface, func);
+            bindgen.abi = self.abi.clone();
+            iface.call(
+                AbiVariant::GuestExport,
+                LiftLower::LowerArgsLiftResults,
+                func,
+                &mut bindgen,
+            );
+
+            let mut code = String::new();
+            push_function_docs(&mut code, func);
+            code.push_str(&format!(
+                "def {}{}\n{}end\n",
+                function_ident(iface, func),
+                param_list(&param_names),
+                bindgen.code.src
+            ));
+
+            self.push(&code);
+        }
+    }
+
+    /// Register the host's implementation of an imported interface with the
+    /// import object.
+    fn import_registration(&mut self, iface: &Interface) {
+        let abi = self.abi.clone();
+        let mut sizes = SizeAlign::default();
+        sizes.fill(iface);
+
+        let mut code = format!(
+            "def add_{}_to_imports(store, import_object, host)\nimport_object.register(\n{},\n{{\n",
+            iface.name.to_snake_case(),
+            quote(&iface.name)
+        );
+        for func in &iface.functions {
+            let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+            let args: Vec<_> = (0..sig.params.len()).map(|i| format!("arg{i}")).collect();
+            let block_params = if args.is_empty() {
+                String::new()
+            } else {
+                format!(" |{}|", args.join(", "))
+            };
+            code.push_str(&format!(
+                "{} => {abi}.function(store, [{}], [{}]) do{block_params}\n{}(host{})\nend,\n",
+                quote(&func.name),
+                value_types(&sig.params),
+                value_types(&sig.results),
+                adapter_name(iface, func),
+                args.iter().map(|a| format!(", {a}")).collect::<String>(),
+            ));
+        }
+        code.push_str("}\n)\nend\n");
+        self.push(&code);
+
+        for func in &iface.functions {
+            let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+            let args: Vec<_> = (0..sig.params.len()).map(|i| format!("arg{i}")).collect();
+            let mut bindgen = FunctionBindgen::new(&sizes, args.clone());
+            bindgen.callee = format!("host.{}", method_ident(&func.name));
+            bindgen.abi = abi.clone();
+            bindgen.wasm_results = sig.results.clone();
+            iface.call(
+                AbiVariant::GuestImport,
+                LiftLower::LiftArgsLowerResults,
+                func,
+                &mut bindgen,
+            );
+
+            let params: Vec<_> = std::iter::once("host".to_string()).chain(args).collect();
+            let code = format!(
+                "def {}{}\n{}end\n",
+                adapter_name(iface, func),
+                param_list(&params),
+                bindgen.code.src
+            );
+            self.push(&code);
+        }
+    }
+
+    /// The intrinsics WebAssembly uses to create, copy, and destroy the
+    /// resources it exports.
+    fn resource_registration(&mut self, iface: &Interface) {
+        let abi = self.abi.clone();
+        if iface.resources.len() == 0 {
+            return;
+        }
+
+        let mut code = String::from(
+            "def add_resources_to_imports(store, import_object)\nimport_object.register(\n\"canonical_abi\",\n{\n",
+        );
+        for (_, resource) in iface.resources.iter() {
+            let handles = handles(&resource.name);
+            let name = &resource.name;
+            code.push_str(&format!(
+                "\"resource_drop_{name}\" => {abi}.function(store, [Wasmer::Type::I32], []) do |index|
+{handles}.remove(index).drop
+end,
+\"resource_clone_{name}\" => {abi}.function(store, [Wasmer::Type::I32], [Wasmer::Type::I32]) do |index|
+{handles}.insert({handles}.get(index).clone_ref)
+end,
+\"resource_get_{name}\" => {abi}.function(store, [Wasmer::Type::I32], [Wasmer::Type::I32]) do |index|
+{handles}.get(index).handle
+end,
+\"resource_new_{name}\" => {abi}.function(store, [Wasmer::Type::I32], [Wasmer::Type::I32]) do |handle|
+{handles}.insert({}.new(self, handle, {}))
+end,
+",
+                type_name(name),
+                destructor_field(name),
+            ));
+        }
+        code.push_str("}\n)\nend\n");
+        self.push(&code);
+    }
+}
+
+/// The method which adapts raw WebAssembly arguments to a call to the host's
+/// implementation of an imported function.
+fn adapter_name(iface: &Interface, func: &Function) -> String {
+    format!(
+        "{}_{}",
+        iface.name.to_snake_case(),
+        func.name.to_snake_case()
+    )
+}
+
+/// The name of the library method which calls an exported function (e.g.
+/// `package_from_webc` for `package::from-webc`).
+fn function_ident(iface: &Interface, func: &Function) -> String {
+    match &func.kind {
+        FunctionKind::Freestanding => method_ident(&func.name),
+        FunctionKind::Static { resource, name } | FunctionKind::Method { resource, name } => {
+            let resource = &iface.resources[*resource].name;
+            format!("{}_{}", resource.to_snake_case(), name.to_snake_case())
+        }
+    }
+}
+
+/// The instance variable holding an exported function.
+fn function_field(iface: &Interface, func: &Function) -> String {
+    format!("@fn_{}", function_ident(iface, func))
+}
+
+/// The instance variable holding the function which destroys a resource.
+fn destructor_field(resource: &str) -> String {
+    format!("@drop_{}", resource.to_snake_case())
+}
+
+/// The instance variable holding the slab for a resource's handles.
+fn handles(resource: &str) -> String {
+    format!("@{}_handles", resource.to_snake_case())
+}
+
+fn handles_for(iface: &Interface, ty: ResourceId) -> String {
+    handles(&iface.resources[ty].name)
+}
+
+fn value_types(types: &[WasmType]) -> String {
+    types
+        .iter()
+        .map(|ty| match ty {
+            WasmType::I32 => "Wasmer::Type::I32",
+            WasmType::I64 => "Wasmer::Type::I64",
+            WasmType::F32 => "Wasmer::Type::F32",
+            WasmType::F64 => "Wasmer::Type::F64",
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn type_name(name: &str) -> String {
+    name.to_pascal_case()
+}
+
+/// Turn a WAI parameter name into a Ruby identifier that won't clash with
+/// keywords or the names used by the glue code.
+pub(crate) fn param_name(name: &str) -> String {
+    codegen::avoid_reserved(name.to_snake_case(), &[RUBY_KEYWORDS, GLUE_NAMES])
+}
+
+fn field_name(name: &str) -> String {
+    method_ident(name)
+}
+
+/// Turn a WAI name into a method name, avoiding the methods every object
+/// (or the generated classes) rely on.
+fn method_ident(name: &str) -> String {
+    let ident = name.to_snake_case();
+    if RESERVED_METHODS.contains(&ident.as_str()) {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+fn param_list(params: &[String]) -> String {
+    if params.is_empty() {
+        String::new()
+    } else {
+        format!("({})", params.join(", "))
+    }
+}
+
+fn uses_expected(iface: &Interface) -> bool {
+    iface
+        .types
+        .iter()
+        .any(|(_, def)| matches!(def.kind, TypeDefKind::Expected(_)))
+}
+
+/// The Ruby constant for a named type.
+fn named_type(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Id(id) => match &iface.types[*id].name {
+            Some(name) => type_name(name),
+            None => unreachable!("only named types have a Ruby class"),
+        },
+        other => unreachable!("{other:?} doesn't have a Ruby class"),
+    }
+}
+
+fn push_docs(code: &mut String, docs: &Docs) {
+    if let Some(contents) = &docs.contents {
+        for line in contents.trim().lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                code.push_str("#\n");
+            } else {
+                code.push_str(&format!("# {line}\n"));
+            }
+        }
+    }
+}
+
+fn push_function_docs(code: &mut String, func: &Function) {
+    if func.docs.contents.is_some() {
+        push_docs(code, &func.docs);
+    } else {
+        code.push_str(&format!("# Call the \"{}\" function.\n", func.item_name()));
+    }
+}
+
+/// Variants and unions are represented as a module containing one struct
+/// per case, each holding the case's payload (if any) as its `value`.
+fn push_sum_type<'a>(
+    code: &mut String,
+    name: &str,
+    cases: impl Iterator<Item = (String, &'a Docs)>,
+) {
+    code.push_str(&format!("module {name}\n"));
+    for (case, docs) in cases {
+        push_docs(code, docs);
+        code.push_str(&format!("{case} = Struct.new(:value)\n"));
+    }
+    code.push_str("end\n");
+}
+
+/// Quote a string so it can be used as a Ruby string literal.
+pub(crate) fn quote(s: &str) -> String {
+    codegen::quote(s, |c| match c {
+        // Avoid accidental interpolation
+        '#' => Some("\\#".to_string()),
+        c if c.is_control() => Some(format!("\\u{:04x}", c as u32)),
+        _ => None,
+    })
+}
+
+/// Indent Ruby source code based on its keywords and brackets, the way
+/// RuboCop would.
+pub(crate) fn indent(src: &str) -> String {
+    codegen::indent(src, "  ", |line| {
+        if line.starts_with('#') {
+            return Layout::default();
+        }
+
+        let first_word = line.split([' ', '.', '(', ',']).next().unwrap_or_default();
+        Layout {
+            closes: first_word == "end"
+                || line.starts_with('}')
+                || line.starts_with(')')
+                || line.starts_with(']'),
+            opens: matches!(
+                first_word,
+                "class" | "module" | "def" | "if" | "unless" | "case" | "while" | "until" | "begin"
+            ) || line.ends_with(" do")
+                || (line.ends_with('|') && line.contains(" do |"))
+                || line.ends_with('{')
+                || line.ends_with('(')
+                || line.ends_with('['),
+            continues: matches!(first_word, "else" | "elsif" | "when" | "rescue" | "ensure"),
+            ..Layout::default()
+        }
+    })
+}
+
+/// Generates the Ruby statements that pass a function's arguments and
+/// results across the canonical ABI.
+struct FunctionBindgen<'a> {
+    sizes: &'a SizeAlign,
+    params: Vec<String>,
+    /// The function to invoke for `CallWasm` or `CallInterface`.
+    callee: String,
+    /// The WebAssembly results returned by a host function.
+    wasm_results: Vec<WasmType>,
+    /// How to refer to the module containing the canonical ABI helpers.
+    abi: String,
+    code: FunctionCode,
+}
+
+impl<'a> FunctionBindgen<'a> {
+    fn new(sizes: &'a SizeAlign, params: Vec<String>) -> Self {
+        FunctionBindgen {
+            sizes,
+            params,
+            callee: String::new(),
+            wasm_results: Vec::new(),
+            abi: String::new(),
+            code: FunctionCode::default(),
+        }
+    }
+
+    /// Assign an expression to a new local variable.
+    fn bind(&mut self, prefix: &str, expr: impl AsRef<str>) -> String {
+        let name = self.code.tmp(prefix);
+        self.code.line(format!("{name} = {}", expr.as_ref()));
+        name
+    }
+
+    /// Lower a variant-like value, where `cases` contains the Ruby class to
+    /// match against for each case and whether it carries a `value`.
+    fn lower_sum(
+        &mut self,
+        name: &str,
+        operand: &str,
+        cases: &[(String, bool)],
+        results: &mut Vec<String>,
+        result_count: usize,
+    ) {
+        let blocks = self.code.pop_blocks(cases.len());
+        let payloads = self.code.pop_payloads(cases.len());
+        let vars: Vec<_> = (0..result_count)
+            .map(|_| self.code.tmp("lowered"))
+            .collect();
+
+        let variant = self.bind("variant", operand);
+        self.code.line(format!("case {variant}"));
+        for (((case, has_value), (block, block_results)), payload) in
+            cases.iter().zip(blocks).zip(payloads)
+        {
+            self.code.line(format!("when {case}"));
+            let uses_payload = uses_ident(&block, &payload)
+                || block_results.iter().any(|r| uses_ident(r, &payload));
+            if *has_value && uses_payload {
+                self.code.line(format!("{payload} = {variant}.value"));
+            }
+            self.code.src.push_str(&block);
+            self.assign(&vars, &block_results);
+        }
+        self.code.line(format!(
+            "else\nraise ArgumentError, \"Invalid value for {name}: #{{{variant}.inspect}}\"\nend"
+        ));
+
+        results.extend(vars);
+    }
+
+    /// Lift a variant-like value with a `case` statement on its
+    /// discriminant.
+    fn lift_sum(
+        &mut self,
+        what: &str,
+        discriminant: &str,
+        cases: &[Lifted],
+        results: &mut Vec<String>,
+    ) {
+        let blocks = self.code.pop_blocks(cases.len());
+        let var = self.code.tmp("variant");
+        let discriminant = self.bind("discriminant", discriminant);
+        self.code.line(format!("case {discriminant}"));
+
+        for (i, (case, (block, block_results))) in cases.iter().zip(blocks).enumerate() {
+            self.code.line(format!("when {i}"));
+            self.code.src.push_str(&block);
+            let value = &block_results[0];
+
+            let lifted = match case {
+                Lifted::Nothing => "nil".to_string(),
+                Lifted::Case { name, has_value } if *has_value => format!("{name}.new({value})"),
+                Lifted::Case { name, .. } => format!("{name}.new"),
+                Lifted::Some { .. } => value.clone(),
+                Lifted::Ok { .. } => format!("Ok.new({value})"),
+                Lifted::Err { .. } => format!("Err.new({value})"),
+            };
+            self.code.line(format!("{var} = {lifted}"));
+        }
+
+        self.code.line(format!(
+            "else\nraise \"Invalid discriminant for {what}: #{{{discriminant}}}\"\nend"
+        ));
+        results.push(var);
+    }
+
+    fn assign(&mut self, vars: &[String], values: &[String]) {
+        for (var, value) in vars.iter().zip(values) {
+            self.code.line(format!("{var} = {value}"));
+        }
+    }
+
+    fn load(&mut self, method: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        let value = self.bind("load", format!("@env.{method}({}, {offset})", operands[0]));
+        results.push(value);
+    }
+
+    fn store(&mut self, method: &str, offset: i32, operands: &[String]) {
+        self.code.line(format!(
+            "@env.{method}({}, {offset}, {})",
+            operands[1], operands[0]
+        ));
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_> {
+    type Operand = String;
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        let abi = self.abi.clone();
+
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } => results.push(val.to_string()),
+            Instruction::ConstZero { tys } => {
+                for ty in tys.iter() {
+                    match ty {
+                        WasmType::I32 | WasmType::I64 => results.push("0".to_string()),
+                        WasmType::F32 | WasmType::F64 => results.push("0.0".to_string()),
+                    }
+                }
+            }
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands.iter()) {
+                    let expr = match cast {
+                        Bitcast::None | Bitcast::I32ToI64 => op.clone(),
+                        Bitcast::I64ToI32 => format!("{abi}.i32({op})"),
+                        Bitcast::F32ToI32 | Bitcast::F32ToI64 => format!("{abi}.f32_to_i32({op})"),
+                        Bitcast::F64ToI64 => format!("{abi}.f64_to_i64({op})"),
+                        Bitcast::I32ToF32 => format!("{abi}.i32_to_f32({op})"),
+                        Bitcast::I64ToF64 => format!("{abi}.i64_to_f64({op})"),
+                        Bitcast::I64ToF32 => format!("{abi}.i32_to_f32({abi}.i32({op}))"),
+                    };
+                    results.push(expr);
+                }
+            }
+
+            Instruction::I32Load { offset } => self.load("load_i32", *offset, operands, results),
+            Instruction::I32Load8U { offset } => self.load("load_u8", *offset, operands, results),
+            Instruction::I32Load8S { offset } => self.load("load_s8", *offset, operands, results),
+            Instruction::I32Load16U { offset } => self.load("load_u16", *offset, operands, results),
+            Instruction::I32Load16S { offset } => self.load("load_s16", *offset, operands, results),
+            Instruction::I64Load { offset } => self.load("load_i64", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("load_f32", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("load_f64", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("store_i32", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("store_i8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("store_i16", *offset, operands),
+            Instruction::I64Store { offset } => self.store("store_i64", *offset, operands),
+            Instruction::F32Store { offset } => self.store("store_f32", *offset, operands),
+            Instruction::F64Store { offset } => self.store("store_f64", *offset, operands),
+
+            Instruction::I32FromChar => results.push(format!("{}.ord", parenthesize(&operands[0]))),
+            Instruction::I32FromU32 => results.push(format!("{abi}.i32({})", operands[0])),
+            Instruction::I64FromU64 => results.push(format!("{abi}.i64({})", operands[0])),
+            Instruction::F32FromFloat32 | Instruction::F64FromFloat64 => {
+                results.push(format!("{}.to_f", parenthesize(&operands[0])))
+            }
+            Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromS32
+            | Instruction::I64FromS64
+            | Instruction::S8FromI32
+            | Instruction::S16FromI32
+            | Instruction::S32FromI32
+            | Instruction::S64FromI64
+            | Instruction::Float32FromF32
+            | Instruction::Float64FromF64 => results.push(operands[0].clone()),
+            Instruction::U8FromI32 => {
+                results.push(format!("{} & 0xFF", parenthesize(&operands[0])))
+            }
+            Instruction::U16FromI32 => {
+                results.push(format!("{} & 0xFFFF", parenthesize(&operands[0])))
+            }
+            Instruction::U32FromI32 => results.push(format!("{abi}.u32({})", operands[0])),
+            Instruction::U64FromI64 => results.push(format!("{abi}.u64({})", operands[0])),
+            Instruction::CharFromI32 => results.push(format!(
+                "{}.chr(Encoding::UTF_8)",
+                parenthesize(&operands[0])
+            )),
+            Instruction::BoolFromI32 => {
+                results.push(format!("{} != 0", parenthesize(&operands[0])))
+            }
+            Instruction::I32FromBool => {
+                results.push(format!("{} ? 1 : 0", parenthesize(&operands[0])))
+            }
+
+            Instruction::UnitLift => results.push("nil".to_string()),
+            Instruction::UnitLower => {}
+
+            // Handles for resources the library exports
+            Instruction::I32FromBorrowedHandle { ty } => {
+                let handles = handles_for(iface, *ty);
+                results.push(format!(
+                    "{handles}.insert({}.clone_ref)",
+                    parenthesize(&operands[0])
+                ));
+            }
+            Instruction::HandleOwnedFromI32 { ty } => {
+                let handles = handles_for(iface, *ty);
+                results.push(self.bind("resource", format!("{handles}.remove({})", operands[0])));
+            }
+            Instruction::I32FromOwnedHandle { .. } | Instruction::HandleBorrowedFromI32 { .. } => {
+                unreachable!("resources in imported interfaces are rejected during validation")
+            }
+
+            Instruction::ListCanonLower { .. } | Instruction::ListCanonLift { .. } => {
+                unreachable!("lists are never canonical")
+            }
+
+            Instruction::StringLower { .. } => {
+                let ptr = self.code.tmp("ptr");
+                let len = self.code.tmp("len");
+                self.code
+                    .line(format!("{ptr}, {len} = @env.lower_string({})", operands[0]));
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::ListLower { element, .. } => {
+                let (body, body_results) = self.code.pop_block();
+                assert!(body_results.is_empty());
+                let size = self.sizes.size(element);
+                let align = self.sizes.align(element);
+
+                let vec = self.bind("vec", &operands[0]);
+                let len = self.bind("len", format!("{vec}.length"));
+                let ptr = self.bind(
+                    "ptr",
+                    format!("@env.realloc(0, 0, {align}, {len} * {size})"),
+                );
+                let elem = self.code.tmp("e");
+                let i = self.code.tmp("i");
+                let base = self.code.tmp("base");
+
+                self.code
+                    .line(format!("{vec}.each_with_index do |{elem}, {i}|"));
+                if uses_ident(&body, ITER_BASE) {
+                    self.code.line(format!("{base} = {ptr} + {i} * {size}"));
+                }
+                let body = replace_ident(&replace_ident(&body, ITER_ELEM, &elem), ITER_BASE, &base);
+                self.code.src.push_str(&body);
+                self.code.line("end");
+
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::StringLift { free } => {
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", &operands[1]);
+                let s = self.bind("str", format!("@env.lift_string({ptr}, {len})"));
+                if free.is_some() {
+                    self.code.line(format!("@env.free({ptr}, {len}, 1)"));
+                }
+                results.push(s);
+            }
+            Instruction::ListLift { element, free, .. } => {
+                let (body, body_results) = self.code.pop_block();
+                let size = self.sizes.size(element);
+                let align = self.sizes.align(element);
+
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", format!("{abi}.u32({})", operands[1]));
+                let i = self.code.tmp("i");
+                let base = self.code.tmp("base");
+                let list = self.code.tmp("list");
+
+                self.code
+                    .line(format!("{list} = Array.new({len}) do |{i}|"));
+                if uses_ident(&body, ITER_BASE) {
+                    self.code.line(format!("{base} = {ptr} + {i} * {size}"));
+                }
+                self.code
+                    .src
+                    .push_str(&replace_ident(&body, ITER_BASE, &base));
+                self.code.line(&body_results[0]);
+                self.code.line("end");
+                if free.is_some() {
+                    self.code
+                        .line(format!("@env.free({ptr}, {len} * {size}, {align})"));
+                }
+
+                results.push(list);
+            }
+            Instruction::IterElem { .. } => results.push(ITER_ELEM.to_string()),
+            Instruction::IterBasePointer => results.push(ITER_BASE.to_string()),
+
+            Instruction::RecordLower { record, .. } => {
+                let rec = self.bind("record", &operands[0]);
+                for field in &record.fields {
+                    results.push(format!("{rec}.{}", field_name(&field.name)));
+                }
+            }
+            Instruction::RecordLift { record, ty, .. } => {
+                let name = named_type(iface, &Type::Id(*ty));
+                if record.fields.is_empty() {
+                    results.push(format!("{name}.new"));
+                } else {
+                    let fields: Vec<_> = record
+                        .fields
+                        .iter()
+                        .zip(operands.iter())
+                        .map(|(field, op)| format!("{}: {op}", field_name(&field.name)))
+                        .collect();
+                    results.push(format!("{name}.new({})", fields.join(", ")));
+                }
+            }
+            Instruction::TupleLower { tuple, .. } => {
+                let t = self.bind("tuple", &operands[0]);
+                for i in 0..tuple.types.len() {
+                    results.push(format!("{t}[{i}]"));
+                }
+            }
+            Instruction::TupleLift { .. } => {
+                results.push(format!("[{}]", operands.join(", ")));
+            }
+
+            Instruction::FlagsLower { flags, .. } => {
+                if flags.repr().count() == 1 {
+                    results.push(format!("{abi}.i32({})", operands[0]));
+                } else {
+                    let f = self.bind("flags", &operands[0]);
+                    results.push(format!("{abi}.i32({f} & 0xFFFF_FFFF)"));
+                    results.push(format!("{abi}.i32({f} >> 32)"));
+                }
+            }
+            Instruction::FlagsLift { flags, .. } => {
+                if flags.repr().count() == 1 {
+                    results.push(format!("{abi}.u32({})", operands[0]));
+                } else {
+                    results.push(format!(
+                        "{abi}.u32({}) | ({abi}.u32({}) << 32)",
+                        operands[0], operands[1]
+                    ));
+                }
+            }
+
+            Instruction::VariantPayloadName => results.push(self.code.payload_name()),
+            Instruction::VariantLower {
+                variant,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let type_name = type_name(name);
+                let cases: Vec<_> = variant
+                    .cases
+                    .iter()
+                    .map(|c| {
+                        (
+                            format!("{type_name}::{}", c.name.to_pascal_case()),
+                            c.ty != Type::Unit,
+                        )
+                    })
+                    .collect();
+                self.lower_sum(
+                    &type_name,
+                    &operands[0],
+                    &cases,
+                    results,
+                    result_types.len(),
+                );
+            }
+            Instruction::UnionLower {
+                union,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let type_name = type_name(name);
+                let cases: Vec<_> = union
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (format!("{type_name}::Case{i}"), c.ty != Type::Unit))
+                    .collect();
+                self.lower_sum(
+                    &type_name,
+                    &operands[0],
+                    &cases,
+                    results,
+                    result_types.len(),
+                );
+            }
+            Instruction::VariantLift { variant, name, .. } => {
+                let type_name = type_name(name);
+                let cases: Vec<_> = variant
+                    .cases
+                    .iter()
+                    .map(|c| {
+                        Lifted::case(format!("{type_name}::{}", c.name.to_pascal_case()), &c.ty)
+                    })
+                    .collect();
+                self.lift_sum(name, &operands[0], &cases, results);
+            }
+            Instruction::UnionLift { union, name, .. } => {
+                let type_name = type_name(name);
+                let cases: Vec<_> = union
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| Lifted::case(format!("{type_name}::Case{i}"), &c.ty))
+                    .collect();
+                self.lift_sum(name, &operands[0], &cases, results);
+            }
+            Instruction::EnumLower { name, .. } => {
+                let type_name = type_name(name);
+                results.push(format!(
+                    "{abi}.enum_index({type_name}::CASES, {}, \"{type_name}\")",
+                    operands[0]
+                ));
+            }
+            Instruction::EnumLift { name, .. } => {
+                results.push(format!("{}::CASES.fetch({})", type_name(name), operands[0]))
+            }
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let (some, some_results) = self.code.pop_block();
+                let (none, none_results) = self.code.pop_block();
+                let some_payload = self.code.pop_payload();
+                let _none_payload = self.code.pop_payload();
+                let vars: Vec<_> = (0..result_types.len())
+                    .map(|_| self.code.tmp("lowered"))
+                    .collect();
+
+                let option = self.bind("option", &operands[0]);
+                self.code.line(format!("if {option}.nil?"));
+                self.code.src.push_str(&none);
+                self.assign(&vars, &none_results);
+                self.code.line("else");
+                self.code.line(format!("{some_payload} = {option}"));
+                self.code.src.push_str(&some);
+                self.assign(&vars, &some_results);
+                self.code.line("end");
+
+                results.extend(vars);
+            }
+            Instruction::OptionLift { payload, .. } => {
+                let cases = Lifted::option(payload);
+                self.lift_sum("option", &operands[0], &cases, results);
+            }
+            Instruction::ExpectedLower {
+                results: result_types,
+                ..
+            } => {
+                let cases = [("Ok".to_string(), true), ("Err".to_string(), true)];
+                self.lower_sum(
+                    "expected",
+                    &operands[0],
+                    &cases,
+                    results,
+                    result_types.len(),
+                );
+            }
+            Instruction::ExpectedLift { expected, .. } => {
+                let cases = Lifted::expected(expected);
+                self.lift_sum("expected", &operands[0], &cases, results);
+            }
+
+            Instruction::CallWasm { sig, .. } => {
+                let call = format!("{}.call({})", self.callee, operands.join(", "));
+                match codegen::single_result(&sig.results) {
+                    None => self.code.line(call),
+                    Some(_) => {
+                        let ret = self.bind("ret", call);
+                        results.push(ret);
+                    }
+                }
+            }
+            Instruction::CallInterface { func, .. } => {
+                let call = format!("{}{}", self.callee, param_list(operands));
+                if func.result == Type::Unit {
+                    self.code.line(call);
+                    results.push("nil".to_string());
+                } else {
+                    let ret = self.bind("ret", call);
+                    results.push(ret);
+                }
+            }
+            Instruction::Return { .. } => {
+                let has_result = codegen::single_result(&self.wasm_results).is_some();
+                match operands.first() {
+                    Some(value) if has_result || value != "nil" => self.code.line(value),
+                    _ => self.code.line("nil"),
+                }
+            }
+
+            Instruction::Malloc { size, align, .. } => {
+                let ptr = self.bind("ptr", format!("@env.realloc(0, 0, {align}, {size})"));
+                results.push(ptr);
+            }
+            Instruction::Free { size, align, .. } => {
+                self.code
+                    .line(format!("@env.free({}, {size}, {align})", operands[0]));
+            }
+
+            Instruction::CallWasmAsyncImport { .. }
+            | Instruction::CallWasmAsyncExport { .. }
+            | Instruction::ReturnAsyncExport { .. }
+            | Instruction::ReturnAsyncImport { .. } => {
+                unreachable!("async functions are rejected during validation")
+            }
+        }
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, _size: usize, _align: usize) -> String {
+        // Only called when a guest passes a return pointer to an import or
+        // returns one from an export, and we only ever generate host code
+        unreachable!("return pointers are only needed by guest code")
+    }
+
+    fn push_block(&mut self) {
+        self.code.push_block();
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        self.code.finish_block(operands);
+    }
+
+    fn sizes(&self) -> &SizeAlign {
+        self.sizes
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, _element: &Type) -> bool {
+        false
+    }
+}
@@ -0,0 +1,292 @@
+mod library;
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Error;
+use heck::{ToPascalCase, ToSnakeCase};
+use once_cell::sync::Lazy;
+
+pub(crate) use self::library::quote;
+use self::library::RUBY_KEYWORDS;
+use crate::{
+    codegen::{self, Context, Naming, Templates},
+    types::{Abi, BindingsOptions, CommandKind, Package},
+    Files, SourceFile,
+};
+
+/// The version of the `wasmer` gem used by the generated code.
+const WASMER_RUBY_VERSION: &str = "1.0";
+
+/// Methods the `Bindings` and `Commands` classes use internally, which a
+/// library or command mustn't override.
+const PRIVATE_METHODS: &[&str] = &["environment", "initialize", "load_module", "run"];
+
+static TEMPLATES: Lazy<Templates> = Lazy::new(|| {
+    Templates::new(&[
+        ("gemspec", include_str!("gemspec.j2")),
+        ("version.rb", include_str!("version.rb.j2")),
+        ("top_level.rb", include_str!("top_level.rb.j2")),
+        ("abi.rb", include_str!("abi.rb.j2")),
+        ("bindings.rb", include_str!("bindings.rb.j2")),
+        ("commands.rb", include_str!("commands.rb.j2")),
+        ("mounts.rb", include_str!("mounts.rb.j2")),
+    ])
+});
+
+/// Generate a Ruby gem which embeds the package and uses `wasmer-ruby` to run
+/// it.
+pub fn generate_ruby(package: &Package, options: &BindingsOptions) -> Result<Files, crate::Error> {
+    crate::validation::check_abis(package.libraries(), "Ruby", &[Abi::None, Abi::Wasi])?;
+    crate::validation::check_command_kinds(package.commands(), "Ruby", &[CommandKind::Wasi])?;
+    crate::validation::deny_unsupported_interface_features_except_exported_resources(
+        package.libraries(),
+        "Ruby",
+    )?;
+    if options.deny_extra_exports {
+        crate::validation::deny_extra_exports(package.libraries())?;
+    }
+
+    let metadata = package.metadata();
+
+    // Gems are conventionally snake_case (e.g. "wasmer_pack") and live in a
+    // module with the PascalCase equivalent (e.g. "WasmerPack")
+    let gem = options
+        .name
+        .as_deref()
+        .unwrap_or_else(|| metadata.package_name.name())
+        .to_snake_case();
+    let module = gem.to_pascal_case();
+
+    let ctx = Context::for_package(package, &module, &NAMING);
+    let lib_dir = Path::new("lib").join(&gem);
+    let mut files = Files::new();
+
+    files.insert(
+        format!("{gem}.gemspec"),
+        gemspec(package, &gem, &module).map_err(crate::Error::Generate)?,
+    );
+    files.insert(
+        lib_dir.join("version.rb"),
+        TEMPLATES
+            .render(
+                "version.rb",
+                minijinja::context! { module, version => quote(&metadata.ruby_version()) },
+            )
+            .map_err(crate::Error::Generate)?,
+    );
+    files.insert(
+        Path::new("lib").join(format!("{gem}.rb")),
+        top_level(package, &gem, &ctx).map_err(crate::Error::Generate)?,
+    );
+
+    if !ctx.libraries.is_empty() {
+        files.insert(
+            lib_dir.join("abi.rb"),
+            TEMPLATES
+                .render("abi.rb", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+        files.insert(
+            lib_dir.join("bindings.rb"),
+            TEMPLATES
+                .render("bindings.rb", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+        for (lib, lib_ctx) in package.libraries().iter().zip(&ctx.libraries) {
+            let src = library::generate(lib, &module);
+            files.insert(
+                lib_dir
+                    .join("bindings")
+                    .join(format!("{}.rb", lib_ctx.ident)),
+                src.into(),
+            );
+        }
+    }
+
+    if !ctx.commands.is_empty() {
+        files.insert(
+            lib_dir.join("commands.rb"),
+            TEMPLATES
+                .render("commands.rb", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+    }
+
+    if !ctx.mounts.is_empty() {
+        files.insert(
+            lib_dir.join("mounts.rb"),
+            TEMPLATES
+                .render("mounts.rb", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+        files.insert_child_directory(lib_dir.join("volumes"), codegen::volumes(package));
+    }
+
+    files.insert_child_directory(lib_dir.join("wasm"), codegen::wasm_modules(package));
+
+    if let Some(readme) = &metadata.readme {
+        files.insert(codegen::README, readme.as_str().into());
+    }
+
+    Ok(files)
+}
+
+/// Turn a library or command name into the method used to access it.
+fn method_name(name: &str) -> String {
+    let ident = name.to_snake_case();
+    if RUBY_KEYWORDS.contains(&ident.as_str()) || PRIVATE_METHODS.contains(&ident.as_str()) {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+const NAMING: Naming = Naming {
+    quote,
+    library_ident: method_name,
+    command_ident: method_name,
+    class_name: str::to_pascal_case,
+    param_name: library::param_name,
+    env: env_entries,
+};
+
+/// Format environment variables as `key => value` hash entries.
+fn env_entries(env: &BTreeMap<String, String>) -> Vec<String> {
+    env.iter()
+        .map(|(key, value)| format!("{} => {}", quote(key), quote(value)))
+        .collect()
+}
+
+fn gemspec(package: &Package, gem: &str, module: &str) -> Result<SourceFile, Error> {
+    let metadata = package.metadata();
+    // RubyGems wants a single-line summary
+    let summary = match &metadata.description {
+        Some(description) => description
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        None => format!(
+            "Bindings to {} v{}.",
+            metadata.package_name, metadata.version
+        ),
+    };
+
+    TEMPLATES.render(
+        "gemspec",
+        minijinja::context! {
+            gem,
+            module,
+            name => quote(gem),
+            summary => quote(&summary),
+            authors => metadata.authors.iter().map(|a| quote(a)).collect::<Vec<_>>(),
+            license => metadata.license.as_deref().map(quote),
+            homepage => metadata.homepage.as_deref().map(quote),
+            repository => metadata.repository.as_deref().map(quote),
+            readme => metadata.readme.is_some(),
+            wasmer_version => WASMER_RUBY_VERSION,
+        },
+    )
+}
+
+fn top_level(package: &Package, gem: &str, ctx: &Context) -> Result<SourceFile, Error> {
+    let metadata = package.metadata();
+    let mut doc = vec![format!(
+        "Bindings to the {} package.",
+        metadata.package_name
+    )];
+
+    if let Some(description) = &metadata.description {
+        doc.push(String::new());
+        doc.extend(description.lines().map(|line| line.trim_end().to_string()));
+    }
+
+    TEMPLATES.render(
+        "top_level.rb",
+        minijinja::context! {
+            doc,
+            gem,
+            generator => crate::GENERATOR,
+            module => &ctx.module,
+            libraries => !ctx.libraries.is_empty(),
+            commands => !ctx.commands.is_empty(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Library, Metadata, Module};
+
+    const WASMER_PACK_EXPORTS: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../wasm/wasmer-pack.exports.wai"
+    ));
+
+    #[test]
+    fn exported_resources_are_supported() {
+        let metadata = Metadata::new(
+            "wasmer/wasmer-pack".parse().unwrap(),
+            "1.2.3".parse().unwrap(),
+        );
+        let exports =
+            crate::Interface::from_wit("wasmer-pack.exports.wai", WASMER_PACK_EXPORTS).unwrap();
+        let lib = Library {
+            module: Module {
+                name: "wasmer_pack_wasm.wasm".to_string(),
+                abi: Abi::None,
+                wasm: crate::exports::stub_module(&exports),
+            },
+            exports,
+            imports: Vec::new(),
+        };
+        let pkg = Package::new(metadata, vec![lib], Vec::new());
+
+        let files = generate_ruby(&pkg, &BindingsOptions::default()).unwrap();
+
+        let src = files["lib/wasmer_pack/bindings/wasmer_pack.rb"]
+            .utf8_contents()
+            .unwrap();
+        // The "abi" enum shadows the helpers, so they need to be fully qualified
+        assert!(src.contains("class Package < ::WasmerPack::Abi::Resource"));
+        assert!(src.contains("\"resource_drop_package\" => "));
+        insta::assert_display_snapshot!(src);
+    }
+
+    #[test]
+    fn imported_resources_are_rejected() {
+        let metadata = Metadata::new("wasmer/host".parse().unwrap(), "1.2.3".parse().unwrap());
+        let exports = crate::Interface::from_wit("host.exports.wai", "").unwrap();
+        let browser = crate::Interface::from_wit(
+            "browser.wai",
+            "resource window { title: func() -> string }",
+        )
+        .unwrap();
+        let lib = Library {
+            module: Module {
+                name: "host.wasm".to_string(),
+                abi: Abi::None,
+                wasm: wat::parse_str("(module)").unwrap(),
+            },
+            exports,
+            imports: vec![browser],
+        };
+        let pkg = Package::new(metadata, vec![lib], Vec::new());
+
+        let err = generate_ruby(&pkg, &BindingsOptions::default()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The \"host\" library uses resources in imported interfaces, which isn't supported by the Ruby bindings"
+        );
+    }
+
+    #[test]
+    fn library_and_command_names_are_valid_methods() {
+        assert_eq!(method_name("second-with-dashes"), "second_with_dashes");
+        assert_eq!(method_name("end"), "end_");
+        assert_eq!(method_name("run"), "run_");
+    }
+}
@@ -0,0 +1,518 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Error;
+use heck::{ToKebabCase, ToPascalCase, ToSnakeCase};
+use once_cell::sync::Lazy;
+
+use crate::{
+    codegen::{self, MountContext, Templates},
+    types::{Abi, BindingsOptions, CommandKind, Interface, Package},
+    validation::{ItemKind, Problem, ValidationError},
+    Files, Metadata, SourceFile,
+};
+
+/// The version of the `wasmer` and `wasmer-wasi` crates used by the generated
+/// crate.
+const WASMER_VERSION: &str = "3.1";
+
+/// Keywords and reserved words which can't be used as identifiers without
+/// being escaped.
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Keywords which can't be used as raw identifiers.
+const NON_RAW_KEYWORDS: &[&str] = &["crate", "self", "super"];
+
+static TEMPLATES: Lazy<Templates> = Lazy::new(|| {
+    Templates::new(&[
+        ("lib.rs", include_str!("lib.rs.j2")),
+        ("bindings.rs", include_str!("bindings.rs.j2")),
+        ("commands.rs", include_str!("commands.rs.j2")),
+        ("mounts.rs", include_str!("mounts.rs.j2")),
+    ])
+});
+
+/// Generate a Rust crate which embeds the package and uses `wasmer` to run
+/// it.
+pub fn generate_rust(package: &Package, options: &BindingsOptions) -> Result<Files, crate::Error> {
+    crate::validation::check_abis(package.libraries(), "Rust", &[Abi::None, Abi::Wasi])?;
+    crate::validation::check_command_kinds(package.commands(), "Rust", &[CommandKind::Wasi])?;
+    if options.deny_extra_exports {
+        crate::validation::deny_extra_exports(package.libraries())?;
+    }
+    check_library_idents(package)?;
+
+    let metadata = package.metadata();
+
+    // Crate names are conventionally kebab-case
+    let crate_name = match &options.name {
+        Some(name) => name.to_kebab_case(),
+        None => metadata.package_name.name().to_kebab_case(),
+    };
+
+    let mut files = Files::new();
+
+    let ctx = Context::for_package(package);
+
+    if !ctx.libraries.is_empty() {
+        files.insert(
+            Path::new("src").join("bindings.rs"),
+            TEMPLATES
+                .render("bindings.rs", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+        files.insert_child_directory("wai", interface_definitions(package));
+    }
+
+    if !ctx.commands.is_empty() {
+        files.insert(
+            Path::new("src").join("commands.rs"),
+            TEMPLATES
+                .render("commands.rs", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+    }
+
+    if !ctx.mounts.is_empty() {
+        files.insert(
+            Path::new("src").join("mounts.rs"),
+            TEMPLATES
+                .render("mounts.rs", &ctx)
+                .map_err(crate::Error::Generate)?,
+        );
+        files.insert_child_directory(Path::new("volumes"), codegen::volumes(package));
+    }
+
+    files.insert_child_directory("wasm", codegen::wasm_modules(package));
+
+    files.insert(
+        Path::new("src").join("lib.rs"),
+        lib_rs(package, &ctx).map_err(crate::Error::Generate)?,
+    );
+
+    files.insert(
+        "Cargo.toml",
+        generate_cargo_toml(package, &crate_name).map_err(crate::Error::Generate)?,
+    );
+
+    if let Some(readme) = &metadata.readme {
+        files.insert(codegen::README, readme.as_str().into());
+    }
+
+    Ok(files)
+}
+
+/// The `wai-bindgen-wasmer` macros generate a module named after each
+/// interface without escaping it, so libraries and imports whose names are
+/// Rust keywords can't be supported.
+fn check_library_idents(package: &Package) -> Result<(), ValidationError> {
+    let mut problems = Vec::new();
+
+    for lib in package.libraries() {
+        let name = lib.interface_name();
+        let ident = name.to_snake_case();
+
+        if RUST_KEYWORDS.contains(&ident.as_str()) {
+            problems.push(Problem::InvalidIdentifier {
+                kind: ItemKind::Library,
+                name: name.to_string(),
+                reason: format!("\"{ident}\" is a reserved word in Rust"),
+            });
+        }
+
+        for interface in &lib.imports {
+            let ident = interface.name().to_snake_case();
+
+            if RUST_KEYWORDS.contains(&ident.as_str()) {
+                problems.push(Problem::InvalidIdentifier {
+                    kind: ItemKind::Library,
+                    name: name.to_string(),
+                    reason: format!(
+                        "the \"{}\" import would be turned into \"{ident}\", which is a reserved word in Rust",
+                        interface.name()
+                    ),
+                });
+            }
+        }
+    }
+
+    ValidationError::from_problems(problems)
+}
+
+/// Turn a name into a Rust identifier, escaping it if it is a keyword.
+fn rust_ident(name: &str) -> String {
+    let ident = name.to_snake_case();
+
+    if NON_RAW_KEYWORDS.contains(&ident.as_str()) {
+        format!("{ident}_")
+    } else if RUST_KEYWORDS.contains(&ident.as_str()) {
+        format!("r#{ident}")
+    } else {
+        ident
+    }
+}
+
+/// Quote a string so it can be used as a Rust string literal.
+pub(crate) fn literal(s: &str) -> String {
+    format!("{s:?}")
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Context {
+    commands: Vec<CommandContext>,
+    libraries: Vec<LibraryContext>,
+    mounts: Vec<MountContext>,
+    volume_files: Vec<VolumeFileContext>,
+    /// Do any libraries need WASI?
+    wasi: bool,
+    /// Are there any libraries which don't use WASI?
+    plain: bool,
+}
+
+impl Context {
+    fn for_package(pkg: &Package) -> Self {
+        let commands = pkg.commands().iter().map(CommandContext::from).collect();
+        let libraries: Vec<_> = pkg.libraries().iter().map(LibraryContext::from).collect();
+
+        let mounts = codegen::mounts(pkg, literal);
+        let volume_files = if mounts.is_empty() {
+            Vec::new()
+        } else {
+            VolumeFileContext::for_package(pkg)
+        };
+
+        Context {
+            commands,
+            wasi: libraries.iter().any(|lib| lib.wasi),
+            plain: libraries.iter().any(|lib| !lib.wasi),
+            libraries,
+            mounts,
+            volume_files,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct VolumeFileContext {
+    /// Where the file is extracted to at runtime, as a string literal.
+    path: String,
+    /// Where the file is saved, relative to the crate root, as a string
+    /// literal.
+    include_path: String,
+}
+
+impl VolumeFileContext {
+    fn for_package(pkg: &Package) -> Vec<Self> {
+        codegen::volume_files(pkg)
+            .into_iter()
+            .map(|(path, _)| VolumeFileContext {
+                include_path: literal(&format!("/volumes/{path}")),
+                path: literal(&path),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct LibraryContext {
+    ident: String,
+    interface_name: String,
+    /// The name of the struct generated by `wai-bindgen` (i.e. `WasmerPack`).
+    class_name: String,
+    /// The name passed to the library as `argv[0]`, as a string literal.
+    program_name: String,
+    /// Where the library's WebAssembly module is saved, relative to the
+    /// crate root, as a string literal.
+    wasm_path: String,
+    /// The exports interface definition, relative to the crate root, as a
+    /// string literal.
+    exports_path: String,
+    wasi: bool,
+    imports: Vec<InterfaceContext>,
+}
+
+impl From<&crate::Library> for LibraryContext {
+    fn from(lib: &crate::Library) -> Self {
+        let ident = lib.interface_name().to_snake_case();
+
+        LibraryContext {
+            program_name: literal(&ident),
+            wasm_path: literal(&format!(
+                "/wasm/{}",
+                crate::files::wasm_filename(&lib.module.wasm)
+            )),
+            exports_path: literal(&interface_path(&ident, &lib.exports)),
+            interface_name: lib.interface_name().to_string(),
+            class_name: lib.class_name(),
+            wasi: lib.requires_wasi(),
+            imports: lib
+                .imports
+                .iter()
+                .map(|interface| InterfaceContext {
+                    ident: interface.name().to_snake_case(),
+                    class_name: interface.name().to_pascal_case(),
+                    path: literal(&interface_path(&ident, interface)),
+                })
+                .collect(),
+            ident,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct InterfaceContext {
+    /// The name of the module generated by `wai-bindgen` (i.e. `browser`).
+    ident: String,
+    /// The name of the trait generated by `wai-bindgen` (i.e. `Browser`).
+    class_name: String,
+    /// The interface definition, relative to the crate root, as a string
+    /// literal.
+    path: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct CommandContext {
+    name: String,
+    ident: String,
+    /// Where the command's WebAssembly module is saved, relative to the
+    /// crate root, as a string literal.
+    wasm_path: String,
+    /// The name passed to the command as `argv[0]`, as a string literal.
+    program_name: String,
+    /// The command's default arguments, as string literals.
+    main_args: Vec<String>,
+    /// The command's default environment variables, as string literals.
+    env: Vec<(String, String)>,
+}
+
+impl From<&crate::Command> for CommandContext {
+    fn from(cmd: &crate::Command) -> Self {
+        CommandContext {
+            name: cmd.name.clone(),
+            ident: rust_ident(&cmd.name),
+            wasm_path: literal(&format!("/wasm/{}", crate::files::wasm_filename(&cmd.wasm))),
+            program_name: literal(cmd.program_name()),
+            main_args: cmd.main_args.iter().map(|arg| literal(arg)).collect(),
+            env: cmd
+                .env
+                .iter()
+                .map(|(key, value)| (literal(key), literal(value)))
+                .collect(),
+        }
+    }
+}
+
+/// Where an interface definition used by a library is saved, relative to the
+/// crate root.
+fn interface_path(library_ident: &str, interface: &Interface) -> String {
+    format!("wai/{library_ident}/{}", interface.source.filename)
+}
+
+/// Save the interface definitions read by the `wai-bindgen-wasmer` macros,
+/// including any files they pull in with `use` statements.
+fn interface_definitions(package: &Package) -> Files {
+    let mut files = Files::new();
+
+    for lib in package.libraries() {
+        let ident = lib.interface_name().to_snake_case();
+
+        for interface in std::iter::once(&lib.exports).chain(&lib.imports) {
+            let source = &interface.source;
+            files.insert(
+                Path::new(&ident).join(&source.filename),
+                source.contents.as_str().into(),
+            );
+
+            for (name, contents) in &source.dependencies {
+                files.insert(
+                    Path::new(&ident).join(name).with_extension("wai"),
+                    contents.as_str().into(),
+                );
+            }
+        }
+    }
+
+    files
+}
+
+fn lib_rs(package: &Package, ctx: &Context) -> Result<SourceFile, Error> {
+    let metadata = package.metadata();
+    let mut doc = vec![format!(
+        "Bindings to the {} package.",
+        metadata.package_name
+    )];
+
+    if let Some(description) = &metadata.description {
+        doc.push(String::new());
+        doc.extend(description.lines().map(|line| line.trim_end().to_string()));
+    }
+
+    let ctx = minijinja::context! {
+        doc,
+        generator => crate::GENERATOR,
+        libraries => !ctx.libraries.is_empty(),
+        commands => !ctx.commands.is_empty(),
+        mounts => !ctx.mounts.is_empty(),
+    };
+
+    TEMPLATES.render("lib.rs", ctx)
+}
+
+fn generate_cargo_toml(package: &Package, crate_name: &str) -> Result<SourceFile, Error> {
+    let metadata = package.metadata();
+    let Metadata {
+        description,
+        license,
+        homepage,
+        repository,
+        readme,
+        authors,
+        ..
+    } = metadata;
+
+    let mut dependencies = BTreeMap::new();
+    dependencies.insert("anyhow".to_string(), "1".to_string());
+    dependencies.insert("wasmer".to_string(), WASMER_VERSION.to_string());
+    if package.requires_wasi() {
+        dependencies.insert("wasmer-wasi".to_string(), WASMER_VERSION.to_string());
+    }
+    if !package.libraries().is_empty() {
+        // The bindings are generated at compile time, so make sure the macros
+        // understand the same WAI dialect we do
+        dependencies.insert(
+            "wai-bindgen-wasmer".to_string(),
+            crate::versions::WAI_BINDGEN_WASMER_VERSION.to_string(),
+        );
+    }
+    // Note: WAPM dependencies aren't added here because they don't map onto
+    // crates on crates.io
+
+    let manifest = CargoToml {
+        package: CargoPackage {
+            name: crate_name,
+            version: metadata.version.to_string(),
+            edition: "2021",
+            description: description.as_deref(),
+            license: license.as_deref(),
+            homepage: homepage.as_deref(),
+            repository: repository.as_deref(),
+            readme: readme.as_ref().map(|_| codegen::README),
+            authors,
+        },
+        dependencies,
+    };
+
+    let serialized = toml::to_string(&manifest)?;
+
+    Ok(serialized.into())
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+struct CargoToml<'a> {
+    package: CargoPackage<'a>,
+    dependencies: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+struct CargoPackage<'a> {
+    name: &'a str,
+    version: String,
+    edition: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    homepage: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repository: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    readme: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: &'a Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Command, Dependency, Library, Module};
+
+    #[test]
+    fn cargo_toml_with_extended_metadata() {
+        let metadata = Metadata::new(
+            "wasmer/wasmer-pack".parse().unwrap(),
+            "1.2.3".parse().unwrap(),
+        )
+        .with_description("A code generator")
+        .with_license("MIT")
+        .with_homepage("https://wasmer.io/")
+        .with_repository("https://github.com/wasmerio/wasmer-pack")
+        .with_readme("# Wasmer Pack")
+        .with_authors(["Wasmer Engineering Team <engineering@wasmer.io>"])
+        .with_dependencies([Dependency::new(
+            "wasmer/coreutils".parse().unwrap(),
+            "^1.2".parse().unwrap(),
+        )]);
+        let package = Package::new(metadata, Vec::new(), Vec::new());
+
+        let got = generate_cargo_toml(&package, "wasmer-pack").unwrap();
+
+        insta::assert_display_snapshot!(got.utf8_contents().unwrap());
+    }
+
+    #[test]
+    fn command_names_which_are_keywords_are_escaped() {
+        let metadata = Metadata::new("wasmer/hello".parse().unwrap(), "1.2.3".parse().unwrap());
+        let commands = vec![Command::new("type", []), Command::new("self", [])];
+        let pkg = Package::new(metadata, Vec::new(), commands);
+
+        let files = generate_rust(&pkg, &BindingsOptions::default()).unwrap();
+
+        let commands_rs = files["src/commands.rs"].utf8_contents().unwrap();
+        assert!(commands_rs.contains("pub fn r#type("));
+        assert!(commands_rs.contains("pub fn self_("));
+    }
+
+    #[test]
+    fn libraries_named_after_keywords_are_rejected() {
+        let metadata = Metadata::new("wasmer/hello".parse().unwrap(), "1.2.3".parse().unwrap());
+        let lib = Library {
+            module: Module {
+                name: "impl.wasm".to_string(),
+                abi: Abi::None,
+                wasm: wat::parse_str("(module)").unwrap(),
+            },
+            exports: crate::Interface::from_wit("impl.exports.wai", "").unwrap(),
+            imports: vec![crate::Interface::from_wit("mod.wai", "").unwrap()],
+        };
+        let pkg = Package::new(metadata, vec![lib], Vec::new());
+
+        let err = generate_rust(&pkg, &BindingsOptions::default()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Found 2 problems with the package\n\
+             - \"impl\" is not a valid library name: \"impl\" is a reserved word in Rust\n\
+             - \"impl\" is not a valid library name: the \"mod\" import would be turned into \"mod\", which is a reserved word in Rust"
+        );
+    }
+
+    #[test]
+    fn wcgi_commands_are_rejected() {
+        let metadata = Metadata::new("wasmer/hello".parse().unwrap(), "1.2.3".parse().unwrap());
+        let serve = Command::new("serve", []).with_kind(CommandKind::Wcgi);
+        let pkg = Package::new(metadata, Vec::new(), vec![serve]);
+
+        let err = generate_rust(&pkg, &BindingsOptions::default()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The \"serve\" command is a wcgi command, which isn't supported by the Rust bindings"
+        );
+    }
+}
@@ -1,18 +1,27 @@
 use std::{
+    collections::BTreeMap,
     fmt::{self, Display, Formatter},
-    path::Path,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
 use anyhow::{Context, Error};
 use heck::{ToPascalCase, ToSnakeCase};
-use webc::Container;
+use semver::{BuildMetadata, Comparator, Op, Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use webc::{metadata::UrlOrManifest, Container};
+
+use crate::{
+    resolver::NoDependencies, Files, Function, Manifest, Resolver, Resource, TypeDefinition,
+    ValidationError, Warning,
+};
 
 #[derive(Debug, Clone)]
 pub struct Package {
     metadata: Metadata,
     libraries: Vec<Library>,
     commands: Vec<Command>,
+    mounts: Vec<Mount>,
 }
 
 impl Package {
@@ -20,21 +29,109 @@ impl Package {
     ///
     /// # Panics
     ///
-    /// This assumes all libraries have a unique [`Library::interface_name()`].
+    /// This will panic if two libraries have the same
+    /// [`Library::interface_name()`] or two commands have the same name.
+    ///
+    /// No other validation is done, so use [`Package::try_new()`] if you also
+    /// want to check that names are valid identifiers and that each library's
+    /// module matches its interface.
     pub fn new(metadata: Metadata, libraries: Vec<Library>, commands: Vec<Command>) -> Self {
-        assert_unique_names("library", libraries.iter().map(|lib| lib.interface_name()));
-        assert_unique_names("command", commands.iter().map(|cmd| cmd.name.as_str()));
+        if let Err(e) = crate::validation::check_unique_names(&libraries, &commands) {
+            panic!("{e}");
+        }
 
         Package {
             metadata,
             libraries,
             commands,
+            mounts: Vec::new(),
         }
     }
 
+    /// Create a new [`Package`], making sure bindings can be generated for
+    /// all of its libraries and commands.
+    ///
+    /// Every problem with the package is reported, rather than just the first.
+    pub fn try_new(
+        metadata: Metadata,
+        libraries: Vec<Library>,
+        commands: Vec<Command>,
+    ) -> Result<Self, ValidationError> {
+        crate::validation::validate(&libraries, &commands)?;
+
+        Ok(Package {
+            metadata,
+            libraries,
+            commands,
+            mounts: Vec::new(),
+        })
+    }
+
     /// Load a [`Package`] from a WEBC binary.
-    pub fn from_webc(webc: &Container) -> Result<Self, Error> {
-        crate::pirita::load_webc_binary(webc)
+    ///
+    /// See [`Package::from_webc_with_warnings()`] if you want to know about
+    /// anything that was skipped.
+    pub fn from_webc(webc: &Container) -> Result<Self, crate::Error> {
+        let (pkg, _warnings) = Package::from_webc_with_warnings(webc)?;
+        Ok(pkg)
+    }
+
+    /// Load a [`Package`] from a WEBC binary, returning [`Warning`]s for
+    /// any commands, atoms, or annotations that were skipped.
+    pub fn from_webc_with_warnings(webc: &Container) -> Result<(Self, Vec<Warning>), crate::Error> {
+        crate::pirita::load_webc_binary(webc, &NoDependencies)
+    }
+
+    /// Load a [`Package`] from a WEBC binary, using a [`Resolver`] to find
+    /// any atoms its commands use from their dependencies.
+    ///
+    /// Commands whose dependencies can't be found are skipped with a
+    /// [`Warning`].
+    pub fn from_webc_with_resolver(
+        webc: &Container,
+        resolver: &dyn Resolver,
+    ) -> Result<(Self, Vec<Warning>), crate::Error> {
+        crate::pirita::load_webc_binary(webc, resolver)
+    }
+
+    /// Find a package (e.g. `wasmer/python@^3.12`) using a [`Resolver`] and
+    /// load it, along with any atoms its commands use from their
+    /// dependencies.
+    ///
+    /// Pair this with a [`crate::WebcDirectory`] to load packages from the
+    /// local Wasmer cache or a registry mirror without network access.
+    pub fn resolve(
+        package: &Dependency,
+        resolver: &dyn Resolver,
+    ) -> Result<(Self, Vec<Warning>), crate::Error> {
+        let webc = resolver.resolve(package)?.ok_or_else(|| {
+            crate::Error::Load(anyhow::anyhow!(
+                "Unable to find a copy of {}@{}",
+                package.package_name,
+                package.version,
+            ))
+        })?;
+
+        Package::from_webc_with_resolver(&webc, resolver)
+    }
+
+    /// Load a [`Package`] from a JSON [`Manifest`].
+    ///
+    /// Any relative paths in the manifest are resolved relative to
+    /// `base_dir`.
+    pub fn from_json_manifest(json: &str, base_dir: &Path) -> Result<Self, crate::Error> {
+        let manifest: Manifest = serde_json::from_str(json)
+            .context("Unable to parse the manifest")
+            .map_err(crate::Error::Load)?;
+
+        manifest.into_package(base_dir)
+    }
+
+    /// Serialize this [`Package`] as a JSON [`Manifest`], embedding all
+    /// WebAssembly modules and interface definitions.
+    pub fn to_json_manifest(&self) -> String {
+        let manifest = Manifest::from_package(self);
+        serde_json::to_string_pretty(&manifest).expect("Serializing a manifest is infallible")
     }
 
     pub fn metadata(&self) -> &Metadata {
@@ -52,15 +149,38 @@ impl Package {
     pub fn commands(&self) -> &[Command] {
         &self.commands
     }
+
+    /// Directories which are mounted into the WASI filesystem for this
+    /// package's commands and libraries.
+    pub fn mounts(&self) -> &[Mount] {
+        &self.mounts
+    }
+
+    /// Set the directories which are bundled with this package (see
+    /// [`Package::mounts()`]).
+    pub fn with_mounts(self, mounts: impl IntoIterator<Item = Mount>) -> Self {
+        Package {
+            mounts: mounts.into_iter().collect(),
+            ..self
+        }
+    }
 }
 
-fn assert_unique_names<'a>(kind: &str, names: impl IntoIterator<Item = &'a str>) {
-    let mut already_seen: Vec<&str> = Vec::new();
+/// A directory bundled with a [`Package`] which is mounted into the WASI
+/// filesystem (e.g. a Python interpreter's standard library).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    /// Where the directory is mounted inside the guest (e.g. `/lib`).
+    pub guest_path: String,
+    /// The directory's contents, relative to the mount point.
+    pub files: Files,
+}
 
-    for name in names {
-        match already_seen.binary_search(&name) {
-            Ok(_) => panic!("Duplicate {kind} name: {name}"),
-            Err(index) => already_seen.insert(index, name),
+impl Mount {
+    pub fn new(guest_path: impl Into<String>, files: Files) -> Self {
+        Mount {
+            guest_path: guest_path.into(),
+            files,
         }
     }
 }
@@ -232,21 +352,87 @@ pub struct Metadata {
     /// The package's name.
     pub package_name: PackageName,
     /// A semver-compliant version number.
-    pub version: String,
+    pub version: Version,
     /// Extended information about the package.
     pub description: Option<String>,
+    /// A [SPDX license expression](https://spdx.org/licenses/) (e.g. `MIT`).
+    pub license: Option<String>,
+    /// The URL for the package's homepage.
+    pub homepage: Option<String>,
+    /// The URL for the package's source code repository.
+    pub repository: Option<String>,
+    /// The contents of the package's README file, in Markdown.
+    pub readme: Option<String>,
+    /// The people who wrote this package (e.g. `Jane Doe <jane@example.com>`).
+    pub authors: Vec<String>,
+    /// Other packages this package depends on.
+    pub dependencies: Vec<Dependency>,
 }
 
 impl Metadata {
     /// Create a new [`Metadata`] object with all required fields.
-    pub fn new(package_name: PackageName, version: impl Into<String>) -> Self {
+    pub fn new(package_name: PackageName, version: Version) -> Self {
         Metadata {
             package_name,
-            version: version.into(),
+            version,
             description: None,
+            license: None,
+            homepage: None,
+            repository: None,
+            readme: None,
+            authors: Vec::new(),
+            dependencies: Vec::new(),
         }
     }
 
+    /// Get the NPM equivalent of [`Metadata::version`].
+    ///
+    /// NPM uses semver natively, so this is just the canonical form of the
+    /// version number.
+    pub fn javascript_version(&self) -> String {
+        self.version.to_string()
+    }
+
+    /// Get the PyPI equivalent of [`Metadata::version`].
+    ///
+    /// Python packages are versioned using
+    /// [PEP 440](https://peps.python.org/pep-0440/), so pre-release
+    /// identifiers are translated to their PEP 440 equivalent (e.g.
+    /// `1.0.0-beta.1` becomes `1.0.0b1`).
+    ///
+    /// PyPI doesn't accept local version labels, so build metadata is dropped
+    /// and pre-release identifiers which don't have a PEP 440 equivalent are
+    /// treated as a development release (e.g. `1.0.0-nightly.3` becomes
+    /// `1.0.0.dev3`).
+    pub fn python_version(&self) -> String {
+        pep440_version(&self.version)
+    }
+
+    /// Get the RubyGems equivalent of [`Metadata::version`].
+    ///
+    /// RubyGems treats any version containing a letter as a pre-release, so
+    /// semver pre-release identifiers are appended after a `pre` segment (e.g.
+    /// `1.0.0-beta.1` becomes `1.0.0.pre.beta.1`, the same as `gem` would
+    /// normalize it). Gem versions can't hold build metadata, so it is
+    /// dropped.
+    pub fn ruby_version(&self) -> String {
+        let Version {
+            major,
+            minor,
+            patch,
+            pre,
+            ..
+        } = &self.version;
+
+        let mut version = format!("{major}.{minor}.{patch}");
+        if !pre.is_empty() {
+            version.push_str(".pre.");
+            version.push_str(&pre.as_str().replace('-', "."));
+        }
+
+        version
+    }
+
     /// Set the [`Metadata::description`] field.
     pub fn with_description(self, description: impl Into<String>) -> Self {
         Metadata {
@@ -254,6 +440,259 @@ impl Metadata {
             ..self
         }
     }
+
+    /// Set the [`Metadata::license`] field.
+    pub fn with_license(self, license: impl Into<String>) -> Self {
+        Metadata {
+            license: Some(license.into()),
+            ..self
+        }
+    }
+
+    /// Set the [`Metadata::homepage`] field.
+    pub fn with_homepage(self, homepage: impl Into<String>) -> Self {
+        Metadata {
+            homepage: Some(homepage.into()),
+            ..self
+        }
+    }
+
+    /// Set the [`Metadata::repository`] field.
+    pub fn with_repository(self, repository: impl Into<String>) -> Self {
+        Metadata {
+            repository: Some(repository.into()),
+            ..self
+        }
+    }
+
+    /// Set the [`Metadata::readme`] field.
+    pub fn with_readme(self, readme: impl Into<String>) -> Self {
+        Metadata {
+            readme: Some(readme.into()),
+            ..self
+        }
+    }
+
+    /// Set the [`Metadata::authors`] field.
+    pub fn with_authors<I>(self, authors: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Metadata {
+            authors: authors.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Set the [`Metadata::dependencies`] field.
+    pub fn with_dependencies(self, dependencies: impl IntoIterator<Item = Dependency>) -> Self {
+        Metadata {
+            dependencies: dependencies.into_iter().collect(),
+            ..self
+        }
+    }
+}
+
+/// A dependency on another package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Dependency {
+    /// The name the package uses when referring to this dependency.
+    pub alias: String,
+    /// The dependency's fully qualified package name (e.g. `wasmer/python`).
+    pub package_name: PackageName,
+    /// Which versions of the dependency are acceptable.
+    pub version: VersionReq,
+}
+
+impl Dependency {
+    /// Create a new [`Dependency`], using the package name as its alias.
+    pub fn new(package_name: PackageName, version: VersionReq) -> Self {
+        Dependency {
+            alias: package_name.to_string(),
+            package_name,
+            version,
+        }
+    }
+
+    /// Parse an entry from a WEBC file's
+    /// [use map][webc::metadata::Manifest::use_map].
+    ///
+    /// Only registry dependencies (e.g. `wasmer/python@^3.12`) are supported.
+    pub(crate) fn from_use_map(alias: &str, specifier: &UrlOrManifest) -> Result<Self, Error> {
+        let raw = match specifier {
+            UrlOrManifest::RegistryDependentUrl(raw) => raw,
+            UrlOrManifest::Url(url) => {
+                anyhow::bail!("dependencies referenced by URL (\"{url}\") aren't supported")
+            }
+            UrlOrManifest::Manifest(_) => {
+                anyhow::bail!("vendored dependencies aren't supported")
+            }
+        };
+
+        let Dependency {
+            package_name,
+            version,
+            ..
+        } = raw.parse()?;
+
+        Ok(Dependency {
+            alias: alias.to_string(),
+            package_name,
+            version,
+        })
+    }
+
+    /// Get the NPM equivalent of [`Dependency::version`] (e.g. `^1.2.3`).
+    pub fn javascript_version(&self) -> String {
+        if self.version.comparators.is_empty() {
+            return "*".to_string();
+        }
+
+        let comparators: Vec<_> = self
+            .version
+            .comparators
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+
+        comparators.join(" ")
+    }
+
+    /// Get the [PEP 508](https://peps.python.org/pep-0508/) requirement for
+    /// this dependency (e.g. `python>=3.12.0,<4.0.0`).
+    ///
+    /// Python doesn't have caret or tilde requirements, so they are expanded
+    /// into their equivalent version ranges.
+    pub fn python_requirement(&self) -> String {
+        let specifiers: Vec<_> = self
+            .version
+            .comparators
+            .iter()
+            .flat_map(pep440_specifiers)
+            .collect();
+
+        format!(
+            "{}{}",
+            self.package_name.python_name(),
+            specifiers.join(",")
+        )
+    }
+}
+
+impl FromStr for Dependency {
+    type Err = Error;
+
+    /// Parse a package specifier like `wasmer/python@^3.12`, treating a
+    /// missing version as `*`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, version) = s.split_once('@').unwrap_or((s, "*"));
+        let package_name = PackageName::parse(name)?;
+        let version = version
+            .parse()
+            .with_context(|| format!("\"{version}\" is not a valid version requirement"))?;
+
+        Ok(Dependency::new(package_name, version))
+    }
+}
+
+fn pep440_version(version: &Version) -> String {
+    // PyPI rejects local version labels, so build metadata is dropped
+    let Version {
+        major,
+        minor,
+        patch,
+        pre,
+        ..
+    } = version;
+
+    let mut pep440 = format!("{major}.{minor}.{patch}");
+
+    if !pre.is_empty() {
+        let mut identifiers = pre.as_str().split('.').peekable();
+        let first = identifiers.next().unwrap_or_default();
+
+        // Pre-release identifiers may be written as "beta.2" or "beta2"
+        let (tag, number) = first.split_at(
+            first
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(first.len()),
+        );
+        let number = if number.is_empty() {
+            identifiers
+                .next_if(|id| id.chars().all(|c| c.is_ascii_digit()))
+                .unwrap_or("0")
+        } else {
+            number
+        };
+
+        // Anything without a PEP 440 equivalent is treated as a development
+        // release, and any trailing identifiers are discarded
+        let segment = match tag.to_ascii_lowercase().as_str() {
+            "alpha" | "a" => "a",
+            "beta" | "b" => "b",
+            "rc" | "c" | "pre" | "preview" => "rc",
+            _ => ".dev",
+        };
+        let number = number.parse::<u64>().unwrap_or(0);
+        pep440.push_str(&format!("{segment}{number}"));
+    }
+
+    pep440
+}
+
+/// Translate a semver [`Comparator`] into the equivalent
+/// [PEP 440 version specifiers](https://peps.python.org/pep-0440/#version-specifiers).
+fn pep440_specifiers(comparator: &Comparator) -> Vec<String> {
+    let Comparator {
+        op,
+        major,
+        minor,
+        patch,
+        pre,
+    } = comparator;
+    let major = *major;
+    let version = |minor: u64, patch: u64| {
+        pep440_version(&Version {
+            major,
+            minor,
+            patch,
+            pre: pre.clone(),
+            build: BuildMetadata::EMPTY,
+        })
+    };
+    let lower = version(minor.unwrap_or(0), patch.unwrap_or(0));
+
+    match (op, *minor, *patch) {
+        (Op::Exact | Op::Wildcard, Some(minor), Some(patch)) => {
+            vec![format!("=={}", version(minor, patch))]
+        }
+        (Op::Exact | Op::Wildcard, Some(minor), None) => vec![format!("=={major}.{minor}.*")],
+        (Op::Exact | Op::Wildcard, None, _) => vec![format!("=={major}.*")],
+        (Op::Greater, Some(minor), Some(patch)) => vec![format!(">{}", version(minor, patch))],
+        (Op::Greater, Some(minor), None) => vec![format!(">={major}.{}.0", minor + 1)],
+        (Op::Greater, None, _) => vec![format!(">={}.0.0", major + 1)],
+        (Op::GreaterEq, ..) => vec![format!(">={lower}")],
+        (Op::Less, ..) => vec![format!("<{lower}")],
+        (Op::LessEq, Some(minor), Some(patch)) => vec![format!("<={}", version(minor, patch))],
+        (Op::LessEq, Some(minor), None) => vec![format!("<{major}.{}.0", minor + 1)],
+        (Op::LessEq, None, _) => vec![format!("<{}.0.0", major + 1)],
+        (Op::Tilde, Some(minor), _) => {
+            vec![format!(">={lower}"), format!("<{major}.{}.0", minor + 1)]
+        }
+        (Op::Tilde, None, _) => vec![format!(">={lower}"), format!("<{}.0.0", major + 1)],
+        (Op::Caret, minor, patch) => {
+            let upper = match (major, minor, patch) {
+                (0, Some(0), Some(patch)) => format!("0.0.{}", patch + 1),
+                (0, Some(minor), _) => format!("0.{}.0", minor + 1),
+                (major, _, _) => format!("{}.0.0", major + 1),
+            };
+            vec![format!(">={lower}"), format!("<{upper}")]
+        }
+        // Note: semver::Op is non-exhaustive
+        _ => Vec::new(),
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -293,7 +732,7 @@ impl Library {
     }
 
     pub fn requires_wasi(&self) -> bool {
-        matches!(self.module.abi, Abi::Wasi)
+        self.module.abi.requires_wasi()
     }
 }
 
@@ -316,28 +755,108 @@ impl Module {
     /// The [`Module::from_path()`] constructor explicitly **doesn't** perform
     /// any validation on the module's file. It is up to the caller to ensure
     /// they pass in the correct [`Abi`].
-    pub fn from_path(path: impl AsRef<Path>, abi: Abi) -> Result<Self, Error> {
+    pub fn from_path(path: impl AsRef<Path>, abi: Abi) -> Result<Self, crate::Error> {
         let path = path.as_ref();
         let name = path
             .file_name()
-            .context("Empty filename")?
+            .context("Empty filename")
+            .map_err(crate::Error::Load)?
             .to_string_lossy()
             .into_owned();
 
         let wasm = std::fs::read(path)
-            .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+            .with_context(|| format!("Unable to read \"{}\"", path.display()))
+            .map_err(crate::Error::Load)?;
 
         Ok(Module { name, abi, wasm })
     }
+
+    /// Load a [`Module`] from a file on disk, using [`Abi::detect()`] to
+    /// figure out which ABI it uses.
+    pub fn from_path_detect(path: impl AsRef<Path>) -> Result<Self, crate::Error> {
+        let mut module = Module::from_path(path, Abi::None)?;
+        module.abi = Abi::detect(&module.wasm);
+        Ok(module)
+    }
 }
 
 /// The [*Application Binary Interface*][abi] used by a [`Module`].
 ///
 /// [abi]: https://www.webassembly.guide/webassembly-guide/webassembly/wasm-abis
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
 pub enum Abi {
     None,
     Wasi,
+    /// [WASIX](https://wasix.org/), Wasmer's superset of WASI.
+    Wasix,
+    /// A module compiled by [Emscripten](https://emscripten.org/), which
+    /// expects to be run by its JavaScript glue code.
+    Emscripten,
+}
+
+impl Abi {
+    /// Guess which ABI a WebAssembly module uses by looking at its imports
+    /// and exports.
+    ///
+    /// Modules that can't be parsed are assumed to use [`Abi::None`].
+    pub fn detect(wasm: &[u8]) -> Abi {
+        let mut imported_modules = Vec::new();
+        let mut names = Vec::new();
+
+        for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+            match payload {
+                Ok(wasmparser::Payload::ImportSection(imports)) => {
+                    for import in imports.into_iter().flatten() {
+                        imported_modules.push(import.module);
+                        names.push(import.name);
+                    }
+                }
+                Ok(wasmparser::Payload::ExportSection(exports)) => {
+                    names.extend(exports.into_iter().flatten().map(|export| export.name));
+                }
+                _ => {}
+            }
+        }
+
+        // Note: WASIX and Emscripten modules often import from the WASI
+        // namespaces too, so they need to be checked first.
+        if imported_modules
+            .iter()
+            .any(|m| matches!(*m, "wasix_32v1" | "wasix_64v1"))
+        {
+            Abi::Wasix
+        } else if names
+            .iter()
+            .any(|name| name.trim_start_matches('_').starts_with("emscripten_"))
+        {
+            Abi::Emscripten
+        } else if imported_modules
+            .iter()
+            .any(|m| matches!(*m, "wasi_unstable" | "wasi_snapshot_preview1"))
+        {
+            Abi::Wasi
+        } else {
+            Abi::None
+        }
+    }
+
+    /// Does this ABI need a WASI environment to run?
+    pub fn requires_wasi(self) -> bool {
+        matches!(self, Abi::Wasi | Abi::Wasix)
+    }
+}
+
+impl Display for Abi {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Abi::None => f.write_str("none"),
+            Abi::Wasi => f.write_str("wasi"),
+            Abi::Wasix => f.write_str("wasix"),
+            Abi::Emscripten => f.write_str("emscripten"),
+        }
+    }
 }
 
 impl FromStr for Abi {
@@ -347,33 +866,120 @@ impl FromStr for Abi {
         match s {
             "none" => Ok(Abi::None),
             "wasi" => Ok(Abi::Wasi),
-            _ => Err(Error::msg("Expected either \"none\" or \"wasi\"")),
+            "wasix" => Ok(Abi::Wasix),
+            "emscripten" => Ok(Abi::Emscripten),
+            _ => Err(Error::msg(
+                "Expected one of \"none\", \"wasi\", \"wasix\", or \"emscripten\"",
+            )),
         }
     }
 }
 
 /// The interface exported by the WebAssembly module.
 #[derive(Debug, Clone)]
-pub struct Interface(pub(crate) wai_parser::Interface);
+pub struct Interface {
+    pub(crate) parsed: wai_parser::Interface,
+    pub(crate) source: InterfaceSource,
+}
 
 impl Interface {
     /// Parse an interface definition in the WIT format.
     ///
     /// This will **not** attempt to parse any other files the interface
     /// definition depends on.
-    pub fn from_wit(name: &str, src: &str) -> Result<Self, Error> {
-        let wit =
-            wai_parser::Interface::parse(name, src).context("Unable to parse the WIT file")?;
-        Ok(Interface(wit))
+    pub fn from_wit(name: &str, src: &str) -> Result<Self, crate::Error> {
+        Interface::parse_with(name, src, |dependency| {
+            Err(anyhow::anyhow!("cannot load submodule `{dependency}`"))
+        })
+        .context("Unable to parse the WIT file")
+        .map_err(crate::Error::Parse)
+    }
+
+    /// Parse an interface definition written in the component model's WIT
+    /// dialect.
+    ///
+    /// The file must contain a single `interface`, which is lowered to its
+    /// WAI equivalent. Constructs which can't be represented in WAI (worlds,
+    /// `use` statements, resource constructors, `stream`s, etc.) are reported
+    /// as errors.
+    ///
+    /// Only the interface definition is taken from the WIT file. The library
+    /// implementing it must still be a core WebAssembly module which uses the
+    /// WAI canonical ABI, not a component built against the component model's
+    /// canonical ABI.
+    pub fn from_component_wit(name: &str, src: &str) -> Result<Self, crate::Error> {
+        let parse = || {
+            let lowered = crate::component_wit::lower(name, src)?;
+            Interface::parse_with(
+                &format!("{}.wai", lowered.name),
+                &lowered.wai,
+                |dependency| Err(anyhow::anyhow!("cannot load submodule `{dependency}`")),
+            )
+        };
+
+        parse()
+            .with_context(|| format!("Unable to parse \"{name}\" as component-model WIT"))
+            .map_err(crate::Error::Parse)
     }
 
     /// Parse an [`Interface`] from its interface definition on disk,
     /// potentially recursively parsing any files it depends on.
-    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, crate::Error> {
         let path = path.as_ref();
-        let wit = wai_parser::Interface::parse_file(path)
-            .with_context(|| format!("Unable to parse \"{}\"", path.display()))?;
-        Ok(Interface(wit))
+
+        let src = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read \"{}\"", path.display()))
+            .map_err(crate::Error::Load)?;
+        let parent = path.parent().unwrap_or_else(|| Path::new("."));
+        let filename = path
+            .file_name()
+            .with_context(|| format!("\"{}\" doesn't have a filename", path.display()))
+            .map_err(crate::Error::Load)?
+            .to_string_lossy();
+
+        // Failing to read a dependency is a loading error, not a parse error
+        let mut unreadable_dependency = false;
+        let result = Interface::parse_with(&filename, &src, |dependency| {
+            load_dependency(parent, dependency).map_err(|e| {
+                unreadable_dependency = true;
+                e
+            })
+        })
+        .with_context(|| format!("Unable to parse \"{}\"", path.display()));
+
+        match result {
+            Ok(interface) => Ok(interface),
+            Err(e) if unreadable_dependency => Err(crate::Error::Load(e)),
+            Err(e) => Err(crate::Error::Parse(e)),
+        }
+    }
+
+    /// Parse an interface definition, using the `load` callback to read the
+    /// source code for any files it depends on.
+    ///
+    /// The source code for each file is kept around so the [`Interface`] can
+    /// be serialized again later.
+    pub(crate) fn parse_with(
+        filename: &str,
+        src: &str,
+        mut load: impl FnMut(&str) -> Result<(PathBuf, String), Error>,
+    ) -> Result<Self, Error> {
+        let mut dependencies = BTreeMap::new();
+
+        let parsed = wai_parser::Interface::parse_with(filename, src, |dependency| {
+            let (path, contents) = load(dependency)?;
+            dependencies.insert(dependency.to_string(), contents.clone());
+            Ok((path, contents))
+        })?;
+
+        Ok(Interface {
+            parsed,
+            source: InterfaceSource {
+                filename: filename.to_string(),
+                contents: src.to_string(),
+                dependencies,
+            },
+        })
     }
 
     /// The name of the interface being generated.
@@ -381,7 +987,52 @@ impl Interface {
     /// If coming from a WIT file, this will be the `wasmer-pack` in
     /// `wasmer-pack.exports.wit`.
     pub fn name(&self) -> &str {
-        &self.0.name
+        &self.parsed.name
+    }
+
+    /// Every function defined by this interface, including static functions
+    /// and methods on [`Resource`]s, in the order they were declared.
+    pub fn functions(&self) -> Vec<Function> {
+        crate::introspection::functions(&self.parsed)
+    }
+
+    /// Every named type defined by this interface (records, flags, variants,
+    /// enums, unions and type aliases), in the order they were declared.
+    pub fn types(&self) -> Vec<TypeDefinition> {
+        crate::introspection::types(&self.parsed)
+    }
+
+    /// Every resource defined by this interface.
+    pub fn resources(&self) -> Vec<Resource> {
+        crate::introspection::resources(&self.parsed)
+    }
+}
+
+/// The source code an [`Interface`] was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InterfaceSource {
+    /// The name of the file the interface was defined in (e.g.
+    /// `wasmer-pack.exports.wai`).
+    pub(crate) filename: String,
+    pub(crate) contents: String,
+    /// The source code for any files pulled in with a `use` statement, keyed
+    /// by the name used to import them.
+    pub(crate) dependencies: BTreeMap<String, String>,
+}
+
+/// Read a file referenced by a `use` statement, mirroring the lookup rules
+/// used by [`wai_parser::Interface::parse_file()`].
+fn load_dependency(root: &Path, name: &str) -> Result<(PathBuf, String), Error> {
+    let wai = root.join(name).with_extension("wai");
+
+    match std::fs::read_to_string(&wai) {
+        Ok(contents) => Ok((wai, contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let wai_md = wai.with_extension("wai.md");
+            let contents = std::fs::read_to_string(&wai_md).map_err(|_| e)?;
+            Ok((wai_md, contents))
+        }
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -389,6 +1040,14 @@ impl Interface {
 pub struct Command {
     pub name: String,
     pub wasm: Vec<u8>,
+    /// Arguments passed to the command before any provided by the caller.
+    pub main_args: Vec<String>,
+    /// Environment variables set when running the command, unless the caller
+    /// overrides them.
+    pub env: BTreeMap<String, String>,
+    /// The name the command sees as `argv[0]`, if it isn't [`Command::name`].
+    pub exec_name: Option<String>,
+    pub kind: CommandKind,
 }
 
 impl Command {
@@ -396,6 +1055,81 @@ impl Command {
         Command {
             name: name.into(),
             wasm: wasm.into(),
+            main_args: Vec::new(),
+            env: BTreeMap::new(),
+            exec_name: None,
+            kind: CommandKind::Wasi,
+        }
+    }
+
+    /// Set the [`Command::main_args`] field.
+    pub fn with_main_args<I>(self, main_args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Command {
+            main_args: main_args.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Set the [`Command::env`] field.
+    pub fn with_env<I, K, V>(self, env: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        Command {
+            env: env.into_iter().map(|(k, v)| (k.into(), v.into())).collect(),
+            ..self
+        }
+    }
+
+    /// Set the [`Command::exec_name`] field.
+    pub fn with_exec_name(self, exec_name: impl Into<String>) -> Self {
+        Command {
+            exec_name: Some(exec_name.into()),
+            ..self
+        }
+    }
+
+    /// Set the [`Command::kind`] field.
+    pub fn with_kind(self, kind: CommandKind) -> Self {
+        Command { kind, ..self }
+    }
+
+    /// The name passed to the command as `argv[0]`.
+    pub fn program_name(&self) -> &str {
+        self.exec_name.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// How a [`Command`] expects to be run.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum CommandKind {
+    /// A WASI executable.
+    #[default]
+    Wasi,
+    /// An executable compiled by Emscripten with `-sSTANDALONE_WASM`, which
+    /// uses WASI for I/O but also imports helpers from the `env` namespace.
+    Emscripten,
+    /// A WASI executable which handles a single HTTP request using the
+    /// [CGI](https://www.rfc-editor.org/rfc/rfc3875) convention, reading the
+    /// request from environment variables and stdin and writing the response
+    /// to stdout.
+    Wcgi,
+}
+
+impl Display for CommandKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandKind::Wasi => f.write_str("wasi"),
+            CommandKind::Emscripten => f.write_str("emscripten"),
+            CommandKind::Wcgi => f.write_str("wcgi"),
         }
     }
 }
@@ -405,12 +1139,58 @@ impl Command {
 pub struct BindingsOptions {
     /// User defined name for the generated bindings
     pub name: Option<String>,
+    /// Fail if a library's WebAssembly module exports functions which aren't
+    /// part of its interface.
+    ///
+    /// Missing and mistyped exports are always rejected when the [`Package`]
+    /// is created.
+    pub deny_extra_exports: bool,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn detect_abi() {
+        let inputs = [
+            ("(module)", Abi::None),
+            (
+                r#"(module (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32))))"#,
+                Abi::Wasi,
+            ),
+            (
+                r#"(module (import "wasi_unstable" "proc_exit" (func (param i32))))"#,
+                Abi::Wasi,
+            ),
+            (
+                r#"(module
+                    (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+                    (import "wasix_32v1" "thread_spawn" (func (param i32) (result i32)))
+                )"#,
+                Abi::Wasix,
+            ),
+            (
+                r#"(module
+                    (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+                    (import "env" "emscripten_memcpy_big" (func (param i32 i32 i32)))
+                )"#,
+                Abi::Emscripten,
+            ),
+            (
+                r#"(module (func (export "_emscripten_stack_restore") (param i32)))"#,
+                Abi::Emscripten,
+            ),
+            ("definitely not wasm", Abi::None),
+        ];
+
+        for (src, expected) in inputs {
+            let wasm = wat::parse_str(src).unwrap_or_else(|_| src.as_bytes().to_vec());
+
+            assert_eq!(Abi::detect(&wasm), expected, "{src}");
+        }
+    }
+
     #[test]
     fn sanitize_package_names() {
         let inputs = vec![
@@ -442,4 +1222,162 @@ mod tests {
             assert_eq!(got.is_ok(), is_okay, "{original}");
         }
     }
+
+    #[test]
+    fn semver_to_rubygems() {
+        let inputs = vec![
+            ("1.2.3", "1.2.3"),
+            ("1.0.0-beta.1", "1.0.0.pre.beta.1"),
+            ("1.0.0-7", "1.0.0.pre.7"),
+            ("1.0.0-nightly.2023-01-01", "1.0.0.pre.nightly.2023.01.01"),
+            ("1.0.0+build.5", "1.0.0"),
+        ];
+
+        for (semver, expected) in inputs {
+            let metadata = Metadata::new("wasmer/test".parse().unwrap(), semver.parse().unwrap());
+
+            assert_eq!(metadata.ruby_version(), expected, "{semver}");
+        }
+    }
+
+    #[test]
+    fn semver_to_pep440() {
+        let inputs = vec![
+            ("1.2.3", "1.2.3"),
+            ("1.0.0-alpha", "1.0.0a0"),
+            ("1.0.0-alpha.1", "1.0.0a1"),
+            ("1.0.0-a.1", "1.0.0a1"),
+            ("1.0.0-beta.1", "1.0.0b1"),
+            ("1.0.0-beta2", "1.0.0b2"),
+            ("1.0.0-rc.1", "1.0.0rc1"),
+            ("1.0.0-RC.1", "1.0.0rc1"),
+            ("1.0.0-pre.3", "1.0.0rc3"),
+            ("1.0.0-dev.4", "1.0.0.dev4"),
+            ("1.0.0-7", "1.0.0.dev7"),
+            ("1.0.0-beta.1.fix", "1.0.0b1"),
+            ("1.0.0-nightly", "1.0.0.dev0"),
+            ("1.0.0-nightly.3", "1.0.0.dev3"),
+            ("1.0.0-nightly.2023-01-01", "1.0.0.dev0"),
+            ("1.0.0+build.5", "1.0.0"),
+            ("1.0.0-rc.1+sha-abcdef", "1.0.0rc1"),
+        ];
+
+        for (semver, expected) in inputs {
+            let metadata = Metadata::new("wasmer/test".parse().unwrap(), semver.parse().unwrap());
+
+            assert_eq!(metadata.python_version(), expected, "{semver}");
+            assert_eq!(metadata.javascript_version(), semver, "{semver}");
+        }
+    }
+
+    #[test]
+    fn version_requirements_for_dependencies() {
+        let inputs = vec![
+            ("*", "*", "coreutils"),
+            ("1.2.3", "^1.2.3", "coreutils>=1.2.3,<2.0.0"),
+            ("^0.2", "^0.2", "coreutils>=0.2.0,<0.3.0"),
+            ("^0.0.4", "^0.0.4", "coreutils>=0.0.4,<0.0.5"),
+            ("~1.2", "~1.2", "coreutils>=1.2.0,<1.3.0"),
+            ("=1.2.3-beta.1", "=1.2.3-beta.1", "coreutils==1.2.3b1"),
+            ("1.2.*", "1.2.*", "coreutils==1.2.*"),
+            (">=1.2, <1.5", ">=1.2 <1.5", "coreutils>=1.2.0,<1.5.0"),
+            (">1.2", ">1.2", "coreutils>=1.3.0"),
+            ("<=2", "<=2", "coreutils<3.0.0"),
+        ];
+
+        for (req, javascript, python) in inputs {
+            let dep = Dependency::new("wasmer/coreutils".parse().unwrap(), req.parse().unwrap());
+
+            assert_eq!(dep.javascript_version(), javascript, "{req}");
+            assert_eq!(dep.python_requirement(), python, "{req}");
+        }
+    }
+
+    #[test]
+    fn load_component_wit() {
+        let src = r#"
+            package wasmer:calc;
+
+            interface calc {
+                /// Add two numbers.
+                add: func(a: u32, b: u32) -> u32;
+                divide: func(a: f64, b: f64) -> result<f64, string>;
+            }
+        "#;
+
+        let interface = Interface::from_component_wit("calc.wit", src).unwrap();
+
+        assert_eq!(interface.name(), "calc");
+        let functions: Vec<_> = interface
+            .functions()
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(
+            functions,
+            [
+                "add: func(a: u32, b: u32) -> u32",
+                "divide: func(a: float64, b: float64) -> expected<float64, string>",
+            ]
+        );
+        assert_eq!(
+            interface.functions()[0].docs.as_deref(),
+            Some("Add two numbers.")
+        );
+
+        let err = Interface::from_component_wit("calc.wit", "world calc {}").unwrap_err();
+        assert!(matches!(err, crate::Error::Parse(_)));
+        assert_eq!(
+            err.to_string(),
+            "Unable to parse \"calc.wit\" as component-model WIT"
+        );
+    }
+
+    #[test]
+    fn io_errors_when_loading_an_interface_are_load_errors() {
+        let temp = tempfile::tempdir().unwrap();
+        let missing = temp.path().join("missing.wai");
+        let uses_missing = temp.path().join("uses-missing.wai");
+        std::fs::write(&uses_missing, "use { thing } from missing-dependency").unwrap();
+        let invalid = temp.path().join("invalid.wai");
+        std::fs::write(&invalid, "this isn't valid WAI").unwrap();
+
+        let err = Interface::from_path(missing).unwrap_err();
+        assert!(matches!(err, crate::Error::Load(_)), "{err:?}");
+        let err = Interface::from_path(uses_missing).unwrap_err();
+        assert!(matches!(err, crate::Error::Load(_)), "{err:?}");
+        let err = Interface::from_path(invalid).unwrap_err();
+        assert!(matches!(err, crate::Error::Parse(_)), "{err:?}");
+    }
+
+    #[test]
+    fn new_only_checks_for_duplicate_names() {
+        let metadata = Metadata::new("wasmer/test".parse().unwrap(), "1.0.0".parse().unwrap());
+        let exports = Interface::from_wit("exports.wai", "run: func()").unwrap();
+        // The module doesn't export "run", which try_new() would reject
+        let library = Library {
+            module: Module {
+                name: "exports.wasm".to_string(),
+                abi: Abi::None,
+                wasm: wat::parse_str("(module)").unwrap(),
+            },
+            exports,
+            imports: Vec::new(),
+        };
+
+        let err =
+            Package::try_new(metadata.clone(), vec![library.clone()], Vec::new()).unwrap_err();
+        assert!(matches!(
+            err.problems(),
+            [crate::Problem::MissingExport { .. }, ..]
+        ));
+
+        let pkg = Package::new(metadata.clone(), vec![library.clone()], Vec::new());
+        assert_eq!(pkg.libraries().len(), 1);
+
+        let duplicates = std::panic::catch_unwind(|| {
+            Package::new(metadata, vec![library.clone(), library], Vec::new())
+        });
+        assert!(duplicates.is_err());
+    }
 }
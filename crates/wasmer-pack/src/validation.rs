@@ -0,0 +1,1097 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display, Formatter},
+};
+
+use heck::ToSnakeCase;
+use wai_parser::TypeDefKind;
+
+use crate::{Abi, Command, CommandKind, Library};
+
+/// Names which would clash with files or directories created by the
+/// generators (e.g. the `index.js` in each `bindings/` directory, or the
+/// `intrinsics.js` generated by `wai-bindgen`).
+const RESERVED_NAMES: &[&str] = &["bindings", "commands", "index", "intrinsics"];
+
+/// Keywords which can't be used as identifiers in the generated Python code.
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Reserved words which can't be used as identifiers in the generated
+/// JavaScript code.
+const JAVASCRIPT_KEYWORDS: &[&str] = &[
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// The reasons a [`crate::Package`] might be rejected by
+/// [`crate::Package::try_new()`].
+///
+/// All problems are collected up-front, so callers can report everything
+/// that is wrong with a package in one go.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    problems: Vec<Problem>,
+    /// Where the problems were found, outermost first.
+    context: Vec<String>,
+}
+
+impl ValidationError {
+    pub(crate) fn from_problems(problems: Vec<Problem>) -> Result<(), ValidationError> {
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError {
+                problems,
+                context: Vec::new(),
+            })
+        }
+    }
+
+    /// Every problem that was found.
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    /// Descriptions of where the problems were found (e.g. which file was
+    /// being loaded), outermost first.
+    pub fn context(&self) -> &[String] {
+        &self.context
+    }
+
+    pub(crate) fn with_context(mut self, context: impl Display) -> Self {
+        self.context.insert(0, context.to_string());
+        self
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for context in &self.context {
+            write!(f, "{context}: ")?;
+        }
+
+        match self.problems.as_slice() {
+            [problem] => write!(f, "{problem}"),
+            problems => {
+                write!(f, "Found {} problems with the package", problems.len())?;
+
+                for problem in problems {
+                    write!(f, "\n- {problem}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A single issue that would prevent bindings from being generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Problem {
+    /// More than one item has the same name.
+    DuplicateName { kind: ItemKind, name: String },
+    /// Two different names would map to the same identifier in the generated
+    /// code (e.g. `first-command` and `first_command`).
+    ConflictingIdentifiers {
+        kind: ItemKind,
+        first: String,
+        second: String,
+        ident: String,
+    },
+    /// The name can't be used as an identifier in at least one of the target
+    /// languages.
+    InvalidIdentifier {
+        kind: ItemKind,
+        name: String,
+        reason: String,
+    },
+    /// The name would clash with a file or directory created by the
+    /// generators.
+    ReservedName { kind: ItemKind, name: String },
+    /// A library's WebAssembly module couldn't be parsed.
+    InvalidModule { library: String, reason: String },
+    /// A library's WebAssembly module doesn't provide something its exports
+    /// interface requires.
+    MissingExport { library: String, name: String },
+    /// A library's WebAssembly module exports a function which isn't part of
+    /// its exports interface.
+    ///
+    /// This is only reported when
+    /// [`crate::BindingsOptions::deny_extra_exports`] is set.
+    ExtraExport { library: String, name: String },
+    /// A library's WebAssembly module uses an ABI the generator can't host.
+    UnsupportedAbi {
+        library: String,
+        abi: Abi,
+        language: String,
+    },
+    /// A command is run in a way the generator doesn't support.
+    UnsupportedCommand {
+        command: String,
+        kind: CommandKind,
+        language: String,
+    },
+    /// A library's interface uses something the generator can't express.
+    UnsupportedInterface {
+        library: String,
+        /// A human-readable description of what was used (e.g. "resources").
+        feature: String,
+        language: String,
+    },
+    /// An export doesn't have the type required by the canonical ABI.
+    MistypedExport {
+        library: String,
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    /// An Emscripten command wasn't compiled with `-sSTANDALONE_WASM`, so it
+    /// needs Emscripten's JavaScript glue to run.
+    NotStandaloneWasm {
+        command: String,
+        /// What the module is missing (e.g. `a "_start" export`).
+        missing: String,
+        language: String,
+    },
+    /// A library or command is a WebAssembly component rather than a core
+    /// module.
+    ComponentBinary {
+        kind: ItemKind,
+        name: String,
+        /// The names imported by the component.
+        imports: Vec<String>,
+        /// The names exported by the component.
+        exports: Vec<String>,
+    },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::DuplicateName { kind, name } => write!(f, "Duplicate {kind} name: {name}"),
+            Problem::ConflictingIdentifiers {
+                kind,
+                first,
+                second,
+                ident,
+            } => write!(
+                f,
+                "The \"{first}\" and \"{second}\" {kind} names would both be turned into \"{ident}\""
+            ),
+            Problem::InvalidIdentifier { kind, name, reason } => {
+                write!(f, "\"{name}\" is not a valid {kind} name: {reason}")
+            }
+            Problem::ReservedName { kind, name } => write!(
+                f,
+                "The \"{name}\" {kind} name would clash with a file created by the generator"
+            ),
+            Problem::InvalidModule { library, reason } => {
+                write!(f, "The \"{library}\" library's module is invalid: {reason}")
+            }
+            Problem::MissingExport { library, name } => {
+                write!(f, "The \"{library}\" library doesn't export \"{name}\"")
+            }
+            Problem::ExtraExport { library, name } => write!(
+                f,
+                "The \"{library}\" library exports \"{name}\", which isn't in its interface"
+            ),
+            Problem::UnsupportedAbi {
+                library,
+                abi,
+                language,
+            } => write!(
+                f,
+                "The \"{library}\" library uses the \"{abi}\" ABI, which isn't supported by the {language} bindings"
+            ),
+            Problem::UnsupportedCommand {
+                command,
+                kind,
+                language,
+            } => write!(
+                f,
+                "The \"{command}\" command is a {kind} command, which isn't supported by the {language} bindings"
+            ),
+            Problem::NotStandaloneWasm {
+                command,
+                missing,
+                language,
+            } => write!(
+                f,
+                "The \"{command}\" command doesn't have {missing}, so it can't be run by the {language} bindings. Try compiling it with -sSTANDALONE_WASM"
+            ),
+            Problem::UnsupportedInterface {
+                library,
+                feature,
+                language,
+            } => write!(
+                f,
+                "The \"{library}\" library uses {feature}, which isn't supported by the {language} bindings"
+            ),
+            Problem::MistypedExport {
+                library,
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "The \"{library}\" library's \"{name}\" export should be {expected}, but found {actual}"
+            ),
+            Problem::ComponentBinary {
+                kind,
+                name,
+                imports,
+                exports,
+            } => {
+                write!(f, "The \"{name}\" {kind} is a WebAssembly component")?;
+                let externs = [("exports", exports), ("imports", imports)]
+                    .into_iter()
+                    .filter(|(_, names)| !names.is_empty())
+                    .map(|(verb, names)| format!("{verb} {}", quoted_list(names)))
+                    .collect::<Vec<_>>();
+                if !externs.is_empty() {
+                    write!(f, " which {}", externs.join(" and "))?;
+                }
+                write!(
+                    f,
+                    ", but only core WebAssembly modules are supported. Compile it as a core module which uses the WAI canonical ABI instead"
+                )
+            }
+        }
+    }
+}
+
+/// The kind of item a [`Problem`] refers to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ItemKind {
+    Library,
+    Command,
+}
+
+impl Display for ItemKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemKind::Library => f.write_str("library"),
+            ItemKind::Command => f.write_str("command"),
+        }
+    }
+}
+
+/// Make sure no two libraries or commands have the same name.
+pub(crate) fn check_unique_names(
+    libraries: &[Library],
+    commands: &[Command],
+) -> Result<(), ValidationError> {
+    let mut problems = Vec::new();
+    let names = [
+        (
+            ItemKind::Library,
+            libraries
+                .iter()
+                .map(|lib| lib.interface_name())
+                .collect::<Vec<_>>(),
+        ),
+        (
+            ItemKind::Command,
+            commands.iter().map(|cmd| cmd.name.as_str()).collect(),
+        ),
+    ];
+
+    for (kind, names) in names {
+        let mut already_seen = BTreeSet::new();
+        for name in names {
+            if !already_seen.insert(name) {
+                problems.push(Problem::DuplicateName {
+                    kind,
+                    name: name.to_string(),
+                });
+            }
+        }
+    }
+
+    ValidationError::from_problems(problems)
+}
+
+/// Check that the libraries and commands in a package can be turned into
+/// bindings.
+pub(crate) fn validate(libraries: &[Library], commands: &[Command]) -> Result<(), ValidationError> {
+    let mut problems = Vec::new();
+
+    check_names(
+        ItemKind::Library,
+        libraries.iter().map(|lib| lib.interface_name()),
+        &mut problems,
+    );
+    check_names(
+        ItemKind::Command,
+        commands.iter().map(|cmd| cmd.name.as_str()),
+        &mut problems,
+    );
+
+    for lib in libraries {
+        if let Some(problem) =
+            check_component(ItemKind::Library, lib.interface_name(), &lib.module.wasm)
+        {
+            // The canonical ABI checks would only add noise
+            problems.push(problem);
+        } else {
+            check_exports(lib, false, &mut problems);
+        }
+    }
+
+    for cmd in commands {
+        problems.extend(check_component(ItemKind::Command, &cmd.name, &cmd.wasm));
+    }
+
+    ValidationError::from_problems(problems)
+}
+
+/// Report a [`Problem::ComponentBinary`] if `wasm` is a component, listing
+/// the names from its top-level import and export sections.
+fn check_component(kind: ItemKind, name: &str, wasm: &[u8]) -> Option<Problem> {
+    let mut is_component = false;
+    let mut depth = 0;
+    let mut imports = Vec::new();
+    let mut exports = Vec::new();
+
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        match payload {
+            Ok(wasmparser::Payload::Version { encoding, .. }) => {
+                if depth == 0 {
+                    is_component = encoding == wasmparser::Encoding::Component;
+                }
+                depth += 1;
+            }
+            Ok(wasmparser::Payload::End(_)) => depth -= 1,
+            // Nested modules and components have their own imports and
+            // exports, which we aren't interested in
+            Ok(wasmparser::Payload::ComponentImportSection(section)) if depth == 1 => {
+                imports.extend(
+                    section
+                        .into_iter()
+                        .flatten()
+                        .map(|import| import.name.as_str().to_string()),
+                );
+            }
+            Ok(wasmparser::Payload::ComponentExportSection(section)) if depth == 1 => {
+                exports.extend(
+                    section
+                        .into_iter()
+                        .flatten()
+                        .map(|export| export.name.as_str().to_string()),
+                );
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+
+        if !is_component {
+            return None;
+        }
+    }
+
+    is_component.then(|| Problem::ComponentBinary {
+        kind,
+        name: name.to_string(),
+        imports,
+        exports,
+    })
+}
+
+fn quoted_list(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("\"{name}\""))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Make sure none of the libraries export functions that aren't in their
+/// interface.
+pub(crate) fn deny_extra_exports(libraries: &[Library]) -> Result<(), ValidationError> {
+    let mut problems = Vec::new();
+
+    for lib in libraries {
+        check_exports(lib, true, &mut problems);
+    }
+
+    ValidationError::from_problems(problems)
+}
+
+/// Make sure the generator for `language` can host every library's
+/// WebAssembly module.
+pub(crate) fn check_abis(
+    libraries: &[Library],
+    language: &str,
+    supported: &[Abi],
+) -> Result<(), ValidationError> {
+    let problems = libraries
+        .iter()
+        .filter(|lib| !supported.contains(&lib.module.abi))
+        .map(|lib| Problem::UnsupportedAbi {
+            library: lib.interface_name().to_string(),
+            abi: lib.module.abi,
+            language: language.to_string(),
+        })
+        .collect();
+
+    ValidationError::from_problems(problems)
+}
+
+/// Make sure the generator for `language` knows how to run every command.
+pub(crate) fn check_command_kinds(
+    commands: &[Command],
+    language: &str,
+    supported: &[CommandKind],
+) -> Result<(), ValidationError> {
+    let problems = commands
+        .iter()
+        .filter(|cmd| !supported.contains(&cmd.kind))
+        .map(|cmd| Problem::UnsupportedCommand {
+            command: cmd.name.clone(),
+            kind: cmd.kind,
+            language: language.to_string(),
+        })
+        .collect();
+
+    ValidationError::from_problems(problems)
+}
+
+/// Make sure every Emscripten command was compiled with `-sSTANDALONE_WASM`.
+///
+/// The generated runners only provide WASI and a handful of `env` helpers, so
+/// a module which needs Emscripten's JavaScript glue (i.e. it has no WASI
+/// imports or no `_start` function) can't be run.
+pub(crate) fn check_emscripten_commands(
+    commands: &[Command],
+    language: &str,
+) -> Result<(), ValidationError> {
+    let problems = commands
+        .iter()
+        .filter(|cmd| cmd.kind == CommandKind::Emscripten)
+        .filter_map(|cmd| {
+            let missing = missing_standalone_wasm_items(&cmd.wasm)?;
+            Some(Problem::NotStandaloneWasm {
+                command: cmd.name.clone(),
+                missing,
+                language: language.to_string(),
+            })
+        })
+        .collect();
+
+    ValidationError::from_problems(problems)
+}
+
+/// Describe what a module needs (WASI imports and a `_start` function) to
+/// run without Emscripten's JavaScript glue, if it doesn't have them.
+fn missing_standalone_wasm_items(wasm: &[u8]) -> Option<String> {
+    let mut imports_wasi = false;
+    let mut has_start = false;
+
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        match payload {
+            Ok(wasmparser::Payload::ImportSection(imports)) => {
+                imports_wasi |= imports.into_iter().flatten().any(|import| {
+                    matches!(import.module, "wasi_unstable" | "wasi_snapshot_preview1")
+                });
+            }
+            Ok(wasmparser::Payload::ExportSection(exports)) => {
+                has_start |= exports.into_iter().flatten().any(|export| {
+                    export.name == "_start" && export.kind == wasmparser::ExternalKind::Func
+                });
+            }
+            _ => {}
+        }
+    }
+
+    match (imports_wasi, has_start) {
+        (true, true) => None,
+        (false, true) => Some("any WASI imports".to_string()),
+        (true, false) => Some("a \"_start\" export".to_string()),
+        (false, false) => Some("any WASI imports or a \"_start\" export".to_string()),
+    }
+}
+
+/// Make sure none of the libraries' interfaces use features the generator
+/// for `language` can't express.
+///
+/// This rejects resources, async functions, futures and streams, and flags
+/// that won't fit in a 64-bit integer.
+pub(crate) fn deny_unsupported_interface_features(
+    libraries: &[Library],
+    language: &str,
+) -> Result<(), ValidationError> {
+    check_interface_features(libraries, language, false)
+}
+
+/// The same as [`deny_unsupported_interface_features()`], except resources
+/// are allowed as long as they are defined by the library's exports.
+pub(crate) fn deny_unsupported_interface_features_except_exported_resources(
+    libraries: &[Library],
+    language: &str,
+) -> Result<(), ValidationError> {
+    check_interface_features(libraries, language, true)
+}
+
+/// Reject WASI libraries that also need functions from the host (imported
+/// interfaces, or the intrinsics backing exported resources), for generators
+/// whose runtime can only provide a WASI module's imports all at once.
+pub(crate) fn deny_imports_in_wasi_libraries(
+    libraries: &[Library],
+    language: &str,
+) -> Result<(), ValidationError> {
+    let mut problems = Vec::new();
+
+    for lib in libraries.iter().filter(|lib| lib.requires_wasi()) {
+        let mut features = Vec::new();
+        if !lib.imports.is_empty() {
+            features.push("imported interfaces alongside WASI");
+        }
+        if lib.exports.parsed.resources.len() > 0 {
+            features.push("resources alongside WASI");
+        }
+
+        problems.extend(
+            features
+                .into_iter()
+                .map(|feature| Problem::UnsupportedInterface {
+                    library: lib.interface_name().to_string(),
+                    feature: feature.to_string(),
+                    language: language.to_string(),
+                }),
+        );
+    }
+
+    ValidationError::from_problems(problems)
+}
+
+fn check_interface_features(
+    libraries: &[Library],
+    language: &str,
+    allow_exported_resources: bool,
+) -> Result<(), ValidationError> {
+    let mut problems = Vec::new();
+
+    for lib in libraries {
+        let mut features = BTreeSet::new();
+
+        if lib.exports.parsed.resources.len() > 0 && !allow_exported_resources {
+            features.insert("resources");
+        }
+        if lib.imports.iter().any(|i| i.parsed.resources.len() > 0) {
+            if allow_exported_resources {
+                features.insert("resources in imported interfaces");
+            } else {
+                features.insert("resources");
+            }
+        }
+
+        let interfaces = std::iter::once(&lib.exports).chain(&lib.imports);
+        for interface in interfaces.map(|i| &i.parsed) {
+            if interface.functions.iter().any(|f| f.is_async) {
+                features.insert("async functions");
+            }
+
+            for (_, ty) in interface.types.iter() {
+                match &ty.kind {
+                    TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                        features.insert("futures or streams");
+                    }
+                    TypeDefKind::Flags(flags) if flags.flags.len() > 64 => {
+                        features.insert("flags with more than 64 members");
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        problems.extend(
+            features
+                .into_iter()
+                .map(|feature| Problem::UnsupportedInterface {
+                    library: lib.interface_name().to_string(),
+                    feature: feature.to_string(),
+                    language: language.to_string(),
+                }),
+        );
+    }
+
+    ValidationError::from_problems(problems)
+}
+
+fn check_exports(lib: &Library, deny_extra: bool, problems: &mut Vec<Problem>) {
+    let library = lib.interface_name().to_string();
+
+    let report = match lib.check_exports() {
+        Ok(report) => report,
+        Err(e) => {
+            let reason = anyhow::Error::from(e)
+                .chain()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(": ");
+            problems.push(Problem::InvalidModule { library, reason });
+            return;
+        }
+    };
+
+    for name in report.missing {
+        problems.push(Problem::MissingExport {
+            library: library.clone(),
+            name,
+        });
+    }
+
+    for export in report.mistyped {
+        problems.push(Problem::MistypedExport {
+            library: library.clone(),
+            name: export.name,
+            expected: export.expected,
+            actual: export.actual,
+        });
+    }
+
+    if deny_extra {
+        for name in report.extra {
+            problems.push(Problem::ExtraExport {
+                library: library.clone(),
+                name,
+            });
+        }
+    }
+}
+
+fn check_names<'a>(
+    kind: ItemKind,
+    names: impl IntoIterator<Item = &'a str>,
+    problems: &mut Vec<Problem>,
+) {
+    // Maps each identifier back to the name it came from
+    let mut already_seen: BTreeMap<String, &str> = BTreeMap::new();
+
+    for name in names {
+        let ident = name.to_snake_case();
+
+        match already_seen.get(&ident) {
+            Some(&original) if original == name => {
+                problems.push(Problem::DuplicateName {
+                    kind,
+                    name: name.to_string(),
+                });
+                continue;
+            }
+            Some(&original) => {
+                problems.push(Problem::ConflictingIdentifiers {
+                    kind,
+                    first: original.to_string(),
+                    second: name.to_string(),
+                    ident,
+                });
+                continue;
+            }
+            None => {}
+        }
+
+        if RESERVED_NAMES.contains(&name) || RESERVED_NAMES.contains(&ident.as_str()) {
+            problems.push(Problem::ReservedName {
+                kind,
+                name: name.to_string(),
+            });
+        } else if let Err(reason) = check_identifier(name, &ident) {
+            problems.push(Problem::InvalidIdentifier {
+                kind,
+                name: name.to_string(),
+                reason,
+            });
+        }
+
+        already_seen.insert(ident, name);
+    }
+}
+
+fn check_identifier(name: &str, ident: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("names can't be empty".to_string());
+    }
+
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+    {
+        return Err("names can only contain '-', '_', ascii numbers, and letters".to_string());
+    }
+
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err("names must start with an ascii letter".to_string());
+    }
+
+    if PYTHON_KEYWORDS.contains(&ident) {
+        return Err(format!("\"{ident}\" is a reserved word in Python"));
+    }
+
+    if JAVASCRIPT_KEYWORDS.contains(&ident) {
+        return Err(format!("\"{ident}\" is a reserved word in JavaScript"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_problems(names: &[&str]) -> Vec<Problem> {
+        let commands: Vec<_> = names.iter().map(|name| Command::new(*name, [])).collect();
+
+        match validate(&[], &commands) {
+            Ok(()) => Vec::new(),
+            Err(e) => e.problems,
+        }
+    }
+
+    #[test]
+    fn valid_names_are_accepted() {
+        let problems = command_problems(&["wat2wasm", "wasm-interp", "first_command", "Second"]);
+
+        assert!(problems.is_empty(), "{problems:?}");
+    }
+
+    #[test]
+    fn generators_reject_unsupported_abis() {
+        let library = |name: &str, abi: Abi| Library {
+            module: crate::Module {
+                name: format!("{name}.wasm"),
+                abi,
+                wasm: wat::parse_str("(module)").unwrap(),
+            },
+            exports: crate::Interface::from_wit(&format!("{name}.exports.wai"), "").unwrap(),
+            imports: Vec::new(),
+        };
+        let libraries = [
+            library("plain", Abi::None),
+            library("wasi", Abi::Wasi),
+            library("wasix", Abi::Wasix),
+        ];
+
+        let err = check_abis(&libraries, "JavaScript", &[Abi::None, Abi::Wasi]).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The \"wasix\" library uses the \"wasix\" ABI, which isn't supported by the JavaScript bindings"
+        );
+    }
+
+    #[test]
+    fn generators_reject_unsupported_command_kinds() {
+        let commands = [
+            Command::new("cli", []),
+            Command::new("server", []).with_kind(CommandKind::Wcgi),
+        ];
+
+        let err = check_command_kinds(&commands, "Rust", &[CommandKind::Wasi]).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The \"server\" command is a wcgi command, which isn't supported by the Rust bindings"
+        );
+    }
+
+    #[test]
+    fn emscripten_commands_must_be_standalone() {
+        let standalone = wat::parse_str(
+            r#"(module
+                (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+                (import "env" "emscripten_memcpy_big" (func (param i32 i32 i32)))
+                (func (export "_start"))
+            )"#,
+        )
+        .unwrap();
+        let needs_glue = wat::parse_str(
+            r#"(module
+                (import "env" "emscripten_memcpy_big" (func (param i32 i32 i32)))
+                (func (export "main"))
+            )"#,
+        )
+        .unwrap();
+        let no_start = wat::parse_str(
+            r#"(module
+                (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+                (func (export "main"))
+            )"#,
+        )
+        .unwrap();
+        let emscripten =
+            |name: &str, wasm: &[u8]| [Command::new(name, wasm).with_kind(CommandKind::Emscripten)];
+
+        check_emscripten_commands(&emscripten("lua", &standalone), "Python").unwrap();
+        let err =
+            check_emscripten_commands(&emscripten("luac", &needs_glue), "Python").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The \"luac\" command doesn't have any WASI imports or a \"_start\" export, so it can't be run by the Python bindings. Try compiling it with -sSTANDALONE_WASM"
+        );
+        let err = check_emscripten_commands(&emscripten("lua", &no_start), "Python").unwrap_err();
+        assert_eq!(
+            err.problems()[0],
+            Problem::NotStandaloneWasm {
+                command: "lua".to_string(),
+                missing: "a \"_start\" export".to_string(),
+                language: "Python".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn generators_reject_unsupported_interface_features() {
+        let exports = crate::Interface::from_wit(
+            "exports.wai",
+            "resource counter { increment: func() }\nflags big { a, b }",
+        )
+        .unwrap();
+        let library = Library {
+            module: crate::Module {
+                name: "counter.wasm".to_string(),
+                abi: Abi::None,
+                wasm: Vec::new(),
+            },
+            exports,
+            imports: Vec::new(),
+        };
+
+        let err = deny_unsupported_interface_features(&[library], "Go").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The \"exports\" library uses resources, which isn't supported by the Go bindings"
+        );
+    }
+
+    #[test]
+    fn exported_resources_can_be_allowed() {
+        let exports =
+            crate::Interface::from_wit("exports.wai", "resource counter { increment: func() }")
+                .unwrap();
+        let host =
+            crate::Interface::from_wit("host.wai", "resource file { close: func() }").unwrap();
+        let module = crate::Module {
+            name: "counter.wasm".to_string(),
+            abi: Abi::None,
+            wasm: Vec::new(),
+        };
+        let library = Library {
+            module: module.clone(),
+            exports: exports.clone(),
+            imports: Vec::new(),
+        };
+        let library_with_imported_resources = Library {
+            module,
+            exports,
+            imports: vec![host],
+        };
+
+        deny_unsupported_interface_features_except_exported_resources(&[library], "Ruby").unwrap();
+        let err = deny_unsupported_interface_features_except_exported_resources(
+            &[library_with_imported_resources],
+            "Ruby",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The \"exports\" library uses resources in imported interfaces, which isn't supported by the Ruby bindings"
+        );
+    }
+
+    #[test]
+    fn wasi_libraries_with_imports_can_be_rejected() {
+        let exports = crate::Interface::from_wit("exports.wai", "run: func()").unwrap();
+        let host = crate::Interface::from_wit("host.wai", "log: func(msg: string)").unwrap();
+        let library = |abi, imports| Library {
+            module: crate::Module {
+                name: "lib.wasm".to_string(),
+                abi,
+                wasm: Vec::new(),
+            },
+            exports: exports.clone(),
+            imports,
+        };
+
+        deny_imports_in_wasi_libraries(&[library(Abi::None, vec![host.clone()])], "PHP").unwrap();
+        deny_imports_in_wasi_libraries(&[library(Abi::Wasi, Vec::new())], "PHP").unwrap();
+        let err =
+            deny_imports_in_wasi_libraries(&[library(Abi::Wasi, vec![host])], "PHP").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The \"exports\" library uses imported interfaces alongside WASI, which isn't supported by the PHP bindings"
+        );
+
+        let mut with_resources = library(Abi::Wasi, Vec::new());
+        with_resources.exports =
+            crate::Interface::from_wit("exports.wai", "resource counter { increment: func() }")
+                .unwrap();
+        let err = deny_imports_in_wasi_libraries(&[with_resources], "PHP").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The \"exports\" library uses resources alongside WASI, which isn't supported by the PHP bindings"
+        );
+    }
+
+    #[test]
+    fn component_binaries_are_rejected() {
+        let component = wat::parse_str(
+            r#"(component
+                (import "host" (func))
+                (core module $m (func (export "run")))
+                (core instance $i (instantiate $m))
+                (func $run (canon lift (core func $i "run")))
+                (export "run" (func $run))
+            )"#,
+        )
+        .unwrap();
+        let library = Library {
+            module: crate::Module {
+                name: "calc.wasm".to_string(),
+                abi: Abi::None,
+                wasm: component.clone(),
+            },
+            exports: crate::Interface::from_wit("calc.exports.wai", "run: func()").unwrap(),
+            imports: Vec::new(),
+        };
+        let commands = [
+            Command::new("calc-cli", component),
+            Command::new("plain", wat::parse_str("(module)").unwrap()),
+        ];
+
+        let err = validate(&[library], &commands).unwrap_err();
+
+        assert_eq!(
+            err.problems,
+            vec![
+                Problem::ComponentBinary {
+                    kind: ItemKind::Library,
+                    name: "calc".to_string(),
+                    imports: vec!["host".to_string()],
+                    exports: vec!["run".to_string()],
+                },
+                Problem::ComponentBinary {
+                    kind: ItemKind::Command,
+                    name: "calc-cli".to_string(),
+                    imports: vec!["host".to_string()],
+                    exports: vec!["run".to_string()],
+                },
+            ]
+        );
+        assert_eq!(
+            err.problems[0].to_string(),
+            "The \"calc\" library is a WebAssembly component which exports \"run\" and imports \"host\", but only core WebAssembly modules are supported. Compile it as a core module which uses the WAI canonical ABI instead"
+        );
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let problems = command_problems(&[
+            "first",
+            "first",
+            "my-cmd",
+            "my_cmd",
+            "index",
+            "bindings",
+            "class",
+            "1st",
+            "with space",
+        ]);
+
+        assert_eq!(
+            problems,
+            vec![
+                Problem::DuplicateName {
+                    kind: ItemKind::Command,
+                    name: "first".to_string(),
+                },
+                Problem::ConflictingIdentifiers {
+                    kind: ItemKind::Command,
+                    first: "my-cmd".to_string(),
+                    second: "my_cmd".to_string(),
+                    ident: "my_cmd".to_string(),
+                },
+                Problem::ReservedName {
+                    kind: ItemKind::Command,
+                    name: "index".to_string(),
+                },
+                Problem::ReservedName {
+                    kind: ItemKind::Command,
+                    name: "bindings".to_string(),
+                },
+                Problem::InvalidIdentifier {
+                    kind: ItemKind::Command,
+                    name: "class".to_string(),
+                    reason: "\"class\" is a reserved word in Python".to_string(),
+                },
+                Problem::InvalidIdentifier {
+                    kind: ItemKind::Command,
+                    name: "1st".to_string(),
+                    reason: "names must start with an ascii letter".to_string(),
+                },
+                Problem::InvalidIdentifier {
+                    kind: ItemKind::Command,
+                    name: "with space".to_string(),
+                    reason: "names can only contain '-', '_', ascii numbers, and letters"
+                        .to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -1,16 +1,26 @@
 /// The versions of [`wai_parser`] this crate is compatible with.
 pub const WAI_PARSER_VERSION: &str = include_str!("wai_version.txt");
 
+/// The version of `wai-bindgen-wasmer` used by generated Rust crates.
+///
+/// All the `wai-*` crates are released together, so this is the version of
+/// [`wai_parser`] we were compiled against.
+pub(crate) const WAI_BINDGEN_WASMER_VERSION: &str = include_str!("wai_bindgen_version.txt");
+
 #[cfg(test)]
 mod tests {
-    use cargo_metadata::MetadataCommand;
+    use cargo_metadata::{Metadata, MetadataCommand};
     use std::path::Path;
 
-    fn wai_parser_version_from_cargo_toml() -> String {
-        let manifest = MetadataCommand::new()
+    fn metadata() -> Metadata {
+        MetadataCommand::new()
             .current_dir(env!("CARGO_MANIFEST_DIR"))
             .exec()
-            .unwrap();
+            .unwrap()
+    }
+
+    fn wai_parser_version_from_cargo_toml() -> String {
+        let manifest = metadata();
         let wasmer_pack = manifest
             .workspace_packages()
             .into_iter()
@@ -46,4 +56,29 @@ mod tests {
             );
         }
     }
+
+    /// The same trick, but for the `wai-bindgen-wasmer` version used by the
+    /// generated Rust crates.
+    #[test]
+    fn wai_bindgen_version_is_up_to_date() {
+        let manifest = metadata();
+        let wai_parser = manifest
+            .packages
+            .iter()
+            .find(|pkg| pkg.name == "wai-parser")
+            .unwrap();
+        let actual_version = wai_parser.version.to_string();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src")
+            .join("wai_bindgen_version.txt");
+
+        let wai_bindgen_version_txt = std::fs::read_to_string(&path).unwrap();
+
+        if wai_bindgen_version_txt != actual_version {
+            std::fs::write(&path, actual_version).unwrap();
+            panic!(
+                "The wai-bindgen-wasmer version was out of date. Re-run the tests and commit the changes."
+            );
+        }
+    }
 }
@@ -0,0 +1,74 @@
+use std::fmt::{self, Display, Formatter};
+
+/// Something in a WEBC file that was skipped or ignored while loading a
+/// [`crate::Package`].
+///
+/// Unlike errors, warnings don't stop bindings from being generated, but
+/// they may explain why something is missing from the generated package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A command was skipped because it uses a well-known runner that
+    /// `wasmer-pack` doesn't support.
+    UnsupportedRunner { command: String, runner: String },
+    /// A command was skipped because its runner isn't recognised.
+    UnknownRunner { command: String, runner: String },
+    /// A command was skipped because it couldn't be loaded.
+    SkippedCommand { command: String, reason: String },
+    /// A dependency was left out of the [`crate::Metadata`] because it
+    /// couldn't be understood.
+    SkippedDependency { dependency: String, reason: String },
+    /// A directory wasn't bundled with the package.
+    SkippedMount { mount_path: String, reason: String },
+    /// An atom isn't used by any of the package's commands or libraries.
+    UnusedAtom { atom: String },
+    /// An annotation was ignored because `wasmer-pack` doesn't know how to
+    /// handle it.
+    ///
+    /// The `command` is `None` for annotations on the package itself.
+    IgnoredAnnotation {
+        command: Option<String>,
+        annotation: String,
+    },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::UnsupportedRunner { command, runner } => write!(
+                f,
+                "Skipped the \"{command}\" command because the \"{runner}\" runner isn't supported"
+            ),
+            Warning::UnknownRunner { command, runner } => write!(
+                f,
+                "Skipped the \"{command}\" command because it uses an unknown runner, \"{runner}\""
+            ),
+            Warning::SkippedCommand { command, reason } => {
+                write!(f, "Skipped the \"{command}\" command: {reason}")
+            }
+            Warning::SkippedDependency { dependency, reason } => {
+                write!(f, "Skipped the \"{dependency}\" dependency: {reason}")
+            }
+            Warning::SkippedMount { mount_path, reason } => {
+                write!(f, "Unable to mount \"{mount_path}\": {reason}")
+            }
+            Warning::UnusedAtom { atom } => {
+                write!(
+                    f,
+                    "The \"{atom}\" atom isn't used by any commands or libraries"
+                )
+            }
+            Warning::IgnoredAnnotation {
+                command: Some(command),
+                annotation,
+            } => write!(
+                f,
+                "Ignored the \"{annotation}\" annotation on the \"{command}\" command"
+            ),
+            Warning::IgnoredAnnotation {
+                command: None,
+                annotation,
+            } => write!(f, "Ignored the package's \"{annotation}\" annotation"),
+        }
+    }
+}
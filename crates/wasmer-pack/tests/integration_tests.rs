@@ -4,7 +4,7 @@ use std::{
     process::{Command, Output, Stdio},
 };
 
-use wasmer_pack::{Abi, Interface, Library, Metadata, Module, Package};
+use wasmer_pack::{Abi, BindingsOptions, Files, Interface, Library, Metadata, Module, Package};
 
 #[test]
 fn use_wasi_javascript_bindings() {
@@ -55,6 +55,101 @@ fn use_wasi_python_bindings() {
     );
 }
 
+#[test]
+fn use_rust_bindings() {
+    let mut volume = wasmer_pack::Files::new();
+    volume.insert("greeting.txt", "Hello, World!".into());
+    let pkg = wabt_fixture().with_mounts([wasmer_pack::Mount::new("/data", volume)]);
+    // Note: the crate is generated outside the workspace so cargo doesn't
+    // think it is meant to be one of our workspace members
+    let temp = tempfile::tempdir().unwrap();
+    let out_dir = temp.path().join("wabt");
+    let options = wasmer_pack::BindingsOptions::default();
+    let rust = wasmer_pack::generate_rust(&pkg, &options).unwrap();
+    rust.save_to_disk(&out_dir).unwrap();
+
+    let mut cmd = Command::new(env!("CARGO"));
+    cmd.arg("check")
+        .arg("--manifest-path")
+        .arg(out_dir.join("Cargo.toml"))
+        .env(
+            "CARGO_TARGET_DIR",
+            Path::new(env!("CARGO_TARGET_TMPDIR")).join("rust-bindings"),
+        );
+
+    assert_runs_successfully(&mut cmd);
+}
+
+#[test]
+fn generate_bindings_for_a_real_package() {
+    type Generate = fn(&Package, &BindingsOptions) -> Result<Files, wasmer_pack::Error>;
+
+    let generators: &[(&str, Generate, &[&str])] = &[
+        (
+            "javascript",
+            wasmer_pack::generate_javascript,
+            &["package/package.json", "package/src/bindings/wabt/wabt.js"],
+        ),
+        (
+            "python",
+            wasmer_pack::generate_python,
+            &["pyproject.toml", "wabt/bindings/wabt/__init__.py"],
+        ),
+        (
+            "rust",
+            wasmer_pack::generate_rust,
+            &["Cargo.toml", "src/bindings.rs", "src/commands.rs"],
+        ),
+        (
+            "go",
+            wasmer_pack::generate_go,
+            &["go.mod", "bindings/wabt/wabt.go", "commands/commands.go"],
+        ),
+        (
+            "c",
+            wasmer_pack::generate_c,
+            &[
+                "CMakeLists.txt",
+                "include/wabt.h",
+                "src/wabt.c",
+                "src/wabt_bundled.h",
+            ],
+        ),
+        (
+            "ruby",
+            wasmer_pack::generate_ruby,
+            &[
+                "wabt.gemspec",
+                "lib/wabt/bindings/wabt.rb",
+                "lib/wabt/commands.rb",
+            ],
+        ),
+        (
+            "php",
+            wasmer_pack::generate_php,
+            &[
+                "composer.json",
+                "src/Bindings/Wabt/Wabt.php",
+                "src/Commands.php",
+            ],
+        ),
+    ];
+    let pkg = wabt_fixture();
+
+    for (language, generate, expected) in generators {
+        let files = generate(&pkg, &BindingsOptions::default())
+            .unwrap_or_else(|e| panic!("Unable to generate {language} bindings: {e:?}"));
+
+        let paths: Vec<_> = files.iter().map(|(path, _)| path).collect();
+        for path in *expected {
+            assert!(
+                paths.contains(&Path::new(path)),
+                "{language}: {path} not in {paths:?}"
+            );
+        }
+    }
+}
+
 fn wabt_fixture() -> Package {
     let project_root = project_root();
 
@@ -64,7 +159,7 @@ fn wabt_fixture() -> Package {
         .join("tests")
         .join("wabt");
 
-    let metadata = Metadata::new("wasmer/wabt".parse().unwrap(), "0.0.0");
+    let metadata = Metadata::new("wasmer/wabt".parse().unwrap(), "0.0.0".parse().unwrap());
     let libraries = vec![
         Library {
             module: Module::from_path(wabt_dir.join("libwabt.wasm"), Abi::Wasi).unwrap(),
@@ -86,10 +181,10 @@ fn wabt_fixture() -> Package {
         let path = entry.path();
 
         if path.extension().is_none() {
-            commands.push(wasmer_pack::Command {
-                name: path.file_stem().unwrap().to_str().unwrap().to_string(),
-                wasm: std::fs::read(&path).unwrap(),
-            });
+            commands.push(wasmer_pack::Command::new(
+                path.file_stem().unwrap().to_str().unwrap(),
+                std::fs::read(&path).unwrap(),
+            ));
         }
     }
 
@@ -18,7 +18,7 @@ describe("Generated WASI bindings", () => {
     });
 
     it("can invoke the wat2wasm executable", async () => {
-        const env = { args: ["wat2wasm", "--help"] };
+        const env = { args: ["--help"] };
 
         const { code, wasi } = await commands.wat2wasm({ wasi: env });
 
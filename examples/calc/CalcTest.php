@@ -0,0 +1,16 @@
+<?php
+
+declare(strict_types=1);
+
+use MichaelFBryan\Calc\Package;
+use PHPUnit\Framework\TestCase;
+
+final class CalcTest extends TestCase
+{
+    public function testTwoPlusTwo(): void
+    {
+        $calc = Package::bindings()->calc();
+
+        $this->assertSame(4.0, $calc->add(2.0, 2.0));
+    }
+}
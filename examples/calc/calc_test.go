@@ -0,0 +1,25 @@
+package tests
+
+import (
+	"testing"
+
+	"Michael-F-Bryan/calc"
+	"github.com/wasmerio/wasmer-go/wasmer"
+)
+
+func TestTwoPlusTwo(t *testing.T) {
+	store := wasmer.NewStore(wasmer.NewEngine())
+
+	wasm, err := calc.Bindings().Calc(store, nil)
+	if err != nil {
+		t.Fatal(err)
+	}
+
+	got, err := wasm.Add(2.0, 2.0)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if got != 4.0 {
+		t.Errorf("expected 4, got %v", got)
+	}
+}
@@ -0,0 +1,10 @@
+module tests
+
+go 1.18
+
+require (
+	Michael-F-Bryan/calc v0.0.0
+	github.com/wasmerio/wasmer-go v1.0.4
+)
+
+replace Michael-F-Bryan/calc => ./generated_bindings/Go
@@ -0,0 +1,9 @@
+require "calc"
+
+RSpec.describe Calc do
+  it "adds 2 and 2" do
+    wasm = Calc.bindings.calc
+
+    expect(wasm.add(2.0, 2.0)).to eq(4.0)
+  end
+end
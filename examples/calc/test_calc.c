@@ -0,0 +1,34 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+#include <calc.h>
+
+int main(void) {
+    wasm_engine_t *engine = wasm_engine_new();
+    wasm_store_t *store = wasm_store_new(engine);
+
+    calc_t *calc = NULL;
+    wasm_trap_t *trap = calc_new(store, NULL, &calc);
+    if (trap != NULL) {
+        fprintf(stderr, "Unable to instantiate the calc library\n");
+        return EXIT_FAILURE;
+    }
+
+    float result = 0;
+    trap = calc_add(calc, 2.0f, 2.0f, &result);
+    if (trap != NULL) {
+        fprintf(stderr, "Calling add() trapped\n");
+        return EXIT_FAILURE;
+    }
+
+    if (result != 4.0f) {
+        fprintf(stderr, "Expected 4, got %f\n", result);
+        return EXIT_FAILURE;
+    }
+
+    calc_delete(calc);
+    wasm_store_delete(store);
+    wasm_engine_delete(engine);
+
+    return EXIT_SUCCESS;
+}